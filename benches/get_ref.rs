@@ -0,0 +1,62 @@
+//! Compares `BTreeDatStore::get` (copies the value into a `Vec`) with
+//! `get_ref` (borrows it from the mapping) on 1MB values.
+//! Run with `cargo bench --bench get_ref`.
+
+use build_an_index::backends::{BTreeDatStore, BTreeDatStoreBuilder};
+use build_an_index::data_gen::{BlobSize, DataGenConfig, DataGenerator};
+use build_an_index::store::{BlobStore, BlobStoreBuilder};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+const ENTRIES: usize = 32;
+const ROUNDS: usize = 20;
+
+/// Median time per lookup over `ROUNDS` passes of `lookup` over every key
+fn median_per_lookup(keys: &[Vec<u8>], mut lookup: impl FnMut(&[u8]) -> usize) -> Duration {
+    let mut rounds: Vec<Duration> = (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            for key in keys {
+                black_box(lookup(key));
+            }
+            start.elapsed() / keys.len() as u32
+        })
+        .collect();
+    rounds.sort_unstable();
+    rounds[ROUNDS / 2]
+}
+
+fn main() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("index_btree.dat");
+    let entries =
+        DataGenerator::new(DataGenConfig::new(ENTRIES, 42)).generate_for_size(BlobSize::Huge);
+    let mut builder = BTreeDatStoreBuilder::create(&path).unwrap();
+    for entry in &entries {
+        builder.insert(&entry.key, &entry.value).unwrap();
+    }
+    builder.finish().unwrap();
+    let keys: Vec<Vec<u8>> = entries.into_iter().map(|entry| entry.key).collect();
+
+    let store = BTreeDatStore::open(&path).unwrap();
+    // Fault every value into the mapping so neither side pays for first touch
+    for key in &keys {
+        black_box(store.get(key).unwrap());
+    }
+
+    let get = median_per_lookup(&keys, |key| store.get(key).unwrap().unwrap().len());
+    let get_ref = median_per_lookup(&keys, |key| store.get_ref(key).unwrap().unwrap().len());
+    println!(
+        "B-tree DAT, {} x {} values, median of {} rounds",
+        keys.len(),
+        BlobSize::Huge.name(),
+        ROUNDS
+    );
+    println!("  get:     {:>10.1} us/lookup", get.as_secs_f64() * 1e6);
+    println!("  get_ref: {:>10.1} us/lookup", get_ref.as_secs_f64() * 1e6);
+    println!(
+        "  get_ref is {:.1}x faster",
+        get.as_secs_f64() / get_ref.as_secs_f64().max(f64::MIN_POSITIVE)
+    );
+}
@@ -0,0 +1,735 @@
+use super::manifest::DatManifest;
+use super::page_cache;
+use crate::store::{prefix_upper_bound, BlobStore, BlobStoreBuilder};
+use anyhow::{bail, Context, Result};
+use memmap2::Mmap;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 8] = b"BPTREE01";
+const HEADER_SIZE: usize = 64;
+const DEFAULT_PAGE_SIZE: usize = 4096;
+const PAGE_HEADER_SIZE: usize = 16;
+const LEAF: u8 = 0;
+const INTERNAL: u8 = 1;
+
+/// Header layout (page 0, zero-padded to page_size):
+/// - magic: 8 bytes
+/// - page_size: 8 bytes (u64)
+/// - root_page: 8 bytes (u64)
+/// - height: 8 bytes (u64, 1 = the root is a leaf)
+/// - entry_count: 8 bytes (u64)
+/// - page_count: 8 bytes (u64, including the header page)
+/// - blob_heap_offset: 8 bytes (u64)
+/// - reserved: 8 bytes
+///
+/// Page n starts at n * page_size. The blob heap follows the last page.
+
+/// Page header layout:
+/// - kind: 1 byte (0 = leaf, 1 = internal)
+/// - reserved: 1 byte
+/// - count: 2 bytes (u16, entries in the page)
+/// - reserved: 4 bytes
+/// - next_leaf: 8 bytes (u64, next leaf page in key order, 0 = last; leaves only)
+
+/// Leaf entry layout:
+/// - key_len: 2 bytes (u16)
+/// - key: variable
+/// - blob_offset: 8 bytes (u64)
+/// - blob_len: 8 bytes (u64)
+
+/// Internal page body:
+/// - first_child: 8 bytes (u64, page holding keys below the first separator)
+/// - count entries of: key_len (u16), separator key, child page (u64)
+///
+/// Each separator is the smallest key in its child's subtree.
+
+struct Header {
+    page_size: usize,
+    root_page: u64,
+    height: u64,
+    entry_count: u64,
+    page_count: u64,
+    blob_heap_offset: u64,
+}
+
+impl Header {
+    fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < HEADER_SIZE {
+            bail!("File too small for header");
+        }
+        if &data[0..8] != MAGIC {
+            bail!("Invalid magic number");
+        }
+        let field = |i: usize| u64::from_le_bytes(data[i * 8..i * 8 + 8].try_into().unwrap());
+        let header = Self {
+            page_size: field(1) as usize,
+            root_page: field(2),
+            height: field(3),
+            entry_count: field(4),
+            page_count: field(5),
+            blob_heap_offset: field(6),
+        };
+        if header.page_size < HEADER_SIZE {
+            bail!("Invalid page size {}", header.page_size);
+        }
+        let pages_len = header.page_count.checked_mul(header.page_size as u64);
+        if !pages_len.is_some_and(|len| len <= data.len() as u64 && len <= header.blob_heap_offset)
+        {
+            bail!(
+                "Page count {} exceeds file length {}",
+                header.page_count,
+                data.len()
+            );
+        }
+        if header.root_page == 0 || header.root_page >= header.page_count {
+            bail!("Root page {} out of range", header.root_page);
+        }
+        Ok(header)
+    }
+}
+
+/// Paged B+tree .dat store using a memory-mapped file. Lookups descend from the
+/// root, touching one page per level; leaves are chained for range scans.
+pub struct BPlusTreeStore {
+    mmap: Mmap,
+    /// The mapped file, kept open to evict it from the page cache
+    file: File,
+    page_size: usize,
+    root_page: u64,
+    height: u64,
+    entry_count: usize,
+    page_count: u64,
+}
+
+/// Cursor over the entries of one leaf page
+struct LeafEntries<'a> {
+    page: &'a [u8],
+    offset: usize,
+    remaining: usize,
+}
+
+impl<'a> Iterator for LeafEntries<'a> {
+    /// (key, blob_offset, blob_len)
+    type Item = (&'a [u8], u64, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let page = self.page;
+        let key_len = u16::from_le_bytes(page[self.offset..self.offset + 2].try_into().unwrap());
+        let key_start = self.offset + 2;
+        let key_end = key_start + key_len as usize;
+        let blob_offset = u64::from_le_bytes(page[key_end..key_end + 8].try_into().unwrap());
+        let blob_len = u64::from_le_bytes(page[key_end + 8..key_end + 16].try_into().unwrap());
+        self.offset = key_end + 16;
+        Some((&page[key_start..key_end], blob_offset, blob_len))
+    }
+}
+
+impl BPlusTreeStore {
+    /// Depth of the tree (1 when the root is a leaf)
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    fn page(&self, page: u64) -> Result<&[u8]> {
+        if page == 0 || page >= self.page_count {
+            bail!("Page {} out of range", page);
+        }
+        let start = page as usize * self.page_size;
+        Ok(&self.mmap[start..start + self.page_size])
+    }
+
+    fn count(page: &[u8]) -> usize {
+        u16::from_le_bytes(page[2..4].try_into().unwrap()) as usize
+    }
+
+    fn next_leaf(page: &[u8]) -> u64 {
+        u64::from_le_bytes(page[8..16].try_into().unwrap())
+    }
+
+    fn leaf_entries(page: &[u8]) -> LeafEntries<'_> {
+        LeafEntries {
+            page,
+            offset: PAGE_HEADER_SIZE,
+            remaining: Self::count(page),
+        }
+    }
+
+    /// Child of an internal page to follow for `key`, or the leftmost child when
+    /// `key` is `None`
+    fn child_for(page: &[u8], key: Option<&[u8]>) -> u64 {
+        let mut offset = PAGE_HEADER_SIZE;
+        let mut child = u64::from_le_bytes(page[offset..offset + 8].try_into().unwrap());
+        offset += 8;
+        let Some(key) = key else {
+            return child;
+        };
+        for _ in 0..Self::count(page) {
+            let key_len = u16::from_le_bytes(page[offset..offset + 2].try_into().unwrap()) as usize;
+            let separator = &page[offset + 2..offset + 2 + key_len];
+            if separator > key {
+                break;
+            }
+            offset += 2 + key_len;
+            child = u64::from_le_bytes(page[offset..offset + 8].try_into().unwrap());
+            offset += 8;
+        }
+        child
+    }
+
+    /// Descend from the root to the leaf that would hold `key`
+    fn find_leaf(&self, key: Option<&[u8]>) -> Result<&[u8]> {
+        let mut page_no = self.root_page;
+        for _ in 1..self.height {
+            let page = self.page(page_no)?;
+            if page[0] != INTERNAL {
+                bail!("Expected internal page at {}", page_no);
+            }
+            page_no = Self::child_for(page, key);
+        }
+        let page = self.page(page_no)?;
+        if page[0] != LEAF {
+            bail!("Expected leaf page at {}", page_no);
+        }
+        Ok(page)
+    }
+
+    fn blob(&self, offset: u64, len: u64) -> Result<&[u8]> {
+        let start = offset as usize;
+        let end = start
+            .checked_add(len as usize)
+            .filter(|&end| end <= self.mmap.len())
+            .context("Blob extends past end of file")?;
+        Ok(&self.mmap[start..end])
+    }
+
+    /// Zero-copy variant of `get`: borrows the value straight out of the mapping
+    pub fn get_ref(&self, key: &[u8]) -> Result<Option<&[u8]>> {
+        let leaf = self.find_leaf(Some(key))?;
+        for (entry_key, offset, len) in Self::leaf_entries(leaf) {
+            if entry_key == key {
+                return self.blob(offset, len).map(Some);
+            }
+            if entry_key > key {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Visit entries within the bounds in key order, following the leaf chain.
+    /// `visit` returns false to stop early.
+    fn scan(
+        &self,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+        mut visit: impl FnMut(&[u8], u64, u64) -> Result<bool>,
+    ) -> Result<()> {
+        let from = match start {
+            Bound::Included(key) | Bound::Excluded(key) => Some(key),
+            Bound::Unbounded => None,
+        };
+        let mut leaf = self.find_leaf(from)?;
+        loop {
+            for (key, offset, len) in Self::leaf_entries(leaf) {
+                let after_start = match start {
+                    Bound::Included(start) => key >= start,
+                    Bound::Excluded(start) => key > start,
+                    Bound::Unbounded => true,
+                };
+                let before_end = match end {
+                    Bound::Included(end) => key <= end,
+                    Bound::Excluded(end) => key < end,
+                    Bound::Unbounded => true,
+                };
+                if !before_end {
+                    return Ok(());
+                }
+                if after_start && !visit(key, offset, len)? {
+                    return Ok(());
+                }
+            }
+            match Self::next_leaf(leaf) {
+                0 => return Ok(()),
+                next => leaf = self.page(next)?,
+            }
+        }
+    }
+
+    /// All (key, value) pairs with keys in the given bounds, in key order
+    pub fn range(&self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut entries = Vec::new();
+        self.scan(start, end, |key, offset, len| {
+            entries.push((key.to_vec(), self.blob(offset, len)?.to_vec()));
+            Ok(true)
+        })?;
+        Ok(entries)
+    }
+}
+
+impl BlobStore for BPlusTreeStore {
+    fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).context("Failed to open B+tree file")?;
+        let mmap = unsafe { Mmap::map(&file).context("Failed to mmap file")? };
+        let header = Header::parse(&mmap)?;
+
+        Ok(Self {
+            mmap,
+            file,
+            page_size: header.page_size,
+            root_page: header.root_page,
+            height: header.height.max(1),
+            entry_count: header.entry_count as usize,
+            page_count: header.page_count,
+        })
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.get_ref(key)?.map(|value| value.to_vec()))
+    }
+
+    fn get_into(&self, key: &[u8], buf: &mut Vec<u8>) -> Result<bool> {
+        buf.clear();
+        let value = self.get_ref(key)?;
+        buf.extend_from_slice(value.unwrap_or_default());
+        Ok(value.is_some())
+    }
+
+    #[cfg(unix)]
+    fn advise_willneed(&self) -> Result<bool> {
+        self.mmap
+            .advise(memmap2::Advice::WillNeed)
+            .context("madvise(MADV_WILLNEED) failed")?;
+        Ok(true)
+    }
+
+    fn drop_caches(&self) -> Result<bool> {
+        page_cache::evict(&self.file, Some(&self.mmap))
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        let mut keys = Vec::with_capacity(self.entry_count);
+        self.scan(Bound::Unbounded, Bound::Unbounded, |key, _, _| {
+            keys.push(key.to_vec());
+            Ok(true)
+        })?;
+        Ok(keys)
+    }
+
+    /// Descends once to the first key with the prefix, then walks the leaf chain.
+    fn prefix_count(&self, prefix: &[u8]) -> Result<usize> {
+        let upper = prefix_upper_bound(prefix);
+        let end = match &upper {
+            Some(upper) => Bound::Excluded(upper.as_slice()),
+            None => Bound::Unbounded,
+        };
+        let mut count = 0;
+        self.scan(Bound::Included(prefix), end, |_, _, _| {
+            count += 1;
+            Ok(true)
+        })?;
+        Ok(count)
+    }
+
+    fn len(&self) -> usize {
+        self.entry_count
+    }
+
+    /// Sums the blob lengths recorded in the leaves.
+    fn total_value_bytes(&self) -> Result<u64> {
+        let mut total = 0u64;
+        self.scan(Bound::Unbounded, Bound::Unbounded, |_, _, len| {
+            total += len;
+            Ok(true)
+        })?;
+        Ok(total)
+    }
+
+    fn backend_name() -> &'static str {
+        "B+tree"
+    }
+}
+
+/// Builder for the paged B+tree store. Entries are buffered and sorted, then
+/// packed into leaves bottom-up on `finish`.
+pub struct BPlusTreeStoreBuilder {
+    path: PathBuf,
+    page_size: usize,
+    entries: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// Append a `DatManifest` trailer, with this fingerprint if set
+    manifest: Option<Option<String>>,
+}
+
+/// A page being laid out by the builder
+struct PageImage {
+    kind: u8,
+    /// Leaf: (key, entry index). Internal: first child, then (separator, child).
+    first_child: u64,
+    keys: Vec<(Vec<u8>, u64)>,
+    next_leaf: u64,
+}
+
+impl BPlusTreeStoreBuilder {
+    /// Use `page_size` bytes per page instead of 4 KiB. Small pages force a
+    /// deeper tree, which is mostly useful for tests.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Append a JSON manifest describing the file (see `manifest::read_manifest`),
+    /// recording `fingerprint` as the description of the input data.
+    pub fn with_manifest(mut self, fingerprint: Option<&str>) -> Self {
+        self.manifest = Some(fingerprint.map(str::to_string));
+        self
+    }
+
+    /// Group items of the given encoded sizes into pages of at most `capacity`
+    /// bytes. Always returns at least one (possibly empty) group.
+    fn pack(sizes: &[usize], capacity: usize) -> Vec<std::ops::Range<usize>> {
+        let mut groups = Vec::new();
+        let mut start = 0;
+        let mut used = 0;
+        for (i, &size) in sizes.iter().enumerate() {
+            if used + size > capacity && i > start {
+                groups.push(start..i);
+                start = i;
+                used = 0;
+            }
+            used += size;
+        }
+        groups.push(start..sizes.len());
+        groups
+    }
+
+    fn encode(&self, page: &PageImage, value_offsets: &[(u64, u64)]) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.page_size);
+        buf.push(page.kind);
+        buf.push(0);
+        buf.extend_from_slice(&(page.keys.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&[0u8; 4]);
+        buf.extend_from_slice(&page.next_leaf.to_le_bytes());
+        if page.kind == INTERNAL {
+            buf.extend_from_slice(&page.first_child.to_le_bytes());
+        }
+        for (key, target) in &page.keys {
+            buf.extend_from_slice(&(key.len() as u16).to_le_bytes());
+            buf.extend_from_slice(key);
+            if page.kind == LEAF {
+                let (offset, len) = value_offsets[*target as usize];
+                buf.extend_from_slice(&offset.to_le_bytes());
+                buf.extend_from_slice(&len.to_le_bytes());
+            } else {
+                buf.extend_from_slice(&target.to_le_bytes());
+            }
+        }
+        debug_assert!(buf.len() <= self.page_size);
+        buf.resize(self.page_size, 0);
+        buf
+    }
+}
+
+impl BlobStoreBuilder for BPlusTreeStoreBuilder {
+    fn create(path: &Path) -> Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            page_size: DEFAULT_PAGE_SIZE,
+            entries: BTreeMap::new(),
+            manifest: None,
+        })
+    }
+
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.entries.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        if !(HEADER_SIZE..=u16::MAX as usize).contains(&self.page_size) {
+            bail!(
+                "Page size {} must be between {} and {}",
+                self.page_size,
+                HEADER_SIZE,
+                u16::MAX
+            );
+        }
+        let capacity = self.page_size - PAGE_HEADER_SIZE;
+        // Every internal page must fit at least two children, or the tree would
+        // never narrow to a single root
+        let max_key_len = (capacity - 8 - 2 * (2 + 8)) / 2;
+        if let Some(key) = self.entries.keys().find(|key| key.len() > max_key_len) {
+            bail!(
+                "Key of {} bytes does not fit in a {}-byte page (max {})",
+                key.len(),
+                self.page_size,
+                max_key_len
+            );
+        }
+
+        // Leaves take pages 1..; page 0 holds the file header
+        let keys: Vec<&Vec<u8>> = self.entries.keys().collect();
+        let mut pages: Vec<PageImage> = Vec::new();
+        let leaf_sizes: Vec<usize> = keys.iter().map(|key| 2 + key.len() + 16).collect();
+        let leaf_groups = Self::pack(&leaf_sizes, capacity);
+        let leaf_count = leaf_groups.len() as u64;
+        for (i, group) in leaf_groups.iter().enumerate() {
+            let page_no = i as u64 + 1;
+            pages.push(PageImage {
+                kind: LEAF,
+                first_child: 0,
+                keys: group
+                    .clone()
+                    .map(|entry| (keys[entry].clone(), entry as u64))
+                    .collect(),
+                next_leaf: if page_no < leaf_count { page_no + 1 } else { 0 },
+            });
+        }
+
+        // Build internal levels until a single root remains
+        let mut level: Vec<(Vec<u8>, u64)> = pages
+            .iter()
+            .enumerate()
+            .map(|(i, page)| {
+                let first = page
+                    .keys
+                    .first()
+                    .map(|(k, _)| k.clone())
+                    .unwrap_or_default();
+                (first, i as u64 + 1)
+            })
+            .collect();
+        let mut height = 1u64;
+        while level.len() > 1 {
+            let sizes: Vec<usize> = level.iter().map(|(key, _)| 2 + key.len() + 8).collect();
+            let groups = Self::pack(&sizes, capacity - 8);
+            let mut next_level = Vec::with_capacity(groups.len());
+            for group in groups {
+                let children = &level[group];
+                pages.push(PageImage {
+                    kind: INTERNAL,
+                    first_child: children[0].1,
+                    keys: children[1..].to_vec(),
+                    next_leaf: 0,
+                });
+                next_level.push((children[0].0.clone(), pages.len() as u64));
+            }
+            level = next_level;
+            height += 1;
+        }
+        let root_page = level[0].1;
+
+        let page_count = pages.len() as u64 + 1;
+        let blob_heap_offset = page_count * self.page_size as u64;
+        let mut value_offsets = Vec::with_capacity(self.entries.len());
+        let mut offset = blob_heap_offset;
+        for value in self.entries.values() {
+            value_offsets.push((offset, value.len() as u64));
+            offset += value.len() as u64;
+        }
+
+        let file = File::create(&self.path).context("Failed to create B+tree file")?;
+        let mut writer = BufWriter::new(file);
+
+        let mut header = vec![0u8; self.page_size];
+        header[0..8].copy_from_slice(MAGIC);
+        let fields = [
+            self.page_size as u64,
+            root_page,
+            height,
+            self.entries.len() as u64,
+            page_count,
+            blob_heap_offset,
+        ];
+        for (i, field) in fields.iter().enumerate() {
+            header[8 + i * 8..16 + i * 8].copy_from_slice(&field.to_le_bytes());
+        }
+        writer.write_all(&header)?;
+
+        for page in &pages {
+            writer.write_all(&self.encode(page, &value_offsets))?;
+        }
+        for value in self.entries.values() {
+            writer.write_all(value)?;
+        }
+        if let Some(fingerprint) = self.manifest {
+            DatManifest::new(
+                BPlusTreeStore::backend_name(),
+                MAGIC,
+                self.entries.len() as u64,
+                fingerprint,
+            )
+            .write_trailer(&mut writer)?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec as prop_vec;
+    use proptest::prelude::*;
+    use tempfile::NamedTempFile;
+
+    fn build(path: &Path, page_size: usize, entries: &BTreeMap<Vec<u8>, Vec<u8>>) {
+        let mut builder = BPlusTreeStoreBuilder::create(path)
+            .unwrap()
+            .with_page_size(page_size);
+        for (key, value) in entries {
+            builder.insert(key, value).unwrap();
+        }
+        builder.finish().unwrap();
+    }
+
+    #[test]
+    fn test_bplustree_roundtrip() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut builder = BPlusTreeStoreBuilder::create(path).unwrap();
+            builder.insert(b"key1", b"value1").unwrap();
+            builder.insert(b"key2", b"value2").unwrap();
+            builder.insert(b"key3", b"value3").unwrap();
+            builder.finish().unwrap();
+        }
+
+        let store = BPlusTreeStore::open(path).unwrap();
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.height(), 1);
+        assert_eq!(store.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+        assert_eq!(store.get(b"key2").unwrap(), Some(b"value2".to_vec()));
+        assert_eq!(store.get(b"key3").unwrap(), Some(b"value3".to_vec()));
+        assert_eq!(store.get(b"nonexistent").unwrap(), None);
+    }
+
+    #[test]
+    fn test_bplustree_empty_store() {
+        let temp_file = NamedTempFile::new().unwrap();
+        BPlusTreeStoreBuilder::create(temp_file.path())
+            .unwrap()
+            .finish()
+            .unwrap();
+
+        let store = BPlusTreeStore::open(temp_file.path()).unwrap();
+        assert!(store.is_empty());
+        assert_eq!(store.get(b"anything").unwrap(), None);
+        assert!(store.keys().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_bplustree_multi_level_tree() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let entries: BTreeMap<Vec<u8>, Vec<u8>> = (0..2000)
+            .map(|i| (format!("key{:05}", i).into_bytes(), vec![i as u8; i % 50]))
+            .collect();
+        build(temp_file.path(), 256, &entries);
+
+        let store = BPlusTreeStore::open(temp_file.path()).unwrap();
+        assert!(store.height() >= 3, "height {}", store.height());
+        assert_eq!(store.len(), entries.len());
+        for (key, value) in &entries {
+            assert_eq!(store.get(key).unwrap().as_ref(), Some(value));
+        }
+        assert_eq!(store.get(b"key").unwrap(), None);
+        assert_eq!(store.get(b"key99999").unwrap(), None);
+        assert_eq!(
+            store.keys().unwrap(),
+            entries.keys().cloned().collect::<Vec<_>>()
+        );
+        assert_eq!(store.prefix_count(b"key001").unwrap(), 100);
+    }
+
+    #[test]
+    fn test_bplustree_rejects_oversized_key() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut builder = BPlusTreeStoreBuilder::create(temp_file.path())
+            .unwrap()
+            .with_page_size(128);
+        builder.insert(&[b'k'; 200], b"v").unwrap();
+        let err = builder.finish().unwrap_err();
+        assert!(err.to_string().contains("does not fit"), "{}", err);
+    }
+
+    #[test]
+    fn test_bplustree_rejects_truncated_file() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let entries: BTreeMap<Vec<u8>, Vec<u8>> =
+            (0..100u8).map(|i| (vec![i], vec![i; 10])).collect();
+        build(temp_file.path(), 128, &entries);
+
+        let bytes = std::fs::read(temp_file.path()).unwrap();
+        std::fs::write(temp_file.path(), &bytes[..bytes.len() / 2]).unwrap();
+        assert!(BPlusTreeStore::open(temp_file.path()).is_err());
+    }
+
+    fn to_bound(bound: &Bound<Vec<u8>>) -> Bound<&[u8]> {
+        match bound {
+            Bound::Included(key) => Bound::Included(key.as_slice()),
+            Bound::Excluded(key) => Bound::Excluded(key.as_slice()),
+            Bound::Unbounded => Bound::Unbounded,
+        }
+    }
+
+    fn bound_strategy() -> impl Strategy<Value = Bound<Vec<u8>>> {
+        prop_oneof![
+            prop_vec(any::<u8>(), 0..4).prop_map(Bound::Included),
+            prop_vec(any::<u8>(), 0..4).prop_map(Bound::Excluded),
+            Just(Bound::Unbounded),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn prop_matches_btreemap(
+            entries in prop::collection::btree_map(
+                prop_vec(any::<u8>(), 0..24),
+                prop_vec(any::<u8>(), 0..64),
+                0..200,
+            ),
+            probes in prop_vec(prop_vec(any::<u8>(), 0..24), 0..20),
+            page_size in prop_oneof![Just(128usize), Just(512), Just(4096)],
+            start in bound_strategy(),
+            end in bound_strategy(),
+        ) {
+            let temp_file = NamedTempFile::new().unwrap();
+            build(temp_file.path(), page_size, &entries);
+            let store = BPlusTreeStore::open(temp_file.path()).unwrap();
+
+            prop_assert_eq!(store.len(), entries.len());
+            for (key, value) in &entries {
+                prop_assert_eq!(store.get(key).unwrap().as_ref(), Some(value));
+            }
+            for probe in &probes {
+                prop_assert_eq!(store.get(probe).unwrap().as_ref(), entries.get(probe));
+            }
+
+            // BTreeMap::range panics on inverted or empty-excluded bounds
+            let valid = match (&start, &end) {
+                (Bound::Included(s), Bound::Included(e)) => s <= e,
+                (
+                    Bound::Included(s) | Bound::Excluded(s),
+                    Bound::Included(e) | Bound::Excluded(e),
+                ) => s < e,
+                _ => true,
+            };
+            if valid {
+                let expected: Vec<(Vec<u8>, Vec<u8>)> = entries
+                    .range((start.clone(), end.clone()))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                let actual = store.range(to_bound(&start), to_bound(&end)).unwrap();
+                prop_assert_eq!(actual, expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::{bail, Result};
+use std::io::{self, Write};
+
+/// Bytes the checksum fields take in a `.dat` header
+pub(crate) const CHECKSUM_FIELDS_LEN: usize = 9;
+
+/// Checksum fields, stored in a `.dat` header's reserved bytes:
+/// - index_crc: 4 bytes (u32, CRC32 of everything between the header and the blob heap)
+/// - heap_crc: 4 bytes (u32, CRC32 of the blob heap)
+/// - has_checksums: 1 byte (u8, 1 = the CRC32s are set; 0 in files built without them)
+
+/// CRC32s of a `.dat` file's regions, written by builders asked for them
+/// and checked by the readers at open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Checksums {
+    /// The lookup structure (bucket table, offset table and entries, ...)
+    pub index: u32,
+    /// The blob heap, in the data file for split indices
+    pub heap: u32,
+}
+
+impl Checksums {
+    /// Parse the checksum fields at the start of `fields`, or `None` for a
+    /// file built without checksums
+    pub(crate) fn parse(fields: &[u8]) -> Option<Self> {
+        (fields[8] == 1).then(|| Self {
+            index: u32::from_le_bytes(fields[0..4].try_into().unwrap()),
+            heap: u32::from_le_bytes(fields[4..8].try_into().unwrap()),
+        })
+    }
+
+    pub(crate) fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&self.index.to_le_bytes())?;
+        writer.write_all(&self.heap.to_le_bytes())?;
+        writer.write_all(&[1])
+    }
+}
+
+/// Fail with a clear error if `actual` isn't the CRC32 recorded for `region`
+pub(crate) fn check(format: &str, region: &str, expected: u32, actual: u32) -> Result<()> {
+    if expected != actual {
+        bail!(
+            "{} checksum mismatch in the {}: expected {:08x}, got {:08x}; the file is corrupt",
+            format,
+            region,
+            expected,
+            actual
+        );
+    }
+    Ok(())
+}
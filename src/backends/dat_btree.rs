@@ -1,40 +1,107 @@
-use crate::store::{BlobStore, BlobStoreBuilder};
+use super::checksum::{self, Checksums, CHECKSUM_FIELDS_LEN};
+use super::manifest::{self, DatManifest};
+use super::page_cache;
+use crate::store::{BlobStore, BlobStoreBuilder, OrderedBlobStore};
 use anyhow::{bail, Context, Result};
 use memmap2::Mmap;
 use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{BufWriter, Seek, SeekFrom, Write};
-use std::path::Path;
-
-const MAGIC: &[u8; 8] = b"BTREEIDX";
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 8] = b"BTREEID3";
+/// Files written before the offset table existed; searched with a linear scan
+const MAGIC_V2: &[u8; 8] = b"BTREEID2";
+/// Files written before per-entry flags existed; read with all flags as 0
+const MAGIC_V1: &[u8; 8] = b"BTREEIDX";
+/// Index-only file whose blob heap lives in a separate data file (see `create_split`)
+const MAGIC_SPLIT: &[u8; 8] = b"BTREESP3";
+/// Split index written before the offset table existed
+const MAGIC_SPLIT_V2: &[u8; 8] = b"BTREESP2";
+/// Front-coded keys with a restart-point table (see `with_front_coding`)
+const MAGIC_FRONT_CODED: &[u8; 8] = b"BTREEFC1";
+/// Split index with front-coded keys
+const MAGIC_SPLIT_FRONT_CODED: &[u8; 8] = b"BTREEFS1";
 const HEADER_SIZE: usize = 64;
+/// Bytes per offset table slot
+const OFFSET_SIZE: usize = 8;
+/// Header offset of the checksum fields (see `checksum`)
+const CHECKSUMS_OFFSET: usize = 40;
+/// Header offset of the restart interval in front-coded files
+const RESTART_INTERVAL_OFFSET: usize = CHECKSUMS_OFFSET + CHECKSUM_FIELDS_LEN;
 
 /// Header layout:
 /// - magic: 8 bytes
 /// - btree_root_offset: 8 bytes (u64)
 /// - blob_heap_offset: 8 bytes (u64)
 /// - entry_count: 8 bytes (u64)
-/// - reserved: 32 bytes
+/// - offset_table_offset: 8 bytes (u64, 0 in files written before the table existed)
+/// - checksums: 9 bytes (see `checksum`; the index CRC32 covers the offset
+///   table and entries)
+/// - restart_interval: 4 bytes (u32, front-coded files only)
+/// - reserved: 11 bytes
+///
+/// In split files (`BTREESP3`, `BTREEFS1`) blob_heap_offset marks the end of the
+/// entries and entry blob offsets point into the separate data file.
 #[repr(C)]
 struct Header {
     magic: [u8; 8],
     btree_root_offset: u64,
     blob_heap_offset: u64,
     entry_count: u64,
+    offset_table_offset: u64,
+    checksums: Option<Checksums>,
+    /// Entries per restart point, or 0 if keys are stored whole
+    restart_interval: u64,
 }
 
+/// Offset table (between the header and the entries):
+/// - entry_offset: 8 bytes (u64) per entry, in key order, pointing at the
+///   entry's key_len so lookups can binary search the variable-width entries
+
 /// B-tree node entry in a page:
 /// - key_len: 4 bytes (u32)
 /// - key: variable
 /// - blob_offset: 8 bytes (u64)
 /// - blob_len: 8 bytes (u64)
+/// - flags: 4 bytes (u32, absent in BTREEIDX files)
+
+/// Front-coded entry (`BTREEFC1`, `BTREEFS1`), storing only what differs from
+/// the previous key:
+/// - shared_len: 4 bytes (u32, bytes shared with the previous key)
+/// - suffix_len: 4 bytes (u32)
+/// - suffix: variable
+/// - blob_offset: 8 bytes (u64)
+/// - blob_len: 8 bytes (u64)
+/// - flags: 4 bytes (u32)
+///
+/// Every restart_interval-th entry is a restart point with shared_len 0, and
+/// the offset table holds one slot per restart point instead of per entry:
+/// lookups binary search the restart keys, then decode at most one interval.
 
 /// B-tree .dat store using memory-mapped file.
 pub struct BTreeDatStore {
+    file: File,
     mmap: Mmap,
+    /// Mapping of the separate data file for split indices
+    data: Option<Mmap>,
+    /// The separate data file itself, for `drop_caches`
+    data_file: Option<File>,
+    magic: [u8; 8],
     btree_root_offset: u64,
     blob_heap_offset: u64,
     entry_count: usize,
+    /// Start of the offset table, or 0 for older files searched linearly
+    offset_table_offset: u64,
+    /// Bytes after the key in each B-tree entry (offset + len, plus flags in current files)
+    entry_tail_len: usize,
+    /// CRC32s of the entries and blob heap, if the file was built with them
+    checksums: Option<Checksums>,
+    /// Entries per restart point in front-coded files, 0 when keys are stored whole
+    restart_interval: usize,
+    /// Entries parsed by lookups, so tests can compare search strategies by work done
+    #[cfg(test)]
+    entries_parsed: std::sync::atomic::AtomicUsize,
 }
 
 impl BTreeDatStore {
@@ -46,43 +113,280 @@ impl BTreeDatStore {
         let mut magic = [0u8; 8];
         magic.copy_from_slice(&data[0..8]);
 
-        if &magic != MAGIC {
+        let front_coded = &magic == MAGIC_FRONT_CODED || &magic == MAGIC_SPLIT_FRONT_CODED;
+        let has_offset_table = front_coded || &magic == MAGIC || &magic == MAGIC_SPLIT;
+        if !has_offset_table && ![MAGIC_V2, MAGIC_V1, MAGIC_SPLIT_V2].contains(&&magic) {
             bail!("Invalid magic number");
         }
 
         let btree_root_offset = u64::from_le_bytes(data[8..16].try_into().unwrap());
         let blob_heap_offset = u64::from_le_bytes(data[16..24].try_into().unwrap());
         let entry_count = u64::from_le_bytes(data[24..32].try_into().unwrap());
+        let offset_table_offset = if has_offset_table {
+            u64::from_le_bytes(data[32..40].try_into().unwrap())
+        } else {
+            0
+        };
+        let restart_interval = if front_coded {
+            let field = &data[RESTART_INTERVAL_OFFSET..RESTART_INTERVAL_OFFSET + 4];
+            match u32::from_le_bytes(field.try_into().unwrap()) {
+                0 => bail!("Corrupt B-tree dat header: front-coded file has no restart interval"),
+                interval => interval as u64,
+            }
+        } else {
+            0
+        };
+
+        if has_offset_table {
+            // Front-coded files only have a slot per restart point
+            let slots = match restart_interval {
+                0 => entry_count,
+                interval => entry_count.div_ceil(interval),
+            };
+            let table_end = slots
+                .checked_mul(OFFSET_SIZE as u64)
+                .and_then(|len| len.checked_add(offset_table_offset));
+            if offset_table_offset < HEADER_SIZE as u64
+                || !table_end.is_some_and(|end| end <= btree_root_offset)
+            {
+                bail!(
+                    "Corrupt B-tree dat header: offset table of {} entries at {} overlaps the \
+                     entries at {}",
+                    entry_count,
+                    offset_table_offset,
+                    btree_root_offset
+                );
+            }
+        }
+        if btree_root_offset < HEADER_SIZE as u64
+            || btree_root_offset > blob_heap_offset
+            || blob_heap_offset > data.len() as u64
+        {
+            bail!(
+                "Corrupt B-tree dat header: entries span {}..{} in a {}-byte file",
+                btree_root_offset,
+                blob_heap_offset,
+                data.len()
+            );
+        }
 
         Ok(Header {
             magic,
             btree_root_offset,
             blob_heap_offset,
             entry_count,
+            offset_table_offset,
+            checksums: Checksums::parse(
+                &data[CHECKSUMS_OFFSET..CHECKSUMS_OFFSET + CHECKSUM_FIELDS_LEN],
+            ),
+            restart_interval,
         })
     }
 
-    /// Binary search through the B-tree pages to find a key.
-    fn find_key(&self, key: &[u8]) -> Option<(u64, u64)> {
+    /// Check the blob heap's CRC32, if the file has one
+    fn verify_heap_checksum(&self, heap: &[u8]) -> Result<()> {
+        match self.checksums {
+            Some(sums) => {
+                checksum::check("B-tree dat", "blob heap", sums.heap, crc32fast::hash(heap))
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn is_split(&self) -> bool {
+        [MAGIC_SPLIT, MAGIC_SPLIT_V2, MAGIC_SPLIT_FRONT_CODED].contains(&&self.magic)
+    }
+
+    /// Parse the entry starting at `offset` into (key, blob_offset, blob_len, flags),
+    /// or `None` if it runs past the end of the entries.
+    fn entry_at(&self, offset: usize) -> Option<(&[u8], u64, u64, u32)> {
+        #[cfg(test)]
+        self.entries_parsed
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let data = &self.mmap[..];
+        let btree_end = self.blob_heap_offset as usize;
+        let key_start = offset.checked_add(4)?;
+        if key_start > btree_end {
+            return None;
+        }
+        let key_len = u32::from_le_bytes(data[offset..key_start].try_into().unwrap()) as usize;
+        let tail = key_start.checked_add(key_len)?;
+        if tail.checked_add(self.entry_tail_len)? > btree_end {
+            return None;
+        }
+
+        let blob_offset = u64::from_le_bytes(data[tail..tail + 8].try_into().unwrap());
+        let blob_len = u64::from_le_bytes(data[tail + 8..tail + 16].try_into().unwrap());
+        let flags = if self.entry_tail_len > 16 {
+            u32::from_le_bytes(data[tail + 16..tail + 20].try_into().unwrap())
+        } else {
+            0
+        };
+        Some((&data[key_start..tail], blob_offset, blob_len, flags))
+    }
+
+    /// Parse the front-coded entry at `offset`, rebuilding its key in `key` from
+    /// the previous entry's. Returns (next entry offset, blob_offset, blob_len,
+    /// flags), or `None` if it runs past the end of the entries or claims to
+    /// share more bytes than the previous key has.
+    fn front_coded_entry_at(
+        &self,
+        offset: usize,
+        key: &mut Vec<u8>,
+    ) -> Option<(usize, u64, u64, u32)> {
         let data = &self.mmap[..];
-        let btree_start = self.btree_root_offset as usize;
         let btree_end = self.blob_heap_offset as usize;
+        let suffix_start = offset.checked_add(8)?;
+        if suffix_start > btree_end {
+            return None;
+        }
+        let shared_len = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        let suffix_len =
+            u32::from_le_bytes(data[offset + 4..suffix_start].try_into().unwrap()) as usize;
+        let tail = suffix_start.checked_add(suffix_len)?;
+        if shared_len > key.len() || tail.checked_add(self.entry_tail_len)? > btree_end {
+            return None;
+        }
+        key.truncate(shared_len);
+        key.extend_from_slice(&data[suffix_start..tail]);
+
+        let blob_offset = u64::from_le_bytes(data[tail..tail + 8].try_into().unwrap());
+        let blob_len = u64::from_le_bytes(data[tail + 8..tail + 16].try_into().unwrap());
+        let flags = u32::from_le_bytes(data[tail + 16..tail + 20].try_into().unwrap());
+        Some((tail + self.entry_tail_len, blob_offset, blob_len, flags))
+    }
+
+    /// File offset of the `i`th restart point in a front-coded file
+    fn restart_offset(&self, i: usize) -> usize {
+        let table = &self.mmap[self.offset_table_offset as usize..];
+        let slot = &table[i * OFFSET_SIZE..(i + 1) * OFFSET_SIZE];
+        u64::from_le_bytes(slot.try_into().unwrap()) as usize
+    }
+
+    /// File offset of the last restart point whose key is `<= key`, or `None`
+    /// if `key` sorts before every entry
+    fn restart_for(&self, key: &[u8]) -> Option<usize> {
+        let (mut lo, mut hi) = (0, self.entry_count.div_ceil(self.restart_interval));
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            // Restart points share nothing with the previous key, so decode from empty
+            let mut restart_key = Vec::new();
+            match self.front_coded_entry_at(self.restart_offset(mid), &mut restart_key) {
+                Some(_) if restart_key.as_slice() <= key => lo = mid + 1,
+                _ => hi = mid,
+            }
+        }
+        lo.checked_sub(1).map(|i| self.restart_offset(i))
+    }
 
-        // The B-tree is stored as a flat sorted array of entries across pages
-        // We'll do a linear scan through pages, then binary search within each page
-        // For simplicity, we store all entries in sorted order across pages
+    /// Decode front-coded entries in key order, starting at the restart point at `offset`
+    fn front_coded_entries(&self, offset: usize) -> FrontCodedEntries<'_> {
+        FrontCodedEntries {
+            store: self,
+            offset,
+            key: Vec::new(),
+        }
+    }
+
+    /// Binary search the restart points, then decode forward from the nearest
+    /// one; the key can be at most one restart interval further on.
+    fn find_key_front_coded(&self, key: &[u8]) -> Option<(u64, u64, u32)> {
+        let mut offset = self.restart_for(key)?;
+        let mut entry_key = Vec::new();
+        for _ in 0..self.restart_interval {
+            let (next, blob_offset, blob_len, flags) =
+                self.front_coded_entry_at(offset, &mut entry_key)?;
+            match entry_key.as_slice().cmp(key) {
+                std::cmp::Ordering::Equal => return Some((blob_offset, blob_len, flags)),
+                std::cmp::Ordering::Greater => return None,
+                std::cmp::Ordering::Less => offset = next,
+            }
+        }
+        None
+    }
+
+    /// Find a key, binary searching the offset table when the file has one.
+    /// Returns (blob_offset, blob_len, flags).
+    fn find_key(&self, key: &[u8]) -> Option<(u64, u64, u32)> {
+        if self.restart_interval > 0 {
+            return self.find_key_front_coded(key);
+        }
+        if self.offset_table_offset == 0 {
+            return self.find_key_linear(key);
+        }
+
+        let table = &self.mmap[self.offset_table_offset as usize..];
+        let (mut lo, mut hi) = (0, self.entry_count);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let slot = &table[mid * OFFSET_SIZE..(mid + 1) * OFFSET_SIZE];
+            let offset = u64::from_le_bytes(slot.try_into().unwrap()) as usize;
+            let (entry_key, blob_offset, blob_len, flags) = self.entry_at(offset)?;
+            match entry_key.cmp(key) {
+                std::cmp::Ordering::Equal => return Some((blob_offset, blob_len, flags)),
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+            }
+        }
+        None
+    }
+
+    /// File offset of the first entry whose key is `>= key`, or the end of the
+    /// entries if there is none
+    fn lower_bound(&self, key: &[u8]) -> usize {
+        let btree_end = self.blob_heap_offset as usize;
+        if self.offset_table_offset == 0 {
+            let mut offset = self.btree_root_offset as usize;
+            while let Some((entry_key, ..)) = self.entry_at(offset) {
+                if entry_key >= key {
+                    return offset;
+                }
+                offset += 4 + entry_key.len() + self.entry_tail_len;
+            }
+            return btree_end;
+        }
+
+        let table = &self.mmap[self.offset_table_offset as usize..];
+        let offset_of = |i: usize| {
+            let slot = &table[i * OFFSET_SIZE..(i + 1) * OFFSET_SIZE];
+            u64::from_le_bytes(slot.try_into().unwrap()) as usize
+        };
+        let (mut lo, mut hi) = (0, self.entry_count);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.entry_at(offset_of(mid)) {
+                Some((entry_key, ..)) if entry_key < key => lo = mid + 1,
+                _ => hi = mid,
+            }
+        }
+        if lo < self.entry_count {
+            offset_of(lo)
+        } else {
+            btree_end
+        }
+    }
+
+    /// Scan the sorted entries from the start until reaching `key` or a
+    /// greater one, for files written before the offset table existed.
+    fn find_key_linear(&self, key: &[u8]) -> Option<(u64, u64, u32)> {
+        let data = &self.mmap[..];
+        let btree_start = self.btree_root_offset as usize;
+        let btree_end = self.blob_heap_offset as usize;
 
         let mut offset = btree_start;
         while offset < btree_end {
-            // Read entry: key_len (4) + key + blob_offset (8) + blob_len (8)
+            // Read entry: key_len (4) + key + blob_offset (8) + blob_len (8) + flags (4)
             if offset + 4 > btree_end {
                 break;
             }
+            #[cfg(test)]
+            self.entries_parsed
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
 
             let key_len = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
             offset += 4;
 
-            if offset + key_len + 16 > btree_end {
+            if offset + key_len + self.entry_tail_len > btree_end {
                 break;
             }
 
@@ -90,13 +394,16 @@ impl BTreeDatStore {
             offset += key_len;
 
             let blob_offset = u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
-            offset += 8;
-
-            let blob_len = u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
-            offset += 8;
+            let blob_len = u64::from_le_bytes(data[offset + 8..offset + 16].try_into().unwrap());
+            let flags = if self.entry_tail_len > 16 {
+                u32::from_le_bytes(data[offset + 16..offset + 20].try_into().unwrap())
+            } else {
+                0
+            };
+            offset += self.entry_tail_len;
 
             match entry_key.cmp(key) {
-                std::cmp::Ordering::Equal => return Some((blob_offset, blob_len)),
+                std::cmp::Ordering::Equal => return Some((blob_offset, blob_len, flags)),
                 std::cmp::Ordering::Greater => return None, // Sorted, so key doesn't exist
                 std::cmp::Ordering::Less => continue,
             }
@@ -105,35 +412,179 @@ impl BTreeDatStore {
         None
     }
 
-    fn get_blob(&self, offset: u64, len: u64) -> Vec<u8> {
-        let start = offset as usize;
-        let end = start + len as usize;
-        self.mmap[start..end].to_vec()
+    fn get_blob(&self, offset: u64, len: u64) -> Result<Vec<u8>> {
+        Ok(self.blob_slice(offset, len)?.to_vec())
     }
-}
 
-impl BlobStore for BTreeDatStore {
-    fn open(path: &Path) -> Result<Self> {
+    /// The value at `offset..offset + len`, or an error if the entry points past
+    /// the end of the file (or data file), as in a truncated one
+    fn blob_slice(&self, offset: u64, len: u64) -> Result<&[u8]> {
+        let data = self.data.as_deref().unwrap_or(&self.mmap);
+        let end = offset
+            .checked_add(len)
+            .filter(|&end| end <= data.len() as u64)
+            .with_context(|| {
+                format!(
+                    "Corrupt B-tree dat entry: value at offset {} with length {} runs past the \
+                     end of the {}-byte file",
+                    offset,
+                    len,
+                    data.len()
+                )
+            })?;
+        Ok(&data[offset as usize..end as usize])
+    }
+
+    /// Open an index written by `BTreeDatStoreBuilder::create_split`: entries are
+    /// searched in `index_path` and values are sliced from `data_path`.
+    pub fn open_split(index_path: &Path, data_path: &Path) -> Result<Self> {
+        let mut store = Self::open_index(index_path)?;
+        if !store.is_split() {
+            bail!("B-tree dat file is not a split index; open it with BTreeDatStore::open");
+        }
+        let file = File::open(data_path).context("Failed to open B-tree dat data file")?;
+        let data = unsafe { Mmap::map(&file).context("Failed to mmap data file")? };
+        store.verify_heap_checksum(&data)?;
+        store.data = Some(data);
+        store.data_file = Some(file);
+        Ok(store)
+    }
+
+    fn open_index(path: &Path) -> Result<Self> {
         let file = File::open(path).context("Failed to open B-tree dat file")?;
+        // Checked before mapping: mapping an empty file fails on some platforms
+        let file_len = file
+            .metadata()
+            .context("Failed to stat B-tree dat file")?
+            .len();
+        if file_len < HEADER_SIZE as u64 {
+            bail!(
+                "File too small for header: {} bytes, need at least {}",
+                file_len,
+                HEADER_SIZE
+            );
+        }
         let mmap = unsafe { Mmap::map(&file).context("Failed to mmap file")? };
 
         let header = Self::read_header(&mmap)?;
+        if let Some(sums) = header.checksums {
+            let index = &mmap[HEADER_SIZE..header.blob_heap_offset as usize];
+            checksum::check("B-tree dat", "entries", sums.index, crc32fast::hash(index))?;
+        }
 
         Ok(Self {
+            file,
             mmap,
+            data: None,
+            data_file: None,
+            magic: header.magic,
             btree_root_offset: header.btree_root_offset,
             blob_heap_offset: header.blob_heap_offset,
             entry_count: header.entry_count as usize,
+            offset_table_offset: header.offset_table_offset,
+            entry_tail_len: if &header.magic == MAGIC_V1 { 16 } else { 20 },
+            checksums: header.checksums,
+            restart_interval: header.restart_interval as usize,
+            #[cfg(test)]
+            entries_parsed: Default::default(),
         })
     }
 
+    /// Zero-copy variant of `get`: borrows the value straight out of the mapping
+    /// instead of copying it into a `Vec`.
+    pub fn get_ref<'a>(&'a self, key: &[u8]) -> Result<Option<&'a [u8]>> {
+        self.find_key(key)
+            .map(|(offset, len, _)| self.blob_slice(offset, len))
+            .transpose()
+    }
+
+    /// Like `get`, but also returns the entry's flags (0 unless set at build time)
+    pub fn get_with_flags(&self, key: &[u8]) -> Result<Option<(Vec<u8>, u32)>> {
+        self.find_key(key)
+            .map(|(offset, len, flags)| Ok((self.get_blob(offset, len)?, flags)))
+            .transpose()
+    }
+}
+
+/// Iterator over the entries of a front-coded file as (key, blob_offset,
+/// blob_len, flags), stopping at the end of the entries or at a corrupt one
+struct FrontCodedEntries<'a> {
+    store: &'a BTreeDatStore,
+    offset: usize,
+    key: Vec<u8>,
+}
+
+impl Iterator for FrontCodedEntries<'_> {
+    type Item = (Vec<u8>, u64, u64, u32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (next, blob_offset, blob_len, flags) = self
+            .store
+            .front_coded_entry_at(self.offset, &mut self.key)?;
+        self.offset = next;
+        Some((self.key.clone(), blob_offset, blob_len, flags))
+    }
+}
+
+impl BlobStore for BTreeDatStore {
+    fn open(path: &Path) -> Result<Self> {
+        let store = Self::open_index(path)?;
+        if store.is_split() {
+            bail!("B-tree dat file is a split index; open it with BTreeDatStore::open_split");
+        }
+        // The heap runs to the manifest trailer, if any, or the end of the file
+        let heap_end = store.mmap.len() - manifest::trailer_len(&store.mmap);
+        let heap_start = (store.blob_heap_offset as usize).min(heap_end);
+        store.verify_heap_checksum(&store.mmap[heap_start..heap_end])?;
+        Ok(store)
+    }
+
     fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
-        Ok(self
-            .find_key(key)
-            .map(|(offset, len)| self.get_blob(offset, len)))
+        self.find_key(key)
+            .map(|(offset, len, _)| self.get_blob(offset, len))
+            .transpose()
+    }
+
+    /// Searches the index only; the blob heap is not touched.
+    fn contains_key(&self, key: &[u8]) -> Result<bool> {
+        Ok(self.find_key(key).is_some())
+    }
+
+    fn value_len(&self, key: &[u8]) -> Result<Option<usize>> {
+        Ok(self.find_key(key).map(|(_, len, _)| len as usize))
+    }
+
+    fn get_into(&self, key: &[u8], buf: &mut Vec<u8>) -> Result<bool> {
+        buf.clear();
+        let value = self.get_ref(key)?;
+        buf.extend_from_slice(value.unwrap_or_default());
+        Ok(value.is_some())
+    }
+
+    /// Advises the index mapping and, for split indices, the data mapping.
+    #[cfg(unix)]
+    fn advise_willneed(&self) -> Result<bool> {
+        for mmap in std::iter::once(&self.mmap).chain(&self.data) {
+            mmap.advise(memmap2::Advice::WillNeed)
+                .context("madvise(MADV_WILLNEED) failed")?;
+        }
+        Ok(true)
+    }
+
+    /// Evicts the index and, for split indices, the data file.
+    fn drop_caches(&self) -> Result<bool> {
+        let mut evicted = page_cache::evict(&self.file, Some(&self.mmap))?;
+        if let (Some(file), Some(data)) = (&self.data_file, &self.data) {
+            evicted &= page_cache::evict(file, Some(data))?;
+        }
+        Ok(evicted)
     }
 
     fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        if self.restart_interval > 0 {
+            let entries = self.front_coded_entries(self.btree_root_offset as usize);
+            return Ok(entries.map(|(key, ..)| key).collect());
+        }
         let data = &self.mmap[..];
         let btree_start = self.btree_root_offset as usize;
         let btree_end = self.blob_heap_offset as usize;
@@ -149,14 +600,14 @@ impl BlobStore for BTreeDatStore {
             let key_len = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
             offset += 4;
 
-            if offset + key_len + 16 > btree_end {
+            if offset + key_len + self.entry_tail_len > btree_end {
                 break;
             }
 
             let entry_key = data[offset..offset + key_len].to_vec();
             keys.push(entry_key);
 
-            offset += key_len + 16; // Skip key + blob_offset + blob_len
+            offset += key_len + self.entry_tail_len; // Skip key + blob_offset + blob_len + flags
         }
 
         Ok(keys)
@@ -166,77 +617,319 @@ impl BlobStore for BTreeDatStore {
         self.entry_count
     }
 
+    /// Sums each entry's blob_len without touching the blob heap.
+    fn total_value_bytes(&self) -> Result<u64> {
+        if self.restart_interval > 0 {
+            let entries = self.front_coded_entries(self.btree_root_offset as usize);
+            return Ok(entries.map(|(_, _, blob_len, _)| blob_len).sum());
+        }
+        let data = &self.mmap[..];
+        let btree_end = self.blob_heap_offset as usize;
+        let mut offset = self.btree_root_offset as usize;
+        let mut total = 0u64;
+
+        while offset + 4 <= btree_end {
+            let key_len = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+            offset += 4 + key_len;
+
+            if offset + self.entry_tail_len > btree_end {
+                break;
+            }
+
+            total += u64::from_le_bytes(data[offset + 8..offset + 16].try_into().unwrap());
+            offset += self.entry_tail_len;
+        }
+
+        Ok(total)
+    }
+
+    /// Entries are stored sorted, so this binary searches to the first key `>=`
+    /// the prefix and walks forward only while keys still start with it.
+    fn prefix_count(&self, prefix: &[u8]) -> Result<usize> {
+        if self.restart_interval > 0 {
+            let start = self
+                .restart_for(prefix)
+                .unwrap_or(self.btree_root_offset as usize);
+            let count = self
+                .front_coded_entries(start)
+                .map(|(key, ..)| key)
+                .skip_while(|key| key.as_slice() < prefix)
+                .take_while(|key| key.starts_with(prefix))
+                .count();
+            return Ok(count);
+        }
+
+        // Matching keys sort contiguously from the prefix itself
+        let mut offset = self.lower_bound(prefix);
+        let mut count = 0;
+        while let Some((entry_key, ..)) = self.entry_at(offset) {
+            if !entry_key.starts_with(prefix) {
+                break;
+            }
+            count += 1;
+            offset += 4 + entry_key.len() + self.entry_tail_len;
+        }
+
+        Ok(count)
+    }
+
     fn backend_name() -> &'static str {
         "B-tree DAT"
     }
 }
 
+impl OrderedBlobStore for BTreeDatStore {
+    /// Binary searches to `start`, then walks the contiguous entries until `end`.
+    fn range(&self, start: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut entries = Vec::new();
+        if start >= end {
+            return Ok(entries);
+        }
+        if self.restart_interval > 0 {
+            let offset = self
+                .restart_for(start)
+                .unwrap_or(self.btree_root_offset as usize);
+            for (key, blob_offset, blob_len, _) in self.front_coded_entries(offset) {
+                if key.as_slice() >= end {
+                    break;
+                }
+                if key.as_slice() >= start {
+                    let value = self.get_blob(blob_offset, blob_len)?;
+                    entries.push((key, value));
+                }
+            }
+            return Ok(entries);
+        }
+        let mut offset = self.lower_bound(start);
+        while let Some((key, blob_offset, blob_len, _)) = self.entry_at(offset) {
+            if key >= end {
+                break;
+            }
+            entries.push((key.to_vec(), self.get_blob(blob_offset, blob_len)?));
+            offset += 4 + key.len() + self.entry_tail_len;
+        }
+        Ok(entries)
+    }
+}
+
 /// Builder for B-tree .dat store.
 pub struct BTreeDatStoreBuilder {
-    path: std::path::PathBuf,
-    entries: BTreeMap<Vec<u8>, Vec<u8>>,
+    path: PathBuf,
+    /// Separate file for the blob heap when building a split index
+    data_path: Option<PathBuf>,
+    entries: BTreeMap<Vec<u8>, (Vec<u8>, u32)>,
+    /// Append a `DatManifest` trailer, with this fingerprint if set
+    manifest: Option<Option<String>>,
+    /// Record CRC32s of the entries and blob heap in the header
+    checksums: bool,
+    /// Front-code keys with a restart point every this many entries
+    restart_interval: Option<usize>,
+}
+
+impl BTreeDatStoreBuilder {
+    /// Build a split index: the header and sorted entries go to `index_path` and
+    /// the blob heap to `data_path`. Read it back with `BTreeDatStore::open_split`.
+    pub fn create_split(index_path: &Path, data_path: &Path) -> Result<Self> {
+        let mut builder = Self::create(index_path)?;
+        builder.data_path = Some(data_path.to_path_buf());
+        Ok(builder)
+    }
+
+    /// Insert an entry tagged with caller-defined flags (`insert` uses 0)
+    pub fn insert_with_flags(&mut self, key: &[u8], value: &[u8], flags: u32) -> Result<()> {
+        self.entries.insert(key.to_vec(), (value.to_vec(), flags));
+        Ok(())
+    }
+
+    /// Append a JSON manifest describing the file (see `manifest::read_manifest`),
+    /// recording `fingerprint` as the description of the input data.
+    pub fn with_manifest(mut self, fingerprint: Option<&str>) -> Self {
+        self.manifest = Some(fingerprint.map(str::to_string));
+        self
+    }
+
+    /// Record CRC32s of the offset table and entries and of the blob heap in
+    /// the header; `open` and `open_split` then check them.
+    pub fn with_checksums(mut self) -> Self {
+        self.checksums = true;
+        self
+    }
+
+    /// Front-code the keys: each entry stores only the length of the prefix it
+    /// shares with the previous key plus the rest, which shrinks the index when
+    /// keys share long prefixes. Every `restart_interval`th key is stored whole,
+    /// and lookups binary search those before decoding forward.
+    pub fn with_front_coding(mut self, restart_interval: usize) -> Self {
+        self.restart_interval = Some(restart_interval);
+        self
+    }
 }
 
 impl BlobStoreBuilder for BTreeDatStoreBuilder {
     fn create(path: &Path) -> Result<Self> {
         Ok(Self {
             path: path.to_path_buf(),
+            data_path: None,
             entries: BTreeMap::new(),
+            manifest: None,
+            checksums: false,
+            restart_interval: None,
         })
     }
 
     fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
-        self.entries.insert(key.to_vec(), value.to_vec());
-        Ok(())
+        self.insert_with_flags(key, value, 0)
     }
 
     fn finish(self) -> Result<()> {
+        if self.restart_interval == Some(0) {
+            bail!("Front coding needs a restart interval of at least 1");
+        }
+        // Every entry is a restart point when keys are stored whole
+        let restart_interval = self.restart_interval.unwrap_or(1);
+
         let file = File::create(&self.path).context("Failed to create B-tree dat file")?;
         let mut writer = BufWriter::new(file);
 
         // Reserve space for header
         writer.write_all(&[0u8; HEADER_SIZE])?;
 
-        let btree_root_offset = HEADER_SIZE as u64;
+        // Encode each key: key_len + key, or shared_len + suffix_len + suffix
+        // when front coding
+        let mut encoded_keys: Vec<Vec<u8>> = Vec::with_capacity(self.entries.len());
+        let mut prev_key: &[u8] = &[];
+        for (i, key) in self.entries.keys().enumerate() {
+            let mut encoded = Vec::with_capacity(8 + key.len());
+            if self.restart_interval.is_some() {
+                let shared_len = if i % restart_interval == 0 {
+                    0
+                } else {
+                    prev_key.iter().zip(key).take_while(|(a, b)| a == b).count()
+                };
+                let suffix = &key[shared_len..];
+                encoded.extend_from_slice(&(shared_len as u32).to_le_bytes());
+                encoded.extend_from_slice(&(suffix.len() as u32).to_le_bytes());
+                encoded.extend_from_slice(suffix);
+            } else {
+                encoded.extend_from_slice(&(key.len() as u32).to_le_bytes());
+                encoded.extend_from_slice(key);
+            }
+            encoded_keys.push(encoded);
+            prev_key = key;
+        }
+
+        // The offset table sits between the header and the entries
+        let offset_table_offset = HEADER_SIZE as u64;
+        let restart_count = self.entries.len().div_ceil(restart_interval);
+        let btree_root_offset = offset_table_offset + (restart_count * OFFSET_SIZE) as u64;
 
         // Write entries in sorted order (BTreeMap maintains order)
         // First, we need to know blob offsets, so we'll compute them
-        let mut btree_entries: Vec<(Vec<u8>, u64, u64)> = Vec::with_capacity(self.entries.len());
+        let mut btree_entries: Vec<(&[u8], u64, u64, u32)> = Vec::with_capacity(self.entries.len());
 
         // Calculate where blob heap will start
-        let mut btree_size = 0usize;
-        for (key, _value) in &self.entries {
-            btree_size += 4 + key.len() + 8 + 8; // key_len + key + blob_offset + blob_len
-        }
+        let btree_size: usize = encoded_keys
+            .iter()
+            .map(|encoded| encoded.len() + 8 + 8 + 4) // + blob_offset + blob_len + flags
+            .sum();
 
         let blob_heap_offset = btree_root_offset + btree_size as u64;
-        let mut current_blob_offset = blob_heap_offset;
+        // Split data files start the heap at 0
+        let mut current_blob_offset = match self.data_path {
+            Some(_) => 0,
+            None => blob_heap_offset,
+        };
 
         // Compute blob offsets
-        for (key, value) in &self.entries {
-            btree_entries.push((key.clone(), current_blob_offset, value.len() as u64));
+        for (encoded, (value, flags)) in encoded_keys.iter().zip(self.entries.values()) {
+            btree_entries.push((
+                encoded.as_slice(),
+                current_blob_offset,
+                value.len() as u64,
+                *flags,
+            ));
             current_blob_offset += value.len() as u64;
         }
 
+        // Write the offset table: where each restart point starts, in key order
+        let mut index_hasher = crc32fast::Hasher::new();
+        let mut entry_offset = btree_root_offset;
+        for (i, (encoded, _, _, _)) in btree_entries.iter().enumerate() {
+            if i % restart_interval == 0 {
+                index_hasher.update(&entry_offset.to_le_bytes());
+                writer.write_all(&entry_offset.to_le_bytes())?;
+            }
+            entry_offset += (encoded.len() + 8 + 8 + 4) as u64;
+        }
+
         // Write B-tree entries
-        for (key, blob_offset, blob_len) in &btree_entries {
-            writer.write_all(&(key.len() as u32).to_le_bytes())?;
-            writer.write_all(key)?;
-            writer.write_all(&blob_offset.to_le_bytes())?;
-            writer.write_all(&blob_len.to_le_bytes())?;
+        for (encoded, blob_offset, blob_len, flags) in &btree_entries {
+            for field in [
+                &encoded[..],
+                &blob_offset.to_le_bytes(),
+                &blob_len.to_le_bytes(),
+                &flags.to_le_bytes(),
+            ] {
+                index_hasher.update(field);
+                writer.write_all(field)?;
+            }
         }
 
         // Write blob heap
-        for (_key, value) in &self.entries {
-            writer.write_all(value)?;
+        let mut heap_hasher = crc32fast::Hasher::new();
+        match &self.data_path {
+            Some(data_path) => {
+                let data_file =
+                    File::create(data_path).context("Failed to create B-tree dat data file")?;
+                let mut data_writer = BufWriter::new(data_file);
+                for (value, _flags) in self.entries.values() {
+                    heap_hasher.update(value);
+                    data_writer.write_all(value)?;
+                }
+                data_writer.flush()?;
+            }
+            None => {
+                for (value, _flags) in self.entries.values() {
+                    heap_hasher.update(value);
+                    writer.write_all(value)?;
+                }
+            }
+        }
+        let checksums = self.checksums.then(|| Checksums {
+            index: index_hasher.finalize(),
+            heap: heap_hasher.finalize(),
+        });
+
+        let magic = match (self.data_path.is_some(), self.restart_interval.is_some()) {
+            (true, true) => MAGIC_SPLIT_FRONT_CODED,
+            (true, false) => MAGIC_SPLIT,
+            (false, true) => MAGIC_FRONT_CODED,
+            (false, false) => MAGIC,
+        };
+        if let Some(fingerprint) = self.manifest {
+            DatManifest::new(
+                BTreeDatStore::backend_name(),
+                magic,
+                self.entries.len() as u64,
+                fingerprint,
+            )
+            .write_trailer(&mut writer)?;
         }
 
         // Go back and write header
         writer.seek(SeekFrom::Start(0))?;
-        writer.write_all(MAGIC)?;
+        writer.write_all(magic)?;
         writer.write_all(&btree_root_offset.to_le_bytes())?;
         writer.write_all(&blob_heap_offset.to_le_bytes())?;
         writer.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+        writer.write_all(&offset_table_offset.to_le_bytes())?;
+        if let Some(checksums) = checksums {
+            checksums.write_to(&mut writer)?;
+        }
+        if self.restart_interval.is_some() {
+            writer.seek(SeekFrom::Start(RESTART_INTERVAL_OFFSET as u64))?;
+            writer.write_all(&(restart_interval as u32).to_le_bytes())?;
+        }
 
         writer.flush()?;
 
@@ -276,6 +969,48 @@ mod tests {
         assert_eq!(store.get(b"nonexistent").unwrap(), None);
     }
 
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_willneed_advice_keeps_lookups_correct() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = (0..50)
+            .map(|i| (format!("key{:02}", i).into_bytes(), vec![i as u8; 4096]))
+            .collect();
+        let mut builder = BTreeDatStoreBuilder::create(temp_file.path()).unwrap();
+        for (key, value) in &entries {
+            builder.insert(key, value).unwrap();
+        }
+        builder.finish().unwrap();
+
+        let store = BTreeDatStore::open(temp_file.path()).unwrap();
+        assert!(store.advise_willneed().unwrap());
+        for (key, value) in &entries {
+            assert_eq!(store.get(key).unwrap().as_ref(), Some(value));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_drop_caches_keeps_split_lookups_correct() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let index_path = dir.path().join("index.idx");
+        let data_path = dir.path().join("index.blobs");
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = (0..50)
+            .map(|i| (format!("key{:02}", i).into_bytes(), vec![i as u8; 4096]))
+            .collect();
+        let mut builder = BTreeDatStoreBuilder::create_split(&index_path, &data_path).unwrap();
+        for (key, value) in &entries {
+            builder.insert(key, value).unwrap();
+        }
+        builder.finish().unwrap();
+
+        let store = BTreeDatStore::open_split(&index_path, &data_path).unwrap();
+        for (key, value) in &entries {
+            assert!(store.drop_caches().unwrap());
+            assert_eq!(store.get(key).unwrap().as_ref(), Some(value));
+        }
+    }
+
     #[test]
     fn test_btree_keys_sorted() {
         let temp_file = NamedTempFile::new().unwrap();
@@ -335,6 +1070,294 @@ mod tests {
         assert_eq!(store.get(b"large").unwrap(), Some(large_value));
     }
 
+    #[test]
+    fn test_entry_flags_roundtrip() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut builder = BTreeDatStoreBuilder::create(path).unwrap();
+            builder.insert_with_flags(b"pinned", b"p", 0x1).unwrap();
+            builder
+                .insert_with_flags(b"json", b"{}", 0xdead_beef)
+                .unwrap();
+            builder.insert(b"plain", b"x").unwrap();
+            builder.finish().unwrap();
+        }
+
+        let store = BTreeDatStore::open(path).unwrap();
+        assert_eq!(
+            store.get_with_flags(b"pinned").unwrap(),
+            Some((b"p".to_vec(), 0x1))
+        );
+        assert_eq!(
+            store.get_with_flags(b"json").unwrap(),
+            Some((b"{}".to_vec(), 0xdead_beef))
+        );
+        assert_eq!(
+            store.get_with_flags(b"plain").unwrap(),
+            Some((b"x".to_vec(), 0))
+        );
+        assert_eq!(store.get_with_flags(b"missing").unwrap(), None);
+        assert_eq!(store.get(b"json").unwrap(), Some(b"{}".to_vec()));
+        assert_eq!(store.keys().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_get_ref_matches_get() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut builder = BTreeDatStoreBuilder::create(path).unwrap();
+            builder.insert(b"key1", b"value1").unwrap();
+            builder.insert(b"key2", b"").unwrap();
+            builder.finish().unwrap();
+        }
+
+        let store = BTreeDatStore::open(path).unwrap();
+        for key in [&b"key1"[..], b"key2", b"missing"] {
+            let copied = store.get(key).unwrap();
+            let borrowed = store.get_ref(key).unwrap();
+            assert_eq!(borrowed, copied.as_deref());
+        }
+    }
+
+    #[test]
+    fn test_get_ref_borrows_1mb_values_from_the_mapping() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        let value: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+
+        {
+            let mut builder = BTreeDatStoreBuilder::create(path).unwrap();
+            for i in 0..4 {
+                builder
+                    .insert(format!("huge_{}", i).as_bytes(), &value)
+                    .unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        // get_ref's slice must point into the mapping (no copy); get's Vec can't
+        let store = BTreeDatStore::open(path).unwrap();
+        let mapped = store.mmap.as_ptr_range();
+        for i in 0..4 {
+            let key = format!("huge_{}", i);
+            let copied = store.get(key.as_bytes()).unwrap().unwrap();
+            let borrowed = store.get_ref(key.as_bytes()).unwrap().unwrap();
+
+            assert_eq!(borrowed, copied.as_slice());
+            let range = borrowed.as_ptr_range();
+            assert!(mapped.start <= range.start && range.end <= mapped.end);
+            assert!(!mapped.contains(&copied.as_ptr()));
+        }
+    }
+
+    #[test]
+    fn test_v2_files_without_offset_table_still_open() {
+        // BTREEID2 layout: header, entries, blob heap (no offset table)
+        let entries: [(&[u8], &[u8]); 2] = [(b"apple", b"red"), (b"kiwi", b"green")];
+        let entries_len: usize = entries.iter().map(|(k, _)| 4 + k.len() + 20).sum();
+        let blob_heap_offset = (HEADER_SIZE + entries_len) as u64;
+        let mut file = MAGIC_V2.to_vec();
+        for field in [HEADER_SIZE as u64, blob_heap_offset, entries.len() as u64] {
+            file.extend_from_slice(&field.to_le_bytes());
+        }
+        file.resize(HEADER_SIZE, 0);
+        let mut blob_offset = blob_heap_offset;
+        for (key, value) in &entries {
+            file.extend_from_slice(&(key.len() as u32).to_le_bytes());
+            file.extend_from_slice(key);
+            file.extend_from_slice(&blob_offset.to_le_bytes());
+            file.extend_from_slice(&(value.len() as u64).to_le_bytes());
+            file.extend_from_slice(&0u32.to_le_bytes());
+            blob_offset += value.len() as u64;
+        }
+        for (_, value) in &entries {
+            file.extend_from_slice(value);
+        }
+        let temp_file = NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), &file).unwrap();
+
+        let store = BTreeDatStore::open(temp_file.path()).unwrap();
+        assert_eq!(store.offset_table_offset, 0);
+        assert_eq!(store.get(b"apple").unwrap(), Some(b"red".to_vec()));
+        assert_eq!(store.get(b"kiwi").unwrap(), Some(b"green".to_vec()));
+        assert_eq!(store.get(b"banana").unwrap(), None);
+        assert_eq!(store.keys().unwrap().len(), 2);
+        assert_eq!(store.prefix_count(b"a").unwrap(), 1);
+        assert_eq!(store.prefix_count(b"b").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_offset_table_search_beats_linear_scan() {
+        use std::sync::atomic::Ordering;
+
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        let keys: Vec<Vec<u8>> = (0..20_000)
+            .map(|i| format!("key_{:06}", i).into_bytes())
+            .collect();
+        {
+            let mut builder = BTreeDatStoreBuilder::create(path).unwrap();
+            for key in &keys {
+                builder.insert(key, b"v").unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        let store = BTreeDatStore::open(path).unwrap();
+        assert_ne!(store.offset_table_offset, 0);
+        let parsed_by = |find: &dyn Fn() -> Option<(u64, u64, u32)>| {
+            store.entries_parsed.store(0, Ordering::Relaxed);
+            let found = find();
+            (found, store.entries_parsed.load(Ordering::Relaxed))
+        };
+        // Keys near the end are the linear scan's worst case; binary search
+        // parses at most ceil(log2(20_000)) + 1 = 16 entries for any key
+        for (i, key) in keys.iter().enumerate().skip(keys.len() - 50) {
+            let (found, searched) = parsed_by(&|| store.find_key(key));
+            let (scanned, scan_parsed) = parsed_by(&|| store.find_key_linear(key));
+            assert!(found.is_some());
+            assert_eq!(found, scanned);
+            assert!(searched <= 16, "{} entries parsed", searched);
+            assert_eq!(scan_parsed, i + 1);
+        }
+        for absent in [&b"key_999999"[..], b"a"] {
+            let (found, searched) = parsed_by(&|| store.find_key(absent));
+            assert_eq!(found, None);
+            assert!(searched <= 16, "{} entries parsed", searched);
+        }
+    }
+
+    #[test]
+    fn test_split_index_reads_values_from_data_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let index_path = dir.path().join("index.idx");
+        let data_path = dir.path().join("index.blobs");
+
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = (0..50)
+            .map(|i| (format!("key{:02}", i).into_bytes(), vec![i as u8; 1000]))
+            .collect();
+        {
+            let mut builder = BTreeDatStoreBuilder::create_split(&index_path, &data_path).unwrap();
+            for (key, value) in &entries {
+                builder.insert(key, value).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        let store = BTreeDatStore::open_split(&index_path, &data_path).unwrap();
+        assert_eq!(store.len(), entries.len());
+        for (key, value) in &entries {
+            assert_eq!(store.get(key).unwrap().as_ref(), Some(value));
+        }
+        assert_eq!(store.get(b"missing").unwrap(), None);
+
+        let index_len = std::fs::metadata(&index_path).unwrap().len();
+        let data_len = std::fs::metadata(&data_path).unwrap().len();
+        assert!(index_len * 10 < data_len, "{} vs {}", index_len, data_len);
+
+        // Values come from the data file, not the index
+        std::fs::write(&data_path, vec![0xffu8; data_len as usize]).unwrap();
+        let store = BTreeDatStore::open_split(&index_path, &data_path).unwrap();
+        assert_eq!(store.get(&entries[1].0).unwrap(), Some(vec![0xff; 1000]));
+
+        let err = BTreeDatStore::open(&index_path).err().unwrap();
+        assert!(err.to_string().contains("split"), "{}", err);
+    }
+
+    #[test]
+    fn test_checksums_catch_flipped_bytes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("index_btree.dat");
+        {
+            let mut builder = BTreeDatStoreBuilder::create(&path)
+                .unwrap()
+                .with_checksums()
+                .with_manifest(Some("fingerprint"));
+            for i in 0..50 {
+                builder
+                    .insert(format!("key{:02}", i).as_bytes(), &vec![i as u8; 100])
+                    .unwrap();
+            }
+            builder.finish().unwrap();
+        }
+        let original = std::fs::read(&path).unwrap();
+        let store = BTreeDatStore::open(&path).unwrap();
+        assert_eq!(store.get(b"key07").unwrap(), Some(vec![7u8; 100]));
+
+        // One byte in the offset table, one in an entry key, one in the heap
+        let blob_heap_offset = u64::from_le_bytes(original[16..24].try_into().unwrap()) as usize;
+        for (offset, region) in [
+            (HEADER_SIZE, "entries"),
+            (blob_heap_offset - 30, "entries"),
+            (blob_heap_offset + 150, "blob heap"),
+        ] {
+            let mut corrupt = original.clone();
+            corrupt[offset] ^= 0x01;
+            std::fs::write(&path, &corrupt).unwrap();
+
+            let err = BTreeDatStore::open(&path).err().unwrap().to_string();
+            assert!(err.contains("checksum mismatch"), "{}", err);
+            assert!(err.contains(region), "{}", err);
+        }
+
+        // Dropping the manifest trailer and part of the heap is caught too
+        let trailer_len = manifest::trailer_len(&original);
+        std::fs::write(&path, &original[..original.len() - trailer_len - 10]).unwrap();
+        let err = BTreeDatStore::open(&path).err().unwrap().to_string();
+        assert!(err.contains("checksum mismatch"), "{}", err);
+    }
+
+    #[test]
+    fn test_short_and_corrupt_files_error_instead_of_panicking() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        // Empty, and shorter than the header
+        for len in [0, 10, HEADER_SIZE - 1] {
+            let mut file = MAGIC.to_vec();
+            file.resize(len, 0);
+            std::fs::write(path, &file).unwrap();
+            let err = BTreeDatStore::open(path).err().unwrap();
+            assert!(err.to_string().contains("too small"), "{}", err);
+        }
+
+        {
+            let mut builder = BTreeDatStoreBuilder::create(path).unwrap();
+            builder.insert(b"key1", b"value1").unwrap();
+            builder.insert(b"key2", b"value2").unwrap();
+            builder.finish().unwrap();
+        }
+        let original = std::fs::read(path).unwrap();
+        let file_len = original.len() as u64;
+
+        // (btree_root_offset, blob_heap_offset) pairs that don't fit the file
+        for (root, heap) in [
+            (HEADER_SIZE as u64, file_len + 1),
+            (HEADER_SIZE as u64, u64::MAX),
+            (file_len, HEADER_SIZE as u64 + 16),
+            (u64::MAX, u64::MAX),
+            (0, file_len),
+        ] {
+            let mut corrupt = original.clone();
+            corrupt[8..16].copy_from_slice(&root.to_le_bytes());
+            corrupt[16..24].copy_from_slice(&heap.to_le_bytes());
+            std::fs::write(path, &corrupt).unwrap();
+            assert!(BTreeDatStore::open(path).is_err(), "{} {}", root, heap);
+        }
+
+        // Cutting off the end of the heap makes the last value's lookup fail
+        std::fs::write(path, &original[..original.len() - 3]).unwrap();
+        let store = BTreeDatStore::open(path).unwrap();
+        assert_eq!(store.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+        let err = store.get(b"key2").err().unwrap();
+        assert!(err.to_string().contains("runs past the end"), "{}", err);
+        assert!(store.get_ref(b"key2").is_err());
+    }
+
     #[test]
     fn test_btree_empty_store() {
         let temp_file = NamedTempFile::new().unwrap();
@@ -351,6 +1374,186 @@ mod tests {
         assert_eq!(store.keys().unwrap(), Vec::<Vec<u8>>::new());
     }
 
+    /// Keys that are prefixes of one another, share hundreds of bytes, or are empty
+    fn pathological_keys() -> Vec<Vec<u8>> {
+        let mut keys: Vec<Vec<u8>> = vec![Vec::new(), vec![0], vec![0, 0], vec![0xff; 3]];
+        for len in 1..=40 {
+            keys.push(vec![b'a'; len]);
+        }
+        let long_prefix = vec![b'p'; 1000];
+        for tail in [&b""[..], b"\0", b"a", b"ab", b"b", b"\xff\xff"] {
+            keys.push([&long_prefix[..], tail].concat());
+        }
+        keys
+    }
+
+    #[test]
+    fn test_front_coding_roundtrip_with_shared_prefixes() {
+        let keys = pathological_keys();
+        for restart_interval in [1, 3, 16, 1000] {
+            let temp_file = NamedTempFile::new().unwrap();
+            let path = temp_file.path();
+            {
+                let mut builder = BTreeDatStoreBuilder::create(path)
+                    .unwrap()
+                    .with_front_coding(restart_interval);
+                for (i, key) in keys.iter().enumerate() {
+                    builder.insert(key, format!("v{}", i).as_bytes()).unwrap();
+                }
+                builder.finish().unwrap();
+            }
+
+            let store = BTreeDatStore::open(path).unwrap();
+            assert_eq!(store.restart_interval, restart_interval);
+            assert_eq!(store.len(), keys.len());
+            for (i, key) in keys.iter().enumerate() {
+                assert_eq!(
+                    store.get(key).unwrap(),
+                    Some(format!("v{}", i).into_bytes()),
+                    "restart interval {}",
+                    restart_interval
+                );
+            }
+            for missing in [&[b'a'; 41][..], b"aab", b"b", &[0, 0, 0], &[b'p'; 999]] {
+                assert_eq!(store.get(missing).unwrap(), None);
+            }
+
+            let mut sorted = keys.clone();
+            sorted.sort();
+            assert_eq!(store.keys().unwrap(), sorted);
+            assert_eq!(store.prefix_count(b"aaa").unwrap(), 38);
+            assert_eq!(store.prefix_count(&[b'p'; 1000]).unwrap(), 6);
+            let range = store.range(b"aa", b"aaaa").unwrap();
+            assert_eq!(
+                range.into_iter().map(|(key, _)| key).collect::<Vec<_>>(),
+                vec![b"aa".to_vec(), b"aaa".to_vec()]
+            );
+        }
+    }
+
+    #[test]
+    fn test_front_coding_empty_key() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        {
+            let mut builder = BTreeDatStoreBuilder::create(path)
+                .unwrap()
+                .with_front_coding(4);
+            builder.insert(b"", b"empty").unwrap();
+            builder.finish().unwrap();
+        }
+
+        let store = BTreeDatStore::open(path).unwrap();
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.get(b"").unwrap(), Some(b"empty".to_vec()));
+        assert_eq!(store.get(b"a").unwrap(), None);
+        assert_eq!(store.keys().unwrap(), vec![Vec::<u8>::new()]);
+    }
+
+    #[test]
+    fn test_front_coding_shrinks_shared_prefix_index() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let plain_path = dir.path().join("plain.dat");
+        let coded_path = dir.path().join("coded.dat");
+        let keys: Vec<Vec<u8>> = (0..1000)
+            .map(|i| format!("https://example.com/some/long/shared/path/{:06}", i).into_bytes())
+            .collect();
+
+        let mut plain = BTreeDatStoreBuilder::create(&plain_path).unwrap();
+        let mut coded = BTreeDatStoreBuilder::create(&coded_path)
+            .unwrap()
+            .with_front_coding(16);
+        for key in &keys {
+            plain.insert(key, b"v").unwrap();
+            coded.insert(key, b"v").unwrap();
+        }
+        plain.finish().unwrap();
+        coded.finish().unwrap();
+
+        let plain_len = std::fs::metadata(&plain_path).unwrap().len();
+        let coded_len = std::fs::metadata(&coded_path).unwrap().len();
+        assert!(coded_len * 2 < plain_len, "{} vs {}", coded_len, plain_len);
+
+        let store = BTreeDatStore::open(&coded_path).unwrap();
+        for key in &keys {
+            assert_eq!(store.get(key).unwrap(), Some(b"v".to_vec()));
+        }
+    }
+
+    #[test]
+    fn test_front_coding_split_index_with_checksums() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let index_path = dir.path().join("index.idx");
+        let data_path = dir.path().join("index.data");
+        {
+            let mut builder = BTreeDatStoreBuilder::create_split(&index_path, &data_path)
+                .unwrap()
+                .with_checksums()
+                .with_front_coding(2);
+            for key in pathological_keys() {
+                builder.insert(&key, &key).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        assert!(BTreeDatStore::open(&index_path).is_err());
+        let store = BTreeDatStore::open_split(&index_path, &data_path).unwrap();
+        for key in pathological_keys() {
+            assert_eq!(store.get(&key).unwrap(), Some(key.clone()));
+        }
+    }
+
+    #[test]
+    fn test_front_coding_rejects_zero_restart_interval() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let builder = BTreeDatStoreBuilder::create(temp_file.path())
+            .unwrap()
+            .with_front_coding(0);
+        assert!(builder.finish().is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_front_coding_matches_plain(
+            keys in prop::collection::btree_set(prop_vec(0u8..3, 0..12), 0..80),
+            restart_interval in 1usize..10,
+            probe in prop_vec(0u8..3, 0..12)
+        ) {
+            let dir = tempfile::TempDir::new().unwrap();
+            let plain_path = dir.path().join("plain.dat");
+            let coded_path = dir.path().join("coded.dat");
+
+            let mut plain = BTreeDatStoreBuilder::create(&plain_path).unwrap();
+            let mut coded = BTreeDatStoreBuilder::create(&coded_path)
+                .unwrap()
+                .with_front_coding(restart_interval);
+            for key in &keys {
+                plain.insert(key, key).unwrap();
+                coded.insert(key, key).unwrap();
+            }
+            plain.finish().unwrap();
+            coded.finish().unwrap();
+
+            let plain = BTreeDatStore::open(&plain_path).unwrap();
+            let coded = BTreeDatStore::open(&coded_path).unwrap();
+            prop_assert_eq!(coded.len(), keys.len());
+            prop_assert_eq!(coded.keys().unwrap(), keys.iter().cloned().collect::<Vec<_>>());
+            for key in &keys {
+                prop_assert_eq!(coded.get(key).unwrap(), Some(key.clone()));
+            }
+            prop_assert_eq!(coded.get(&probe).unwrap(), plain.get(&probe).unwrap());
+            prop_assert_eq!(coded.prefix_count(&probe).unwrap(), plain.prefix_count(&probe).unwrap());
+            prop_assert_eq!(
+                coded.total_value_bytes().unwrap(),
+                plain.total_value_bytes().unwrap()
+            );
+            prop_assert_eq!(
+                coded.range(&probe, &[2; 12]).unwrap(),
+                plain.range(&probe, &[2; 12]).unwrap()
+            );
+        }
+    }
+
     proptest! {
         #[test]
         fn prop_btree_roundtrip_single(key in prop_vec(any::<u8>(), 1..100), value in prop_vec(any::<u8>(), 0..1000)) {
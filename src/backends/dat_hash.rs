@@ -1,23 +1,86 @@
-use crate::store::{BlobStore, BlobStoreBuilder};
+use super::checksum::{self, Checksums, CHECKSUM_FIELDS_LEN};
+use super::file_io::read_exact_at;
+use super::manifest::{self, DatManifest};
+use super::page_cache;
+use crate::store::{BlobStore, BlobStoreBuilder, LookupPhases};
 use anyhow::{bail, Context, Result};
-use std::cell::RefCell;
+use memmap2::Mmap;
 use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::fs::File;
 use std::hash::{Hash, Hasher};
 use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
-use std::path::Path;
-
-const MAGIC: &[u8; 8] = b"HASHIDX1";
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+
+mod read_tracker;
+use read_tracker::ReadTracker;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub use uring::HashDatUringStore;
+
+const MAGIC: &[u8; 8] = b"HASHIDX4";
+/// Files written before the optional bloom filter existed; readable, with the
+/// bloom header fields required to be 0
+const MAGIC_V3: &[u8; 8] = b"HASHIDX3";
+/// Files written before entries recorded their value length; readable, but not repairable
+const MAGIC_V2: &[u8; 8] = b"HASHIDX2";
+/// Files written before per-entry flags existed; read with all flags as 0
+const MAGIC_V1: &[u8; 8] = b"HASHIDX1";
+/// Index-only file whose blob heap lives in a separate data file (see `create_split`)
+const MAGIC_SPLIT: &[u8; 8] = b"HASHSPL3";
+/// Split index written before entries recorded their value length; readable
+/// with `open_split`
+const MAGIC_SPLIT_V2: &[u8; 8] = b"HASHSPL2";
+/// Values are zstd-compressed and entries also record the uncompressed length;
+/// read with `ZstdDatStore`
+const MAGIC_ZSTD: &[u8; 8] = b"HASHZST1";
+/// Bytes before the key in a current blob heap entry
+const ENTRY_HEADER_LEN: usize = 16;
+/// Bytes before the key in a `HASHZST1` blob heap entry
+const ZSTD_ENTRY_HEADER_LEN: usize = 24;
 const HEADER_SIZE: usize = 64;
 const BUCKET_SIZE: usize = 24; // key_hash (8) + blob_offset (8) + blob_len (8)
 const LOAD_FACTOR: f64 = 0.7; // Keep load factor below this
+/// Bloom filter sizing: ~1% false positives at 10 bits and 7 probes per key
+const BLOOM_BITS_PER_KEY: usize = 10;
+const BLOOM_HASH_COUNT: u32 = 7;
+/// Most probes a bloom section may ask for; more is corruption, not tuning
+const MAX_BLOOM_HASH_COUNT: u32 = 32;
+/// Bytes before the bit array in the bloom section: hash_count (4)
+const BLOOM_PREFIX_LEN: usize = 4;
+/// Header offset of the checksum fields (see `checksum`)
+const CHECKSUMS_OFFSET: usize = 49;
+/// Streaming chunk size when checking the blob heap's CRC32
+const CHECKSUM_CHUNK_SIZE: usize = 64 * 1024;
 
 /// Header layout:
 /// - magic: 8 bytes
 /// - bucket_count: 8 bytes (u64)
 /// - blob_heap_offset: 8 bytes (u64)
 /// - entry_count: 8 bytes (u64)
-/// - reserved: 32 bytes
+/// - hash_algo: 1 byte (u8, 0 = SipHash for files written before this field existed)
+/// - bloom_offset: 8 bytes (u64, 0 = no bloom filter)
+/// - bloom_len: 8 bytes (u64, 0 = no bloom filter)
+/// - checksums: 9 bytes (see `checksum`; the index CRC32 covers the bloom
+///   filter and bucket table)
+/// - reserved: 6 bytes
+///
+/// Split files (`HASHSPL3`, or `HASHSPL2` without value_len) contain only the
+/// header and bucket table; their blob_heap_offset is 0 and bucket blob offsets
+/// point into the data file.
+
+/// Bloom filter section (optional, see `HashDatStoreBuilder::with_bloom_filter`),
+/// written between the header and the bucket table, which then starts at
+/// `HEADER_SIZE + bloom_len`:
+/// - hash_count: 4 bytes (u32)
+/// - bits: rest of the section
+///
+/// Probe i sets bit `(h1 + i * h2) % bit_count`, with h1 the key's bucket hash
+/// and h2 derived from it, so checking the filter costs no extra key hashing.
 
 /// Bucket layout:
 /// - key_hash: 8 bytes (u64, 0 = empty)
@@ -26,18 +89,222 @@ const LOAD_FACTOR: f64 = 0.7; // Keep load factor below this
 
 /// Blob heap entry layout:
 /// - key_len: 4 bytes (u32)
+/// - flags: 4 bytes (u32, absent in HASHIDX1 files)
+/// - value_len: 8 bytes (u64, absent in HASHIDX1/HASHIDX2 files; the compressed
+///   length in HASHZST1 files)
+/// - raw_len: 8 bytes (u64, HASHZST1 only: the uncompressed value length)
 /// - key: variable
 /// - value: rest until blob_len
+///
+/// value_len makes the heap walkable without the bucket table (see `repair`).
+
+/// Length of the value in a `blob_len`-byte entry whose header and key take
+/// up the first `value_start` bytes.
+/// Errors if a corrupt bucket records an entry shorter than that.
+fn entry_value_len(blob_len: u64, value_start: u64) -> Result<usize> {
+    let value_len = blob_len.checked_sub(value_start).with_context(|| {
+        format!(
+            "Corrupt bucket: {}-byte entry is shorter than its {}-byte header and key",
+            blob_len, value_start
+        )
+    })?;
+    Ok(value_len as usize)
+}
+
+/// Split a blob heap entry into (key, flags, value).
+/// `entry_header_len` is 16 for current (and HASHIDX3) files, 24 for HASHZST1,
+/// 8 for HASHIDX2 and 4 for HASHIDX1 files.
+/// Errors if the entry is too short for its header or recorded key length.
+fn split_entry(blob: &[u8], entry_header_len: usize) -> Result<(&[u8], u32, &[u8])> {
+    if blob.len() < entry_header_len {
+        bail!(
+            "Corrupt entry: {} bytes is shorter than the {}-byte entry header",
+            blob.len(),
+            entry_header_len
+        );
+    }
+    let key_len = u32::from_le_bytes(blob[0..4].try_into().unwrap()) as usize;
+    let flags = if entry_header_len >= 8 {
+        u32::from_le_bytes(blob[4..8].try_into().unwrap())
+    } else {
+        0
+    };
+    let key = entry_header_len
+        .checked_add(key_len)
+        .and_then(|key_end| blob.get(entry_header_len..key_end))
+        .with_context(|| {
+            format!(
+                "Corrupt entry: key_len {} exceeds the {}-byte entry",
+                key_len,
+                blob.len()
+            )
+        })?;
+    let value = &blob[entry_header_len + key_len..];
+    Ok((key, flags, value))
+}
+
+/// Hash function used to place keys into buckets. Recorded in the header so the
+/// reader always probes with the same function the builder used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum HashAlgo {
+    /// std's `DefaultHasher` (SipHash-1-3)
+    #[default]
+    SipHash,
+    /// FNV-1a, 64-bit
+    Fnv,
+}
+
+impl HashAlgo {
+    fn id(self) -> u8 {
+        match self {
+            HashAlgo::SipHash => 0,
+            HashAlgo::Fnv => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(HashAlgo::SipHash),
+            1 => Some(HashAlgo::Fnv),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgo::SipHash => "SipHash",
+            HashAlgo::Fnv => "FNV-1a",
+        }
+    }
+
+    fn hash(self, key: &[u8]) -> u64 {
+        match self {
+            HashAlgo::SipHash => {
+                let mut hasher = DefaultHasher::new();
+                key.hash(&mut hasher);
+                hasher.finish()
+            }
+            HashAlgo::Fnv => {
+                const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+                const FNV_PRIME: u64 = 0x100000001b3;
+                key.iter().fold(FNV_OFFSET_BASIS, |h, &b| {
+                    (h ^ b as u64).wrapping_mul(FNV_PRIME)
+                })
+            }
+        }
+    }
+}
+
+/// Second hash for the bloom filter's double hashing (SplitMix64 finalizer),
+/// forced odd so successive probes don't repeat
+fn bloom_step(key_hash: u64) -> u64 {
+    let mut z = key_hash.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    (z ^ (z >> 31)) | 1
+}
+
+/// Bit positions probed for `key_hash` in a filter of `bit_count` bits
+fn bloom_bits(key_hash: u64, hash_count: u32, bit_count: u64) -> impl Iterator<Item = u64> {
+    let step = bloom_step(key_hash);
+    (0..hash_count as u64).map(move |i| key_hash.wrapping_add(i.wrapping_mul(step)) % bit_count)
+}
+
+/// Build a bloom section over the given key hashes, sized from their count
+fn build_bloom(key_hashes: &[u64]) -> Vec<u8> {
+    let bit_bytes = (key_hashes.len() * BLOOM_BITS_PER_KEY).div_ceil(8).max(8);
+    let mut section = vec![0u8; BLOOM_PREFIX_LEN + bit_bytes];
+    section[..BLOOM_PREFIX_LEN].copy_from_slice(&BLOOM_HASH_COUNT.to_le_bytes());
+    let bits = &mut section[BLOOM_PREFIX_LEN..];
+    let bit_count = bits.len() as u64 * 8;
+    for &key_hash in key_hashes {
+        for bit in bloom_bits(key_hash, BLOOM_HASH_COUNT, bit_count) {
+            bits[(bit / 8) as usize] |= 1 << (bit % 8);
+        }
+    }
+    section
+}
+
+/// Check a bloom `section` read from a file before lookups probe it: its
+/// hash_count must be within `1..=MAX_BLOOM_HASH_COUNT`, or every lookup could
+/// spin through billions of probes. An empty section (no filter) is fine.
+fn check_bloom(section: &[u8]) -> Result<()> {
+    if section.is_empty() {
+        return Ok(());
+    }
+    let hash_count = u32::from_le_bytes(section[..BLOOM_PREFIX_LEN].try_into().unwrap());
+    if !(1..=MAX_BLOOM_HASH_COUNT).contains(&hash_count) {
+        bail!(
+            "Corrupt hash dat bloom filter: hash_count {} is outside 1..={}",
+            hash_count,
+            MAX_BLOOM_HASH_COUNT
+        );
+    }
+    Ok(())
+}
+
+/// Whether a key with `key_hash` may be in the set the bloom `section` was
+/// built from. Never false for a key that is; an empty section (no filter)
+/// lets every key through.
+fn bloom_may_contain(section: &[u8], key_hash: u64) -> bool {
+    if section.is_empty() {
+        return true;
+    }
+    let hash_count = u32::from_le_bytes(section[..BLOOM_PREFIX_LEN].try_into().unwrap());
+    let bits = &section[BLOOM_PREFIX_LEN..];
+    let bit_count = bits.len() as u64 * 8;
+    bloom_bits(key_hash, hash_count, bit_count)
+        .all(|bit| bits[(bit / 8) as usize] & (1 << (bit % 8)) != 0)
+}
+
+/// Seekable byte source a `HashDatStore` can read from: a `File`, an in-memory
+/// `Cursor`, or anything else that serves reads at arbitrary offsets.
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// Where a `HashDatStore` reads blob data from
+enum DataSource {
+    /// Read with positional reads, so concurrent lookups never share a cursor
+    File(Arc<File>),
+    /// A `from_reader` source; lookups take turns seeking its one cursor
+    Reader(Mutex<Box<dyn ReadSeek>>),
+}
+
+impl DataSource {
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> std::io::Result<()> {
+        match self {
+            DataSource::File(file) => read_exact_at(file, buf, offset),
+            DataSource::Reader(reader) => {
+                let mut reader = reader.lock().unwrap();
+                reader.seek(SeekFrom::Start(offset))?;
+                reader.read_exact(buf)
+            }
+        }
+    }
+}
 
 /// Hash .dat store with an in-memory lookup table and disk-based blob reads.
-/// (No mmap.) Buckets are read into RAM on open; blob data is read via disk seeks.
+/// (No mmap.) Buckets are read into RAM on open; blob data is read with
+/// positional reads, so `get` can run from several threads at once.
 pub struct HashDatStore {
     /// Parsed hash buckets loaded into memory at open()
     buckets: Vec<Bucket>,
-    /// File handle for reading blob data via seeks
-    data_file: RefCell<File>,
+    /// Source for reading blob data
+    data_file: DataSource,
     bucket_count: u64,
     entry_count: usize,
+    hash_algo: HashAlgo,
+    /// Bytes before the key in each blob heap entry
+    entry_header_len: usize,
+    /// Bloom filter section loaded at open(), empty if the file has none
+    bloom: Vec<u8>,
+    /// Pages and bytes read since access tracking started
+    read_tracker: RwLock<Option<ReadTracker>>,
+    /// Set once tracking starts, so untracked reads skip the `read_tracker` lock
+    tracking: AtomicBool,
+    /// End of the furthest blob heap entry, which bounds what lookups can read
+    heap_end: u64,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -47,11 +314,206 @@ struct Bucket {
     blob_len: u64,
 }
 
+impl Bucket {
+    /// Parse a bucket from its 24-byte on-disk form
+    fn parse(data: &[u8]) -> Self {
+        Self {
+            key_hash: u64::from_le_bytes(data[0..8].try_into().unwrap()),
+            blob_offset: u64::from_le_bytes(data[8..16].try_into().unwrap()),
+            blob_len: u64::from_le_bytes(data[16..24].try_into().unwrap()),
+        }
+    }
+}
+
+/// Parsed and validated file header
+struct Header {
+    bucket_count: u64,
+    blob_heap_offset: u64,
+    entry_count: usize,
+    hash_algo: HashAlgo,
+    entry_header_len: usize,
+    /// Blob heap is stored in a separate data file
+    split: bool,
+    /// Values are zstd-compressed (`HASHZST1`)
+    compressed: bool,
+    /// Length of the bloom filter section at `HEADER_SIZE` (0 = none)
+    bloom_len: usize,
+    /// CRC32s of the index and blob heap, if the file was built with them
+    checksums: Option<Checksums>,
+}
+
+impl Header {
+    /// Parse the header and check that the bucket table it describes fits in a
+    /// file of `file_len` bytes, so a corrupt `bucket_count` can't trigger a huge
+    /// allocation before anything is read.
+    fn parse(header: &[u8], file_len: u64) -> Result<Self> {
+        if header.len() < HEADER_SIZE {
+            bail!("File too small for header");
+        }
+
+        let (entry_header_len, split) = match &header[0..8] {
+            m if m == MAGIC || m == MAGIC_V3 => (ENTRY_HEADER_LEN, false),
+            m if m == MAGIC_ZSTD => (ZSTD_ENTRY_HEADER_LEN, false),
+            m if m == MAGIC_V2 => (8, false),
+            m if m == MAGIC_V1 => (4, false),
+            m if m == MAGIC_SPLIT => (ENTRY_HEADER_LEN, true),
+            m if m == MAGIC_SPLIT_V2 => (8, true),
+            _ => bail!("Invalid magic number"),
+        };
+        let compressed = &header[0..8] == MAGIC_ZSTD;
+
+        let bucket_count = u64::from_le_bytes(header[8..16].try_into().unwrap());
+        let blob_heap_offset = u64::from_le_bytes(header[16..24].try_into().unwrap());
+        let entry_count = u64::from_le_bytes(header[24..32].try_into().unwrap()) as usize;
+        let hash_algo = HashAlgo::from_id(header[32])
+            .with_context(|| format!("Unknown hash algorithm id {}", header[32]))?;
+
+        let bloom_offset = u64::from_le_bytes(header[33..41].try_into().unwrap());
+        let bloom_len = u64::from_le_bytes(header[41..49].try_into().unwrap());
+
+        if bucket_count == 0 {
+            bail!("Corrupt hash dat header: bucket_count is 0");
+        }
+        if &header[0..8] == MAGIC_V3 && (bloom_offset != 0 || bloom_len != 0) {
+            bail!("Corrupt hash dat header: HASHIDX3 files have no bloom filter");
+        }
+        if bloom_len != 0
+            && (bloom_offset != HEADER_SIZE as u64
+                || bloom_len <= BLOOM_PREFIX_LEN as u64
+                || bloom_len > file_len)
+        {
+            bail!(
+                "Corrupt hash dat header: bloom filter at offset {} with length {}",
+                bloom_offset,
+                bloom_len
+            );
+        }
+
+        let expected_blob_heap_offset = bucket_count
+            .checked_mul(BUCKET_SIZE as u64)
+            .and_then(|table_len| table_len.checked_add(HEADER_SIZE as u64 + bloom_len))
+            .filter(|&end| end <= file_len)
+            .with_context(|| {
+                format!(
+                    "Corrupt hash dat header: bucket_count {} needs a bucket table larger than \
+                     the {}-byte file",
+                    bucket_count, file_len
+                )
+            })?;
+        let expected_blob_heap_offset = if split { 0 } else { expected_blob_heap_offset };
+        if blob_heap_offset != expected_blob_heap_offset {
+            bail!(
+                "Invalid blob_heap_offset: expected {}, got {}",
+                expected_blob_heap_offset,
+                blob_heap_offset
+            );
+        }
+
+        Ok(Self {
+            bucket_count,
+            blob_heap_offset,
+            entry_count,
+            hash_algo,
+            entry_header_len,
+            split,
+            compressed,
+            bloom_len: bloom_len as usize,
+            checksums: Checksums::parse(
+                &header[CHECKSUMS_OFFSET..CHECKSUMS_OFFSET + CHECKSUM_FIELDS_LEN],
+            ),
+        })
+    }
+
+    /// End of the bucket table, where a single-file blob heap starts
+    fn index_end(&self) -> usize {
+        self.bucket_table_offset() + self.bucket_count as usize * BUCKET_SIZE
+    }
+
+    /// (start, end) of the blob heap: from blob_heap_offset (0 in a split data
+    /// file) to the end of the furthest entry
+    fn heap_span(&self, buckets: &[Bucket]) -> (u64, u64) {
+        let start = self.blob_heap_offset;
+        let end = buckets
+            .iter()
+            .filter(|bucket| bucket.key_hash != 0)
+            .map(|bucket| bucket.blob_offset.saturating_add(bucket.blob_len))
+            .max()
+            .unwrap_or(start);
+        (start, end.max(start))
+    }
+
+    /// Check the CRC32 of the bloom filter and bucket table, given as
+    /// consecutive `parts`, if the file has one
+    fn verify_index_checksum(&self, parts: &[&[u8]]) -> Result<()> {
+        let Some(sums) = self.checksums else {
+            return Ok(());
+        };
+        let mut hasher = crc32fast::Hasher::new();
+        for part in parts {
+            hasher.update(part);
+        }
+        checksum::check("Hash dat", "bucket table", sums.index, hasher.finalize())
+    }
+
+    /// Check the blob heap's CRC32, if the file has one, streaming the heap
+    /// from `source` rather than loading it
+    fn verify_heap_checksum(&self, buckets: &[Bucket], source: &mut impl ReadSeek) -> Result<()> {
+        let Some(sums) = self.checksums else {
+            return Ok(());
+        };
+        let (start, end) = self.heap_span(buckets);
+        source.seek(SeekFrom::Start(start))?;
+        let mut hasher = crc32fast::Hasher::new();
+        let mut chunk = vec![0u8; CHECKSUM_CHUNK_SIZE];
+        let mut remaining = end - start;
+        while remaining > 0 {
+            let len = remaining.min(chunk.len() as u64) as usize;
+            source
+                .read_exact(&mut chunk[..len])
+                .context("Hash dat blob heap is truncated")?;
+            hasher.update(&chunk[..len]);
+            remaining -= len as u64;
+        }
+        checksum::check("Hash dat", "blob heap", sums.heap, hasher.finalize())
+    }
+
+    /// `verify_heap_checksum` for a file that is already mapped
+    fn verify_mapped_heap_checksum(&self, buckets: &[Bucket], data: &[u8]) -> Result<()> {
+        let Some(sums) = self.checksums else {
+            return Ok(());
+        };
+        let (start, end) = self.heap_span(buckets);
+        let heap = data
+            .get(start as usize..end as usize)
+            .context("Hash dat blob heap is truncated")?;
+        checksum::check("Hash dat", "blob heap", sums.heap, crc32fast::hash(heap))
+    }
+
+    /// Offset of the bucket table, after the header and any bloom filter
+    fn bucket_table_offset(&self) -> usize {
+        HEADER_SIZE + self.bloom_len
+    }
+
+    /// Reject split index files in `open`, which expects the blob heap in the same file
+    fn require_single_file(&self) -> Result<()> {
+        if self.split {
+            bail!("Hash dat file is a split index; open it with HashDatStore::open_split");
+        }
+        self.require_plain_values()
+    }
+
+    /// Reject compressed files in readers that would return the stored bytes as values
+    fn require_plain_values(&self) -> Result<()> {
+        if self.compressed {
+            bail!("Hash dat file holds zstd-compressed values; open it with ZstdDatStore");
+        }
+        Ok(())
+    }
+}
+
 impl HashDatStore {
-    fn hash_key(key: &[u8]) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        key.hash(&mut hasher);
-        let h = hasher.finish();
+    fn hash_key(algo: HashAlgo, key: &[u8]) -> u64 {
+        let h = algo.hash(key);
         // Ensure non-zero (0 means empty bucket)
         if h == 0 {
             1
@@ -60,6 +522,11 @@ impl HashDatStore {
         }
     }
 
+    /// Hash algorithm recorded in the file header
+    pub fn hash_algo(&self) -> HashAlgo {
+        self.hash_algo
+    }
+
     fn get_bucket(&self, index: usize) -> (u64, u64, u64) {
         let b = self.buckets[index];
         (b.key_hash, b.blob_offset, b.blob_len)
@@ -67,15 +534,30 @@ impl HashDatStore {
 
     /// Read data from file at the given offset (disk seek)
     fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
-        let mut file = self.data_file.borrow_mut();
-        file.seek(SeekFrom::Start(offset))?;
-        let mut buf = vec![0u8; len];
-        file.read_exact(&mut buf)?;
+        let mut buf = Vec::new();
+        self.read_into(offset, len, &mut buf)?;
         Ok(buf)
     }
 
+    /// Read `len` bytes at `offset` into `buf`, replacing its contents and
+    /// reusing its allocation
+    fn read_into(&self, offset: u64, len: usize, buf: &mut Vec<u8>) -> Result<()> {
+        buf.clear();
+        buf.resize(len, 0);
+        self.data_file.read_exact_at(buf, offset)?;
+        if self.tracking.load(Ordering::Acquire) {
+            if let Some(tracker) = self.read_tracker.read().unwrap().as_ref() {
+                tracker.record(offset, len as u64);
+            }
+        }
+        Ok(())
+    }
+
     fn find_key(&self, key: &[u8]) -> Result<Option<(u64, u64)>> {
-        let key_hash = Self::hash_key(key);
+        let key_hash = Self::hash_key(self.hash_algo, key);
+        if !bloom_may_contain(&self.bloom, key_hash) {
+            return Ok(None);
+        }
         let bucket_count = self.bucket_count as usize;
         let mut index = (key_hash as usize) % bucket_count;
 
@@ -87,17 +569,21 @@ impl HashDatStore {
                 return Ok(None);
             }
 
-            if stored_hash == key_hash {
+            // An entry too short to hold this key can't match; skip the disk read
+            let fits = blob_len >= (self.entry_header_len + key.len()) as u64;
+            if stored_hash == key_hash && fits {
                 // Potential match, verify key in blob heap via disk read
                 // Read just the key_len first (4 bytes)
                 let key_len_buf = self.read_at(blob_offset, 4)?;
                 let key_len = u32::from_le_bytes(key_len_buf[0..4].try_into().unwrap()) as usize;
 
-                // Read the actual key
-                let stored_key = self.read_at(blob_offset + 4, key_len)?;
-
-                if stored_key == key {
-                    return Ok(Some((blob_offset, blob_len)));
+                // Only read the key bytes when the lengths agree
+                if key_len == key.len() {
+                    let stored_key =
+                        self.read_at(blob_offset + self.entry_header_len as u64, key_len)?;
+                    if stored_key == key {
+                        return Ok(Some((blob_offset, blob_len)));
+                    }
                 }
             }
 
@@ -108,84 +594,259 @@ impl HashDatStore {
         Ok(None)
     }
 
-    fn get_blob(&self, offset: u64, len: u64) -> Result<Vec<u8>> {
+    /// Read a blob heap entry and return its (value, flags)
+    fn get_blob(&self, offset: u64, len: u64) -> Result<(Vec<u8>, u32)> {
         // Read the entire blob entry
         let blob_data = self.read_at(offset, len as usize)?;
+        let (_, flags, value) = split_entry(&blob_data, self.entry_header_len)?;
+        Ok((value.to_vec(), flags))
+    }
 
-        let key_len = u32::from_le_bytes(blob_data[0..4].try_into().unwrap()) as usize;
-        let value_start = 4 + key_len;
-        let value_len = len as usize - 4 - key_len;
+    /// Like `get`, but also returns the entry's flags (0 unless set at build time)
+    pub fn get_with_flags(&self, key: &[u8]) -> Result<Option<(Vec<u8>, u32)>> {
+        match self.find_key(key)? {
+            Some((offset, len)) => Ok(Some(self.get_blob(offset, len)?)),
+            None => Ok(None),
+        }
+    }
 
-        Ok(blob_data[value_start..value_start + value_len].to_vec())
+    /// Open a `HASHZST1` file, whose values `get` would return still compressed;
+    /// `ZstdDatStore` reads them through `get_compressed`.
+    pub(crate) fn open_compressed(path: &Path) -> Result<Self> {
+        let mut file = File::open(path).context("Failed to open hash dat file")?;
+        let (header, bloom, buckets) = Self::read_index(&mut file)?;
+        if !header.compressed {
+            bail!("Hash dat file is not zstd-compressed; open it with HashDatStore::open");
+        }
+        header.verify_heap_checksum(&buckets, &mut file)?;
+        Ok(Self::from_parts(
+            header,
+            bloom,
+            buckets,
+            DataSource::File(Arc::new(file)),
+        ))
     }
-}
 
-impl BlobStore for HashDatStore {
-    fn open(path: &Path) -> Result<Self> {
-        // Read header (no mmap)
-        let mut header_file = File::open(path).context("Failed to open hash dat file")?;
-        let mut header = [0u8; HEADER_SIZE];
-        header_file
-            .read_exact(&mut header)
-            .context("Failed to read hash dat header")?;
+    /// The stored (compressed) value for `key` in a `HASHZST1` file, and its
+    /// uncompressed length from the entry header
+    pub(crate) fn get_compressed(&self, key: &[u8]) -> Result<Option<(Vec<u8>, u64)>> {
+        let Some((offset, len)) = self.find_key(key)? else {
+            return Ok(None);
+        };
+        let mut entry = self.read_at(offset, len as usize)?;
+        let raw_len = u64::from_le_bytes(entry[16..24].try_into().unwrap());
+        entry.drain(..self.entry_header_len + key.len());
+        Ok(Some((entry, raw_len)))
+    }
 
-        if &header[0..8] != MAGIC {
-            bail!("Invalid magic number");
+    /// Total stored (compressed) and uncompressed value bytes of a `HASHZST1`
+    /// file, summed from the entry headers without reading any key or value
+    pub(crate) fn compressed_totals(&self) -> Result<(u64, u64)> {
+        let (mut stored, mut uncompressed) = (0u64, 0u64);
+        for bucket in self.buckets.iter().filter(|b| b.key_hash != 0) {
+            if bucket.blob_len < ZSTD_ENTRY_HEADER_LEN as u64 {
+                bail!(
+                    "Corrupt entry: {} bytes is shorter than the {}-byte entry header",
+                    bucket.blob_len,
+                    ZSTD_ENTRY_HEADER_LEN
+                );
+            }
+            let header = self.read_at(bucket.blob_offset, ZSTD_ENTRY_HEADER_LEN)?;
+            stored += u64::from_le_bytes(header[8..16].try_into().unwrap());
+            uncompressed += u64::from_le_bytes(header[16..24].try_into().unwrap());
         }
+        Ok((stored, uncompressed))
+    }
+}
 
-        let bucket_count = u64::from_le_bytes(header[8..16].try_into().unwrap());
-        let blob_heap_offset = u64::from_le_bytes(header[16..24].try_into().unwrap());
-        let entry_count = u64::from_le_bytes(header[24..32].try_into().unwrap()) as usize;
-
-        let expected_blob_heap_offset = (HEADER_SIZE + bucket_count as usize * BUCKET_SIZE) as u64;
-        if blob_heap_offset != expected_blob_heap_offset {
-            bail!(
-                "Invalid blob_heap_offset: expected {}, got {}",
-                expected_blob_heap_offset,
-                blob_heap_offset
-            );
+impl HashDatStore {
+    /// Open an index written by `HashDatStoreBuilder::create_split`: buckets are
+    /// loaded from `index_path` and values are read from `data_path`.
+    pub fn open_split(index_path: &Path, data_path: &Path) -> Result<Self> {
+        let mut index_file = File::open(index_path).context("Failed to open hash dat file")?;
+        let (header, bloom, buckets) = Self::read_index(&mut index_file)?;
+        if !header.split {
+            bail!("Hash dat file is not a split index; open it with HashDatStore::open");
         }
+        let mut data_file = File::open(data_path).context("Failed to open hash dat data file")?;
+        header.verify_heap_checksum(&buckets, &mut data_file)?;
+        Ok(Self::from_parts(
+            header,
+            bloom,
+            buckets,
+            DataSource::File(Arc::new(data_file)),
+        ))
+    }
+
+    /// Open a single-file index from any seekable source. The header and buckets
+    /// are read up front; values are then read from `source` on each lookup.
+    pub fn from_reader(mut source: Box<dyn ReadSeek>) -> Result<Self> {
+        let (header, bloom, buckets) = Self::read_index(&mut source)?;
+        header.require_single_file()?;
+        header.verify_heap_checksum(&buckets, &mut source)?;
+        Ok(Self::from_parts(
+            header,
+            bloom,
+            buckets,
+            DataSource::Reader(Mutex::new(source)),
+        ))
+    }
+
+    /// Read and validate the header, then load the bloom filter (empty if the
+    /// file has none) and the bucket table into memory, checking their CRC32
+    /// if the file has one
+    fn read_index(source: &mut impl ReadSeek) -> Result<(Header, Vec<u8>, Vec<Bucket>)> {
+        // Read header (no mmap)
+        let file_len = source
+            .seek(SeekFrom::End(0))
+            .context("Failed to find hash dat length")?;
+        source.seek(SeekFrom::Start(0))?;
+        let mut header_bytes = [0u8; HEADER_SIZE];
+        source
+            .read_exact(&mut header_bytes)
+            .context("Failed to read hash dat header")?;
+        let header = Header::parse(&header_bytes, file_len)?;
+
+        let mut bloom = vec![0u8; header.bloom_len];
+        source
+            .read_exact(&mut bloom)
+            .context("Failed to read hash dat bloom filter")?;
+        check_bloom(&bloom)?;
 
         // Read and parse buckets into memory.
-        let bucket_bytes_len = bucket_count as usize * BUCKET_SIZE;
+        let bucket_bytes_len = header.bucket_count as usize * BUCKET_SIZE;
         let mut bucket_bytes = vec![0u8; bucket_bytes_len];
-        header_file
+        source
             .read_exact(&mut bucket_bytes)
             .context("Failed to read hash buckets")?;
+        header.verify_index_checksum(&[&bloom, &bucket_bytes])?;
 
-        let mut buckets = Vec::with_capacity(bucket_count as usize);
-        for i in 0..bucket_count as usize {
-            let off = i * BUCKET_SIZE;
-            let data = &bucket_bytes[off..off + BUCKET_SIZE];
+        let buckets: Vec<Bucket> = bucket_bytes
+            .chunks_exact(BUCKET_SIZE)
+            .map(Bucket::parse)
+            .collect();
 
-            let key_hash = u64::from_le_bytes(data[0..8].try_into().unwrap());
-            let blob_offset = u64::from_le_bytes(data[8..16].try_into().unwrap());
-            let blob_len = u64::from_le_bytes(data[16..24].try_into().unwrap());
-            buckets.push(Bucket {
-                key_hash,
-                blob_offset,
-                blob_len,
-            });
-        }
+        Ok((header, bloom, buckets))
+    }
 
-        // Open another file handle for data reads
-        let data_file = File::open(path).context("Failed to open hash dat file for data reads")?;
+    fn from_parts(
+        header: Header,
+        bloom: Vec<u8>,
+        buckets: Vec<Bucket>,
+        data_file: DataSource,
+    ) -> Self {
+        let (_, heap_end) = header.heap_span(&buckets);
+        Self {
+            buckets,
+            bloom,
+            data_file,
+            bucket_count: header.bucket_count,
+            entry_count: header.entry_count,
+            hash_algo: header.hash_algo,
+            entry_header_len: header.entry_header_len,
+            read_tracker: RwLock::new(None),
+            tracking: AtomicBool::new(false),
+            heap_end,
+        }
+    }
+}
 
-        Ok(Self {
+impl BlobStore for HashDatStore {
+    fn open(path: &Path) -> Result<Self> {
+        let mut file = File::open(path).context("Failed to open hash dat file")?;
+        let (header, bloom, buckets) = Self::read_index(&mut file)?;
+        header.require_single_file()?;
+        header.verify_heap_checksum(&buckets, &mut file)?;
+        Ok(Self::from_parts(
+            header,
+            bloom,
             buckets,
-            data_file: RefCell::new(data_file),
-            bucket_count,
-            entry_count,
-        })
+            DataSource::File(Arc::new(file)),
+        ))
     }
 
     fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
-        match self.find_key(key)? {
-            Some((offset, len)) => Ok(Some(self.get_blob(offset, len)?)),
-            None => Ok(None),
+        Ok(self.get_with_flags(key)?.map(|(value, _)| value))
+    }
+
+    /// Reads only the value bytes, straight into `buf`; the matched key's
+    /// length gives where they start within the entry.
+    fn get_into(&self, key: &[u8], buf: &mut Vec<u8>) -> Result<bool> {
+        let Some((offset, len)) = self.find_key(key)? else {
+            buf.clear();
+            return Ok(false);
+        };
+        let value_start = (self.entry_header_len + key.len()) as u64;
+        self.read_into(
+            offset + value_start,
+            entry_value_len(len, value_start)?,
+            buf,
+        )?;
+        Ok(true)
+    }
+
+    /// Probes the bucket table and compares the stored key; the value is never read.
+    fn contains_key(&self, key: &[u8]) -> Result<bool> {
+        Ok(self.find_key(key)?.is_some())
+    }
+
+    /// The bucket's `blob_len` covers the entry header and key as well as the value.
+    fn value_len(&self, key: &[u8]) -> Result<Option<usize>> {
+        let value_start = (self.entry_header_len + key.len()) as u64;
+        self.find_key(key)?
+            .map(|(_, len)| entry_value_len(len, value_start))
+            .transpose()
+    }
+
+    /// Evicts the data file; the bucket table stays in memory. Stores read
+    /// through `from_reader` have no file to evict.
+    fn drop_caches(&self) -> Result<bool> {
+        match &self.data_file {
+            DataSource::File(file) => page_cache::evict(file, None),
+            DataSource::Reader(_) => Ok(false),
         }
     }
 
+    /// Tracks the blob heap reads made by lookups, in 4 KiB pages (see
+    /// `ReadTracker`); the bucket table is read once at open and isn't counted.
+    fn start_access_tracking(&self) {
+        *self.read_tracker.write().unwrap() = Some(ReadTracker::new(self.heap_end));
+        self.tracking.store(true, Ordering::Release);
+    }
+
+    fn accessed_bytes(&self) -> Option<u64> {
+        self.read_tracker
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(ReadTracker::accessed_bytes)
+    }
+
+    fn bytes_read(&self) -> Option<u64> {
+        self.read_tracker
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(ReadTracker::bytes_read)
+    }
+
+    /// The index phase covers probing the in-memory bucket table, including the
+    /// disk reads that confirm a hash match; the IO phase is the value read.
+    fn get_with_phases(&self, key: &[u8]) -> Result<(Option<Vec<u8>>, Option<LookupPhases>)> {
+        let index_start = Instant::now();
+        let found = self.find_key(key)?;
+        let index = index_start.elapsed();
+
+        let io_start = Instant::now();
+        let value = match found {
+            Some((offset, len)) => Some(self.get_blob(offset, len)?.0),
+            None => None,
+        };
+        let io = io_start.elapsed();
+
+        Ok((value, Some(LookupPhases { index, io })))
+    }
+
     fn keys(&self) -> Result<Vec<Vec<u8>>> {
         let mut keys = Vec::with_capacity(self.entry_count);
         let bucket_count = self.bucket_count as usize;
@@ -196,16 +857,35 @@ impl BlobStore for HashDatStore {
             if key_hash != 0 {
                 // Read blob from disk to extract key
                 let blob_data = self.read_at(blob_offset, blob_len as usize)?;
-
-                let key_len = u32::from_le_bytes(blob_data[0..4].try_into().unwrap()) as usize;
-                let key = blob_data[4..4 + key_len].to_vec();
-                keys.push(key);
+                let (key, _, _) = split_entry(&blob_data, self.entry_header_len)?;
+                keys.push(key.to_vec());
             }
         }
 
         Ok(keys)
     }
 
+    /// Reads only each entry's key_len; the value is whatever follows the key.
+    fn total_value_bytes(&self) -> Result<u64> {
+        let mut total = 0u64;
+        for i in 0..self.bucket_count as usize {
+            let (key_hash, blob_offset, blob_len) = self.get_bucket(i);
+            if key_hash != 0 {
+                let key_len_buf = self.read_at(blob_offset, 4)?;
+                let key_len = u32::from_le_bytes(key_len_buf[0..4].try_into().unwrap()) as u64;
+                total += blob_len
+                    .checked_sub(self.entry_header_len as u64 + key_len)
+                    .with_context(|| {
+                        format!(
+                            "Corrupt entry at offset {}: key_len {} exceeds the {}-byte entry",
+                            blob_offset, key_len, blob_len
+                        )
+                    })?;
+            }
+        }
+        Ok(total)
+    }
+
     fn len(&self) -> usize {
         self.entry_count
     }
@@ -215,97 +895,537 @@ impl BlobStore for HashDatStore {
     }
 }
 
-/// Builder for hash .dat store.
-pub struct HashDatStoreBuilder {
-    path: std::path::PathBuf,
-    entries: Vec<(Vec<u8>, Vec<u8>)>,
+/// Hash .dat store that memory-maps the file and reads buckets lazily.
+/// `open` only validates the header, so it costs the same regardless of table size;
+/// each probe parses its 24-byte bucket straight out of the mapping instead of from
+/// an eagerly-built `Vec<Bucket>`. Blobs are sliced from the same mapping.
+pub struct HashDatMmapStore {
+    mmap: Mmap,
+    /// The mapped file, kept open to evict it from the page cache
+    file: File,
+    /// Start of the bucket table, after the header and any bloom filter
+    bucket_table_offset: usize,
+    bucket_count: u64,
+    entry_count: usize,
+    hash_algo: HashAlgo,
+    entry_header_len: usize,
 }
 
-impl BlobStoreBuilder for HashDatStoreBuilder {
-    fn create(path: &Path) -> Result<Self> {
-        Ok(Self {
-            path: path.to_path_buf(),
-            entries: Vec::new(),
-        })
+impl HashDatMmapStore {
+    /// Hash algorithm recorded in the file header
+    pub fn hash_algo(&self) -> HashAlgo {
+        self.hash_algo
     }
 
-    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
-        self.entries.push((key.to_vec(), value.to_vec()));
-        Ok(())
+    fn get_bucket(&self, index: usize) -> Bucket {
+        let off = self.bucket_table_offset + index * BUCKET_SIZE;
+        Bucket::parse(&self.mmap[off..off + BUCKET_SIZE])
     }
 
-    fn finish(self) -> Result<()> {
-        let file = File::create(&self.path).context("Failed to create hash dat file")?;
-        let mut writer = BufWriter::new(file);
-
-        let entry_count = self.entries.len();
-        let bucket_count = ((entry_count as f64 / LOAD_FACTOR).ceil() as usize).max(1);
-
-        // Write header placeholder
-        writer.write_all(&[0u8; HEADER_SIZE])?;
-
-        // Initialize buckets
-        let mut buckets: Vec<(u64, u64, u64)> = vec![(0, 0, 0); bucket_count];
-
-        // Calculate blob heap offset
-        let blob_heap_offset = (HEADER_SIZE + bucket_count * BUCKET_SIZE) as u64;
-        let mut current_blob_offset = blob_heap_offset;
-
-        // Build blob heap entries and populate buckets
-        let mut blob_heap: Vec<u8> = Vec::new();
-
-        for (key, value) in &self.entries {
-            let key_hash = HashDatStore::hash_key(key);
-
-            // Find bucket using linear probing
-            let mut index = (key_hash as usize) % bucket_count;
-            loop {
-                if buckets[index].0 == 0 {
-                    // Empty bucket found
-                    let blob_len = 4 + key.len() + value.len();
-                    buckets[index] = (key_hash, current_blob_offset, blob_len as u64);
+    /// Blob heap entry for a bucket, split into (key, flags, value). The
+    /// bucket's offset and length come from the file, so they're bounds-checked
+    /// against the mapping rather than trusted.
+    fn entry(&self, bucket: &Bucket) -> Result<(&[u8], u32, &[u8])> {
+        let blob = usize::try_from(bucket.blob_offset)
+            .ok()
+            .zip(usize::try_from(bucket.blob_len).ok())
+            .and_then(|(start, len)| self.mmap.get(start..start.checked_add(len)?))
+            .with_context(|| {
+                format!(
+                    "Corrupt bucket: entry at offset {} with length {} extends past the {}-byte file",
+                    bucket.blob_offset,
+                    bucket.blob_len,
+                    self.mmap.len()
+                )
+            })?;
+        split_entry(blob, self.entry_header_len)
+    }
 
-                    // Add to blob heap: key_len + key + value
-                    blob_heap.extend_from_slice(&(key.len() as u32).to_le_bytes());
-                    blob_heap.extend_from_slice(key);
-                    blob_heap.extend_from_slice(value);
+    /// Zero-copy variant of `get`: borrows the value straight out of the mapping
+    /// instead of copying it into a `Vec`.
+    pub fn get_ref<'a>(&'a self, key: &[u8]) -> Result<Option<&'a [u8]>> {
+        match self.find_key(key)? {
+            Some(bucket) => Ok(Some(self.entry(&bucket)?.2)),
+            None => Ok(None),
+        }
+    }
 
-                    current_blob_offset += blob_len as u64;
-                    break;
-                }
-                index = (index + 1) % bucket_count;
+    /// Like `get`, but also returns the entry's flags (0 unless set at build time)
+    pub fn get_with_flags(&self, key: &[u8]) -> Result<Option<(Vec<u8>, u32)>> {
+        match self.find_key(key)? {
+            Some(bucket) => {
+                let (_, flags, value) = self.entry(&bucket)?;
+                Ok(Some((value.to_vec(), flags)))
             }
+            None => Ok(None),
         }
+    }
 
-        // Write buckets
-        for (key_hash, blob_offset, blob_len) in &buckets {
-            writer.write_all(&key_hash.to_le_bytes())?;
-            writer.write_all(&blob_offset.to_le_bytes())?;
-            writer.write_all(&blob_len.to_le_bytes())?;
+    fn find_key(&self, key: &[u8]) -> Result<Option<Bucket>> {
+        let key_hash = HashDatStore::hash_key(self.hash_algo, key);
+        let bloom = &self.mmap[HEADER_SIZE..self.bucket_table_offset];
+        if !bloom_may_contain(bloom, key_hash) {
+            return Ok(None);
         }
+        let bucket_count = self.bucket_count as usize;
+        let mut index = (key_hash as usize) % bucket_count;
 
-        // Write blob heap
-        writer.write_all(&blob_heap)?;
+        for _ in 0..bucket_count {
+            let bucket = self.get_bucket(index);
 
-        // Go back and write header
-        writer.seek(SeekFrom::Start(0))?;
-        writer.write_all(MAGIC)?;
-        writer.write_all(&(bucket_count as u64).to_le_bytes())?;
-        writer.write_all(&blob_heap_offset.to_le_bytes())?;
-        writer.write_all(&(entry_count as u64).to_le_bytes())?;
+            if bucket.key_hash == 0 {
+                // Empty bucket, key not found
+                return Ok(None);
+            }
 
-        writer.flush()?;
+            if bucket.key_hash == key_hash && self.entry(&bucket)?.0 == key {
+                return Ok(Some(bucket));
+            }
 
-        Ok(())
+            // Linear probing
+            index = (index + 1) % bucket_count;
+        }
+
+        Ok(None)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use proptest::collection::vec as prop_vec;
+impl BlobStore for HashDatMmapStore {
+    fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).context("Failed to open hash dat file")?;
+        let mmap = unsafe { Mmap::map(&file).context("Failed to mmap file")? };
+
+        let header = Header::parse(&mmap, mmap.len() as u64)?;
+        header.require_single_file()?;
+        check_bloom(&mmap[HEADER_SIZE..header.bucket_table_offset()])?;
+        if header.checksums.is_some() {
+            header.verify_index_checksum(&[&mmap[HEADER_SIZE..header.index_end()]])?;
+            let buckets: Vec<Bucket> = mmap[header.bucket_table_offset()..header.index_end()]
+                .chunks_exact(BUCKET_SIZE)
+                .map(Bucket::parse)
+                .collect();
+            header.verify_mapped_heap_checksum(&buckets, &mmap)?;
+        }
+
+        Ok(Self {
+            mmap,
+            file,
+            bucket_table_offset: header.bucket_table_offset(),
+            bucket_count: header.bucket_count,
+            entry_count: header.entry_count,
+            hash_algo: header.hash_algo,
+            entry_header_len: header.entry_header_len,
+        })
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.get_ref(key)?.map(|value| value.to_vec()))
+    }
+
+    fn contains_key(&self, key: &[u8]) -> Result<bool> {
+        Ok(self.find_key(key)?.is_some())
+    }
+
+    fn value_len(&self, key: &[u8]) -> Result<Option<usize>> {
+        let value_start = (self.entry_header_len + key.len()) as u64;
+        self.find_key(key)?
+            .map(|bucket| entry_value_len(bucket.blob_len, value_start))
+            .transpose()
+    }
+
+    fn get_into(&self, key: &[u8], buf: &mut Vec<u8>) -> Result<bool> {
+        buf.clear();
+        let value = self.get_ref(key)?;
+        buf.extend_from_slice(value.unwrap_or_default());
+        Ok(value.is_some())
+    }
+
+    #[cfg(unix)]
+    fn advise_willneed(&self) -> Result<bool> {
+        self.mmap
+            .advise(memmap2::Advice::WillNeed)
+            .context("madvise(MADV_WILLNEED) failed")?;
+        Ok(true)
+    }
+
+    fn drop_caches(&self) -> Result<bool> {
+        page_cache::evict(&self.file, Some(&self.mmap))
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        let mut keys = Vec::with_capacity(self.entry_count);
+
+        for i in 0..self.bucket_count as usize {
+            let bucket = self.get_bucket(i);
+            if bucket.key_hash != 0 {
+                keys.push(self.entry(&bucket)?.0.to_vec());
+            }
+        }
+
+        Ok(keys)
+    }
+
+    fn total_value_bytes(&self) -> Result<u64> {
+        let mut total = 0u64;
+        for i in 0..self.bucket_count as usize {
+            let bucket = self.get_bucket(i);
+            if bucket.key_hash != 0 {
+                total += self.entry(&bucket)?.2.len() as u64;
+            }
+        }
+        Ok(total)
+    }
+
+    fn len(&self) -> usize {
+        self.entry_count
+    }
+
+    fn backend_name() -> &'static str {
+        "Custom Offset File Format (mmap)"
+    }
+}
+
+/// Builder for hash .dat store.
+pub struct HashDatStoreBuilder {
+    path: PathBuf,
+    /// Separate file for the blob heap when building a split index
+    data_path: Option<PathBuf>,
+    /// Distinct keys in first-insertion order, which fixes the blob heap layout
+    entries: Vec<(Vec<u8>, Vec<u8>, u32)>,
+    /// Position of each key in `entries`, so re-inserting a key replaces its value
+    positions: HashMap<Vec<u8>, usize>,
+    hash_algo: HashAlgo,
+    /// Append a `DatManifest` trailer, with this fingerprint if set
+    manifest: Option<Option<String>>,
+    /// Write a bloom filter over the keys ahead of the bucket table
+    bloom: bool,
+    /// Compress values with zstd at this level (`HASHZST1`)
+    zstd_level: Option<i32>,
+    /// Record CRC32s of the bucket table and blob heap in the header
+    checksums: bool,
+}
+
+impl HashDatStoreBuilder {
+    /// Build a split index: the header and bucket table go to `index_path` and
+    /// the blob heap to `data_path`. Read it back with `HashDatStore::open_split`.
+    pub fn create_split(index_path: &Path, data_path: &Path) -> Result<Self> {
+        let mut builder = Self::create(index_path)?;
+        builder.data_path = Some(data_path.to_path_buf());
+        Ok(builder)
+    }
+
+    /// Select the hash function used to place keys (defaults to SipHash).
+    pub fn with_hash_algo(mut self, hash_algo: HashAlgo) -> Self {
+        self.hash_algo = hash_algo;
+        self
+    }
+
+    /// Append a JSON manifest describing the file (see `manifest::read_manifest`),
+    /// recording `fingerprint` as the description of the input data.
+    pub fn with_manifest(mut self, fingerprint: Option<&str>) -> Self {
+        self.manifest = Some(fingerprint.map(str::to_string));
+        self
+    }
+
+    /// Write a bloom filter over all keys between the header and the bucket
+    /// table, so lookups of absent keys usually stop before probing buckets.
+    pub fn with_bloom_filter(mut self) -> Self {
+        self.bloom = true;
+        self
+    }
+
+    /// Record CRC32s of the bloom filter and bucket table and of the blob heap
+    /// in the header; every reader then checks them at open.
+    pub fn with_checksums(mut self) -> Self {
+        self.checksums = true;
+        self
+    }
+
+    /// Compress every value with zstd at `level` and write a `HASHZST1` file,
+    /// read back with `ZstdDatStore`.
+    pub(crate) fn with_zstd(mut self, level: i32) -> Self {
+        self.zstd_level = Some(level);
+        self
+    }
+
+    /// Insert an entry tagged with caller-defined flags (`insert` uses 0)
+    pub fn insert_with_flags(&mut self, key: &[u8], value: &[u8], flags: u32) -> Result<()> {
+        match self.positions.get(key) {
+            // Last write wins, as with the SQLite and B-tree builders
+            Some(&pos) => self.entries[pos] = (key.to_vec(), value.to_vec(), flags),
+            None => {
+                self.positions.insert(key.to_vec(), self.entries.len());
+                self.entries.push((key.to_vec(), value.to_vec(), flags));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// First empty bucket at or after `key_hash`'s home slot (linear probing).
+/// Errors instead of spinning when every bucket is taken.
+fn find_empty_bucket(buckets: &[(u64, u64, u64)], key_hash: u64) -> Result<usize> {
+    let bucket_count = buckets.len();
+    let mut index = (key_hash as usize) % bucket_count;
+    for _ in 0..bucket_count {
+        if buckets[index].0 == 0 {
+            return Ok(index);
+        }
+        index = (index + 1) % bucket_count;
+    }
+    bail!(
+        "Hash dat bucket table is full ({} buckets); cannot place another entry",
+        bucket_count
+    )
+}
+
+impl BlobStoreBuilder for HashDatStoreBuilder {
+    fn create(path: &Path) -> Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            data_path: None,
+            entries: Vec::new(),
+            positions: HashMap::new(),
+            hash_algo: HashAlgo::default(),
+            manifest: None,
+            bloom: false,
+            zstd_level: None,
+            checksums: false,
+        })
+    }
+
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.insert_with_flags(key, value, 0)
+    }
+
+    fn finish(self) -> Result<()> {
+        if self.zstd_level.is_some() && self.data_path.is_some() {
+            bail!("Split zstd-compressed hash dat files are not supported");
+        }
+        let file = File::create(&self.path).context("Failed to create hash dat file")?;
+        let mut writer = BufWriter::new(file);
+
+        let entry_count = self.entries.len();
+        let bucket_count = ((entry_count as f64 / LOAD_FACTOR).ceil() as usize).max(1);
+
+        // Write header placeholder
+        writer.write_all(&[0u8; HEADER_SIZE])?;
+
+        let bloom = if self.bloom {
+            let key_hashes: Vec<u64> = self
+                .entries
+                .iter()
+                .map(|(key, _, _)| HashDatStore::hash_key(self.hash_algo, key))
+                .collect();
+            build_bloom(&key_hashes)
+        } else {
+            Vec::new()
+        };
+        writer.write_all(&bloom)?;
+
+        // Initialize buckets
+        let mut buckets: Vec<(u64, u64, u64)> = vec![(0, 0, 0); bucket_count];
+
+        // Calculate blob heap offset (split data files start the heap at 0)
+        let blob_heap_offset = match self.data_path {
+            Some(_) => 0,
+            None => (HEADER_SIZE + bloom.len() + bucket_count * BUCKET_SIZE) as u64,
+        };
+        let mut current_blob_offset = blob_heap_offset;
+
+        // Build blob heap entries and populate buckets
+        let mut blob_heap: Vec<u8> = Vec::new();
+
+        for (key, value, flags) in &self.entries {
+            let key_hash = HashDatStore::hash_key(self.hash_algo, key);
+            let index = find_empty_bucket(&buckets, key_hash)?;
+
+            let compressed = match self.zstd_level {
+                Some(level) => {
+                    Some(zstd::bulk::compress(value, level).context("Failed to compress value")?)
+                }
+                None => None,
+            };
+            let (stored, entry_header_len) = match &compressed {
+                Some(compressed) => (compressed.as_slice(), ZSTD_ENTRY_HEADER_LEN),
+                None => (value.as_slice(), ENTRY_HEADER_LEN),
+            };
+
+            let blob_len = entry_header_len + key.len() + stored.len();
+            buckets[index] = (key_hash, current_blob_offset, blob_len as u64);
+
+            // Add to blob heap: key_len + flags + value_len (+ raw_len) + key + value
+            blob_heap.extend_from_slice(&(key.len() as u32).to_le_bytes());
+            blob_heap.extend_from_slice(&flags.to_le_bytes());
+            blob_heap.extend_from_slice(&(stored.len() as u64).to_le_bytes());
+            if compressed.is_some() {
+                blob_heap.extend_from_slice(&(value.len() as u64).to_le_bytes());
+            }
+            blob_heap.extend_from_slice(key);
+            blob_heap.extend_from_slice(stored);
+
+            current_blob_offset += blob_len as u64;
+        }
+
+        // Write buckets
+        let mut index_hasher = crc32fast::Hasher::new();
+        index_hasher.update(&bloom);
+        for (key_hash, blob_offset, blob_len) in &buckets {
+            let mut bucket = [0u8; BUCKET_SIZE];
+            bucket[0..8].copy_from_slice(&key_hash.to_le_bytes());
+            bucket[8..16].copy_from_slice(&blob_offset.to_le_bytes());
+            bucket[16..24].copy_from_slice(&blob_len.to_le_bytes());
+            index_hasher.update(&bucket);
+            writer.write_all(&bucket)?;
+        }
+        let checksums = self.checksums.then(|| Checksums {
+            index: index_hasher.finalize(),
+            heap: crc32fast::hash(&blob_heap),
+        });
+
+        // Write blob heap
+        match &self.data_path {
+            Some(data_path) => {
+                std::fs::write(data_path, &blob_heap)
+                    .context("Failed to write hash dat data file")?;
+            }
+            None => writer.write_all(&blob_heap)?,
+        }
+
+        let magic = if self.data_path.is_some() {
+            MAGIC_SPLIT
+        } else if self.zstd_level.is_some() {
+            MAGIC_ZSTD
+        } else {
+            MAGIC
+        };
+        if let Some(fingerprint) = self.manifest {
+            let mut manifest = DatManifest::new(
+                HashDatStore::backend_name(),
+                magic,
+                entry_count as u64,
+                fingerprint,
+            );
+            manifest.hash_algo = Some(self.hash_algo.name().to_string());
+            manifest.write_trailer(&mut writer)?;
+        }
+
+        // Go back and write header
+        writer.seek(SeekFrom::Start(0))?;
+        writer.write_all(magic)?;
+        writer.write_all(&(bucket_count as u64).to_le_bytes())?;
+        writer.write_all(&blob_heap_offset.to_le_bytes())?;
+        writer.write_all(&(entry_count as u64).to_le_bytes())?;
+        writer.write_all(&[self.hash_algo.id()])?;
+        let bloom_offset = if bloom.is_empty() { 0 } else { HEADER_SIZE };
+        writer.write_all(&(bloom_offset as u64).to_le_bytes())?;
+        writer.write_all(&(bloom.len() as u64).to_le_bytes())?;
+        if let Some(checksums) = checksums {
+            checksums.write_to(&mut writer)?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Rebuild a Hash DAT file whose bucket table (or header fields other than the
+/// magic and bucket_count) is corrupt by walking the blob heap and re-inserting
+/// every entry into a fresh file at `out`. Returns the number of entries recovered.
+pub fn repair(path: &Path, out: &Path) -> Result<usize> {
+    let file = File::open(path).context("Failed to open hash dat file")?;
+    let mmap = unsafe { Mmap::map(&file).context("Failed to mmap file")? };
+
+    if mmap.len() < HEADER_SIZE {
+        bail!("File too small for header");
+    }
+    match &mmap[0..8] {
+        m if m == MAGIC || m == MAGIC_V3 => {}
+        m if m == MAGIC_V1 || m == MAGIC_V2 => {
+            bail!(
+                "Entries in this file do not record their value length, so its blob heap \
+                 can't be walked; rebuild it instead"
+            )
+        }
+        m if m == MAGIC_SPLIT || m == MAGIC_SPLIT_V2 => {
+            bail!("Repairing split index files is not supported")
+        }
+        m if m == MAGIC_ZSTD => bail!("Repairing zstd-compressed files is not supported"),
+        _ => bail!("Invalid magic number"),
+    }
+
+    // The stored blob_heap_offset may be the corrupt part; derive it from
+    // bucket_count and the bloom filter length, which must fit in the file
+    // before anything is sized from them
+    let bucket_count = u64::from_le_bytes(mmap[8..16].try_into().unwrap());
+    if bucket_count == 0 {
+        bail!("Corrupt hash dat header: bucket_count is 0");
+    }
+    let bloom_len = u64::from_le_bytes(mmap[41..49].try_into().unwrap());
+    let heap_start = bucket_count
+        .checked_mul(BUCKET_SIZE as u64)
+        .and_then(|table_len| table_len.checked_add(HEADER_SIZE as u64))
+        .and_then(|table_end| table_end.checked_add(bloom_len))
+        .filter(|&end| end <= mmap.len() as u64)
+        .with_context(|| format!("Corrupt hash dat header: bucket_count {}", bucket_count))?
+        as usize;
+    let hash_algo = HashAlgo::from_id(mmap[32]).unwrap_or_default();
+
+    let mut builder = HashDatStoreBuilder::create(out)?.with_hash_algo(hash_algo);
+    if bloom_len > 0 {
+        builder = builder.with_bloom_filter();
+    }
+    let checksum_fields = &mmap[CHECKSUMS_OFFSET..CHECKSUMS_OFFSET + CHECKSUM_FIELDS_LEN];
+    if Checksums::parse(checksum_fields).is_some() {
+        builder = builder.with_checksums();
+    }
+    // Keep the manifest, if any, and don't mistake its trailer for heap entries
+    let trailer_len = manifest::trailer_len(&mmap);
+    if trailer_len > 0 {
+        let fingerprint = manifest::read_manifest(path)
+            .ok()
+            .flatten()
+            .and_then(|m| m.fingerprint);
+        builder = builder.with_manifest(fingerprint.as_deref());
+    }
+    let heap = &mmap[..mmap.len() - trailer_len];
+    let mut offset = heap_start;
+    let mut recovered = 0;
+    while offset < heap.len() {
+        let header = heap
+            .get(offset..offset + ENTRY_HEADER_LEN)
+            .with_context(|| format!("Truncated blob heap entry at offset {}", offset))?;
+        let key_len = u32::from_le_bytes(header[0..4].try_into().unwrap()) as usize;
+        let flags = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        let value_len = u64::from_le_bytes(header[8..16].try_into().unwrap()) as usize;
+
+        let key_start = offset + ENTRY_HEADER_LEN;
+        let entry_end = key_start
+            .checked_add(key_len)
+            .and_then(|value_start| value_start.checked_add(value_len))
+            .filter(|&end| end <= heap.len())
+            .with_context(|| format!("Blob heap entry at offset {} runs past EOF", offset))?;
+        let value_start = key_start + key_len;
+
+        builder.insert_with_flags(
+            &heap[key_start..value_start],
+            &heap[value_start..entry_end],
+            flags,
+        )?;
+        recovered += 1;
+        offset = entry_end;
+    }
+
+    builder.finish()?;
+    Ok(recovered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec as prop_vec;
     use proptest::prelude::*;
-    use std::collections::HashMap;
     use tempfile::NamedTempFile;
 
     #[test]
@@ -332,6 +1452,42 @@ mod tests {
         assert_eq!(store.get(b"nonexistent").unwrap(), None);
     }
 
+    #[test]
+    fn test_full_bucket_table_errors_instead_of_spinning() {
+        let full = vec![(7, 0, 0); 4];
+        let err = find_empty_bucket(&full, 7).unwrap_err();
+        assert!(err.to_string().contains("bucket table is full"));
+
+        // Probing wraps around to the one free slot before the home slot
+        let mut one_free = full;
+        one_free[1] = (0, 0, 0);
+        assert_eq!(find_empty_bucket(&one_free, 2).unwrap(), 1);
+
+        // Repeated inserts of one key still finish
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut builder = HashDatStoreBuilder::create(temp_file.path()).unwrap();
+        for _ in 0..20 {
+            builder.insert(b"same", b"value").unwrap();
+        }
+        builder.finish().unwrap();
+        let store = HashDatStore::open(temp_file.path()).unwrap();
+        assert_eq!(store.get(b"same").unwrap(), Some(b"value".to_vec()));
+    }
+
+    #[test]
+    fn test_reinserted_key_keeps_last_value() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut builder = HashDatStoreBuilder::create(temp_file.path()).unwrap();
+        builder.insert(b"key1", b"a").unwrap();
+        builder.insert(b"key1", b"b").unwrap();
+        builder.finish().unwrap();
+
+        let store = HashDatStore::open(temp_file.path()).unwrap();
+        assert_eq!(store.get(b"key1").unwrap(), Some(b"b".to_vec()));
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.keys().unwrap(), vec![b"key1".to_vec()]);
+    }
+
     #[test]
     fn test_hash_keys() {
         let temp_file = NamedTempFile::new().unwrap();
@@ -433,7 +1589,735 @@ mod tests {
         assert!(store.is_empty());
     }
 
+    #[test]
+    fn test_hash_algo_recorded_in_header() {
+        for algo in [HashAlgo::SipHash, HashAlgo::Fnv] {
+            let temp_file = NamedTempFile::new().unwrap();
+            let path = temp_file.path();
+
+            {
+                let mut builder = HashDatStoreBuilder::create(path)
+                    .unwrap()
+                    .with_hash_algo(algo);
+                for i in 0..50 {
+                    let key = format!("key_{:04}", i);
+                    let value = format!("value_{:04}", i);
+                    builder.insert(key.as_bytes(), value.as_bytes()).unwrap();
+                }
+                builder.finish().unwrap();
+            }
+
+            let header = std::fs::read(path).unwrap();
+            assert_eq!(header[32], algo.id());
+
+            let store = HashDatStore::open(path).unwrap();
+            assert_eq!(store.hash_algo(), algo);
+            assert_eq!(store.len(), 50);
+            for i in 0..50 {
+                let key = format!("key_{:04}", i);
+                let value = format!("value_{:04}", i);
+                assert_eq!(store.get(key.as_bytes()).unwrap(), Some(value.into_bytes()));
+            }
+            assert_eq!(store.get(b"nonexistent").unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_phase_timings_cover_get() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut builder = HashDatStoreBuilder::create(path).unwrap();
+            for i in 0..100 {
+                let key = format!("key_{:03}", i);
+                builder
+                    .insert(key.as_bytes(), &vec![i as u8; 4096])
+                    .unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        let store = HashDatStore::open(path).unwrap();
+        let mut index_total = std::time::Duration::ZERO;
+        let mut io_total = std::time::Duration::ZERO;
+        let mut get_total = std::time::Duration::ZERO;
+        for i in 0..100 {
+            let key = format!("key_{:03}", i);
+            let start = Instant::now();
+            let (value, phases) = store.get_with_phases(key.as_bytes()).unwrap();
+            get_total += start.elapsed();
+
+            assert_eq!(value, Some(vec![i as u8; 4096]));
+            let phases = phases.unwrap();
+            index_total += phases.index;
+            io_total += phases.io;
+        }
+
+        assert!(index_total > std::time::Duration::ZERO);
+        assert!(io_total > std::time::Duration::ZERO);
+        // The phases are nested inside the outer measurement, so they can't exceed it
+        assert!(index_total + io_total <= get_total);
+    }
+
+    #[test]
+    fn test_entry_flags_roundtrip() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut builder = HashDatStoreBuilder::create(path).unwrap();
+            builder.insert_with_flags(b"pinned", b"p", 0x1).unwrap();
+            builder
+                .insert_with_flags(b"json", b"{}", 0xdead_beef)
+                .unwrap();
+            builder.insert(b"plain", b"x").unwrap();
+            builder.finish().unwrap();
+        }
+        assert_eq!(&std::fs::read(path).unwrap()[0..8], MAGIC);
+
+        let store = HashDatStore::open(path).unwrap();
+        let mmap_store = HashDatMmapStore::open(path).unwrap();
+        for (key, value, flags) in [
+            (&b"pinned"[..], &b"p"[..], 0x1),
+            (&b"json"[..], &b"{}"[..], 0xdead_beef),
+            (&b"plain"[..], &b"x"[..], 0),
+        ] {
+            let expected = Some((value.to_vec(), flags));
+            assert_eq!(store.get_with_flags(key).unwrap(), expected);
+            assert_eq!(mmap_store.get_with_flags(key).unwrap(), expected);
+            assert_eq!(store.get(key).unwrap(), Some(value.to_vec()));
+        }
+        assert_eq!(store.get_with_flags(b"missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_reads_v1_files_without_flags() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        // Hand-assemble a one-entry HASHIDX1 file: key_len + key + value, no flags
+        let key = b"key";
+        let value = b"value";
+        let bucket_count = 1u64;
+        let blob_heap_offset = (HEADER_SIZE + BUCKET_SIZE) as u64;
+        let mut file = Vec::new();
+        file.extend_from_slice(MAGIC_V1);
+        file.extend_from_slice(&bucket_count.to_le_bytes());
+        file.extend_from_slice(&blob_heap_offset.to_le_bytes());
+        file.extend_from_slice(&1u64.to_le_bytes());
+        file.resize(HEADER_SIZE, 0);
+        let blob_len = (4 + key.len() + value.len()) as u64;
+        file.extend_from_slice(&HashDatStore::hash_key(HashAlgo::SipHash, key).to_le_bytes());
+        file.extend_from_slice(&blob_heap_offset.to_le_bytes());
+        file.extend_from_slice(&blob_len.to_le_bytes());
+        file.extend_from_slice(&(key.len() as u32).to_le_bytes());
+        file.extend_from_slice(key);
+        file.extend_from_slice(value);
+        std::fs::write(path, &file).unwrap();
+
+        let store = HashDatStore::open(path).unwrap();
+        assert_eq!(
+            store.get_with_flags(key).unwrap(),
+            Some((value.to_vec(), 0))
+        );
+        assert_eq!(store.keys().unwrap(), vec![key.to_vec()]);
+        let mmap_store = HashDatMmapStore::open(path).unwrap();
+        assert_eq!(mmap_store.get(key).unwrap(), Some(value.to_vec()));
+    }
+
+    #[test]
+    fn test_split_index_reads_values_from_data_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let index_path = dir.path().join("index.idx");
+        let data_path = dir.path().join("index.blobs");
+
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = (0..50)
+            .map(|i| (format!("key{}", i).into_bytes(), vec![i as u8; 1000]))
+            .collect();
+        {
+            let mut builder = HashDatStoreBuilder::create_split(&index_path, &data_path).unwrap();
+            for (key, value) in &entries {
+                builder.insert(key, value).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        let store = HashDatStore::open_split(&index_path, &data_path).unwrap();
+        assert_eq!(store.len(), entries.len());
+        for (key, value) in &entries {
+            assert_eq!(store.get(key).unwrap().as_ref(), Some(value));
+        }
+        assert_eq!(store.get(b"missing").unwrap(), None);
+
+        let index_len = std::fs::metadata(&index_path).unwrap().len();
+        let data_len = std::fs::metadata(&data_path).unwrap().len();
+        assert!(index_len * 10 < data_len, "{} vs {}", index_len, data_len);
+
+        // Values come from the data file, not the index
+        std::fs::write(&data_path, vec![0u8; data_len as usize]).unwrap();
+        let store = HashDatStore::open_split(&index_path, &data_path).unwrap();
+        assert_eq!(store.get(&entries[0].0).unwrap(), None);
+
+        // The single-file readers refuse a split index
+        let err = HashDatStore::open(&index_path).err().unwrap();
+        assert!(err.to_string().contains("split"), "{}", err);
+        assert!(HashDatMmapStore::open(&index_path).is_err());
+    }
+
+    #[test]
+    fn test_v3_file_still_opens_but_cannot_claim_a_bloom_filter() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        {
+            let mut builder = HashDatStoreBuilder::create(path).unwrap();
+            for i in 0..20 {
+                builder
+                    .insert(format!("key{}", i).as_bytes(), &[i as u8; 10])
+                    .unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        // HASHIDX3 shares the layout of a HASHIDX4 file without a bloom filter
+        let mut bytes = std::fs::read(path).unwrap();
+        bytes[0..8].copy_from_slice(MAGIC_V3);
+        std::fs::write(path, &bytes).unwrap();
+        let store = HashDatStore::open(path).unwrap();
+        let mmap_store = HashDatMmapStore::open(path).unwrap();
+        assert_eq!(store.get(b"key7").unwrap(), Some(vec![7; 10]));
+        assert_eq!(mmap_store.get(b"key7").unwrap(), Some(vec![7; 10]));
+        assert_eq!(store.get(b"absent").unwrap(), None);
+
+        // A bloom filter in a HASHIDX3 header is corruption, not a feature
+        bytes[33..41].copy_from_slice(&(HEADER_SIZE as u64).to_le_bytes());
+        bytes[41..49].copy_from_slice(&64u64.to_le_bytes());
+        std::fs::write(path, &bytes).unwrap();
+        assert!(HashDatStore::open(path).is_err());
+    }
+
+    #[test]
+    fn test_v2_split_index_still_opens() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let index_path = dir.path().join("index.idx");
+        let data_path = dir.path().join("index.blobs");
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = (0..20)
+            .map(|i| (format!("key{}", i).into_bytes(), vec![i as u8; i * 3]))
+            .collect();
+        {
+            let mut builder = HashDatStoreBuilder::create_split(&index_path, &data_path).unwrap();
+            for (key, value) in &entries {
+                builder.insert(key, value).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        // Rewrite as HASHSPL2: entries lose their 8-byte value_len field
+        let mut index = std::fs::read(&index_path).unwrap();
+        let data = std::fs::read(&data_path).unwrap();
+        let mut v2_data = Vec::new();
+        index[0..8].copy_from_slice(MAGIC_SPLIT_V2);
+        for bucket in index[HEADER_SIZE..].chunks_exact_mut(BUCKET_SIZE) {
+            if bucket[0..8] == [0; 8] {
+                continue;
+            }
+            let parsed = Bucket::parse(bucket);
+            let entry = &data[parsed.blob_offset as usize..][..parsed.blob_len as usize];
+            bucket[8..16].copy_from_slice(&(v2_data.len() as u64).to_le_bytes());
+            bucket[16..24].copy_from_slice(&(parsed.blob_len - 8).to_le_bytes());
+            v2_data.extend_from_slice(&entry[0..8]);
+            v2_data.extend_from_slice(&entry[ENTRY_HEADER_LEN..]);
+        }
+        std::fs::write(&index_path, &index).unwrap();
+        std::fs::write(&data_path, &v2_data).unwrap();
+
+        let store = HashDatStore::open_split(&index_path, &data_path).unwrap();
+        assert_eq!(store.len(), entries.len());
+        for (key, value) in &entries {
+            assert_eq!(store.get(key).unwrap().as_ref(), Some(value));
+        }
+        assert_eq!(store.get(b"missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_from_reader_serves_lookups_from_memory() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut builder = HashDatStoreBuilder::create(path).unwrap();
+            builder.insert(b"key1", b"value1").unwrap();
+            builder.insert(b"key2", &[7u8; 5000]).unwrap();
+            builder.finish().unwrap();
+        }
+        let bytes = std::fs::read(path).unwrap();
+        drop(temp_file);
+
+        let store = HashDatStore::from_reader(Box::new(std::io::Cursor::new(bytes))).unwrap();
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+        assert_eq!(store.get(b"key2").unwrap(), Some(vec![7u8; 5000]));
+        assert_eq!(store.get(b"missing").unwrap(), None);
+        assert_eq!(store.keys().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_concurrent_lookups_share_one_store() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut builder = HashDatStoreBuilder::create(path).unwrap();
+            for i in 0..200u32 {
+                builder
+                    .insert(
+                        format!("key{}", i).as_bytes(),
+                        &vec![i as u8; 100 + i as usize],
+                    )
+                    .unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        let store = Arc::new(HashDatStore::open(path).unwrap());
+        let handles: Vec<_> = (0..4u32)
+            .map(|t| {
+                let store = Arc::clone(&store);
+                std::thread::spawn(move || {
+                    for round in 0..5u32 {
+                        for i in (t..200).step_by(4) {
+                            let i = (i + round * 7) % 200;
+                            let value = store.get(format!("key{}", i).as_bytes()).unwrap();
+                            assert_eq!(value, Some(vec![i as u8; 100 + i as usize]));
+                        }
+                    }
+                    assert_eq!(store.get(b"missing").unwrap(), None);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    /// Cursor that counts the bytes served after the store is opened
+    struct CountingReader {
+        inner: std::io::Cursor<Vec<u8>>,
+        bytes_read: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.bytes_read
+                .fetch_add(n, std::sync::atomic::Ordering::Relaxed);
+            Ok(n)
+        }
+    }
+
+    impl Seek for CountingReader {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_key_length_mismatch_skips_key_read() {
+        use std::sync::atomic::Ordering;
+
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        {
+            let mut builder = HashDatStoreBuilder::create(path).unwrap();
+            builder.insert(b"k", &[9u8; 64]).unwrap();
+            builder.finish().unwrap();
+        }
+
+        // Move the only bucket to a probe key's home slot under that key's hash,
+        // so the probe reaches key verification as a real hash collision would
+        let bytes = std::fs::read(path).unwrap();
+        let bucket_count = u64::from_le_bytes(bytes[8..16].try_into().unwrap()) as usize;
+        let table = HEADER_SIZE..HEADER_SIZE + bucket_count * BUCKET_SIZE;
+        let bucket = bytes[table.clone()]
+            .chunks(BUCKET_SIZE)
+            .find(|b| b[0..8] != [0u8; 8])
+            .unwrap()
+            .to_vec();
+        let open_with_hash_of = |probe: &[u8]| {
+            let hash = HashDatStore::hash_key(HashAlgo::default(), probe);
+            let mut bytes = bytes.clone();
+            bytes[table.clone()].fill(0);
+            let at = HEADER_SIZE + (hash as usize % bucket_count) * BUCKET_SIZE;
+            bytes[at..at + 8].copy_from_slice(&hash.to_le_bytes());
+            bytes[at + 8..at + BUCKET_SIZE].copy_from_slice(&bucket[8..]);
+            let bytes_read = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+            let reader = CountingReader {
+                inner: std::io::Cursor::new(bytes),
+                bytes_read: bytes_read.clone(),
+            };
+            let store = HashDatStore::from_reader(Box::new(reader)).unwrap();
+            bytes_read.store(0, Ordering::Relaxed);
+            (store, bytes_read)
+        };
+
+        // Same length as the stored key: key_len and the key bytes are read
+        let (store, bytes_read) = open_with_hash_of(b"x");
+        assert_eq!(store.get(b"x").unwrap(), None);
+        assert_eq!(bytes_read.load(Ordering::Relaxed), 4 + 1);
+
+        // Different length that still fits in the entry: only key_len is read
+        let (store, bytes_read) = open_with_hash_of(b"xy");
+        assert_eq!(store.get(b"xy").unwrap(), None);
+        assert_eq!(bytes_read.load(Ordering::Relaxed), 4);
+
+        // Longer than the whole entry: nothing is read
+        let long_key = vec![b'x'; 200];
+        let (store, bytes_read) = open_with_hash_of(&long_key);
+        assert_eq!(store.get(&long_key).unwrap(), None);
+        assert_eq!(bytes_read.load(Ordering::Relaxed), 0);
+
+        // Lookups of real keys are unaffected
+        let store = HashDatStore::open(path).unwrap();
+        assert_eq!(store.get(b"k").unwrap(), Some(vec![9u8; 64]));
+    }
+
+    #[test]
+    fn test_repair_rebuilds_corrupt_bucket_table() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("index_hash.dat");
+        let repaired = dir.path().join("repaired.dat");
+
+        let entries: Vec<(Vec<u8>, Vec<u8>, u32)> = (0..40)
+            .map(|i| {
+                (
+                    format!("key{}", i).into_bytes(),
+                    vec![i as u8; i * 7],
+                    i as u32,
+                )
+            })
+            .collect();
+        {
+            let mut builder = HashDatStoreBuilder::create(&path).unwrap();
+            for (key, value, flags) in &entries {
+                builder.insert_with_flags(key, value, *flags).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        // Scribble over the whole bucket table
+        let mut bytes = std::fs::read(&path).unwrap();
+        let bucket_count = u64::from_le_bytes(bytes[8..16].try_into().unwrap()) as usize;
+        bytes[HEADER_SIZE..HEADER_SIZE + bucket_count * BUCKET_SIZE].fill(0xab);
+        std::fs::write(&path, &bytes).unwrap();
+        let corrupt = HashDatStore::open(&path).unwrap();
+        assert!(!matches!(corrupt.get(&entries[0].0), Ok(Some(_))));
+
+        assert_eq!(repair(&path, &repaired).unwrap(), entries.len());
+
+        let store = HashDatStore::open(&repaired).unwrap();
+        assert_eq!(store.len(), entries.len());
+        for (key, value, flags) in &entries {
+            assert_eq!(
+                store.get_with_flags(key).unwrap(),
+                Some((value.clone(), *flags))
+            );
+        }
+    }
+
+    #[test]
+    fn test_repair_rejects_impossible_bucket_count() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("index_hash.dat");
+        let repaired = dir.path().join("repaired.dat");
+        {
+            let mut builder = HashDatStoreBuilder::create(&path).unwrap();
+            builder.insert(b"key", b"value").unwrap();
+            builder.finish().unwrap();
+        }
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        for bucket_count in [0, bytes.len() as u64, u64::MAX / 2] {
+            bytes[8..16].copy_from_slice(&bucket_count.to_le_bytes());
+            std::fs::write(&path, &bytes).unwrap();
+            let err = repair(&path, &repaired).unwrap_err();
+            assert!(err.to_string().contains("bucket_count"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_gigantic_bucket_count_rejected() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut builder = HashDatStoreBuilder::create(path).unwrap();
+            builder.insert(b"key", b"value").unwrap();
+            builder.finish().unwrap();
+        }
+        let original = std::fs::read(path).unwrap();
+
+        // One count that overflows the table size, one that merely exceeds the file
+        for bucket_count in [u64::MAX / 2, 1u64 << 40] {
+            let mut corrupt = original.clone();
+            corrupt[8..16].copy_from_slice(&bucket_count.to_le_bytes());
+            std::fs::write(path, &corrupt).unwrap();
+
+            let err = HashDatStore::open(path).err().unwrap();
+            assert!(err.to_string().contains("bucket_count"), "{}", err);
+            let err = HashDatMmapStore::open(path).err().unwrap();
+            assert!(err.to_string().contains("bucket_count"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_checksums_catch_flipped_bytes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("index_hash.dat");
+        {
+            let mut builder = HashDatStoreBuilder::create(&path)
+                .unwrap()
+                .with_bloom_filter()
+                .with_checksums();
+            for i in 0..50 {
+                builder
+                    .insert(format!("key{}", i).as_bytes(), &vec![i as u8; 100])
+                    .unwrap();
+            }
+            builder.finish().unwrap();
+        }
+        let original = std::fs::read(&path).unwrap();
+        assert_eq!(original[CHECKSUMS_OFFSET + 8], 1);
+        let store = HashDatStore::open(&path).unwrap();
+        assert_eq!(store.get(b"key7").unwrap(), Some(vec![7u8; 100]));
+
+        // One byte in the bloom filter, one in a bucket's blob_len, one in a value
+        let bucket_table_offset = u64::from_le_bytes(original[16..24].try_into().unwrap()) as usize
+            - u64::from_le_bytes(original[8..16].try_into().unwrap()) as usize * BUCKET_SIZE;
+        let occupied_bucket = original[bucket_table_offset..]
+            .chunks_exact(BUCKET_SIZE)
+            .position(|bucket| bucket[0..8] != [0u8; 8])
+            .unwrap();
+        let blob_len_byte = bucket_table_offset + occupied_bucket * BUCKET_SIZE + 16;
+        for (offset, region) in [
+            (HEADER_SIZE + BLOOM_PREFIX_LEN, "bucket table"),
+            (blob_len_byte, "bucket table"),
+            (original.len() - 1, "blob heap"),
+        ] {
+            let mut corrupt = original.clone();
+            corrupt[offset] ^= 0x01;
+            std::fs::write(&path, &corrupt).unwrap();
+
+            let err = HashDatStore::open(&path).err().unwrap().to_string();
+            assert!(err.contains("checksum mismatch"), "{}", err);
+            assert!(err.contains(region), "{}", err);
+            let err = HashDatMmapStore::open(&path).err().unwrap().to_string();
+            assert!(err.contains("checksum mismatch"), "{}", err);
+        }
+
+        // A truncated heap fails with an error too, not a panic
+        std::fs::write(&path, &original[..original.len() - 10]).unwrap();
+        assert!(HashDatStore::open(&path).is_err());
+        assert!(HashDatMmapStore::open(&path).is_err());
+    }
+
+    #[test]
+    fn test_fnv_known_vector() {
+        // FNV-1a 64-bit of "a"
+        assert_eq!(HashAlgo::Fnv.hash(b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn test_mmap_variant_matches_eager() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        let num_entries = 50_000;
+
+        {
+            let mut builder = HashDatStoreBuilder::create(path).unwrap();
+            for i in 0..num_entries {
+                let key = format!("key_{:06}", i);
+                let value = format!("value_{:06}", i);
+                builder.insert(key.as_bytes(), value.as_bytes()).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        let eager = HashDatStore::open(path).unwrap();
+        let lazy = HashDatMmapStore::open(path).unwrap();
+        assert_eq!(lazy.len(), eager.len());
+        assert_eq!(lazy.hash_algo(), eager.hash_algo());
+        for i in (0..num_entries).step_by(97) {
+            let key = format!("key_{:06}", i);
+            assert_eq!(
+                lazy.get(key.as_bytes()).unwrap(),
+                eager.get(key.as_bytes()).unwrap()
+            );
+        }
+        assert_eq!(lazy.get(b"nonexistent").unwrap(), None);
+        let borrowed = lazy.get_ref(b"key_000042").unwrap().unwrap();
+        assert_eq!(borrowed, b"value_000042");
+        // Zero-copy: the slice points into the mapping
+        let mapped = lazy.mmap.as_ptr_range();
+        let range = borrowed.as_ptr_range();
+        assert!(mapped.start <= range.start && range.end <= mapped.end);
+
+        let mut eager_keys = eager.keys().unwrap();
+        let mut lazy_keys = lazy.keys().unwrap();
+        eager_keys.sort();
+        lazy_keys.sort();
+        assert_eq!(lazy_keys, eager_keys);
+    }
+
+    #[test]
+    fn test_mmap_out_of_range_bucket_is_an_error() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        {
+            let mut builder = HashDatStoreBuilder::create(path).unwrap();
+            builder.insert(b"key", b"value").unwrap();
+            builder.finish().unwrap();
+        }
+
+        let mut bytes = std::fs::read(path).unwrap();
+        let bucket = bytes[HEADER_SIZE..]
+            .chunks_exact(BUCKET_SIZE)
+            .position(|b| b[0..8] != [0; 8])
+            .unwrap();
+        let off = HEADER_SIZE + bucket * BUCKET_SIZE;
+
+        // An offset that overflows when the length is added
+        bytes[off + 8..off + 16].copy_from_slice(&(u64::MAX - 2).to_le_bytes());
+        std::fs::write(path, &bytes).unwrap();
+        let store = HashDatMmapStore::open(path).unwrap();
+        assert!(store.get(b"key").is_err());
+        assert!(store.keys().is_err());
+
+        // An in-range offset whose length runs past the end of the file
+        bytes[off + 8..off + 16].copy_from_slice(&(HEADER_SIZE as u64).to_le_bytes());
+        bytes[off + 16..off + 24].copy_from_slice(&(bytes.len() as u64).to_le_bytes());
+        std::fs::write(path, &bytes).unwrap();
+        let store = HashDatMmapStore::open(path).unwrap();
+        assert!(store.get_ref(b"key").is_err());
+        assert!(store.total_value_bytes().is_err());
+    }
+
+    #[test]
+    fn test_entry_shorter_than_its_key_is_an_error() {
+        assert_eq!(entry_value_len(24, 19).unwrap(), 5);
+        assert_eq!(entry_value_len(19, 19).unwrap(), 0);
+        assert!(entry_value_len(18, 19).is_err());
+    }
+
+    #[test]
+    fn test_bloom_filter_rejects_absent_keys() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        let key = |i: usize| format!("present_{:04}", i).into_bytes();
+        {
+            let mut builder = HashDatStoreBuilder::create(path)
+                .unwrap()
+                .with_bloom_filter();
+            for i in 0..500 {
+                builder.insert(&key(i), &i.to_le_bytes()).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        let bytes = std::fs::read(path).unwrap();
+        let bloom_offset = u64::from_le_bytes(bytes[33..41].try_into().unwrap());
+        let bloom_len = u64::from_le_bytes(bytes[41..49].try_into().unwrap()) as usize;
+        assert_eq!(bloom_offset, HEADER_SIZE as u64);
+        assert_eq!(bloom_len, BLOOM_PREFIX_LEN + 500 * BLOOM_BITS_PER_KEY / 8);
+
+        let store = HashDatStore::open(path).unwrap();
+        let mmap_store = HashDatMmapStore::open(path).unwrap();
+        for i in 0..500 {
+            let expected = Some(i.to_le_bytes().to_vec());
+            assert_eq!(store.get(&key(i)).unwrap(), expected);
+            assert_eq!(mmap_store.get(&key(i)).unwrap(), expected);
+        }
+
+        let absent: Vec<Vec<u8>> = (0..1000)
+            .map(|i| format!("absent_{:04}", i).into_bytes())
+            .collect();
+        for key in &absent {
+            assert_eq!(store.get(key).unwrap(), None);
+            assert_eq!(mmap_store.get(key).unwrap(), None);
+        }
+        // ~1% expected false positives; most misses never reach the buckets
+        let passed = absent
+            .iter()
+            .filter(|key| {
+                bloom_may_contain(&store.bloom, HashDatStore::hash_key(store.hash_algo, key))
+            })
+            .count();
+        assert!(
+            passed < 50,
+            "{} of 1000 absent keys passed the filter",
+            passed
+        );
+
+        // Repair keeps the filter
+        let repaired = NamedTempFile::new().unwrap();
+        assert_eq!(repair(path, repaired.path()).unwrap(), 500);
+        let repaired = HashDatStore::open(repaired.path()).unwrap();
+        assert_eq!(repaired.bloom.len(), bloom_len);
+        assert_eq!(
+            repaired.get(&key(7)).unwrap(),
+            Some(7usize.to_le_bytes().to_vec())
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_bloom_hash_count_fails_to_open() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        {
+            let mut builder = HashDatStoreBuilder::create(path)
+                .unwrap()
+                .with_bloom_filter();
+            builder.insert(b"key1", b"value1").unwrap();
+            builder.finish().unwrap();
+        }
+        let original = std::fs::read(path).unwrap();
+
+        for hash_count in [0, MAX_BLOOM_HASH_COUNT + 1, u32::MAX] {
+            let mut bytes = original.clone();
+            bytes[HEADER_SIZE..HEADER_SIZE + BLOOM_PREFIX_LEN]
+                .copy_from_slice(&hash_count.to_le_bytes());
+            std::fs::write(path, &bytes).unwrap();
+            assert!(HashDatStore::open(path).is_err(), "{}", hash_count);
+            assert!(HashDatMmapStore::open(path).is_err(), "{}", hash_count);
+        }
+    }
+
+    #[test]
+    fn test_files_without_bloom_filter_probe_buckets() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        {
+            let mut builder = HashDatStoreBuilder::create(path).unwrap();
+            builder.insert(b"key1", b"value1").unwrap();
+            builder.finish().unwrap();
+        }
+
+        let bytes = std::fs::read(path).unwrap();
+        assert!(bytes[33..49].iter().all(|&b| b == 0));
+        let store = HashDatStore::open(path).unwrap();
+        assert!(store.bloom.is_empty());
+        assert_eq!(store.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+        assert_eq!(store.get(b"key2").unwrap(), None);
+    }
+
     proptest! {
+        #[test]
+        fn prop_bloom_filter_has_no_false_negatives(key_hashes in prop_vec(any::<u64>(), 0..2000)) {
+            let section = build_bloom(&key_hashes);
+            for &key_hash in &key_hashes {
+                prop_assert!(bloom_may_contain(&section, key_hash));
+            }
+        }
+
         #[test]
         fn prop_hash_roundtrip_single(key in prop_vec(any::<u8>(), 1..100), value in prop_vec(any::<u8>(), 0..1000)) {
             let temp_file = NamedTempFile::new().unwrap();
@@ -0,0 +1,92 @@
+//! Page-granular record of the blob heap bytes lookups read.
+//!
+//! Memory is fixed when tracking starts (one bit per 4 KiB page of the heap),
+//! however many lookups run afterwards, and recording a read is a handful of
+//! atomic ORs with no allocation, so tracking stays cheap inside timed loops.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Granularity of `accessed_bytes`: the unit the page cache holds
+const PAGE_SIZE: u64 = 4096;
+
+pub(super) struct ReadTracker {
+    /// Bit `i` is set once any byte of page `i` has been read
+    pages: Vec<AtomicU64>,
+    /// Pages in the tracked length; `pages` may have spare bits past it
+    page_count: u64,
+    /// Total bytes read, counting repeated reads each time
+    bytes_read: AtomicU64,
+}
+
+impl ReadTracker {
+    /// Track reads within the first `len` bytes of the file
+    pub(super) fn new(len: u64) -> Self {
+        let page_count = len.div_ceil(PAGE_SIZE);
+        Self {
+            pages: (0..page_count.div_ceil(64))
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+            page_count,
+            bytes_read: AtomicU64::new(0),
+        }
+    }
+
+    /// Record a read of `len` bytes at `offset`. Pages past the tracked length
+    /// still count towards `bytes_read` but aren't marked.
+    pub(super) fn record(&self, offset: u64, len: u64) {
+        self.bytes_read.fetch_add(len, Ordering::Relaxed);
+        if len == 0 {
+            return;
+        }
+        let first = offset / PAGE_SIZE;
+        let end = (offset.saturating_add(len - 1) / PAGE_SIZE + 1).min(self.page_count);
+        for page in first..end {
+            self.pages[(page / 64) as usize].fetch_or(1 << (page % 64), Ordering::Relaxed);
+        }
+    }
+
+    /// Bytes of the distinct pages read so far
+    pub(super) fn accessed_bytes(&self) -> u64 {
+        let pages: u64 = self
+            .pages
+            .iter()
+            .map(|word| word.load(Ordering::Relaxed).count_ones() as u64)
+            .sum();
+        pages * PAGE_SIZE
+    }
+
+    /// Bytes read so far, counting repeated reads each time
+    pub(super) fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlapping_reads_count_pages_once() {
+        let tracker = ReadTracker::new(10 * PAGE_SIZE);
+        assert_eq!(tracker.accessed_bytes(), 0);
+
+        tracker.record(10, 20);
+        tracker.record(15, 30);
+        assert_eq!(tracker.accessed_bytes(), PAGE_SIZE);
+
+        // Straddles pages 0 and 1
+        tracker.record(PAGE_SIZE - 1, 2);
+        assert_eq!(tracker.accessed_bytes(), 2 * PAGE_SIZE);
+        tracker.record(0, 0);
+        assert_eq!(tracker.bytes_read(), 20 + 30 + 2);
+    }
+
+    #[test]
+    fn test_reads_past_the_tracked_length_are_ignored() {
+        let tracker = ReadTracker::new(PAGE_SIZE);
+        tracker.record(0, 3 * PAGE_SIZE);
+        tracker.record(u64::MAX - 1, 8);
+        assert_eq!(tracker.accessed_bytes(), PAGE_SIZE);
+        assert_eq!(tracker.bytes_read(), 3 * PAGE_SIZE + 8);
+    }
+}
@@ -0,0 +1,239 @@
+//! Batched Hash DAT lookups over io_uring (Linux, `io-uring` feature).
+//!
+//! The bucket table is probed in memory for every key first, then one read per
+//! candidate entry is submitted to the ring at once, so the device sees many
+//! outstanding reads from a single thread instead of one seek at a time.
+
+use super::{bloom_may_contain, split_entry, Bucket, HashAlgo, HashDatStore};
+use anyhow::{bail, Context, Result};
+use io_uring::{opcode, types, IoUring};
+use std::cell::RefCell;
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// Reads submitted to the ring before waiting for completions
+const DEFAULT_QUEUE_DEPTH: u32 = 64;
+
+/// Read-only Hash DAT reader whose `get_batch` overlaps blob reads with io_uring
+pub struct HashDatUringStore {
+    buckets: Vec<Bucket>,
+    /// Bloom filter section, empty if the file has none
+    bloom: Vec<u8>,
+    file: File,
+    /// Length of `file`, which bounds every read submitted to the ring
+    file_len: u64,
+    ring: RefCell<IoUring>,
+    queue_depth: usize,
+    hash_algo: HashAlgo,
+    entry_header_len: usize,
+    entry_count: usize,
+}
+
+/// A blob heap entry that may hold the value for `keys[key_index]`
+struct Candidate {
+    key_index: usize,
+    blob_offset: u64,
+    blob_len: u64,
+}
+
+impl HashDatUringStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::open_with_queue_depth(path, DEFAULT_QUEUE_DEPTH)
+    }
+
+    /// Open with at most `queue_depth` reads in flight at once
+    pub fn open_with_queue_depth(path: &Path, queue_depth: u32) -> Result<Self> {
+        let mut file = File::open(path).context("Failed to open hash dat file")?;
+        let (header, bloom, buckets) = HashDatStore::read_index(&mut file)?;
+        header.require_single_file()?;
+        header.verify_heap_checksum(&buckets, &mut file)?;
+        let file_len = file
+            .metadata()
+            .context("Failed to stat hash dat file")?
+            .len();
+        let ring = IoUring::new(queue_depth).context("Failed to set up io_uring")?;
+        Ok(Self {
+            buckets,
+            bloom,
+            file,
+            file_len,
+            ring: RefCell::new(ring),
+            queue_depth: queue_depth as usize,
+            hash_algo: header.hash_algo,
+            entry_header_len: header.entry_header_len,
+            entry_count: header.entry_count,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entry_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entry_count == 0
+    }
+
+    /// Look up every key, returning values in the same order as `keys`
+    pub fn get_batch(&self, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>> {
+        let candidates = self.candidates(keys);
+        let mut values: Vec<Option<Vec<u8>>> = vec![None; keys.len()];
+
+        for chunk in candidates.chunks(self.queue_depth) {
+            let blobs = self.read_all(chunk)?;
+            for (candidate, blob) in chunk.iter().zip(blobs) {
+                if values[candidate.key_index].is_some() {
+                    continue;
+                }
+                let (stored_key, _, value) = split_entry(&blob, self.entry_header_len)?;
+                if stored_key == keys[candidate.key_index] {
+                    values[candidate.key_index] = Some(value.to_vec());
+                }
+            }
+        }
+        Ok(values)
+    }
+
+    /// Walk each key's probe sequence in the in-memory bucket table and keep
+    /// every bucket whose hash matches and whose entry is long enough for the key
+    fn candidates(&self, keys: &[&[u8]]) -> Vec<Candidate> {
+        let bucket_count = self.buckets.len();
+        let mut candidates = Vec::with_capacity(keys.len());
+        for (key_index, key) in keys.iter().enumerate() {
+            let key_hash = HashDatStore::hash_key(self.hash_algo, key);
+            if !bloom_may_contain(&self.bloom, key_hash) {
+                continue;
+            }
+            let mut index = (key_hash as usize) % bucket_count;
+            for _ in 0..bucket_count {
+                let bucket = self.buckets[index];
+                if bucket.key_hash == 0 {
+                    break;
+                }
+                if bucket.key_hash == key_hash
+                    && bucket.blob_len >= (self.entry_header_len + key.len()) as u64
+                {
+                    candidates.push(Candidate {
+                        key_index,
+                        blob_offset: bucket.blob_offset,
+                        blob_len: bucket.blob_len,
+                    });
+                }
+                index = (index + 1) % bucket_count;
+            }
+        }
+        candidates
+    }
+
+    /// Submit one read per candidate and wait for all of them to complete.
+    /// Entries that run past the end of the file are rejected before any
+    /// buffer is allocated for them.
+    fn read_all(&self, candidates: &[Candidate]) -> Result<Vec<Vec<u8>>> {
+        for candidate in candidates {
+            let in_file = candidate
+                .blob_offset
+                .checked_add(candidate.blob_len)
+                .is_some_and(|end| end <= self.file_len);
+            if !in_file {
+                bail!(
+                    "Corrupt hash dat file: entry at offset {} ({} bytes) runs past the end of the {}-byte file",
+                    candidate.blob_offset,
+                    candidate.blob_len,
+                    self.file_len
+                );
+            }
+        }
+        let mut buffers: Vec<Vec<u8>> = candidates
+            .iter()
+            .map(|c| vec![0u8; c.blob_len as usize])
+            .collect();
+        let fd = types::Fd(self.file.as_raw_fd());
+        let mut ring = self.ring.borrow_mut();
+
+        for (i, (candidate, buf)) in candidates.iter().zip(buffers.iter_mut()).enumerate() {
+            let read = opcode::Read::new(fd, buf.as_mut_ptr(), buf.len() as u32)
+                .offset(candidate.blob_offset)
+                .build()
+                .user_data(i as u64);
+            // SAFETY: each buffer outlives the wait below and is not touched
+            // until its completion has been reaped
+            unsafe {
+                ring.submission()
+                    .push(&read)
+                    .context("io_uring submission queue is full")?;
+            }
+        }
+        ring.submit_and_wait(candidates.len())
+            .context("Failed to submit io_uring reads")?;
+
+        let mut completed = 0;
+        for cqe in ring.completion() {
+            let i = cqe.user_data() as usize;
+            let result = cqe.result();
+            if result < 0 {
+                bail!(
+                    "io_uring read at offset {} failed: {}",
+                    candidates[i].blob_offset,
+                    std::io::Error::from_raw_os_error(-result)
+                );
+            }
+            if result as usize != buffers[i].len() {
+                bail!(
+                    "Short io_uring read at offset {}: {} of {} bytes",
+                    candidates[i].blob_offset,
+                    result,
+                    buffers[i].len()
+                );
+            }
+            completed += 1;
+        }
+        if completed != candidates.len() {
+            bail!(
+                "Expected {} io_uring completions, got {}",
+                candidates.len(),
+                completed
+            );
+        }
+        Ok(buffers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::HashDatStoreBuilder;
+    use crate::store::{BlobStore, BlobStoreBuilder};
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_uring_batch_matches_sync_gets() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut builder = HashDatStoreBuilder::create(temp_file.path()).unwrap();
+        for i in 0..200 {
+            let value = vec![(i % 251) as u8; 10 + i * 13];
+            builder
+                .insert(format!("key{}", i).as_bytes(), &value)
+                .unwrap();
+        }
+        builder.finish().unwrap();
+
+        let sync = HashDatStore::open(temp_file.path()).unwrap();
+        // A small queue forces several submission rounds
+        let uring = HashDatUringStore::open_with_queue_depth(temp_file.path(), 8).unwrap();
+        assert_eq!(uring.len(), 200);
+
+        let keys: Vec<Vec<u8>> = (0..210)
+            .rev()
+            .map(|i| format!("key{}", i).into_bytes())
+            .collect();
+        let key_refs: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+        let batch = uring.get_batch(&key_refs).unwrap();
+
+        assert_eq!(batch.len(), keys.len());
+        for (key, value) in keys.iter().zip(&batch) {
+            assert_eq!(value, &sync.get(key).unwrap(), "{:?}", key);
+        }
+        // key200..key209 were never inserted
+        assert_eq!(batch.iter().filter(|v| v.is_none()).count(), 10);
+    }
+}
@@ -0,0 +1,370 @@
+use super::page_cache;
+use crate::store::{BlobStore, BlobStoreBuilder};
+use anyhow::{bail, Context, Result};
+use boomphf::Mphf;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 8] = b"MPHIDX01";
+const HEADER_SIZE: usize = 64;
+const SLOT_SIZE: usize = 16; // blob_offset (8) + blob_len (8)
+/// Bytes before the key in a blob heap entry: key_len (4)
+const ENTRY_HEADER_LEN: usize = 4;
+/// Space/speed tradeoff passed to boomphf (its recommended default)
+const GAMMA: f64 = 1.7;
+
+/// Header layout:
+/// - magic: 8 bytes
+/// - entry_count: 8 bytes (u64)
+/// - mph_offset: 8 bytes (u64)
+/// - mph_len: 8 bytes (u64, 0 for an empty store)
+/// - slots_offset: 8 bytes (u64)
+/// - blob_heap_offset: 8 bytes (u64)
+/// - reserved: 16 bytes
+///
+/// The MPH section is boomphf's `Mphf` serialized as JSON. It maps each of the
+/// entry_count keys to a distinct slot in 0..entry_count.
+
+/// Slot layout:
+/// - blob_offset: 8 bytes (u64)
+/// - blob_len: 8 bytes (u64)
+
+/// Blob heap entry layout:
+/// - key_len: 4 bytes (u32)
+/// - key: variable
+/// - value: rest until blob_len
+
+/// Minimal perfect hash .dat store. Every key has its own slot, so a lookup
+/// is one hash and one disk read, with no probing. The MPH also maps absent
+/// keys to some slot, so the stored key is checked before returning a value.
+pub struct MphDatStore {
+    /// `None` for an empty store
+    mphf: Option<Mphf<Vec<u8>>>,
+    /// (blob_offset, blob_len) per MPH slot, loaded at open()
+    slots: Vec<(u64, u64)>,
+    data_file: RefCell<File>,
+}
+
+impl MphDatStore {
+    /// Slot the MPH assigns `key`; for absent keys this is an arbitrary slot or `None`
+    fn slot(&self, key: &[u8]) -> Option<(u64, u64)> {
+        let index = self.mphf.as_ref()?.try_hash(key)? as usize;
+        self.slots.get(index).copied()
+    }
+
+    /// Read the whole entry for a slot and return its value if the key matches
+    fn read_value(&self, key: &[u8], (offset, len): (u64, u64)) -> Result<Option<Vec<u8>>> {
+        if len < (ENTRY_HEADER_LEN + key.len()) as u64 {
+            return Ok(None);
+        }
+        let mut entry = vec![0u8; len as usize];
+        {
+            let mut file = self.data_file.borrow_mut();
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut entry)?;
+        }
+        let key_len = u32::from_le_bytes(entry[0..4].try_into().unwrap()) as usize;
+        let key_end = ENTRY_HEADER_LEN + key_len;
+        if key_len != key.len() || &entry[ENTRY_HEADER_LEN..key_end] != key {
+            return Ok(None);
+        }
+        entry.drain(..key_end);
+        Ok(Some(entry))
+    }
+}
+
+impl BlobStore for MphDatStore {
+    fn open(path: &Path) -> Result<Self> {
+        let mut file = File::open(path).context("Failed to open MPH dat file")?;
+        let file_len = file.metadata()?.len();
+
+        let mut header = [0u8; HEADER_SIZE];
+        file.read_exact(&mut header)
+            .context("Failed to read MPH dat header")?;
+        if &header[0..8] != MAGIC {
+            bail!("Invalid magic number");
+        }
+        let field = |i: usize| u64::from_le_bytes(header[i..i + 8].try_into().unwrap());
+        let entry_count = field(8);
+        let mph_offset = field(16);
+        let mph_len = field(24);
+        let slots_offset = field(32);
+        let blob_heap_offset = field(40);
+
+        let slots_end = entry_count
+            .checked_mul(SLOT_SIZE as u64)
+            .and_then(|len| len.checked_add(slots_offset));
+        if mph_offset != HEADER_SIZE as u64
+            || mph_offset.checked_add(mph_len) != Some(slots_offset)
+            || slots_end != Some(blob_heap_offset)
+            || blob_heap_offset > file_len
+        {
+            bail!("Corrupt MPH dat header");
+        }
+
+        let mut mph = vec![0u8; mph_len as usize];
+        file.read_exact(&mut mph)
+            .context("Failed to read MPH section")?;
+        let mphf = if entry_count == 0 {
+            None
+        } else {
+            Some(serde_json::from_slice(&mph).context("Corrupt MPH section")?)
+        };
+
+        let mut slot_bytes = vec![0u8; (blob_heap_offset - slots_offset) as usize];
+        file.read_exact(&mut slot_bytes)
+            .context("Failed to read MPH slot table")?;
+        let slots = slot_bytes
+            .chunks_exact(SLOT_SIZE)
+            .map(|slot| {
+                (
+                    u64::from_le_bytes(slot[0..8].try_into().unwrap()),
+                    u64::from_le_bytes(slot[8..16].try_into().unwrap()),
+                )
+            })
+            .collect();
+
+        Ok(Self {
+            mphf,
+            slots,
+            data_file: RefCell::new(file),
+        })
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        match self.slot(key) {
+            Some(slot) => self.read_value(key, slot),
+            None => Ok(None),
+        }
+    }
+
+    /// The hash function and slot table are loaded at open; this evicts the
+    /// blob heap they point into.
+    fn drop_caches(&self) -> Result<bool> {
+        page_cache::evict(&self.data_file.borrow(), None)
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        let mut file = self.data_file.borrow_mut();
+        let mut keys = Vec::with_capacity(self.slots.len());
+        for &(offset, _) in &self.slots {
+            let mut key_len = [0u8; ENTRY_HEADER_LEN];
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut key_len)?;
+            let mut key = vec![0u8; u32::from_le_bytes(key_len) as usize];
+            file.read_exact(&mut key)?;
+            keys.push(key);
+        }
+        Ok(keys)
+    }
+
+    /// Sums entry lengths from the slot table, reading only each key length.
+    fn total_value_bytes(&self) -> Result<u64> {
+        let mut file = self.data_file.borrow_mut();
+        let mut total = 0u64;
+        for &(offset, len) in &self.slots {
+            let mut key_len = [0u8; ENTRY_HEADER_LEN];
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut key_len)?;
+            total += len - (ENTRY_HEADER_LEN as u64 + u32::from_le_bytes(key_len) as u64);
+        }
+        Ok(total)
+    }
+
+    fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn backend_name() -> &'static str {
+        "MPH DAT"
+    }
+}
+
+/// Builder for MPH .dat store.
+/// Keys are collected in memory; `finish` builds the perfect hash over all of them.
+pub struct MphDatStoreBuilder {
+    path: PathBuf,
+    /// Distinct keys in first-insertion order, which fixes the blob heap layout
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Position of each key in `entries`, so re-inserting a key replaces its value
+    positions: HashMap<Vec<u8>, usize>,
+}
+
+impl BlobStoreBuilder for MphDatStoreBuilder {
+    fn create(path: &Path) -> Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries: Vec::new(),
+            positions: HashMap::new(),
+        })
+    }
+
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        match self.positions.get(key) {
+            // Last write wins, as with the other builders
+            Some(&pos) => self.entries[pos].1 = value.to_vec(),
+            None => {
+                self.positions.insert(key.to_vec(), self.entries.len());
+                self.entries.push((key.to_vec(), value.to_vec()));
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        let entry_count = self.entries.len();
+        let keys: Vec<Vec<u8>> = self.entries.iter().map(|(key, _)| key.clone()).collect();
+        let mphf = (!keys.is_empty()).then(|| Mphf::new(GAMMA, &keys));
+        let mph = match &mphf {
+            Some(mphf) => serde_json::to_vec(mphf).context("Failed to encode MPH")?,
+            None => Vec::new(),
+        };
+
+        let slots_offset = (HEADER_SIZE + mph.len()) as u64;
+        let blob_heap_offset = slots_offset + (entry_count * SLOT_SIZE) as u64;
+
+        // Blob heap in insertion order; each key's entry goes in its MPH slot
+        let mut slots = vec![(0u64, 0u64); entry_count];
+        let mut filled = vec![false; entry_count];
+        let mut offset = blob_heap_offset;
+        for (key, value) in &self.entries {
+            let index = mphf.as_ref().map_or(0, |mphf| mphf.hash(key) as usize);
+            if std::mem::replace(&mut filled[index], true) {
+                bail!("MPH mapped two keys to slot {}", index);
+            }
+            let len = (ENTRY_HEADER_LEN + key.len() + value.len()) as u64;
+            slots[index] = (offset, len);
+            offset += len;
+        }
+
+        let file = File::create(&self.path).context("Failed to create MPH dat file")?;
+        let mut writer = BufWriter::new(file);
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(entry_count as u64).to_le_bytes())?;
+        writer.write_all(&(HEADER_SIZE as u64).to_le_bytes())?;
+        writer.write_all(&(mph.len() as u64).to_le_bytes())?;
+        writer.write_all(&slots_offset.to_le_bytes())?;
+        writer.write_all(&blob_heap_offset.to_le_bytes())?;
+        writer.write_all(&[0u8; HEADER_SIZE - 48])?;
+
+        writer.write_all(&mph)?;
+        for (blob_offset, blob_len) in &slots {
+            writer.write_all(&blob_offset.to_le_bytes())?;
+            writer.write_all(&blob_len.to_le_bytes())?;
+        }
+        for (key, value) in &self.entries {
+            writer.write_all(&(key.len() as u32).to_le_bytes())?;
+            writer.write_all(key)?;
+            writer.write_all(value)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    fn build(path: &Path, entries: &[(Vec<u8>, Vec<u8>)]) {
+        let mut builder = MphDatStoreBuilder::create(path).unwrap();
+        for (key, value) in entries {
+            builder.insert(key, value).unwrap();
+        }
+        builder.finish().unwrap();
+    }
+
+    #[test]
+    fn test_mph_roundtrip() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        build(
+            path,
+            &[
+                (b"key1".to_vec(), b"value1".to_vec()),
+                (b"key2".to_vec(), b"value2".to_vec()),
+                (b"key3".to_vec(), b"value3".to_vec()),
+            ],
+        );
+
+        let store = MphDatStore::open(path).unwrap();
+
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+        assert_eq!(store.get(b"key2").unwrap(), Some(b"value2".to_vec()));
+        assert_eq!(store.get(b"key3").unwrap(), Some(b"value3".to_vec()));
+        assert_eq!(store.get(b"nonexistent").unwrap(), None);
+        assert_eq!(store.total_value_bytes().unwrap(), 18);
+    }
+
+    #[test]
+    fn test_mph_rejects_missing_keys() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = (0..1000)
+            .map(|i| (format!("key_{:05}", i).into_bytes(), vec![i as u8; i % 64]))
+            .collect();
+        build(path, &entries);
+
+        let store = MphDatStore::open(path).unwrap();
+        // Absent keys land on occupied slots, so only the key check rejects them
+        for i in 1000..3000 {
+            let key = format!("key_{:05}", i).into_bytes();
+            assert_eq!(store.get(&key).unwrap(), None, "{}", i);
+        }
+        // Including keys that are prefixes or extensions of stored keys
+        assert_eq!(store.get(b"key_0000").unwrap(), None);
+        assert_eq!(store.get(b"key_000000").unwrap(), None);
+        assert_eq!(store.get(b"").unwrap(), None);
+    }
+
+    #[test]
+    fn test_mph_assigns_every_key_its_own_slot() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        // Short, similar keys and a repeated key, which must collapse to one slot
+        let mut entries: Vec<(Vec<u8>, Vec<u8>)> = (0..=255u8)
+            .map(|b| (vec![b], vec![b, b]))
+            .chain((0..=255u8).map(|b| (vec![0, b], vec![b])))
+            .collect();
+        entries.push((vec![7], b"last".to_vec()));
+        build(path, &entries);
+
+        let store = MphDatStore::open(path).unwrap();
+        assert_eq!(store.len(), 512);
+        let mut offsets: Vec<u64> = store.slots.iter().map(|&(offset, _)| offset).collect();
+        offsets.sort_unstable();
+        offsets.dedup();
+        assert_eq!(offsets.len(), 512);
+
+        assert_eq!(store.get(&[7]).unwrap(), Some(b"last".to_vec()));
+        for b in 0..=255u8 {
+            if b != 7 {
+                assert_eq!(store.get(&[b]).unwrap(), Some(vec![b, b]));
+            }
+            assert_eq!(store.get(&[0, b]).unwrap(), Some(vec![b]));
+        }
+        let mut keys = store.keys().unwrap();
+        keys.sort();
+        let mut expected: Vec<Vec<u8>> = entries[..512].iter().map(|(k, _)| k.clone()).collect();
+        expected.sort();
+        assert_eq!(keys, expected);
+    }
+
+    #[test]
+    fn test_mph_empty_store() {
+        let temp_file = NamedTempFile::new().unwrap();
+        build(temp_file.path(), &[]);
+
+        let store = MphDatStore::open(temp_file.path()).unwrap();
+        assert_eq!(store.len(), 0);
+        assert_eq!(store.get(b"anything").unwrap(), None);
+        assert!(store.keys().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+use crate::backends::dat_hash::{HashDatStore, HashDatStoreBuilder};
+use crate::store::{BlobStore, BlobStoreBuilder};
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// zstd level used unless the builder is given another with `with_level`
+const DEFAULT_LEVEL: i32 = 3;
+
+/// Hash DAT store whose values are zstd-compressed one by one (`HASHZST1`).
+/// The layout is the Hash DAT one; each blob heap entry also records the
+/// uncompressed length, so a lookup decompresses into an exactly sized buffer.
+pub struct ZstdDatStore {
+    inner: HashDatStore,
+}
+
+impl BlobStore for ZstdDatStore {
+    fn open(path: &Path) -> Result<Self> {
+        Ok(Self {
+            inner: HashDatStore::open_compressed(path)?,
+        })
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        match self.inner.get_compressed(key)? {
+            Some((stored, raw_len)) => {
+                let value = zstd::bulk::decompress(&stored, raw_len as usize)
+                    .context("Failed to decompress value")?;
+                Ok(Some(value))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn drop_caches(&self) -> Result<bool> {
+        self.inner.drop_caches()
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        self.inner.keys()
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Reads the stored and uncompressed lengths from each entry header only,
+    /// without reading or decompressing any value.
+    fn compression_ratio(&self) -> Result<f64> {
+        let (stored, uncompressed) = self.inner.compressed_totals()?;
+        if stored == 0 {
+            return Ok(1.0);
+        }
+        Ok(uncompressed as f64 / stored as f64)
+    }
+
+    fn backend_name() -> &'static str {
+        "Zstd DAT"
+    }
+}
+
+/// Builder for zstd-compressed Hash DAT store.
+/// Values are compressed in `finish`, at level 3 unless set with `with_level`.
+pub struct ZstdDatStoreBuilder {
+    inner: HashDatStoreBuilder,
+}
+
+impl ZstdDatStoreBuilder {
+    /// Compress values at `level` (zstd's 1..=22; higher is smaller and slower)
+    pub fn with_level(mut self, level: i32) -> Self {
+        self.inner = self.inner.with_zstd(level);
+        self
+    }
+}
+
+impl BlobStoreBuilder for ZstdDatStoreBuilder {
+    fn create(path: &Path) -> Result<Self> {
+        Ok(Self {
+            inner: HashDatStoreBuilder::create(path)?.with_zstd(DEFAULT_LEVEL),
+        })
+    }
+
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.inner.insert(key, value)
+    }
+
+    fn finish(self) -> Result<()> {
+        self.inner.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_zstd_dat_roundtrip() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = (0..100usize)
+            .map(|i| {
+                let key = format!("key_{}", i).into_bytes();
+                let value = (0..i * 13).map(|j| (j % 7) as u8).collect();
+                (key, value)
+            })
+            .collect();
+
+        {
+            let mut builder = ZstdDatStoreBuilder::create(temp_file.path()).unwrap();
+            for (key, value) in &entries {
+                builder.insert(key, value).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        let store = ZstdDatStore::open(temp_file.path()).unwrap();
+        assert_eq!(store.len(), entries.len());
+        for (key, value) in &entries {
+            assert_eq!(store.get(key).unwrap().as_ref(), Some(value));
+        }
+        assert_eq!(store.get(b"nonexistent").unwrap(), None);
+        assert_eq!(store.keys().unwrap().len(), entries.len());
+    }
+
+    #[test]
+    fn test_zstd_dat_compresses_values() {
+        let plain_file = NamedTempFile::new().unwrap();
+        let zstd_file = NamedTempFile::new().unwrap();
+        let value = b"repetitive payload ".repeat(1000);
+
+        {
+            let mut builder = HashDatStoreBuilder::create(plain_file.path()).unwrap();
+            builder.insert(b"key", &value).unwrap();
+            builder.finish().unwrap();
+        }
+        {
+            let mut builder = ZstdDatStoreBuilder::create(zstd_file.path())
+                .unwrap()
+                .with_level(19);
+            builder.insert(b"key", &value).unwrap();
+            builder.finish().unwrap();
+        }
+
+        let plain_len = std::fs::metadata(plain_file.path()).unwrap().len();
+        let zstd_len = std::fs::metadata(zstd_file.path()).unwrap().len();
+        assert!(zstd_len < plain_len);
+
+        let store = ZstdDatStore::open(zstd_file.path()).unwrap();
+        assert_eq!(store.get(b"key").unwrap(), Some(value));
+
+        // Only the 24-byte entry header is read, not the key or the value
+        store.inner.start_access_tracking();
+        assert!(store.compression_ratio().unwrap() > 10.0);
+        assert_eq!(store.inner.bytes_read(), Some(24));
+    }
+
+    #[test]
+    fn test_readers_reject_the_other_format() {
+        let plain_file = NamedTempFile::new().unwrap();
+        let zstd_file = NamedTempFile::new().unwrap();
+
+        {
+            let mut builder = HashDatStoreBuilder::create(plain_file.path()).unwrap();
+            builder.insert(b"key", b"value").unwrap();
+            builder.finish().unwrap();
+        }
+        {
+            let mut builder = ZstdDatStoreBuilder::create(zstd_file.path()).unwrap();
+            builder.insert(b"key", b"value").unwrap();
+            builder.finish().unwrap();
+        }
+
+        // Plain readers would hand back compressed bytes as the value
+        assert!(HashDatStore::open(zstd_file.path()).is_err());
+        assert!(ZstdDatStore::open(plain_file.path()).is_err());
+    }
+}
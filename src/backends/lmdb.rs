@@ -0,0 +1,237 @@
+use super::page_cache;
+use crate::store::{BlobStore, BlobStoreBuilder};
+use anyhow::{bail, Context, Result};
+use heed::types::Bytes;
+use heed::{Database, Env, EnvFlags, EnvOpenOptions};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Upper bound on the memory map. LMDB only reserves address space for it; the
+/// file grows with the data.
+const MAP_SIZE: usize = 1 << 40;
+/// Name of LMDB's data file inside the environment directory
+const DATA_FILE: &str = "data.mdb";
+
+type BlobDb = Database<Bytes, Bytes>;
+
+/// LMDB-backed blob store: a memory-mapped copy-on-write B+tree, the production
+/// counterpart to `BTreeDatStore`. The index is an environment directory.
+pub struct LmdbStore {
+    env: Env,
+    db: BlobDb,
+    count: usize,
+    path: PathBuf,
+}
+
+impl BlobStore for LmdbStore {
+    /// Opens the environment read-only with `MDB_NORDAHEAD`, so the OS doesn't
+    /// read ahead around each random lookup.
+    fn open(path: &Path) -> Result<Self> {
+        let mut options = EnvOpenOptions::new();
+        options.map_size(MAP_SIZE);
+        let env = unsafe {
+            options.flags(EnvFlags::READ_ONLY | EnvFlags::NO_READ_AHEAD);
+            options.open(path)
+        }
+        .context("Failed to open LMDB environment")?;
+
+        let rtxn = env.read_txn().context("Failed to start LMDB read txn")?;
+        let db: BlobDb = env
+            .open_database(&rtxn, None)
+            .context("Failed to open LMDB database")?
+            .context("LMDB environment has no database")?;
+        // Entry count from the database stat (mdb_stat), not a scan
+        let count = db.len(&rtxn).context("Failed to stat LMDB database")? as usize;
+        drop(rtxn);
+
+        Ok(Self {
+            env,
+            db,
+            count,
+            path: path.to_path_buf(),
+        })
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .context("Failed to start LMDB read txn")?;
+        let value = self
+            .db
+            .get(&rtxn, key)
+            .context("Failed to read from LMDB")?;
+        Ok(value.map(|value| value.to_vec()))
+    }
+
+    /// Copies straight from the mapping into `buf`.
+    fn get_into(&self, key: &[u8], buf: &mut Vec<u8>) -> Result<bool> {
+        buf.clear();
+        let rtxn = self
+            .env
+            .read_txn()
+            .context("Failed to start LMDB read txn")?;
+        let value = self
+            .db
+            .get(&rtxn, key)
+            .context("Failed to read from LMDB")?;
+        buf.extend_from_slice(value.unwrap_or_default());
+        Ok(value.is_some())
+    }
+
+    /// Drops LMDB's mapping of the data file from this process, then evicts
+    /// the file itself.
+    fn drop_caches(&self) -> Result<bool> {
+        let info = self.env.info();
+        // SAFETY: the environment is opened read-only without `WRITE_MAP`, so
+        // its map is a read-only, file-backed mapping
+        let dropped = unsafe { page_cache::drop_mapping(info.map_addr, info.map_size)? };
+        let file =
+            File::open(self.path.join(DATA_FILE)).context("Failed to open LMDB data file")?;
+        let evicted = page_cache::evict(&file, None)?;
+        Ok(dropped && evicted)
+    }
+
+    /// Walks a cursor over the whole database, in key order.
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .context("Failed to start LMDB read txn")?;
+        let mut keys = Vec::with_capacity(self.count);
+        for entry in self.db.iter(&rtxn).context("Failed to open LMDB cursor")? {
+            let (key, _) = entry.context("Failed to read LMDB entry")?;
+            keys.push(key.to_vec());
+        }
+        Ok(keys)
+    }
+
+    fn len(&self) -> usize {
+        self.count
+    }
+
+    fn backend_name() -> &'static str {
+        "LMDB"
+    }
+}
+
+/// Builder for LMDB blob store.
+/// Inserts are buffered and written in key order in a single write
+/// transaction, committed by `finish`.
+pub struct LmdbStoreBuilder {
+    path: PathBuf,
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl BlobStoreBuilder for LmdbStoreBuilder {
+    fn create(path: &Path) -> Result<Self> {
+        // Start from an empty environment, but only ever delete an old LMDB one
+        if path.exists() {
+            if !path.join(DATA_FILE).exists() {
+                bail!(
+                    "{} exists and is not an LMDB environment; refusing to overwrite it",
+                    path.display()
+                );
+            }
+            std::fs::remove_dir_all(path).context("Failed to remove old LMDB environment")?;
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries: Vec::new(),
+        })
+    }
+
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.entries.push((key.to_vec(), value.to_vec()));
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        std::fs::create_dir_all(&self.path).context("Failed to create LMDB directory")?;
+        let mut options = EnvOpenOptions::new();
+        options.map_size(MAP_SIZE);
+        let env =
+            unsafe { options.open(&self.path) }.context("Failed to create LMDB environment")?;
+
+        // Sorted inserts fill B+tree pages in order; the sort is stable, so a
+        // repeated key's last value is still written last and wins
+        self.entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut wtxn = env.write_txn().context("Failed to start LMDB write txn")?;
+        let db: BlobDb = env
+            .create_database(&mut wtxn, None)
+            .context("Failed to create LMDB database")?;
+        for (key, value) in &self.entries {
+            db.put(&mut wtxn, key, value)
+                .context("Failed to write to LMDB")?;
+        }
+        wtxn.commit().context("Failed to commit LMDB write txn")?;
+        env.force_sync()
+            .context("Failed to sync LMDB environment")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lmdb_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index_lmdb");
+
+        {
+            let mut builder = LmdbStoreBuilder::create(&path).unwrap();
+            builder.insert(b"key2", b"value2").unwrap();
+            builder.insert(b"key3", b"stale").unwrap();
+            builder.insert(b"key1", b"value1").unwrap();
+            builder.insert(b"key3", b"value3").unwrap();
+            builder.finish().unwrap();
+        }
+
+        let store = LmdbStore::open(&path).unwrap();
+
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+        assert_eq!(store.get(b"key2").unwrap(), Some(b"value2".to_vec()));
+        assert_eq!(store.get(b"key3").unwrap(), Some(b"value3".to_vec()));
+        assert_eq!(store.get(b"nonexistent").unwrap(), None);
+        assert_eq!(
+            store.keys().unwrap(),
+            vec![b"key1".to_vec(), b"key2".to_vec(), b"key3".to_vec()]
+        );
+
+        let mut buf = Vec::new();
+        assert!(store.get_into(b"key2", &mut buf).unwrap());
+        assert_eq!(buf, b"value2");
+        assert!(!store.get_into(b"nonexistent", &mut buf).unwrap());
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_lmdb_rebuild_replaces_old_store() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index_lmdb");
+
+        for keys in [&[b"old_a", b"old_b"][..], &[b"new_a"][..]] {
+            let mut builder = LmdbStoreBuilder::create(&path).unwrap();
+            for key in keys {
+                builder.insert(*key, b"v").unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        let store = LmdbStore::open(&path).unwrap();
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.keys().unwrap(), vec![b"new_a".to_vec()]);
+
+        // Anything that isn't an LMDB environment is left alone
+        let file = dir.path().join("not_a_db");
+        std::fs::write(&file, b"keep me").unwrap();
+        assert!(LmdbStoreBuilder::create(&file).is_err());
+        assert_eq!(std::fs::read(&file).unwrap(), b"keep me");
+    }
+}
@@ -0,0 +1,159 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const TRAILER_MAGIC: &[u8; 8] = b"DATMANI1";
+/// Bytes after the manifest JSON: manifest_len (4) + magic (8)
+const TRAILER_SUFFIX_LEN: usize = 12;
+/// Largest manifest JSON a builder will embed
+pub const MAX_MANIFEST_LEN: usize = 1024;
+
+/// Trailer layout, appended after everything else in a `.dat` file built with a
+/// manifest (the formats locate their data from the header, so readers that
+/// don't know about the trailer ignore it):
+/// - manifest: JSON, at most `MAX_MANIFEST_LEN` bytes
+/// - manifest_len: 4 bytes (u32)
+/// - magic: 8 bytes ("DATMANI1")
+
+/// Human-readable description of a `.dat` file, embedded by builders when
+/// asked so the file documents itself without parsing its structure.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatManifest {
+    /// Backend name as `BlobStore::backend_name` reports it, e.g. "Custom Offset File Format"
+    pub format: String,
+    /// Magic number of the format revision, e.g. "HASHIDX4"
+    pub version: String,
+    pub entry_count: u64,
+    /// Build time in seconds since the Unix epoch
+    pub built_at: u64,
+    /// Caller-supplied description of the input data (e.g. the data generation config)
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+    /// Hash function placing keys, for hashed formats
+    #[serde(default)]
+    pub hash_algo: Option<String>,
+}
+
+impl DatManifest {
+    pub(crate) fn new(
+        format: &str,
+        magic: &[u8; 8],
+        entry_count: u64,
+        fingerprint: Option<String>,
+    ) -> Self {
+        Self {
+            format: format.to_string(),
+            version: String::from_utf8_lossy(magic).into_owned(),
+            entry_count,
+            built_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            fingerprint,
+            hash_algo: None,
+        }
+    }
+
+    /// Append the manifest trailer at the writer's current position (the end of the file)
+    pub(crate) fn write_trailer(&self, writer: &mut impl Write) -> Result<()> {
+        let json = serde_json::to_vec(self)?;
+        if json.len() > MAX_MANIFEST_LEN {
+            bail!(
+                "Manifest is {} bytes, more than the {}-byte maximum",
+                json.len(),
+                MAX_MANIFEST_LEN
+            );
+        }
+        writer.write_all(&json)?;
+        writer.write_all(&(json.len() as u32).to_le_bytes())?;
+        writer.write_all(TRAILER_MAGIC)?;
+        Ok(())
+    }
+}
+
+/// Length of the manifest trailer at the end of `data`, or 0 if there is none
+pub(crate) fn trailer_len(data: &[u8]) -> usize {
+    let Some(suffix_start) = data.len().checked_sub(TRAILER_SUFFIX_LEN) else {
+        return 0;
+    };
+    let suffix = &data[suffix_start..];
+    if &suffix[4..] != TRAILER_MAGIC {
+        return 0;
+    }
+    let json_len = u32::from_le_bytes(suffix[0..4].try_into().unwrap()) as usize;
+    if json_len > MAX_MANIFEST_LEN || json_len > suffix_start {
+        return 0;
+    }
+    json_len + TRAILER_SUFFIX_LEN
+}
+
+/// Read the manifest embedded at the end of a `.dat` file, reading only the
+/// trailer. Returns `None` for files built without one.
+pub fn read_manifest(path: &Path) -> Result<Option<DatManifest>> {
+    let mut file = File::open(path).context("Failed to open dat file")?;
+    let file_len = file.seek(SeekFrom::End(0))?;
+    let tail_len = file_len.min((MAX_MANIFEST_LEN + TRAILER_SUFFIX_LEN) as u64);
+    file.seek(SeekFrom::Start(file_len - tail_len))?;
+    let mut tail = vec![0u8; tail_len as usize];
+    file.read_exact(&mut tail)
+        .context("Failed to read dat file trailer")?;
+
+    let len = trailer_len(&tail);
+    if len == 0 {
+        return Ok(None);
+    }
+    let json = &tail[tail.len() - len..tail.len() - TRAILER_SUFFIX_LEN];
+    let manifest = serde_json::from_slice(json).context("Corrupt dat manifest")?;
+    Ok(Some(manifest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::{
+        BPlusTreeStore, BPlusTreeStoreBuilder, BTreeDatStore, BTreeDatStoreBuilder,
+    };
+    use crate::store::{BlobStore, BlobStoreBuilder};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_tree_formats_open_with_manifest_trailer() {
+        let dir = TempDir::new().unwrap();
+        let btree_path = dir.path().join("index_btree.dat");
+        let bplustree_path = dir.path().join("index_bplustree.dat");
+        let mut btree = BTreeDatStoreBuilder::create(&btree_path)
+            .unwrap()
+            .with_manifest(None);
+        let mut bplustree = BPlusTreeStoreBuilder::create(&bplustree_path)
+            .unwrap()
+            .with_manifest(Some("seed=7"));
+        for i in 0..50u32 {
+            let key = format!("key_{:04}", i).into_bytes();
+            btree.insert(&key, &i.to_le_bytes()).unwrap();
+            bplustree.insert(&key, &i.to_le_bytes()).unwrap();
+        }
+        btree.finish().unwrap();
+        bplustree.finish().unwrap();
+
+        let manifest = read_manifest(&btree_path).unwrap().unwrap();
+        assert_eq!(manifest.format, "B-tree DAT");
+        assert_eq!(manifest.version, "BTREEID3");
+        assert_eq!(manifest.entry_count, 50);
+        assert_eq!(manifest.fingerprint, None);
+        let manifest = read_manifest(&bplustree_path).unwrap().unwrap();
+        assert_eq!(manifest.format, "B+tree");
+        assert_eq!(manifest.fingerprint.as_deref(), Some("seed=7"));
+
+        let btree = BTreeDatStore::open(&btree_path).unwrap();
+        let bplustree = BPlusTreeStore::open(&bplustree_path).unwrap();
+        for i in [0u32, 25, 49] {
+            let key = format!("key_{:04}", i).into_bytes();
+            assert_eq!(btree.get(&key).unwrap(), Some(i.to_le_bytes().to_vec()));
+            assert_eq!(bplustree.get(&key).unwrap(), Some(i.to_le_bytes().to_vec()));
+        }
+        assert_eq!(btree.keys().unwrap().len(), 50);
+        assert_eq!(bplustree.keys().unwrap().len(), 50);
+    }
+}
@@ -0,0 +1,256 @@
+use crate::store::{BlobStore, BlobStoreBuilder};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 8] = b"MEMSTOR1";
+const HEADER_SIZE: usize = 16;
+/// key_len and value_len: the size of an entry with an empty key and value
+const MIN_ENTRY_SIZE: usize = 12;
+
+/// File layout:
+/// - magic: 8 bytes
+/// - entry_count: 8 bytes (u64)
+/// - entries, sorted by key:
+///   - key_len: 4 bytes (u32)
+///   - value_len: 8 bytes (u64)
+///   - key: variable
+///   - value: variable
+///
+/// The file only exists so `open` can reload the data; every lookup is served
+/// from a `HashMap` held in RAM.
+
+/// Store that loads every entry into a `HashMap` on open. Lookups never touch
+/// the disk, so its latency is the floor the on-disk formats are measured against.
+pub struct MemoryStore {
+    entries: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+/// Read a little-endian integer field of `N` bytes at `*pos`, advancing it
+fn read_field<const N: usize>(data: &[u8], pos: &mut usize) -> Result<[u8; N]> {
+    let bytes = data
+        .get(*pos..*pos + N)
+        .context("In-memory store file is truncated")?;
+    *pos += N;
+    Ok(bytes.try_into().unwrap())
+}
+
+impl BlobStore for MemoryStore {
+    fn open(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path).context("Failed to read in-memory store file")?;
+        if data.len() < HEADER_SIZE || &data[..8] != MAGIC {
+            bail!("Invalid in-memory store file (bad magic)");
+        }
+        let entry_count = u64::from_le_bytes(data[8..16].try_into().unwrap()) as usize;
+
+        // entry_count is untrusted, so only the file's length may size the map
+        let mut entries =
+            HashMap::with_capacity(entry_count.min((data.len() - HEADER_SIZE) / MIN_ENTRY_SIZE));
+        let mut pos = HEADER_SIZE;
+        for _ in 0..entry_count {
+            let key_len = u32::from_le_bytes(read_field(&data, &mut pos)?) as usize;
+            let value_len = u64::from_le_bytes(read_field(&data, &mut pos)?) as usize;
+            let end = pos
+                .checked_add(key_len)
+                .and_then(|key_end| key_end.checked_add(value_len))
+                .filter(|&end| end <= data.len())
+                .context("In-memory store file is truncated")?;
+            let key = data[pos..pos + key_len].to_vec();
+            let value = data[pos + key_len..end].to_vec();
+            entries.insert(key, value);
+            pos = end;
+        }
+
+        Ok(Self { entries })
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.entries.get(key).cloned())
+    }
+
+    fn get_into(&self, key: &[u8], buf: &mut Vec<u8>) -> Result<bool> {
+        buf.clear();
+        let value = self.entries.get(key);
+        buf.extend_from_slice(value.map(Vec::as_slice).unwrap_or_default());
+        Ok(value.is_some())
+    }
+
+    fn contains_key(&self, key: &[u8]) -> Result<bool> {
+        Ok(self.entries.contains_key(key))
+    }
+
+    fn value_len(&self, key: &[u8]) -> Result<Option<usize>> {
+        Ok(self.entries.get(key).map(Vec::len))
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        Ok(self.entries.keys().cloned().collect())
+    }
+
+    fn total_value_bytes(&self) -> Result<u64> {
+        Ok(self.entries.values().map(|value| value.len() as u64).sum())
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn backend_name() -> &'static str {
+        "In-Memory"
+    }
+}
+
+/// Builder that collects entries in a `HashMap` (a repeated key keeps its last
+/// value) and writes them out sorted by key, so the same entries always give
+/// the same file.
+pub struct MemoryStoreBuilder {
+    path: PathBuf,
+    entries: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+impl BlobStoreBuilder for MemoryStoreBuilder {
+    fn create(path: &Path) -> Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries: HashMap::new(),
+        })
+    }
+
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        if key.len() > u32::MAX as usize {
+            bail!("Key of {} bytes is too long", key.len());
+        }
+        self.entries.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        let file = File::create(&self.path).context("Failed to create in-memory store file")?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        for (key, value) in entries {
+            writer.write_all(&(key.len() as u32).to_le_bytes())?;
+            writer.write_all(&(value.len() as u64).to_le_bytes())?;
+            writer.write_all(key)?;
+            writer.write_all(value)?;
+        }
+
+        writer
+            .into_inner()
+            .context("Failed to flush in-memory store file")?
+            .sync_all()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec as prop_vec;
+    use proptest::prelude::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_memory_roundtrip() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        // Build the store
+        {
+            let mut builder = MemoryStoreBuilder::create(path).unwrap();
+            builder.insert(b"key1", b"value1").unwrap();
+            builder.insert(b"key2", b"value2").unwrap();
+            builder.insert(b"key3", b"value3").unwrap();
+            builder.finish().unwrap();
+        }
+
+        // Read it back
+        let store = MemoryStore::open(path).unwrap();
+
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+        assert_eq!(store.get(b"key2").unwrap(), Some(b"value2".to_vec()));
+        assert_eq!(store.get(b"key3").unwrap(), Some(b"value3".to_vec()));
+        assert_eq!(store.get(b"nonexistent").unwrap(), None);
+    }
+
+    #[test]
+    fn test_memory_duplicate_key_keeps_last_value() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut builder = MemoryStoreBuilder::create(path).unwrap();
+            builder.insert(b"key", b"first").unwrap();
+            builder.insert(b"key", b"second").unwrap();
+            builder.finish().unwrap();
+        }
+
+        let store = MemoryStore::open(path).unwrap();
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.get(b"key").unwrap(), Some(b"second".to_vec()));
+    }
+
+    #[test]
+    fn test_memory_truncated_file_fails_to_open() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut builder = MemoryStoreBuilder::create(path).unwrap();
+            builder.insert(b"key", &[7u8; 100]).unwrap();
+            builder.finish().unwrap();
+        }
+        let data = std::fs::read(path).unwrap();
+        std::fs::write(path, &data[..data.len() - 1]).unwrap();
+
+        let err = MemoryStore::open(path).err().unwrap();
+        assert!(err.to_string().contains("truncated"), "{}", err);
+    }
+
+    #[test]
+    fn test_memory_huge_entry_count_fails_to_open() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut builder = MemoryStoreBuilder::create(path).unwrap();
+            builder.insert(b"key", b"value").unwrap();
+            builder.finish().unwrap();
+        }
+        let mut data = std::fs::read(path).unwrap();
+        data[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(path, &data).unwrap();
+
+        let err = MemoryStore::open(path).err().unwrap();
+        assert!(err.to_string().contains("truncated"), "{}", err);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_memory_roundtrip(
+            entries in prop::collection::hash_map(prop_vec(any::<u8>(), 0..32), prop_vec(any::<u8>(), 0..256), 0..50)
+        ) {
+            let temp_file = NamedTempFile::new().unwrap();
+            let path = temp_file.path();
+
+            let mut builder = MemoryStoreBuilder::create(path).unwrap();
+            for (key, value) in &entries {
+                builder.insert(key, value).unwrap();
+            }
+            builder.finish().unwrap();
+
+            let store = MemoryStore::open(path).unwrap();
+            prop_assert_eq!(store.len(), entries.len());
+            for (key, value) in &entries {
+                prop_assert_eq!(store.get(key).unwrap().as_ref(), Some(value));
+            }
+        }
+    }
+}
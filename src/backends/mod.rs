@@ -1,12 +1,329 @@
+pub mod bplustree;
+mod checksum;
 pub mod dat_btree;
 pub mod dat_hash;
+#[cfg(feature = "mph")]
+pub mod dat_mph;
+pub mod dat_zstd;
+mod file_io;
+#[cfg(feature = "lmdb")]
+pub mod lmdb;
+pub mod manifest;
+pub mod memory;
+mod page_cache;
+#[cfg(feature = "rocksdb")]
+pub mod rocksdb;
 pub mod sqlite;
+pub mod sstable;
 pub mod zip;
 
+pub use bplustree::{BPlusTreeStore, BPlusTreeStoreBuilder};
 pub use dat_btree::{BTreeDatStore, BTreeDatStoreBuilder};
-pub use dat_hash::{HashDatStore, HashDatStoreBuilder};
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub use dat_hash::HashDatUringStore;
+pub use dat_hash::{HashAlgo, HashDatMmapStore, HashDatStore, HashDatStoreBuilder, ReadSeek};
+#[cfg(feature = "mph")]
+pub use dat_mph::{MphDatStore, MphDatStoreBuilder};
+pub use dat_zstd::{ZstdDatStore, ZstdDatStoreBuilder};
+#[cfg(feature = "lmdb")]
+pub use lmdb::{LmdbStore, LmdbStoreBuilder};
+pub use manifest::{read_manifest, DatManifest};
+pub use memory::{MemoryStore, MemoryStoreBuilder};
+#[cfg(feature = "rocksdb")]
+pub use rocksdb::{RocksDbStore, RocksDbStoreBuilder};
 pub use sqlite::{
-    SqliteRowidStore, SqliteRowidStoreBuilder, SqliteStore, SqliteStoreBuilder,
-    SqliteWithoutRowidStore, SqliteWithoutRowidStoreBuilder,
+    SqliteRowidPooledStore, SqliteRowidStore, SqliteRowidStoreBuilder, SqliteStore,
+    SqliteStoreBuilder, SqliteWithoutRowidPooledStore, SqliteWithoutRowidStore,
+    SqliteWithoutRowidStoreBuilder,
 };
-pub use zip::{ZipStore, ZipStoreBuilder};
+pub use sstable::{SstableStore, SstableStoreBuilder};
+pub use zip::{ZipCompression, ZipStore, ZipStoreBuilder};
+
+use crate::store::{BlobStore, DynBlobStore};
+use anyhow::{Context, Result};
+use std::io::Read;
+use std::path::Path;
+
+/// On-disk index formats that can be told apart by their header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StoreFormat {
+    Sqlite,
+    Hash,
+    Zstd,
+    #[value(name = "btree")]
+    BTree,
+    #[value(name = "bplustree")]
+    BPlusTree,
+    Zip,
+    Memory,
+    Sstable,
+    #[cfg(feature = "mph")]
+    Mph,
+}
+
+impl StoreFormat {
+    /// Identify an index by its leading magic bytes, falling back to the file
+    /// extension when the header is not recognized
+    pub fn detect(path: &Path) -> Result<Self> {
+        let mut header = Vec::with_capacity(16);
+        std::fs::File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?
+            .take(16)
+            .read_to_end(&mut header)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        #[cfg(feature = "mph")]
+        if header.starts_with(b"MPHIDX") {
+            return Ok(StoreFormat::Mph);
+        }
+
+        // Zstd DAT files share the Hash DAT layout, so check them first
+        let format = if header.starts_with(b"SQLite format 3\0") {
+            Some(StoreFormat::Sqlite)
+        } else if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            Some(StoreFormat::Zip)
+        } else if header.starts_with(b"HASHZST") {
+            Some(StoreFormat::Zstd)
+        } else if header.starts_with(b"HASH") {
+            Some(StoreFormat::Hash)
+        } else if header.starts_with(b"BTREE") {
+            Some(StoreFormat::BTree)
+        } else if header.starts_with(b"BPTREE") {
+            Some(StoreFormat::BPlusTree)
+        } else if header.starts_with(b"MEMSTOR") {
+            Some(StoreFormat::Memory)
+        } else if header.starts_with(b"SSTABLE") {
+            Some(StoreFormat::Sstable)
+        } else {
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("sqlite") | Some("db") => Some(StoreFormat::Sqlite),
+                Some("zip") => Some(StoreFormat::Zip),
+                _ => None,
+            }
+        };
+        format.with_context(|| format!("Unrecognized index format: {}", path.display()))
+    }
+}
+
+/// Open the index at `path` with whichever backend its header identifies.
+/// SQLite files open as the ROWID or WITHOUT ROWID store their schema says.
+pub fn open_any(path: &Path) -> Result<Box<dyn DynBlobStore>> {
+    Ok(match StoreFormat::detect(path)? {
+        StoreFormat::Sqlite if sqlite::is_without_rowid(path)? => {
+            Box::new(SqliteWithoutRowidStore::open(path)?)
+        }
+        StoreFormat::Sqlite => Box::new(SqliteRowidStore::open(path)?),
+        StoreFormat::Hash => Box::new(HashDatStore::open(path)?),
+        StoreFormat::Zstd => Box::new(ZstdDatStore::open(path)?),
+        StoreFormat::BTree => Box::new(BTreeDatStore::open(path)?),
+        StoreFormat::BPlusTree => Box::new(BPlusTreeStore::open(path)?),
+        StoreFormat::Zip => Box::new(ZipStore::open(path)?),
+        StoreFormat::Memory => Box::new(MemoryStore::open(path)?),
+        StoreFormat::Sstable => Box::new(SstableStore::open(path)?),
+        #[cfg(feature = "mph")]
+        StoreFormat::Mph => Box::new(MphDatStore::open(path)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{BlobStore, BlobStoreBuilder};
+    use sha2::{Digest, Sha256};
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    /// Fixed entries for the golden files. Built by hand rather than with
+    /// `DataGenerator` so only an on-disk format change can move the digests.
+    fn golden_entries() -> Vec<(Vec<u8>, Vec<u8>)> {
+        (0..20usize)
+            .map(|i| {
+                let key = format!("golden_{:03}", i).into_bytes();
+                let value = (0..(i * 37) % 200 + 1)
+                    .map(|j| ((i * 31 + j * 7) % 256) as u8)
+                    .collect();
+                (key, value)
+            })
+            .collect()
+    }
+
+    fn populate<B: BlobStoreBuilder>(mut builder: B) {
+        for (key, value) in golden_entries() {
+            builder.insert(&key, &value).unwrap();
+        }
+        builder.finish().unwrap();
+    }
+
+    /// Build a small store with `B` and check `S::contains_key` agrees with
+    /// `S::get` for present, empty and absent keys
+    fn check_contains_key<B: BlobStoreBuilder, S: BlobStore>() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index");
+        let mut builder = B::create(&path).unwrap();
+        builder.insert(b"key1", b"value1").unwrap();
+        builder.insert(b"empty", b"").unwrap();
+        builder.finish().unwrap();
+
+        let store = S::open(&path).unwrap();
+        for key in [&b"key1"[..], b"empty", b"a", b"zzz", b"nonexistent"] {
+            assert_eq!(
+                store.contains_key(key).unwrap(),
+                store.get(key).unwrap().is_some(),
+                "{}",
+                String::from_utf8_lossy(key)
+            );
+        }
+        assert!(store.contains_key(b"empty").unwrap());
+        assert!(!store.contains_key(b"nonexistent").unwrap());
+    }
+
+    #[test]
+    fn test_contains_key_matches_get() {
+        check_contains_key::<SqliteStoreBuilder, SqliteStore>();
+        check_contains_key::<BTreeDatStoreBuilder, BTreeDatStore>();
+        check_contains_key::<ZipStoreBuilder, ZipStore>();
+        check_contains_key::<HashDatStoreBuilder, HashDatStore>();
+        check_contains_key::<HashDatStoreBuilder, HashDatMmapStore>();
+    }
+
+    /// Build stores of assorted value sizes with `B` and check `S::value_len`
+    /// matches the length of what `S::get` returns
+    fn check_value_len<B: BlobStoreBuilder, S: BlobStore>() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index");
+        let sizes = [0usize, 1, 100, 4096, 100_000];
+        let mut builder = B::create(&path).unwrap();
+        for size in sizes {
+            builder
+                .insert(format!("key{}", size).as_bytes(), &vec![7u8; size])
+                .unwrap();
+        }
+        builder.finish().unwrap();
+
+        let store = S::open(&path).unwrap();
+        for size in sizes {
+            let key = format!("key{}", size);
+            let len = store.value_len(key.as_bytes()).unwrap();
+            assert_eq!(len, Some(store.get(key.as_bytes()).unwrap().unwrap().len()));
+            assert_eq!(len, Some(size));
+        }
+        assert_eq!(store.value_len(b"nonexistent").unwrap(), None);
+    }
+
+    #[test]
+    fn test_value_len_matches_get() {
+        check_value_len::<SqliteStoreBuilder, SqliteStore>();
+        check_value_len::<BTreeDatStoreBuilder, BTreeDatStore>();
+        check_value_len::<ZipStoreBuilder, ZipStore>();
+        check_value_len::<HashDatStoreBuilder, HashDatStore>();
+        check_value_len::<HashDatStoreBuilder, HashDatMmapStore>();
+    }
+
+    fn file_sha256(path: &Path) -> String {
+        format!("{:x}", Sha256::digest(std::fs::read(path).unwrap()))
+    }
+
+    /// If one of these fails after an intentional format change, bump the
+    /// backend's magic and update its digest here; otherwise the change broke
+    /// compatibility with existing files.
+    #[test]
+    fn test_dat_formats_match_golden_digests() {
+        let dir = TempDir::new().unwrap();
+
+        // FNV rather than the default SipHash: std doesn't promise DefaultHasher
+        // output is stable across Rust releases
+        let hash_path = dir.path().join("golden_hash.dat");
+        populate(
+            HashDatStoreBuilder::create(&hash_path)
+                .unwrap()
+                .with_hash_algo(HashAlgo::Fnv),
+        );
+        let btree_path = dir.path().join("golden_btree.dat");
+        populate(BTreeDatStoreBuilder::create(&btree_path).unwrap());
+        // Small pages so the golden file includes an internal level
+        let bplustree_path = dir.path().join("golden_bplustree.dat");
+        populate(
+            BPlusTreeStoreBuilder::create(&bplustree_path)
+                .unwrap()
+                .with_page_size(256),
+        );
+        assert_eq!(BPlusTreeStore::open(&bplustree_path).unwrap().height(), 2);
+
+        assert_eq!(
+            file_sha256(&hash_path),
+            "0b10d716225e5c80d027be029ef993de4d4e18885c6fdeeb3673737462fe0437",
+            "Hash DAT (HASHIDX4) format changed"
+        );
+        assert_eq!(
+            file_sha256(&btree_path),
+            "d423729925c3ebf28b55ee2caf1b6e561ea4d39559e53af53a3178187ccba634",
+            "B-tree DAT (BTREEID3) format changed"
+        );
+        assert_eq!(
+            file_sha256(&bplustree_path),
+            "33223fb03d380ad22755bf69c6442924d329f7f4921df9d9fa5893f003075e0b",
+            "B+tree (BPTREE01) format changed"
+        );
+    }
+
+    #[test]
+    fn test_open_any_identifies_each_backend() {
+        let dir = TempDir::new().unwrap();
+        let entries = golden_entries();
+
+        let cases = [
+            ("rowid.sqlite", StoreFormat::Sqlite, "SQLite (ROWID)"),
+            (
+                "without_rowid.sqlite",
+                StoreFormat::Sqlite,
+                "SQLite (WITHOUT ROWID)",
+            ),
+            ("hash.dat", StoreFormat::Hash, "Custom Offset File Format"),
+            ("zstd.dat", StoreFormat::Zstd, "Zstd DAT"),
+            ("btree.dat", StoreFormat::BTree, "B-tree DAT"),
+            ("bplustree.dat", StoreFormat::BPlusTree, "B+tree"),
+            // No extension, so only the zip signature can identify it
+            ("archive", StoreFormat::Zip, "Zip"),
+            ("memory.bin", StoreFormat::Memory, "In-Memory"),
+            ("table.sst", StoreFormat::Sstable, "SSTable"),
+        ];
+        populate(SqliteRowidStoreBuilder::create(&dir.path().join(cases[0].0)).unwrap());
+        populate(SqliteWithoutRowidStoreBuilder::create(&dir.path().join(cases[1].0)).unwrap());
+        populate(HashDatStoreBuilder::create(&dir.path().join(cases[2].0)).unwrap());
+        populate(ZstdDatStoreBuilder::create(&dir.path().join(cases[3].0)).unwrap());
+        populate(BTreeDatStoreBuilder::create(&dir.path().join(cases[4].0)).unwrap());
+        populate(BPlusTreeStoreBuilder::create(&dir.path().join(cases[5].0)).unwrap());
+        populate(ZipStoreBuilder::create(&dir.path().join(cases[6].0)).unwrap());
+        populate(MemoryStoreBuilder::create(&dir.path().join(cases[7].0)).unwrap());
+        populate(SstableStoreBuilder::create(&dir.path().join(cases[8].0)).unwrap());
+
+        let check = |file_name: &str, format: StoreFormat, backend_name: &str| {
+            let path = dir.path().join(file_name);
+            assert_eq!(StoreFormat::detect(&path).unwrap(), format, "{}", file_name);
+
+            let store = open_any(&path).unwrap();
+            assert_eq!(store.backend_name(), backend_name);
+            assert_eq!(store.len(), entries.len(), "{}", backend_name);
+            for (key, value) in &entries {
+                assert_eq!(
+                    store.get(key).unwrap().as_ref(),
+                    Some(value),
+                    "{}",
+                    backend_name
+                );
+            }
+        };
+        for (file_name, format, backend_name) in cases {
+            check(file_name, format, backend_name);
+        }
+        #[cfg(feature = "mph")]
+        {
+            populate(MphDatStoreBuilder::create(&dir.path().join("index_mph.dat")).unwrap());
+            check("index_mph.dat", StoreFormat::Mph, "MPH DAT");
+        }
+
+        let unknown = dir.path().join("unknown.bin");
+        std::fs::write(&unknown, b"not an index").unwrap();
+        assert!(open_any(&unknown).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::Result;
+use memmap2::Mmap;
+use std::fs::File;
+
+/// Drop `mmap`'s pages from this process (`MADV_DONTNEED`), then `file`'s pages
+/// from the OS page cache (`POSIX_FADV_DONTNEED`), so the next read of either
+/// goes to disk. The page cache won't evict pages that are still mapped, hence
+/// the order. Returns whether the advice was issued: only on Linux.
+#[cfg(target_os = "linux")]
+pub(crate) fn evict(file: &File, mmap: Option<&Mmap>) -> Result<bool> {
+    use anyhow::{bail, Context};
+    use std::os::unix::io::AsRawFd;
+
+    if let Some(mmap) = mmap {
+        // SAFETY: the mapping is read-only and file-backed, so dropped pages
+        // are read back from the file on the next access
+        unsafe { mmap.unchecked_advise(memmap2::UncheckedAdvice::DontNeed) }
+            .context("madvise(MADV_DONTNEED) failed")?;
+    }
+    let ret = unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+    if ret != 0 {
+        bail!(
+            "posix_fadvise(POSIX_FADV_DONTNEED) failed: {}",
+            std::io::Error::from_raw_os_error(ret)
+        );
+    }
+    Ok(true)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn evict(_file: &File, _mmap: Option<&Mmap>) -> Result<bool> {
+    Ok(false)
+}
+
+/// `MADV_DONTNEED` for a mapping owned by a library rather than a memmap2
+/// `Mmap`, such as LMDB's. Returns whether the advice was issued: only on Linux.
+///
+/// # Safety
+/// `addr..addr + len` must be a read-only, file-backed mapping, so dropped
+/// pages are read back from the file on the next access.
+#[cfg(all(feature = "lmdb", target_os = "linux"))]
+pub(crate) unsafe fn drop_mapping(addr: *mut std::ffi::c_void, len: usize) -> Result<bool> {
+    if libc::madvise(addr, len, libc::MADV_DONTNEED) != 0 {
+        anyhow::bail!(
+            "madvise(MADV_DONTNEED) failed: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    Ok(true)
+}
+
+#[cfg(all(feature = "lmdb", not(target_os = "linux")))]
+pub(crate) unsafe fn drop_mapping(_addr: *mut std::ffi::c_void, _len: usize) -> Result<bool> {
+    Ok(false)
+}
+
+/// `evict` every file directly inside `dir`, for backends whose index is a
+/// directory of files opened by a library (RocksDB's SSTs)
+#[cfg(feature = "rocksdb")]
+pub(crate) fn evict_dir(dir: &std::path::Path) -> Result<bool> {
+    use anyhow::Context;
+
+    let mut evicted = false;
+    for entry in std::fs::read_dir(dir).context("Failed to list index directory")? {
+        let entry = entry.context("Failed to list index directory")?;
+        if entry.file_type()?.is_file() {
+            let file = File::open(entry.path())
+                .with_context(|| format!("Failed to open {}", entry.path().display()))?;
+            evicted |= evict(&file, None)?;
+        }
+    }
+    Ok(evicted)
+}
@@ -0,0 +1,247 @@
+use super::page_cache;
+use crate::store::{BlobStore, BlobStoreBuilder};
+use anyhow::{bail, Context, Result};
+use rocksdb::{BlockBasedOptions, Cache, DBCompressionType, Options, WriteBatch, WriteOptions, DB};
+use std::path::{Path, PathBuf};
+
+/// Written by the builder next to RocksDB's own files: the number of distinct
+/// keys, since RocksDB itself only offers an estimate
+const ENTRY_COUNT_FILE: &str = "ENTRY_COUNT";
+/// Buffered batch size at which the builder writes the batch out
+const BATCH_FLUSH_BYTES: usize = 64 * 1024 * 1024;
+/// Block cache size, RocksDB's default; the store owns the cache so `--cold`
+/// can empty it
+const BLOCK_CACHE_BYTES: usize = 32 * 1024 * 1024;
+
+/// RocksDB-backed blob store, for comparing the hand-rolled formats with a
+/// production LSM engine. Unlike the other backends the index is a directory.
+pub struct RocksDbStore {
+    db: DB,
+    count: usize,
+    block_cache: Cache,
+    path: PathBuf,
+}
+
+impl RocksDbStore {
+    /// Count keys with a full scan, for directories built without `ENTRY_COUNT`
+    fn count_keys(db: &DB) -> Result<usize> {
+        let mut iter = db.raw_iterator();
+        iter.seek_to_first();
+        let mut count = 0;
+        while iter.valid() {
+            count += 1;
+            iter.next();
+        }
+        iter.status().context("Failed to scan RocksDB keys")?;
+        Ok(count)
+    }
+}
+
+impl BlobStore for RocksDbStore {
+    fn open(path: &Path) -> Result<Self> {
+        let block_cache = Cache::new_lru_cache(BLOCK_CACHE_BYTES);
+        let mut table_options = BlockBasedOptions::default();
+        table_options.set_block_cache(&block_cache);
+        let mut options = Options::default();
+        options.set_block_based_table_factory(&table_options);
+        let db = DB::open_for_read_only(&options, path, false).context("Failed to open RocksDB")?;
+
+        let count = match std::fs::read_to_string(path.join(ENTRY_COUNT_FILE)) {
+            Ok(count) => count
+                .trim()
+                .parse()
+                .context("Corrupt RocksDB entry count")?,
+            Err(_) => Self::count_keys(&db)?,
+        };
+
+        Ok(Self {
+            db,
+            count,
+            block_cache,
+            path: path.to_path_buf(),
+        })
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.db.get(key).context("Failed to read from RocksDB")
+    }
+
+    /// Copies from a pinned block-cache slice instead of allocating a `Vec`.
+    fn get_into(&self, key: &[u8], buf: &mut Vec<u8>) -> Result<bool> {
+        buf.clear();
+        let value = self
+            .db
+            .get_pinned(key)
+            .context("Failed to read from RocksDB")?;
+        match value {
+            Some(value) => {
+                buf.extend_from_slice(&value);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Empties the block cache (by shrinking it to zero and back), then evicts
+    /// every file in the directory.
+    fn drop_caches(&self) -> Result<bool> {
+        let mut block_cache = self.block_cache.clone();
+        block_cache.set_capacity(0);
+        block_cache.set_capacity(BLOCK_CACHE_BYTES);
+        page_cache::evict_dir(&self.path)
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        let mut keys = Vec::with_capacity(self.count);
+        let mut iter = self.db.raw_iterator();
+        iter.seek_to_first();
+        while let Some(key) = iter.key() {
+            keys.push(key.to_vec());
+            iter.next();
+        }
+        iter.status().context("Failed to scan RocksDB keys")?;
+        Ok(keys)
+    }
+
+    fn len(&self) -> usize {
+        self.count
+    }
+
+    fn backend_name() -> &'static str {
+        "RocksDB"
+    }
+}
+
+/// Builder for RocksDB blob store.
+/// Inserts accumulate in a write batch (with the WAL off) that is written out
+/// every 64 MB; `finish` flushes and compacts so the store opens as a single
+/// sorted run.
+pub struct RocksDbStoreBuilder {
+    db: DB,
+    batch: WriteBatch,
+    write_options: WriteOptions,
+    path: PathBuf,
+}
+
+impl RocksDbStoreBuilder {
+    fn write_batch(&mut self) -> Result<()> {
+        let batch = std::mem::take(&mut self.batch);
+        self.db
+            .write_opt(batch, &self.write_options)
+            .context("Failed to write RocksDB batch")
+    }
+}
+
+impl BlobStoreBuilder for RocksDbStoreBuilder {
+    fn create(path: &Path) -> Result<Self> {
+        // Start from an empty directory, but only ever delete an old RocksDB
+        if path.exists() {
+            if !path.join("CURRENT").exists() {
+                bail!(
+                    "{} exists and is not a RocksDB directory; refusing to overwrite it",
+                    path.display()
+                );
+            }
+            std::fs::remove_dir_all(path).context("Failed to remove old RocksDB directory")?;
+        }
+
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.prepare_for_bulk_load();
+        // Values are stored as-is, like the other uncompressed backends
+        options.set_compression_type(DBCompressionType::None);
+        let db = DB::open(&options, path).context("Failed to create RocksDB")?;
+
+        let mut write_options = WriteOptions::default();
+        write_options.disable_wal(true);
+
+        Ok(Self {
+            db,
+            batch: WriteBatch::default(),
+            write_options,
+            path: path.to_path_buf(),
+        })
+    }
+
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.batch.put(key, value);
+        if self.batch.size_in_bytes() >= BATCH_FLUSH_BYTES {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        self.write_batch()?;
+        self.db.flush().context("Failed to flush RocksDB")?;
+        self.db.compact_range(None::<&[u8]>, None::<&[u8]>);
+
+        // Repeated keys collapse in RocksDB, so count what actually landed
+        let count = RocksDbStore::count_keys(&self.db)?;
+        std::fs::write(self.path.join(ENTRY_COUNT_FILE), count.to_string())
+            .context("Failed to write RocksDB entry count")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rocksdb_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index_rocksdb");
+
+        {
+            let mut builder = RocksDbStoreBuilder::create(&path).unwrap();
+            builder.insert(b"key1", b"value1").unwrap();
+            builder.insert(b"key2", b"value2").unwrap();
+            builder.insert(b"key3", b"stale").unwrap();
+            builder.insert(b"key3", b"value3").unwrap();
+            builder.finish().unwrap();
+        }
+
+        let store = RocksDbStore::open(&path).unwrap();
+
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+        assert_eq!(store.get(b"key2").unwrap(), Some(b"value2".to_vec()));
+        assert_eq!(store.get(b"key3").unwrap(), Some(b"value3".to_vec()));
+        assert_eq!(store.get(b"nonexistent").unwrap(), None);
+        assert_eq!(
+            store.keys().unwrap(),
+            vec![b"key1".to_vec(), b"key2".to_vec(), b"key3".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_rocksdb_rebuild_replaces_old_store() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index_rocksdb");
+
+        for keys in [&[b"old_a", b"old_b"][..], &[b"new_a"][..]] {
+            let mut builder = RocksDbStoreBuilder::create(&path).unwrap();
+            for key in keys {
+                builder.insert(*key, b"v").unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        let store = RocksDbStore::open(&path).unwrap();
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.keys().unwrap(), vec![b"new_a".to_vec()]);
+
+        // Without ENTRY_COUNT the count comes from a scan
+        drop(store);
+        std::fs::remove_file(path.join(ENTRY_COUNT_FILE)).unwrap();
+        assert_eq!(RocksDbStore::open(&path).unwrap().len(), 1);
+
+        // Anything that isn't a RocksDB directory is left alone
+        let file = dir.path().join("not_a_db");
+        std::fs::write(&file, b"keep me").unwrap();
+        assert!(RocksDbStoreBuilder::create(&file).is_err());
+        assert_eq!(std::fs::read(&file).unwrap(), b"keep me");
+    }
+}
@@ -1,7 +1,14 @@
-use crate::store::{BlobStore, BlobStoreBuilder};
+use super::page_cache;
+use crate::store::{prefix_upper_bound, BlobStore, BlobStoreBuilder, OrderedBlobStore};
 use anyhow::{Context, Result};
 use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
-use std::path::Path;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Every SQLite database file starts with this 16-byte header string.
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
 
 pub type SqliteWithoutRowidStore = SqliteStoreImpl<true>;
 pub type SqliteWithoutRowidStoreBuilder = SqliteStoreBuilderImpl<true>;
@@ -9,6 +16,11 @@ pub type SqliteWithoutRowidStoreBuilder = SqliteStoreBuilderImpl<true>;
 pub type SqliteRowidStore = SqliteStoreImpl<false>;
 pub type SqliteRowidStoreBuilder = SqliteStoreBuilderImpl<false>;
 
+/// `SqliteWithoutRowidStore` that can be shared between reader threads
+pub type SqliteWithoutRowidPooledStore = SqliteStoreImpl<true, ConnectionPool>;
+/// `SqliteRowidStore` that can be shared between reader threads
+pub type SqliteRowidPooledStore = SqliteStoreImpl<false, ConnectionPool>;
+
 /// Backwards-compatible alias: historically this was `WITHOUT ROWID`.
 pub type SqliteStore = SqliteStoreImpl<true>;
 /// Backwards-compatible alias: historically this was `WITHOUT ROWID`.
@@ -18,71 +30,245 @@ pub type SqliteStoreBuilder = SqliteStoreBuilderImpl<true>;
 ///
 /// `WITHOUT_ROWID = true` uses SQLite's `WITHOUT ROWID` table layout.
 /// `WITHOUT_ROWID = false` uses a normal ROWID table with a unique index on `key`.
-pub struct SqliteStoreImpl<const WITHOUT_ROWID: bool> {
-    conn: Connection,
+///
+/// Connections are opened with `SQLITE_OPEN_NO_MUTEX`, so one must never be
+/// used by two threads at once. `C` decides how queries get one: the default
+/// `SingleConnection` uses one connection without any locking, for
+/// single-threaded readers; `ConnectionPool` makes the store `Sync`.
+pub struct SqliteStoreImpl<const WITHOUT_ROWID: bool, C = SingleConnection> {
+    path: PathBuf,
+    conns: C,
     count: usize,
 }
 
-impl<const WITHOUT_ROWID: bool> BlobStore for SqliteStoreImpl<WITHOUT_ROWID> {
+/// How a SQLite store hands out the connection each query runs on
+pub trait SqliteConnections: Sized {
+    /// Open the connection(s) for the database at `path`
+    fn open(path: &Path) -> Result<Self>;
+
+    /// Run `f` on a connection no other thread is using
+    fn with_conn<T>(&self, f: impl FnOnce(&Connection) -> Result<T>) -> Result<T>;
+
+    /// Run `f` on every connection, e.g. to free their page caches
+    fn for_each(&self, f: impl FnMut(&Connection) -> Result<()>) -> Result<()>;
+}
+
+/// One connection, used without locking. Not `Sync`.
+pub struct SingleConnection(Connection);
+
+impl SqliteConnections for SingleConnection {
     fn open(path: &Path) -> Result<Self> {
-        let conn = Connection::open_with_flags(
-            path,
-            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
-        )
-        .context("Failed to open SQLite database")?;
+        open_connection(path).map(Self)
+    }
 
-        // Read-time optimizations
-        conn.execute_batch(
-            "
-            PRAGMA mmap_size = 0;  -- Disable memory-mapped I/O
-            PRAGMA cache_size = -32768;    -- 32MB page cache (negative = KB)
-            PRAGMA temp_store = MEMORY;
-            PRAGMA query_only = ON;
-            ",
-        )
-        .context("Failed to set read pragmas")?;
+    fn with_conn<T>(&self, f: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+        f(&self.0)
+    }
 
-        // Get the count
-        let count: usize = conn
-            .query_row("SELECT COUNT(*) FROM blobs", [], |row| row.get(0))
-            .context("Failed to get count")?;
+    fn for_each(&self, mut f: impl FnMut(&Connection) -> Result<()>) -> Result<()> {
+        f(&self.0)
+    }
+}
 
-        Ok(Self { conn, count })
+/// One connection per available CPU, all opened up front, each behind its own
+/// lock. A query takes the first idle connection, so that many threads can read
+/// at once without waiting on each other or opening connections mid-run.
+pub struct ConnectionPool(Vec<Mutex<Connection>>);
+
+impl SqliteConnections for ConnectionPool {
+    fn open(path: &Path) -> Result<Self> {
+        let size = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let conns = (0..size)
+            .map(|_| open_connection(path).map(Mutex::new))
+            .collect::<Result<_>>()?;
+        Ok(Self(conns))
+    }
+
+    fn with_conn<T>(&self, f: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+        for conn in &self.0 {
+            if let Ok(conn) = conn.try_lock() {
+                return f(&conn);
+            }
+        }
+        // More readers than connections: wait for the first one
+        f(&self.0[0].lock().unwrap())
+    }
+
+    fn for_each(&self, mut f: impl FnMut(&Connection) -> Result<()>) -> Result<()> {
+        self.0.iter().try_for_each(|conn| f(&conn.lock().unwrap()))
+    }
+}
+
+/// Open a read-only connection with the read-time pragmas applied
+fn open_connection(path: &Path) -> Result<Connection> {
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .context("Failed to open SQLite database")?;
+
+    // Read-time optimizations
+    conn.execute_batch(
+        "
+        PRAGMA mmap_size = 0;  -- Disable memory-mapped I/O
+        PRAGMA cache_size = -32768;    -- 32MB page cache (negative = KB)
+        PRAGMA temp_store = MEMORY;
+        PRAGMA query_only = ON;
+        ",
+    )
+    .context("Failed to set read pragmas")?;
+    Ok(conn)
+}
+
+impl<const WITHOUT_ROWID: bool, C: SqliteConnections> BlobStore
+    for SqliteStoreImpl<WITHOUT_ROWID, C>
+{
+    fn open(path: &Path) -> Result<Self> {
+        let conns = C::open(path)?;
+
+        // Get the count
+        let count: usize = conns.with_conn(|conn| {
+            conn.query_row("SELECT COUNT(*) FROM blobs", [], |row| row.get(0))
+                .context("Failed to get count")
+        })?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            conns,
+            count,
+        })
     }
 
     fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
-        let mut stmt = self
-            .conn
-            .prepare_cached("SELECT value FROM blobs WHERE key = ?")
-            .context("Failed to prepare statement")?;
+        self.conns.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare_cached("SELECT value FROM blobs WHERE key = ?")
+                .context("Failed to prepare statement")?;
+
+            let result = stmt
+                .query_row([key], |row| row.get(0))
+                .optional()
+                .context("Failed to query blob")?;
+
+            Ok(result)
+        })
+    }
+
+    /// Copies the value out of SQLite's row buffer instead of allocating a `Vec`.
+    fn get_into(&self, key: &[u8], buf: &mut Vec<u8>) -> Result<bool> {
+        buf.clear();
+        self.conns.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare_cached("SELECT value FROM blobs WHERE key = ?")
+                .context("Failed to prepare statement")?;
+
+            let found = stmt
+                .query_row([key], |row| {
+                    buf.extend_from_slice(row.get_ref(0)?.as_blob()?);
+                    Ok(())
+                })
+                .optional()
+                .context("Failed to query blob")?;
+
+            Ok(found.is_some())
+        })
+    }
+
+    /// Selects a constant so SQLite never loads the value column.
+    fn contains_key(&self, key: &[u8]) -> Result<bool> {
+        self.conns.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare_cached("SELECT 1 FROM blobs WHERE key = ?")
+                .context("Failed to prepare statement")?;
+
+            let found = stmt
+                .query_row([key], |_| Ok(()))
+                .optional()
+                .context("Failed to query blob")?;
+
+            Ok(found.is_some())
+        })
+    }
 
-        let result = stmt
-            .query_row([key], |row| row.get(0))
-            .optional()
-            .context("Failed to query blob")?;
+    /// `length()` of a BLOB is its byte count, read from the record header.
+    fn value_len(&self, key: &[u8]) -> Result<Option<usize>> {
+        self.conns.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare_cached("SELECT length(value) FROM blobs WHERE key = ?")
+                .context("Failed to prepare statement")?;
 
-        Ok(result)
+            let len: Option<i64> = stmt
+                .query_row([key], |row| row.get(0))
+                .optional()
+                .context("Failed to query blob")?;
+
+            Ok(len.map(|len| len as usize))
+        })
+    }
+
+    /// Frees every connection's page cache (`PRAGMA shrink_memory`), then
+    /// evicts the database file. I/O already bypasses mmap (`mmap_size = 0`).
+    fn drop_caches(&self) -> Result<bool> {
+        self.conns.for_each(|conn| {
+            conn.execute_batch("PRAGMA shrink_memory;")
+                .context("Failed to shrink SQLite page cache")
+        })?;
+        let file = File::open(&self.path).context("Failed to open SQLite database")?;
+        page_cache::evict(&file, None)
     }
 
     fn keys(&self) -> Result<Vec<Vec<u8>>> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT key FROM blobs")
-            .context("Failed to prepare statement")?;
-
-        let keys = stmt
-            .query_map([], |row| row.get(0))
-            .context("Failed to query keys")?
-            .collect::<Result<Vec<Vec<u8>>, _>>()
-            .context("Failed to collect keys")?;
-
-        Ok(keys)
+        self.conns.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare("SELECT key FROM blobs")
+                .context("Failed to prepare statement")?;
+
+            let keys = stmt
+                .query_map([], |row| row.get(0))
+                .context("Failed to query keys")?
+                .collect::<Result<Vec<Vec<u8>>, _>>()
+                .context("Failed to collect keys")?;
+
+            Ok(keys)
+        })
+    }
+
+    fn prefix_count(&self, prefix: &[u8]) -> Result<usize> {
+        self.conns.with_conn(|conn| {
+            let count = match prefix_upper_bound(prefix) {
+                Some(upper) => conn.query_row(
+                    "SELECT COUNT(*) FROM blobs WHERE key >= ?1 AND key < ?2",
+                    params![prefix, upper],
+                    |row| row.get(0),
+                ),
+                None => conn.query_row(
+                    "SELECT COUNT(*) FROM blobs WHERE key >= ?1",
+                    params![prefix],
+                    |row| row.get(0),
+                ),
+            }
+            .context("Failed to count keys by prefix")?;
+
+            Ok(count)
+        })
     }
 
     fn len(&self) -> usize {
         self.count
     }
 
+    fn total_value_bytes(&self) -> Result<u64> {
+        let total: i64 = self.conns.with_conn(|conn| {
+            conn.query_row(
+                "SELECT COALESCE(SUM(length(value)), 0) FROM blobs",
+                [],
+                |row| row.get(0),
+            )
+            .context("Failed to sum value lengths")
+        })?;
+        Ok(total as u64)
+    }
+
     fn backend_name() -> &'static str {
         if WITHOUT_ROWID {
             "SQLite (WITHOUT ROWID)"
@@ -92,6 +278,28 @@ impl<const WITHOUT_ROWID: bool> BlobStore for SqliteStoreImpl<WITHOUT_ROWID> {
     }
 }
 
+impl<const WITHOUT_ROWID: bool, C: SqliteConnections> OrderedBlobStore
+    for SqliteStoreImpl<WITHOUT_ROWID, C>
+{
+    fn range(&self, start: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.conns.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare_cached(
+                    "SELECT key, value FROM blobs WHERE key >= ?1 AND key < ?2 ORDER BY key",
+                )
+                .context("Failed to prepare statement")?;
+
+            let entries = stmt
+                .query_map(params![start, end], |row| Ok((row.get(0)?, row.get(1)?)))
+                .context("Failed to query key range")?
+                .collect::<Result<Vec<(Vec<u8>, Vec<u8>)>, _>>()
+                .context("Failed to collect key range")?;
+
+            Ok(entries)
+        })
+    }
+}
+
 /// Builder for SQLite blob store.
 pub struct SqliteStoreBuilderImpl<const WITHOUT_ROWID: bool> {
     conn: Connection,
@@ -168,6 +376,40 @@ impl<const WITHOUT_ROWID: bool> BlobStoreBuilder for SqliteStoreBuilderImpl<WITH
     }
 }
 
+/// Check whether the file at `path` is a SQLite database (by its header string).
+pub fn is_sqlite_file(path: &Path) -> Result<bool> {
+    let mut file = File::open(path).context("Failed to open file")?;
+    let mut header = [0u8; 16];
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(&header == SQLITE_HEADER),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e).context("Failed to read file header"),
+    }
+}
+
+/// Check whether the SQLite index at `path` stores its `blobs` table `WITHOUT ROWID`.
+pub fn is_without_rowid(path: &Path) -> Result<bool> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .context("Failed to open SQLite database")?;
+    let sql: String = conn
+        .query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'blobs'",
+            [],
+            |row| row.get(0),
+        )
+        .context("Failed to read blobs table schema")?;
+    Ok(sql.to_ascii_uppercase().contains("WITHOUT ROWID"))
+}
+
+/// Rewrite a SQLite index in place with `VACUUM`, releasing any free pages.
+pub fn compact(path: &Path) -> Result<()> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_WRITE)
+        .context("Failed to open SQLite database for compaction")?;
+    conn.execute_batch("VACUUM;")
+        .context("Failed to vacuum database")?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -253,6 +495,49 @@ mod tests {
         assert_eq!(store.keys().unwrap(), Vec::<Vec<u8>>::new());
     }
 
+    #[test]
+    fn test_sqlite_compact_shrinks_file() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        let value = vec![7u8; 1024];
+        {
+            let mut builder = SqliteStoreBuilder::create(path).unwrap();
+            for i in 0..400 {
+                builder
+                    .insert(format!("key_{:04}", i).as_bytes(), &value)
+                    .unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        // Leave free pages behind by deleting half the rows without vacuuming
+        {
+            let conn = Connection::open(path).unwrap();
+            conn.execute("DELETE FROM blobs WHERE key >= ?", [b"key_0200".as_slice()])
+                .unwrap();
+        }
+        let before = std::fs::metadata(path).unwrap().len();
+
+        assert!(is_sqlite_file(path).unwrap());
+        compact(path).unwrap();
+        let after = std::fs::metadata(path).unwrap().len();
+        assert!(after < before, "expected {} < {}", after, before);
+
+        let store = SqliteStore::open(path).unwrap();
+        assert_eq!(store.len(), 200);
+        assert_eq!(store.get(b"key_0000").unwrap(), Some(value.clone()));
+        assert_eq!(store.get(b"key_0199").unwrap(), Some(value));
+        assert_eq!(store.get(b"key_0200").unwrap(), None);
+    }
+
+    #[test]
+    fn test_is_sqlite_file_rejects_other_formats() {
+        let temp_file = NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), b"HASHIDX1").unwrap();
+        assert!(!is_sqlite_file(temp_file.path()).unwrap());
+    }
+
     proptest! {
         #[test]
         fn prop_sqlite_roundtrip_single(key in prop_vec(any::<u8>(), 1..100), value in prop_vec(any::<u8>(), 0..1000)) {
@@ -0,0 +1,510 @@
+use super::file_io::read_exact_at;
+use super::page_cache;
+use crate::store::{BlobStore, BlobStoreBuilder};
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 8] = b"SSTABLE1";
+const HEADER_SIZE: usize = 32;
+/// Target size of a data block; a block is closed before an entry would push it
+/// past this, so only a single oversized entry makes a larger block
+const DEFAULT_BLOCK_SIZE: usize = 4096;
+/// key_len + value_len ahead of each entry in a data block
+const ENTRY_HEADER_LEN: usize = 8;
+/// key_len + block_offset + block_len: an index entry with an empty first key
+const MIN_INDEX_ENTRY_LEN: usize = 20;
+
+/// Header layout:
+/// - magic: 8 bytes
+/// - entry_count: 8 bytes (u64)
+/// - index_offset: 8 bytes (u64, where the sparse index starts)
+/// - block_count: 8 bytes (u64)
+///
+/// Data blocks follow the header, in key order, each holding whole entries:
+/// - key_len: 4 bytes (u32)
+/// - value_len: 4 bytes (u32)
+/// - key: variable
+/// - value: variable
+///
+/// Sparse index (after the last block), one entry per block:
+/// - key_len: 4 bytes (u32)
+/// - first_key: variable (the block's smallest key)
+/// - block_offset: 8 bytes (u64)
+/// - block_len: 8 bytes (u64)
+
+/// Location and first key of one data block
+struct BlockHandle {
+    first_key: Vec<u8>,
+    offset: u64,
+    len: u64,
+}
+
+/// Sorted, block-based store in the style of a LevelDB SSTable. Only the sparse
+/// block index is held in memory; a lookup binary searches it for the one block
+/// that could hold the key, reads that block and scans it.
+pub struct SstableStore {
+    file: File,
+    index: Vec<BlockHandle>,
+    entry_count: usize,
+}
+
+/// Entries of one data block, in key order
+struct BlockEntries<'a> {
+    block: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BlockEntries<'a> {
+    fn parse_entry(&mut self) -> Result<(&'a [u8], &'a [u8])> {
+        let block = self.block;
+        let header = block
+            .get(self.pos..self.pos + ENTRY_HEADER_LEN)
+            .context("SSTable block entry header is truncated")?;
+        let key_len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+        let value_len = u32::from_le_bytes(header[4..].try_into().unwrap()) as usize;
+        let key_start = self.pos + ENTRY_HEADER_LEN;
+        let value_start = key_start + key_len;
+        let end = value_start + value_len;
+        if end > block.len() {
+            bail!("SSTable block entry overruns its block");
+        }
+        self.pos = end;
+        Ok((&block[key_start..value_start], &block[value_start..end]))
+    }
+}
+
+impl<'a> Iterator for BlockEntries<'a> {
+    type Item = Result<(&'a [u8], &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos == self.block.len() {
+            return None;
+        }
+        let entry = self.parse_entry();
+        if entry.is_err() {
+            // Stop after reporting a corrupt entry rather than misreading the rest
+            self.pos = self.block.len();
+        }
+        Some(entry)
+    }
+}
+
+impl SstableStore {
+    /// Number of data blocks in the file
+    pub fn block_count(&self) -> usize {
+        self.index.len()
+    }
+
+    /// The block whose key range could contain `key`: the last block whose
+    /// first key is <= `key`. `None` if `key` sorts before every block.
+    fn find_block(&self, key: &[u8]) -> Option<&BlockHandle> {
+        let after = self
+            .index
+            .partition_point(|block| block.first_key.as_slice() <= key);
+        after.checked_sub(1).map(|i| &self.index[i])
+    }
+
+    fn read_block(&self, block: &BlockHandle) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; block.len as usize];
+        read_exact_at(&self.file, &mut buf, block.offset)
+            .context("Failed to read SSTable block")?;
+        Ok(buf)
+    }
+
+    fn entries(block: &[u8]) -> BlockEntries<'_> {
+        BlockEntries { block, pos: 0 }
+    }
+
+    fn parse_index(data: &[u8], block_count: usize, data_end: u64) -> Result<Vec<BlockHandle>> {
+        if block_count > data.len() / MIN_INDEX_ENTRY_LEN {
+            bail!(
+                "SSTable block_count {} is more than the {}-byte index can hold",
+                block_count,
+                data.len()
+            );
+        }
+        let mut index = Vec::with_capacity(block_count);
+        let mut pos = 0;
+        for _ in 0..block_count {
+            let key_len = data
+                .get(pos..pos + 4)
+                .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize)
+                .context("SSTable index is truncated")?;
+            let handle = data
+                .get(pos + 4..pos + 4 + key_len + 16)
+                .context("SSTable index is truncated")?;
+            let first_key = handle[..key_len].to_vec();
+            let offset = u64::from_le_bytes(handle[key_len..key_len + 8].try_into().unwrap());
+            let len = u64::from_le_bytes(handle[key_len + 8..].try_into().unwrap());
+            if offset < HEADER_SIZE as u64 || offset.saturating_add(len) > data_end {
+                bail!("SSTable index points outside the data blocks");
+            }
+            index.push(BlockHandle {
+                first_key,
+                offset,
+                len,
+            });
+            pos += 4 + key_len + 16;
+        }
+        Ok(index)
+    }
+}
+
+impl BlobStore for SstableStore {
+    fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).context("Failed to open SSTable file")?;
+        let file_len = file.metadata()?.len();
+        if file_len < HEADER_SIZE as u64 {
+            bail!("Invalid SSTable file (too short)");
+        }
+        let mut header = [0u8; HEADER_SIZE];
+        read_exact_at(&file, &mut header, 0).context("Failed to read SSTable header")?;
+        if &header[..8] != MAGIC {
+            bail!("Invalid SSTable file (bad magic)");
+        }
+        let entry_count = u64::from_le_bytes(header[8..16].try_into().unwrap()) as usize;
+        let index_offset = u64::from_le_bytes(header[16..24].try_into().unwrap());
+        let block_count = u64::from_le_bytes(header[24..32].try_into().unwrap()) as usize;
+        if !(HEADER_SIZE as u64..=file_len).contains(&index_offset) {
+            bail!("SSTable index offset {} is out of range", index_offset);
+        }
+        let data_len = (index_offset - HEADER_SIZE as u64) as usize;
+        if entry_count > data_len / ENTRY_HEADER_LEN {
+            bail!(
+                "SSTable entry_count {} is more than {} bytes of data blocks can hold",
+                entry_count,
+                data_len
+            );
+        }
+
+        let mut index_data = vec![0u8; (file_len - index_offset) as usize];
+        read_exact_at(&file, &mut index_data, index_offset)
+            .context("Failed to read SSTable index")?;
+        let index = Self::parse_index(&index_data, block_count, index_offset)?;
+
+        Ok(Self {
+            file,
+            index,
+            entry_count,
+        })
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let Some(handle) = self.find_block(key) else {
+            return Ok(None);
+        };
+        let block = self.read_block(handle)?;
+        for entry in Self::entries(&block) {
+            let (entry_key, value) = entry?;
+            match entry_key.cmp(key) {
+                std::cmp::Ordering::Less => continue,
+                std::cmp::Ordering::Equal => return Ok(Some(value.to_vec())),
+                std::cmp::Ordering::Greater => break,
+            }
+        }
+        Ok(None)
+    }
+
+    /// Evicts the file; the sparse index stays in memory.
+    fn drop_caches(&self) -> Result<bool> {
+        page_cache::evict(&self.file, None)
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        let mut keys = Vec::with_capacity(self.entry_count);
+        for handle in &self.index {
+            let block = self.read_block(handle)?;
+            for entry in Self::entries(&block) {
+                keys.push(entry?.0.to_vec());
+            }
+        }
+        Ok(keys)
+    }
+
+    fn len(&self) -> usize {
+        self.entry_count
+    }
+
+    fn backend_name() -> &'static str {
+        "SSTable"
+    }
+}
+
+/// Builder that collects entries in a `BTreeMap` (a repeated key keeps its last
+/// value) and writes them as ~4KB blocks in key order.
+pub struct SstableStoreBuilder {
+    path: PathBuf,
+    block_size: usize,
+    entries: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl SstableStoreBuilder {
+    /// Close data blocks at `block_size` bytes instead of 4KB
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size;
+        self
+    }
+}
+
+impl BlobStoreBuilder for SstableStoreBuilder {
+    fn create(path: &Path) -> Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            block_size: DEFAULT_BLOCK_SIZE,
+            entries: BTreeMap::new(),
+        })
+    }
+
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        if key.len() > u32::MAX as usize || value.len() > u32::MAX as usize {
+            bail!(
+                "Entry too large for SSTable ({} byte key, {} byte value)",
+                key.len(),
+                value.len()
+            );
+        }
+        self.entries.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        let file = File::create(&self.path).context("Failed to create SSTable file")?;
+        let mut writer = BufWriter::new(file);
+        // Header is rewritten once the index offset is known
+        writer.write_all(&[0u8; HEADER_SIZE])?;
+
+        let mut index: Vec<BlockHandle> = Vec::new();
+        let mut block: Vec<u8> = Vec::with_capacity(self.block_size);
+        let mut block_first_key: &[u8] = &[];
+        let mut offset = HEADER_SIZE as u64;
+        let mut flush = |block: &mut Vec<u8>, first_key: &[u8]| -> Result<()> {
+            writer.write_all(block)?;
+            index.push(BlockHandle {
+                first_key: first_key.to_vec(),
+                offset,
+                len: block.len() as u64,
+            });
+            offset += block.len() as u64;
+            block.clear();
+            Ok(())
+        };
+
+        for (key, value) in &self.entries {
+            let entry_len = ENTRY_HEADER_LEN + key.len() + value.len();
+            if !block.is_empty() && block.len() + entry_len > self.block_size {
+                flush(&mut block, block_first_key)?;
+            }
+            if block.is_empty() {
+                block_first_key = key;
+            }
+            block.extend_from_slice(&(key.len() as u32).to_le_bytes());
+            block.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            block.extend_from_slice(key);
+            block.extend_from_slice(value);
+        }
+        if !block.is_empty() {
+            flush(&mut block, block_first_key)?;
+        }
+
+        let index_offset = offset;
+        for handle in &index {
+            writer.write_all(&(handle.first_key.len() as u32).to_le_bytes())?;
+            writer.write_all(&handle.first_key)?;
+            writer.write_all(&handle.offset.to_le_bytes())?;
+            writer.write_all(&handle.len.to_le_bytes())?;
+        }
+
+        let mut file = writer
+            .into_inner()
+            .context("Failed to flush SSTable file")?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(MAGIC)?;
+        file.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+        file.write_all(&index_offset.to_le_bytes())?;
+        file.write_all(&(index.len() as u64).to_le_bytes())?;
+        file.sync_all()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec as prop_vec;
+    use proptest::prelude::*;
+    use std::collections::HashMap;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_sstable_roundtrip() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        // Build the store
+        {
+            let mut builder = SstableStoreBuilder::create(path).unwrap();
+            builder.insert(b"key1", b"value1").unwrap();
+            builder.insert(b"key2", b"value2").unwrap();
+            builder.insert(b"key3", b"value3").unwrap();
+            builder.finish().unwrap();
+        }
+
+        // Read it back
+        let store = SstableStore::open(path).unwrap();
+
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.block_count(), 1);
+        assert_eq!(store.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+        assert_eq!(store.get(b"key2").unwrap(), Some(b"value2".to_vec()));
+        assert_eq!(store.get(b"key3").unwrap(), Some(b"value3".to_vec()));
+        assert_eq!(store.get(b"nonexistent").unwrap(), None);
+    }
+
+    #[test]
+    fn test_sstable_keys_on_block_boundaries() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        // Each entry is 8 + 6 + 10 = 24 bytes, so a 72-byte block holds exactly
+        // three and every third key starts a new block
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = (0..30)
+            .map(|i| (format!("key{:03}", i * 2).into_bytes(), vec![i as u8; 10]))
+            .collect();
+        {
+            let mut builder = SstableStoreBuilder::create(path)
+                .unwrap()
+                .with_block_size(72);
+            for (key, value) in &entries {
+                builder.insert(key, value).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        let store = SstableStore::open(path).unwrap();
+        assert_eq!(store.len(), 30);
+        assert_eq!(store.block_count(), 10);
+        for (i, handle) in store.index.iter().enumerate() {
+            assert_eq!(handle.first_key, entries[i * 3].0);
+            assert_eq!(handle.len, 72);
+        }
+
+        for (key, value) in &entries {
+            assert_eq!(store.get(key).unwrap().as_ref(), Some(value));
+        }
+        // Odd keys fall between entries, including between the last key of one
+        // block and the first key of the next
+        for i in 0..30 {
+            let missing = format!("key{:03}", i * 2 + 1).into_bytes();
+            assert_eq!(store.get(&missing).unwrap(), None);
+        }
+        // Before the first block and after the last
+        assert_eq!(store.get(b"a").unwrap(), None);
+        assert_eq!(store.get(b"key").unwrap(), None);
+        assert_eq!(store.get(b"zzz").unwrap(), None);
+
+        let keys = store.keys().unwrap();
+        let expected: Vec<Vec<u8>> = entries.iter().map(|(key, _)| key.clone()).collect();
+        assert_eq!(keys, expected);
+    }
+
+    #[test]
+    fn test_sstable_oversized_entry_gets_its_own_block() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut builder = SstableStoreBuilder::create(path).unwrap();
+            builder.insert(b"a", b"small").unwrap();
+            builder.insert(b"b", &vec![7u8; 10_000]).unwrap();
+            builder.insert(b"c", b"small").unwrap();
+            builder.finish().unwrap();
+        }
+
+        let store = SstableStore::open(path).unwrap();
+        assert_eq!(store.block_count(), 3);
+        assert_eq!(store.get(b"b").unwrap(), Some(vec![7u8; 10_000]));
+        assert_eq!(store.get(b"c").unwrap(), Some(b"small".to_vec()));
+    }
+
+    #[test]
+    fn test_sstable_empty_store() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        SstableStoreBuilder::create(path).unwrap().finish().unwrap();
+
+        let store = SstableStore::open(path).unwrap();
+        assert_eq!(store.len(), 0);
+        assert_eq!(store.block_count(), 0);
+        assert_eq!(store.get(b"key").unwrap(), None);
+        assert!(store.keys().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sstable_truncated_index_fails_to_open() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        {
+            let mut builder = SstableStoreBuilder::create(path).unwrap();
+            builder.insert(b"key", b"value").unwrap();
+            builder.finish().unwrap();
+        }
+        let data = std::fs::read(path).unwrap();
+        std::fs::write(path, &data[..data.len() - 1]).unwrap();
+
+        let err = SstableStore::open(path).err().unwrap();
+        assert!(err.to_string().contains("truncated"), "{}", err);
+    }
+
+    #[test]
+    fn test_sstable_huge_header_counts_fail_to_open() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        {
+            let mut builder = SstableStoreBuilder::create(path).unwrap();
+            builder.insert(b"key", b"value").unwrap();
+            builder.finish().unwrap();
+        }
+        let original = std::fs::read(path).unwrap();
+
+        // entry_count, then block_count
+        for field in [8..16, 24..32] {
+            let mut data = original.clone();
+            data[field].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+            std::fs::write(path, &data).unwrap();
+            let err = SstableStore::open(path).err().unwrap();
+            assert!(err.to_string().contains("can hold"), "{}", err);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_sstable_roundtrip_multiple(
+            entries in prop_vec((prop_vec(any::<u8>(), 0..50), prop_vec(any::<u8>(), 0..500)), 1..60),
+            block_size in 16usize..2048,
+        ) {
+            let temp_file = NamedTempFile::new().unwrap();
+            let path = temp_file.path();
+
+            // Last write wins, as in the builder
+            let expected: HashMap<Vec<u8>, Vec<u8>> = entries.iter().cloned().collect();
+            {
+                let mut builder = SstableStoreBuilder::create(path)
+                    .unwrap()
+                    .with_block_size(block_size);
+                for (key, value) in &entries {
+                    builder.insert(key, value).unwrap();
+                }
+                builder.finish().unwrap();
+            }
+
+            let store = SstableStore::open(path).unwrap();
+            prop_assert_eq!(store.len(), expected.len());
+            for (key, value) in &expected {
+                prop_assert_eq!(store.get(key).unwrap().as_ref(), Some(value));
+            }
+        }
+    }
+}
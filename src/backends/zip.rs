@@ -1,9 +1,12 @@
+use super::page_cache;
 use crate::store::{BlobStore, BlobStoreBuilder};
-use anyhow::{Context, Result};
+use crate::util::hex;
+use anyhow::{bail, Context, Result};
 use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
 use std::fs::File;
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use zip::read::ZipArchive;
 use zip::write::FileOptions;
 use zip::ZipWriter;
@@ -13,6 +16,8 @@ use zip::ZipWriter;
 pub struct ZipStore {
     archive: RefCell<ZipArchive<File>>,
     count: usize,
+    /// Kept to reopen the archive for `drop_caches`, since `ZipArchive` owns its file
+    path: PathBuf,
 }
 
 impl ZipStore {
@@ -34,9 +39,17 @@ impl BlobStore for ZipStore {
         Ok(Self {
             archive: RefCell::new(archive),
             count,
+            path: path.to_path_buf(),
         })
     }
 
+    /// The central directory is parsed into memory at open; this evicts the
+    /// archive's file, so entries are read back from disk.
+    fn drop_caches(&self) -> Result<bool> {
+        let file = File::open(&self.path).context("Failed to open zip file")?;
+        page_cache::evict(&file, None)
+    }
+
     fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
         let filename = Self::key_to_filename(key);
 
@@ -55,6 +68,50 @@ impl BlobStore for ZipStore {
         result
     }
 
+    fn get_into(&self, key: &[u8], buf: &mut Vec<u8>) -> Result<bool> {
+        buf.clear();
+        let filename = Self::key_to_filename(key);
+
+        let mut archive = self.archive.borrow_mut();
+
+        match archive.by_name(&filename) {
+            Ok(mut file) => {
+                buf.reserve(file.size() as usize);
+                file.read_to_end(buf)
+                    .context("Failed to read file from zip")?;
+                Ok(true)
+            }
+            Err(zip::result::ZipError::FileNotFound) => Ok(false),
+            Err(e) => Err(e).context("Failed to find file in zip"),
+        }
+    }
+
+    /// Looks the entry up in the central directory without decompressing it.
+    fn contains_key(&self, key: &[u8]) -> Result<bool> {
+        let filename = Self::key_to_filename(key);
+
+        let mut archive = self.archive.borrow_mut();
+
+        match archive.by_name(&filename) {
+            Ok(_) => Ok(true),
+            Err(zip::result::ZipError::FileNotFound) => Ok(false),
+            Err(e) => Err(e).context("Failed to find file in zip"),
+        }
+    }
+
+    /// The uncompressed size recorded in the central directory.
+    fn value_len(&self, key: &[u8]) -> Result<Option<usize>> {
+        let filename = Self::key_to_filename(key);
+
+        let mut archive = self.archive.borrow_mut();
+
+        match archive.by_name(&filename) {
+            Ok(file) => Ok(Some(file.size() as usize)),
+            Err(zip::result::ZipError::FileNotFound) => Ok(None),
+            Err(e) => Err(e).context("Failed to find file in zip"),
+        }
+    }
+
     fn keys(&self) -> Result<Vec<Vec<u8>>> {
         let mut keys = Vec::with_capacity(self.count);
         let archive = self.archive.borrow();
@@ -73,87 +130,223 @@ impl BlobStore for ZipStore {
         self.count
     }
 
+    /// Sums the uncompressed sizes in the central directory.
+    fn total_value_bytes(&self) -> Result<u64> {
+        let mut archive = self.archive.borrow_mut();
+        let mut total = 0u64;
+        for i in 0..self.count {
+            let file = archive
+                .by_index_raw(i)
+                .context("Failed to read zip entry")?;
+            total += file.size();
+        }
+        Ok(total)
+    }
+
+    fn compression_ratio(&self) -> Result<f64> {
+        let mut archive = self.archive.borrow_mut();
+        let mut uncompressed = 0u64;
+        let mut stored = 0u64;
+
+        for i in 0..self.count {
+            // Raw access reads the central directory sizes without decompressing
+            let file = archive
+                .by_index_raw(i)
+                .context("Failed to read zip entry")?;
+            uncompressed += file.size();
+            stored += file.compressed_size();
+        }
+
+        if stored == 0 {
+            return Ok(1.0);
+        }
+        Ok(uncompressed as f64 / stored as f64)
+    }
+
     fn backend_name() -> &'static str {
         "Zip"
     }
 }
 
+/// Compression method and optional level for each zip entry, written as
+/// `method[:level]` (e.g. `stored`, `deflate:9`, `zstd:3`, `bzip2`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZipCompression {
+    pub method: zip::CompressionMethod,
+    /// Method-specific level; `None` uses the method's default
+    pub level: Option<i64>,
+}
+
+impl ZipCompression {
+    /// No compression, for a fair comparison with the uncompressed backends
+    pub const STORED: Self = Self {
+        method: zip::CompressionMethod::Stored,
+        level: None,
+    };
+
+    pub fn name(&self) -> String {
+        let method = match self.method {
+            zip::CompressionMethod::Stored => "stored",
+            zip::CompressionMethod::Deflated => "deflate",
+            zip::CompressionMethod::Bzip2 => "bzip2",
+            zip::CompressionMethod::Zstd => "zstd",
+            _ => "other",
+        };
+        match self.level {
+            Some(level) => format!("{}:{}", method, level),
+            None => method.to_string(),
+        }
+    }
+}
+
+impl Default for ZipCompression {
+    fn default() -> Self {
+        Self::STORED
+    }
+}
+
+impl std::str::FromStr for ZipCompression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (method, level) = match s.split_once(':') {
+            Some((method, level)) => {
+                let level = level
+                    .parse()
+                    .with_context(|| format!("Invalid compression level in '{}'", s))?;
+                (method, Some(level))
+            }
+            None => (s, None),
+        };
+        let method = match method {
+            "stored" => zip::CompressionMethod::Stored,
+            "deflate" => zip::CompressionMethod::Deflated,
+            "bzip2" => zip::CompressionMethod::Bzip2,
+            "zstd" => zip::CompressionMethod::Zstd,
+            other => bail!(
+                "Unknown compression method '{}' (expected stored, deflate, bzip2 or zstd)",
+                other
+            ),
+        };
+        if method == zip::CompressionMethod::Stored && level.is_some() {
+            bail!("'stored' does not take a level");
+        }
+        Ok(Self { method, level })
+    }
+}
+
 /// Builder for zip blob store.
 pub struct ZipStoreBuilder {
+    path: PathBuf,
     writer: ZipWriter<File>,
     count: usize,
+    compression: ZipCompression,
+    /// File names already written, to catch repeated keys
+    seen: HashSet<String>,
+    /// Latest values of repeated keys, swapped in for the originals by `finish`
+    overwrites: BTreeMap<String, Vec<u8>>,
 }
 
-impl BlobStoreBuilder for ZipStoreBuilder {
-    fn create(path: &Path) -> Result<Self> {
-        let file = File::create(path).context("Failed to create zip file")?;
-        let writer = ZipWriter::new(file);
-
-        Ok(Self { writer, count: 0 })
+impl ZipStoreBuilder {
+    /// Compress entries with `compression` instead of storing them as-is
+    pub fn with_compression(mut self, compression: ZipCompression) -> Self {
+        self.compression = compression;
+        self
     }
 
-    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
-        let filename = ZipStore::key_to_filename(key);
-
+    fn write_entry(
+        writer: &mut ZipWriter<File>,
+        compression: ZipCompression,
+        filename: &str,
+        value: &[u8],
+    ) -> Result<()> {
         let options = FileOptions::<()>::default()
-            .compression_method(zip::CompressionMethod::Stored) // No compression for fair comparison
+            .compression_method(compression.method)
+            .compression_level(compression.level)
             .unix_permissions(0o644);
 
-        self.writer
-            .start_file(&filename, options)
+        writer
+            .start_file(filename, options)
             .context("Failed to start file in zip")?;
 
-        self.writer
+        writer
             .write_all(value)
             .context("Failed to write file to zip")?;
-
-        self.count += 1;
         Ok(())
     }
 
-    fn finish(self) -> Result<()> {
-        self.writer.finish().context("Failed to finish zip")?;
+    /// Rewrite the finished archive at `path` with the latest value of each
+    /// repeated key. Other entries are copied without recompressing them.
+    fn rewrite_overwritten(
+        path: &Path,
+        compression: ZipCompression,
+        overwrites: &BTreeMap<String, Vec<u8>>,
+    ) -> Result<()> {
+        let file = File::open(path).context("Failed to reopen zip file")?;
+        let mut archive = ZipArchive::new(file).context("Failed to read zip archive")?;
+
+        let mut rewrite_path = path.as_os_str().to_owned();
+        rewrite_path.push(".rewrite");
+        let rewrite_path = PathBuf::from(rewrite_path);
+        let rewrite = File::create(&rewrite_path).context("Failed to create zip file")?;
+        let mut writer = ZipWriter::new(rewrite);
+
+        for i in 0..archive.len() {
+            let entry = archive
+                .by_index_raw(i)
+                .context("Failed to read zip entry")?;
+            if !overwrites.contains_key(entry.name()) {
+                writer
+                    .raw_copy_file(entry)
+                    .context("Failed to copy zip entry")?;
+            }
+        }
+        for (filename, value) in overwrites {
+            Self::write_entry(&mut writer, compression, filename, value)?;
+        }
+        writer.finish().context("Failed to finish zip")?;
+
+        std::fs::rename(&rewrite_path, path).context("Failed to replace zip file")?;
         Ok(())
     }
 }
 
-// We need hex encoding for filenames
-mod hex {
-    const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
+impl BlobStoreBuilder for ZipStoreBuilder {
+    fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path).context("Failed to create zip file")?;
+        let writer = ZipWriter::new(file);
 
-    pub fn encode(data: &[u8]) -> String {
-        let mut result = String::with_capacity(data.len() * 2);
-        for byte in data {
-            result.push(HEX_CHARS[(byte >> 4) as usize] as char);
-            result.push(HEX_CHARS[(byte & 0xf) as usize] as char);
-        }
-        result
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer,
+            count: 0,
+            compression: ZipCompression::default(),
+            seen: HashSet::new(),
+            overwrites: BTreeMap::new(),
+        })
     }
 
-    pub fn decode(s: &str) -> Option<Vec<u8>> {
-        if s.len() % 2 != 0 {
-            return None;
-        }
-
-        let mut result = Vec::with_capacity(s.len() / 2);
-        let bytes = s.as_bytes();
-
-        for chunk in bytes.chunks(2) {
-            let high = hex_char_to_nibble(chunk[0])?;
-            let low = hex_char_to_nibble(chunk[1])?;
-            result.push((high << 4) | low);
+    /// A repeated key keeps its last value, as in the other backends. Zip
+    /// writers reject repeated file names, so the value is held until `finish`
+    /// rewrites the archive.
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        let filename = ZipStore::key_to_filename(key);
+        if !self.seen.insert(filename.clone()) {
+            self.overwrites.insert(filename, value.to_vec());
+            return Ok(());
         }
 
-        Some(result)
+        Self::write_entry(&mut self.writer, self.compression, &filename, value)?;
+        self.count += 1;
+        Ok(())
     }
 
-    fn hex_char_to_nibble(c: u8) -> Option<u8> {
-        match c {
-            b'0'..=b'9' => Some(c - b'0'),
-            b'a'..=b'f' => Some(c - b'a' + 10),
-            b'A'..=b'F' => Some(c - b'A' + 10),
-            _ => None,
+    fn finish(self) -> Result<()> {
+        self.writer.finish().context("Failed to finish zip")?;
+        if !self.overwrites.is_empty() {
+            Self::rewrite_overwritten(&self.path, self.compression, &self.overwrites)?;
         }
+        Ok(())
     }
 }
 
@@ -208,6 +401,52 @@ mod tests {
         assert_eq!(keys, vec![b"alpha".to_vec(), b"beta".to_vec()]);
     }
 
+    #[test]
+    fn test_zip_duplicate_key_keeps_last_value() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut builder = ZipStoreBuilder::create(path).unwrap();
+            builder.insert(b"key", b"first").unwrap();
+            builder.insert(b"key", b"second").unwrap();
+            builder.finish().unwrap();
+        }
+
+        let store = ZipStore::open(path).unwrap();
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.keys().unwrap(), vec![b"key".to_vec()]);
+        assert_eq!(store.get(b"key").unwrap(), Some(b"second".to_vec()));
+    }
+
+    #[test]
+    fn test_zip_duplicates_among_other_entries_are_rewritten() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut builder = ZipStoreBuilder::create(path)
+                .unwrap()
+                .with_compression("deflate".parse().unwrap());
+            builder.insert(b"a", &[1u8; 1000]).unwrap();
+            builder.insert(b"b", b"b1").unwrap();
+            builder.insert(b"c", b"c1").unwrap();
+            builder.insert(b"b", b"b2").unwrap();
+            builder.insert(b"b", b"b3").unwrap();
+            builder.finish().unwrap();
+        }
+
+        let store = ZipStore::open(path).unwrap();
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.get(b"a").unwrap(), Some(vec![1u8; 1000]));
+        assert_eq!(store.get(b"b").unwrap(), Some(b"b3".to_vec()));
+        assert_eq!(store.get(b"c").unwrap(), Some(b"c1".to_vec()));
+        // The rewrite goes to a sibling file that replaces the original
+        let mut rewrite_path = path.as_os_str().to_owned();
+        rewrite_path.push(".rewrite");
+        assert!(!Path::new(&rewrite_path).exists());
+    }
+
     #[test]
     fn test_zip_binary_data() {
         let temp_file = NamedTempFile::new().unwrap();
@@ -258,6 +497,78 @@ mod tests {
         assert!(store.is_empty());
     }
 
+    #[test]
+    fn test_zip_compression_ratio() {
+        let stored_file = NamedTempFile::new().unwrap();
+        let deflated_file = NamedTempFile::new().unwrap();
+        let value = b"repetitive payload ".repeat(1000);
+
+        {
+            let mut builder = ZipStoreBuilder::create(stored_file.path()).unwrap();
+            builder.insert(b"key", &value).unwrap();
+            builder.finish().unwrap();
+        }
+
+        {
+            let mut builder = ZipStoreBuilder::create(deflated_file.path())
+                .unwrap()
+                .with_compression("deflate".parse().unwrap());
+            builder.insert(b"key", &value).unwrap();
+            builder.finish().unwrap();
+        }
+
+        let stored = ZipStore::open(stored_file.path()).unwrap();
+        assert_eq!(stored.compression_ratio().unwrap(), 1.0);
+
+        let deflated = ZipStore::open(deflated_file.path()).unwrap();
+        assert!(deflated.compression_ratio().unwrap() > 1.0);
+        assert_eq!(deflated.get(b"key").unwrap(), Some(value));
+    }
+
+    #[test]
+    fn test_zip_deflated_roundtrip_is_smaller_than_stored() {
+        let stored_file = NamedTempFile::new().unwrap();
+        let deflated_file = NamedTempFile::new().unwrap();
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = (0..50)
+            .map(|i| {
+                let key = format!("key_{}", i).into_bytes();
+                let value = format!("compressible value {} ", i)
+                    .repeat(100)
+                    .into_bytes();
+                (key, value)
+            })
+            .collect();
+
+        for (path, compression) in [
+            (stored_file.path(), ZipCompression::STORED),
+            (
+                deflated_file.path(),
+                ZipCompression {
+                    method: zip::CompressionMethod::Deflated,
+                    level: None,
+                },
+            ),
+        ] {
+            let mut builder = ZipStoreBuilder::create(path)
+                .unwrap()
+                .with_compression(compression);
+            for (key, value) in &entries {
+                builder.insert(key, value).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        let store = ZipStore::open(deflated_file.path()).unwrap();
+        assert_eq!(store.len(), entries.len());
+        for (key, value) in &entries {
+            assert_eq!(store.get(key).unwrap().as_ref(), Some(value));
+        }
+
+        let stored_len = std::fs::metadata(stored_file.path()).unwrap().len();
+        let deflated_len = std::fs::metadata(deflated_file.path()).unwrap().len();
+        assert!(deflated_len < stored_len);
+    }
+
     #[test]
     fn test_hex_encode_decode() {
         let original = b"hello world";
@@ -343,4 +654,19 @@ mod tests {
             prop_assert_eq!(decoded, data);
         }
     }
+
+    #[test]
+    fn test_parse_compression_setting() {
+        let zstd: ZipCompression = "zstd:3".parse().unwrap();
+        assert_eq!(zstd.method, zip::CompressionMethod::Zstd);
+        assert_eq!(zstd.level, Some(3));
+        assert_eq!(zstd.name(), "zstd:3");
+        assert_eq!(
+            "stored".parse::<ZipCompression>().unwrap(),
+            ZipCompression::STORED
+        );
+        assert!("stored:1".parse::<ZipCompression>().is_err());
+        assert!("lz4".parse::<ZipCompression>().is_err());
+        assert!("deflate:x".parse::<ZipCompression>().is_err());
+    }
 }
@@ -1,14 +1,64 @@
-use crate::data_gen::BlobSize;
+use crate::data_gen::{BlobSize, SeededRng};
 use crate::store::BlobStore;
-use anyhow::Result;
-use rand::rngs::StdRng;
+use anyhow::{bail, Context, Result};
+use hdrhistogram::Histogram;
 use rand::seq::SliceRandom;
-use rand::SeedableRng;
-use std::io::{self, Write};
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 
+/// Two-sided z-score for a 95% confidence interval
+const Z_95: f64 = 1.96;
+
+/// Prepended to existing keys to make the keys for missed lookups. Every
+/// `KeyShape` generates printable ASCII, so no generated key begins with a NUL
+/// byte. Keys read from `--new-keys-file` are arbitrary bytes and carry no such
+/// guarantee.
+const MISS_KEY_PREFIX: &[u8] = b"\0miss:";
+
+/// Distinct miss keys made per size category; misses cycle through them
+const MISS_KEY_POOL: usize = 1024;
+
+/// Largest latency a histogram records exactly (one hour); longer samples are clamped
+const HISTOGRAM_MAX_NS: u64 = 3_600_000_000_000;
+
+/// Significant figures a latency histogram keeps, bounding its error to 0.1%
+const HISTOGRAM_SIGFIG: u8 = 3;
+
+/// Which build of the benchmark produced a result set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Crate version (`CARGO_PKG_VERSION`)
+    pub version: String,
+    /// Short git SHA captured by `build.rs`, or "unknown" outside a git checkout
+    pub git_sha: String,
+}
+
+impl Provenance {
+    /// Provenance of the running binary
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_sha: env!("BUILD_GIT_SHA").to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for Provenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{} (git {})", self.version, self.git_sha)
+    }
+}
+
 /// Memory usage snapshot
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct MemoryStats {
     /// Physical memory used by the process in bytes
     pub physical_mem: usize,
@@ -29,6 +79,287 @@ impl MemoryStats {
     }
 }
 
+/// Memory snapshot taken by a `MemorySampler`
+#[derive(Debug, Clone)]
+pub struct MemorySample {
+    /// Time since the sampler started
+    pub elapsed: Duration,
+    pub stats: MemoryStats,
+}
+
+/// Background thread that captures `MemoryStats` at a fixed interval until stopped,
+/// giving a memory timeline for the whole run. The thread sleeps between samples,
+/// so its overhead is one stats read per interval.
+pub struct MemorySampler {
+    stop: mpsc::Sender<()>,
+    handle: JoinHandle<Vec<MemorySample>>,
+}
+
+impl MemorySampler {
+    pub fn start(interval: Duration) -> Self {
+        let (stop, stopped) = mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            let start = Instant::now();
+            let mut samples = Vec::new();
+            loop {
+                samples.push(MemorySample {
+                    elapsed: start.elapsed(),
+                    stats: MemoryStats::capture(),
+                });
+                if stopped.recv_timeout(interval) != Err(mpsc::RecvTimeoutError::Timeout) {
+                    break;
+                }
+            }
+            samples
+        });
+        Self { stop, handle }
+    }
+
+    /// Stop sampling and return the collected series in time order
+    pub fn stop(self) -> Vec<MemorySample> {
+        let _ = self.stop.send(());
+        self.handle.join().unwrap_or_default()
+    }
+}
+
+/// Cost and granularity of `Instant::now()` on this machine. Lookups that take
+/// only a few ticks can't be timed one at a time; see `BenchmarkConfig::batch_size`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerCalibration {
+    /// Mean cost of one `Instant::now()` call
+    pub overhead: Duration,
+    /// Smallest nonzero step observed between consecutive readings
+    pub resolution: Duration,
+}
+
+impl TimerCalibration {
+    const SAMPLES: u32 = 10_000;
+
+    pub fn measure() -> Self {
+        let start = Instant::now();
+        for _ in 0..Self::SAMPLES {
+            std::hint::black_box(Instant::now());
+        }
+        let overhead = start.elapsed() / Self::SAMPLES;
+
+        let mut resolution = Duration::MAX;
+        let first = Instant::now();
+        let mut last = first;
+        for _ in 0..Self::SAMPLES {
+            let now = Instant::now();
+            let step = now.duration_since(last);
+            if !step.is_zero() {
+                resolution = resolution.min(step);
+            }
+            last = now;
+        }
+        if resolution == Duration::MAX {
+            // The clock never ticked: resolution is at least the whole span
+            resolution = last.duration_since(first).max(Duration::from_nanos(1));
+        }
+        Self {
+            overhead,
+            resolution,
+        }
+    }
+
+    /// Whether a latency is within 10x of what the timer itself can measure
+    pub fn is_near_floor(&self, latency: Duration) -> bool {
+        latency < self.resolution.max(self.overhead) * 10
+    }
+}
+
+/// Name of the global allocator the binary was built with.
+/// Selected via the `mimalloc` / `jemalloc` cargo features (mimalloc wins if both are on).
+pub fn allocator_name() -> &'static str {
+    if cfg!(feature = "mimalloc") {
+        "mimalloc"
+    } else if cfg!(feature = "jemalloc") {
+        "jemalloc"
+    } else {
+        "system"
+    }
+}
+
+/// Order in which measured lookups visit the keys of a size category
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum AccessOrder {
+    /// Uniform random sampling (with replacement)
+    #[default]
+    Random,
+    /// Ascending key order, wrapping around when lookups exceed the key count
+    Sorted,
+    /// Descending key order, wrapping around when lookups exceed the key count
+    Reverse,
+}
+
+impl AccessOrder {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AccessOrder::Random => "random",
+            AccessOrder::Sorted => "sorted",
+            AccessOrder::Reverse => "reverse",
+        }
+    }
+
+    /// Deterministic key sequence for the ordered modes; `None` for random sampling.
+    fn ordered_keys<'a>(&self, keys: &'a [Vec<u8>]) -> Option<Vec<&'a [u8]>> {
+        let mut ordered: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+        match self {
+            AccessOrder::Random => return None,
+            AccessOrder::Sorted => ordered.sort_unstable(),
+            AccessOrder::Reverse => ordered.sort_unstable_by(|a, b| b.cmp(a)),
+        }
+        Some(ordered)
+    }
+}
+
+/// Distribution that random measured lookups draw keys from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum AccessPattern {
+    /// Every key is equally likely for the whole run
+    #[default]
+    Uniform,
+    /// Uniform within a window of consecutive keys that slides from the start of
+    /// the key list to the end over the run, so early keys fall out of the working set
+    SlidingWindow,
+}
+
+impl AccessPattern {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AccessPattern::Uniform => "uniform",
+            AccessPattern::SlidingWindow => "sliding-window",
+        }
+    }
+}
+
+/// Latency samples in nanoseconds: either every sample, or an HDR histogram
+/// whose size doesn't grow with the number of lookups (see `BenchmarkConfig::histogram`)
+#[derive(Debug, Clone)]
+pub enum LatencySamples {
+    Raw(Vec<u64>),
+    Hist(Histogram<u64>),
+}
+
+impl Default for LatencySamples {
+    fn default() -> Self {
+        Self::Raw(Vec::new())
+    }
+}
+
+impl From<Vec<u64>> for LatencySamples {
+    fn from(samples: Vec<u64>) -> Self {
+        Self::Raw(samples)
+    }
+}
+
+impl LatencySamples {
+    /// Empty histogram covering 1ns to `HISTOGRAM_MAX_NS`
+    pub fn histogram() -> Self {
+        Self::Hist(
+            Histogram::new_with_bounds(1, HISTOGRAM_MAX_NS, HISTOGRAM_SIGFIG)
+                .expect("histogram bounds are valid"),
+        )
+    }
+
+    pub fn record(&mut self, ns: u64) {
+        match self {
+            Self::Raw(samples) => samples.push(ns),
+            Self::Hist(hist) => hist.saturating_record(ns),
+        }
+    }
+
+    /// Fold `other`'s samples into these. Raw samples stay raw only if both
+    /// sides are; otherwise the result is a histogram.
+    pub fn merge(&mut self, other: LatencySamples) {
+        match (&mut *self, other) {
+            (Self::Raw(samples), Self::Raw(more)) => samples.extend(more),
+            (Self::Hist(hist), Self::Hist(more)) => {
+                hist.add(&more).expect("histograms share bounds");
+            }
+            (Self::Hist(hist), Self::Raw(more)) => {
+                for ns in more {
+                    hist.saturating_record(ns);
+                }
+            }
+            (Self::Raw(samples), Self::Hist(mut hist)) => {
+                for &ns in samples.iter() {
+                    hist.saturating_record(ns);
+                }
+                *self = Self::Hist(hist);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Raw(samples) => samples.len(),
+            Self::Hist(hist) => hist.len() as usize,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Several percentiles (each 0-100) from a single sorted copy of raw
+    /// samples, or straight from a histogram within its 0.1% precision
+    pub fn percentiles(&self, ps: &[f64]) -> Vec<Duration> {
+        if self.is_empty() {
+            return vec![Duration::ZERO; ps.len()];
+        }
+
+        let samples = match self {
+            Self::Raw(samples) => samples,
+            Self::Hist(hist) => {
+                return ps
+                    .iter()
+                    .map(|&p| Duration::from_nanos(hist.value_at_quantile(p / 100.0)))
+                    .collect();
+            }
+        };
+        let mut sorted = samples.clone();
+        sorted.sort_unstable();
+
+        ps.iter()
+            .map(|&p| Duration::from_nanos(sorted[percentile_index(p, sorted.len())]))
+            .collect()
+    }
+
+    /// Every sample, unless they were recorded into a histogram
+    pub fn raw(&self) -> Option<&[u64]> {
+        match self {
+            Self::Raw(samples) => Some(samples),
+            Self::Hist(_) => None,
+        }
+    }
+
+    /// Mean and sample variance (n - 1 denominator), in nanoseconds
+    fn mean_and_variance(&self) -> (f64, f64) {
+        let n = self.len();
+        match self {
+            Self::Raw(samples) => {
+                let mean = samples.iter().map(|&v| v as f64).sum::<f64>() / n as f64;
+                let variance = samples
+                    .iter()
+                    .map(|&v| {
+                        let d = v as f64 - mean;
+                        d * d
+                    })
+                    .sum::<f64>()
+                    / (n - 1) as f64;
+                (mean, variance)
+            }
+            // `stdev` is the population deviation; rescale to the sample variance
+            Self::Hist(hist) => (
+                hist.mean(),
+                hist.stdev().powi(2) * n as f64 / (n - 1) as f64,
+            ),
+        }
+    }
+}
+
 /// Results from a single benchmark run
 #[derive(Debug, Clone)]
 pub struct BenchmarkResult {
@@ -36,26 +367,142 @@ pub struct BenchmarkResult {
     pub backend_name: String,
     /// Size category being benchmarked
     pub blob_size: BlobSize,
-    /// All individual latencies in nanoseconds
-    pub latencies_ns: Vec<u64>,
+    /// Latencies in nanoseconds: every sample, or a histogram of them
+    pub latencies_ns: LatencySamples,
     /// File size on disk in bytes
     pub file_size: u64,
     /// Memory usage after opening the store
     pub memory_stats: MemoryStats,
+    /// Key order used for the measured lookups
+    pub access_order: AccessOrder,
+    /// Distribution of the random measured lookups
+    pub access_pattern: AccessPattern,
+    /// True if the run was cancelled before all lookups completed
+    pub interrupted: bool,
+    /// Total bytes of stored values, if measured (see `BenchmarkConfig::report_index_size`)
+    pub payload_bytes: Option<u64>,
+    /// Time taken by `BlobStore::open` (zero unless the caller measured it)
+    pub open_time: Duration,
+    /// Per-lookup index/IO split, if phase timing was requested and the backend supports it
+    pub phase_ns: Option<PhaseLatencies>,
+    /// Lookups timed together per latency sample (1 = every lookup timed on its own)
+    pub batch_size: usize,
+    /// Unique file bytes the measured lookups read, rounded out to whole 4 KiB
+    /// pages, if tracked (see `BenchmarkConfig::track_access`) and the backend
+    /// supports it
+    pub accessed_bytes: Option<u64>,
+    /// File bytes read per byte of value returned, if tracked and supported
+    pub read_amplification: Option<f64>,
+    /// Measured lookups actually performed (fewer than requested if interrupted)
+    pub lookups: usize,
+    /// Measured lookups that found no value (see `BenchmarkConfig::miss_ratio`)
+    pub misses: usize,
+    /// Latency samples in which every lookup missed; these are also in `latencies_ns`
+    pub miss_latencies_ns: LatencySamples,
+    /// Wall-clock time of the whole measured loop, including the untimed work
+    /// between samples (key selection, `--cold` eviction)
+    pub wall_clock: Duration,
+    /// Answer single-percentile queries (`percentile`, `pXX()`) by quickselect
+    /// instead of a full sort (see `BenchmarkConfig::fast_percentile`)
+    pub fast_percentile: bool,
+}
+
+/// Per-lookup latencies split into index and IO phases (nanoseconds)
+#[derive(Debug, Clone, Default)]
+pub struct PhaseLatencies {
+    pub index_ns: Vec<u64>,
+    pub io_ns: Vec<u64>,
+}
+
+impl PhaseLatencies {
+    /// Mean index-phase latency
+    pub fn mean_index(&self) -> Duration {
+        mean_duration(&self.index_ns)
+    }
+
+    /// Mean IO-phase latency
+    pub fn mean_io(&self) -> Duration {
+        mean_duration(&self.io_ns)
+    }
+}
+
+/// Nearest-rank index of percentile `p` (0-100) in `len` sorted samples
+fn percentile_index(p: f64, len: usize) -> usize {
+    ((p / 100.0) * (len - 1) as f64).round() as usize
+}
+
+fn mean_duration(samples_ns: &[u64]) -> Duration {
+    if samples_ns.is_empty() {
+        return Duration::ZERO;
+    }
+    Duration::from_nanos(samples_ns.iter().sum::<u64>() / samples_ns.len() as u64)
+}
+
+/// An empty, uninterrupted run of single-lookup samples, for filling in the
+/// fields a caller doesn't measure
+impl Default for BenchmarkResult {
+    fn default() -> Self {
+        Self {
+            backend_name: String::new(),
+            blob_size: BlobSize::Tiny,
+            latencies_ns: LatencySamples::default(),
+            file_size: 0,
+            memory_stats: MemoryStats::default(),
+            access_order: AccessOrder::default(),
+            access_pattern: AccessPattern::default(),
+            interrupted: false,
+            payload_bytes: None,
+            open_time: Duration::ZERO,
+            phase_ns: None,
+            batch_size: 1,
+            accessed_bytes: None,
+            read_amplification: None,
+            lookups: 0,
+            misses: 0,
+            miss_latencies_ns: LatencySamples::default(),
+            wall_clock: Duration::ZERO,
+            fast_percentile: false,
+        }
+    }
 }
 
 impl BenchmarkResult {
-    /// Calculate percentile latency (p is 0-100)
+    /// Measured lookups that found a value
+    pub fn hits(&self) -> usize {
+        self.lookups - self.misses
+    }
+
+    /// Calculate percentile latency (p is 0-100), by quickselect if the run
+    /// asked for `fast_percentile`
     pub fn percentile(&self, p: f64) -> Duration {
+        if self.fast_percentile {
+            self.percentile_select(p)
+        } else {
+            self.percentiles(&[p])[0]
+        }
+    }
+
+    /// Several percentiles (each 0-100) from a single sorted copy of the samples.
+    /// Prefer this over repeated `pXX()` calls, which each sort the full vector.
+    /// Histograms answer each percentile directly, within their 0.1% precision.
+    pub fn percentiles(&self, ps: &[f64]) -> Vec<Duration> {
+        self.latencies_ns.percentiles(ps)
+    }
+
+    /// Single percentile via quickselect: O(n) instead of a full sort, for very
+    /// large sample counts where only one percentile is needed. Same result as `percentile`.
+    pub fn percentile_select(&self, p: f64) -> Duration {
         if self.latencies_ns.is_empty() {
             return Duration::ZERO;
         }
 
-        let mut sorted = self.latencies_ns.clone();
-        sorted.sort_unstable();
-
-        let idx = ((p / 100.0) * (sorted.len() - 1) as f64).round() as usize;
-        Duration::from_nanos(sorted[idx])
+        let LatencySamples::Raw(samples) = &self.latencies_ns else {
+            return self.percentiles(&[p])[0];
+        };
+        let mut samples = samples.clone();
+        let idx = percentile_index(p, samples.len());
+        let (_, &mut nth, _) = samples.select_nth_unstable(idx);
+        Duration::from_nanos(nth)
     }
 
     pub fn p50(&self) -> Duration {
@@ -74,30 +521,47 @@ impl BenchmarkResult {
         self.percentile(99.0)
     }
 
+    pub fn p999(&self) -> Duration {
+        self.percentile(99.9)
+    }
+
     pub fn min(&self) -> Duration {
-        self.latencies_ns
-            .iter()
-            .min()
-            .map(|&ns| Duration::from_nanos(ns))
-            .unwrap_or(Duration::ZERO)
+        match &self.latencies_ns {
+            LatencySamples::Raw(samples) => samples
+                .iter()
+                .min()
+                .map(|&ns| Duration::from_nanos(ns))
+                .unwrap_or(Duration::ZERO),
+            LatencySamples::Hist(hist) if hist.is_empty() => Duration::ZERO,
+            LatencySamples::Hist(hist) => Duration::from_nanos(hist.min()),
+        }
     }
 
     pub fn max(&self) -> Duration {
-        self.latencies_ns
-            .iter()
-            .max()
-            .map(|&ns| Duration::from_nanos(ns))
-            .unwrap_or(Duration::ZERO)
+        match &self.latencies_ns {
+            LatencySamples::Raw(samples) => samples
+                .iter()
+                .max()
+                .map(|&ns| Duration::from_nanos(ns))
+                .unwrap_or(Duration::ZERO),
+            LatencySamples::Hist(hist) if hist.is_empty() => Duration::ZERO,
+            LatencySamples::Hist(hist) => Duration::from_nanos(hist.max()),
+        }
     }
 
     pub fn mean(&self) -> Duration {
         if self.latencies_ns.is_empty() {
             return Duration::ZERO;
         }
-        let sum: u64 = self.latencies_ns.iter().sum();
-        Duration::from_nanos(sum / self.latencies_ns.len() as u64)
+        match &self.latencies_ns {
+            LatencySamples::Raw(samples) => {
+                Duration::from_nanos(samples.iter().sum::<u64>() / samples.len() as u64)
+            }
+            LatencySamples::Hist(hist) => Duration::from_nanos(hist.mean() as u64),
+        }
     }
 
+    /// Throughput as the reciprocal of the mean sampled latency
     pub fn ops_per_second(&self) -> f64 {
         if self.latencies_ns.is_empty() {
             return 0.0;
@@ -109,6 +573,66 @@ impl BenchmarkResult {
             0.0
         }
     }
+
+    /// Throughput as lookups performed over the loop's wall-clock time. Unlike
+    /// `ops_per_second` this counts loop overhead, which dominates for lookups
+    /// near the timer resolution. Zero if no wall-clock time was recorded.
+    pub fn ops_per_second_wall(&self) -> f64 {
+        let secs = self.wall_clock.as_secs_f64();
+        if secs > 0.0 {
+            self.lookups as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// Share of the file's bytes read by the measured lookups, if tracked
+    pub fn accessed_fraction(&self) -> Option<f64> {
+        self.accessed_bytes
+            .filter(|_| self.file_size > 0)
+            .map(|bytes| bytes as f64 / self.file_size as f64)
+    }
+
+    /// Bytes of the file not accounted for by value payload (index structures,
+    /// headers, stored keys, padding). `None` unless payload was measured, or
+    /// if the payload exceeds the file size (compressed values, e.g. a
+    /// deflated Zip), where no index/payload split exists.
+    pub fn index_bytes(&self) -> Option<u64> {
+        self.payload_bytes
+            .and_then(|payload| self.file_size.checked_sub(payload))
+    }
+
+    /// 95% confidence interval for the mean latency in nanoseconds
+    /// (normal approximation using the sample standard deviation).
+    pub fn mean_ci_ns(&self) -> (f64, f64) {
+        let n = self.latencies_ns.len();
+        if n == 0 {
+            return (0.0, 0.0);
+        }
+        let (mean, variance) = self.latencies_ns.mean_and_variance();
+        if n < 2 {
+            return (mean, mean);
+        }
+        let half_width = Z_95 * (variance / n as f64).sqrt();
+        ((mean - half_width).max(0.0), mean + half_width)
+    }
+
+    /// 95% confidence interval for ops/sec, derived from `mean_ci_ns`.
+    /// Returns `(low, high)`; `high` is infinite if the latency interval reaches zero.
+    pub fn throughput_ci(&self) -> (f64, f64) {
+        if self.latencies_ns.is_empty() {
+            return (0.0, 0.0);
+        }
+        let (low_ns, high_ns) = self.mean_ci_ns();
+        let to_ops = |ns: f64| {
+            if ns > 0.0 {
+                1_000_000_000.0 / ns
+            } else {
+                f64::INFINITY
+            }
+        };
+        (to_ops(high_ns), to_ops(low_ns))
+    }
 }
 
 /// Configuration for benchmark runs
@@ -120,6 +644,96 @@ pub struct BenchmarkConfig {
     pub warmup_iterations: usize,
     /// Random seed for reproducibility
     pub seed: u64,
+    /// Key order for the measured lookups (warmup is always random)
+    pub access_order: AccessOrder,
+    /// Distribution for random measured lookups (ignored by the sorted/reverse orders)
+    pub access_pattern: AccessPattern,
+    /// Keys in the working set for `AccessPattern::SlidingWindow`
+    pub window_size: usize,
+    /// Cancellation flag (e.g. set from a SIGINT handler); loops stop early once set
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Sum all value sizes after the measured lookups to split file size into
+    /// index overhead vs payload (reads every value, so it runs last)
+    pub report_index_size: bool,
+    /// Precomputed access pattern shared by every backend; when unset each run
+    /// draws keys from its own freshly-seeded RNG
+    pub shared_plan: Option<Arc<AccessPlan>>,
+    /// Time the index and IO phases of each measured lookup separately
+    /// (only backends that implement `get_with_phases` report them)
+    pub phase_timing: bool,
+    /// Time this many consecutive lookups together and record their mean as one
+    /// latency sample, for lookups faster than the timer resolution. Phase
+    /// timing only applies when this is 1.
+    pub batch_size: usize,
+    /// Record the file pages the measured lookups read, to report the fraction
+    /// of the file they touched and their read amplification. Only Hash DAT
+    /// tracks its reads; every other backend reports nothing.
+    pub track_access: bool,
+    /// Issue `MADV_WILLNEED` over the store's mapping before warmup (no-op for
+    /// backends that don't mmap their file)
+    pub willneed: bool,
+    /// Per-backend replacements for `num_lookups`, keyed by `BlobStore::backend_name`
+    pub backend_lookups: HashMap<String, usize>,
+    /// Reader threads for `run_concurrent_benchmark` (1 = sequential lookups only)
+    pub threads: usize,
+    /// Fraction (0.0-1.0) of measured lookups made with keys that don't exist,
+    /// to time negative lookups. Whether each lookup misses is drawn from a
+    /// separate RNG, so the key RNG's draws are left untouched.
+    pub miss_ratio: f64,
+    /// Percentiles (0-100) shown as columns by `print_results`
+    pub percentiles: Vec<f64>,
+    /// Record measured latencies into an HDR histogram instead of keeping every
+    /// sample, so memory stays flat however many lookups run
+    pub histogram: bool,
+    /// Evict the store's file from the page cache (`BlobStore::drop_caches`)
+    /// before every measured sample, outside the timed region, to measure
+    /// cold reads. Backends that can't evict (and every backend off Linux) are
+    /// left out of the results.
+    pub cold: bool,
+    /// Compute single percentiles of each result by quickselect (O(n)) rather
+    /// than sorting every sample; same values, less work for huge sample counts
+    pub fast_percentile: bool,
+}
+
+impl BenchmarkConfig {
+    /// Whether the run has been asked to stop
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+
+    /// Empty latency store for a measured run: a histogram if requested,
+    /// otherwise a vector with room for `capacity` samples
+    fn new_samples(&self, capacity: usize) -> LatencySamples {
+        if self.histogram {
+            LatencySamples::histogram()
+        } else {
+            LatencySamples::Raw(Vec::with_capacity(capacity))
+        }
+    }
+
+    /// Measured lookups per size for `backend_name`, after any override
+    pub fn lookups_for(&self, backend_name: &str) -> usize {
+        self.backend_lookups
+            .get(backend_name)
+            .copied()
+            .unwrap_or(self.num_lookups)
+    }
+
+    /// Key index for random measured lookup `i` out of `len` keys. The sliding
+    /// window starts at the first key and reaches the last one on the final lookup.
+    fn measured_index<R: Rng>(&self, rng: &mut R, i: usize, len: usize) -> usize {
+        match self.access_pattern {
+            AccessPattern::Uniform => rng.gen_range(0..len),
+            AccessPattern::SlidingWindow => {
+                let window = self.window_size.clamp(1, len);
+                let last = self.num_lookups.saturating_sub(1).max(1);
+                let start = (len - window) * i.min(last) / last;
+                start + rng.gen_range(0..window)
+            }
+        }
+    }
 }
 
 impl Default for BenchmarkConfig {
@@ -128,12 +742,72 @@ impl Default for BenchmarkConfig {
             num_lookups: 10_000,
             warmup_iterations: 1000,
             seed: 42,
+            access_order: AccessOrder::Random,
+            access_pattern: AccessPattern::Uniform,
+            window_size: 100,
+            cancel: None,
+            report_index_size: false,
+            shared_plan: None,
+            phase_timing: false,
+            batch_size: 1,
+            track_access: false,
+            willneed: false,
+            backend_lookups: HashMap::new(),
+            threads: 1,
+            miss_ratio: 0.0,
+            percentiles: vec![50.0, 95.0, 99.0],
+            histogram: false,
+            cold: false,
+            fast_percentile: false,
+        }
+    }
+}
+
+/// Key indices for the warmup and random measured lookups, generated once from
+/// the seed so every backend is driven through byte-identical access patterns
+/// regardless of the order backends run in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessPlan {
+    /// Indices into the full key list, one per warmup iteration
+    pub warmup: Vec<usize>,
+    /// Indices into each size category's key list, one per measured lookup
+    pub measured: HashMap<BlobSize, Vec<usize>>,
+}
+
+impl AccessPlan {
+    /// Draw the warmup and measured indices for `config` from `config.seed`
+    pub fn generate(
+        config: &BenchmarkConfig,
+        keys: &[Vec<u8>],
+        keys_by_size: &HashMap<BlobSize, Vec<Vec<u8>>>,
+    ) -> Self {
+        let mut rng = SeededRng::seed_from_u64(config.seed);
+
+        let warmup = if keys.is_empty() {
+            Vec::new()
+        } else {
+            (0..config.warmup_iterations)
+                .map(|_| rng.gen_range(0..keys.len()))
+                .collect()
+        };
+
+        let mut measured = HashMap::new();
+        for size in BlobSize::all() {
+            if let Some(size_keys) = keys_by_size.get(size).filter(|k| !k.is_empty()) {
+                let indices = (0..config.num_lookups)
+                    .map(|i| config.measured_index(&mut rng, i, size_keys.len()))
+                    .collect();
+                measured.insert(*size, indices);
+            }
         }
+
+        Self { warmup, measured }
     }
 }
 
-/// Run a benchmark for a single backend
-pub fn run_benchmark<S: BlobStore>(
+/// Run a benchmark for a single backend. Takes any `BlobStore` or a
+/// `&dyn DynBlobStore`, e.g. one opened with `backends::open_any`.
+pub fn run_benchmark<S: crate::store::DynBlobStore + ?Sized>(
     store: &S,
     keys: &[Vec<u8>],
     keys_by_size: &std::collections::HashMap<BlobSize, Vec<Vec<u8>>>,
@@ -144,7 +818,7 @@ pub fn run_benchmark<S: BlobStore>(
 }
 
 /// Run a benchmark for a single backend with optional verbose logging
-pub fn run_benchmark_with_logging<S: BlobStore>(
+pub fn run_benchmark_with_logging<S: crate::store::DynBlobStore + ?Sized>(
     store: &S,
     keys: &[Vec<u8>],
     keys_by_size: &std::collections::HashMap<BlobSize, Vec<Vec<u8>>>,
@@ -152,10 +826,35 @@ pub fn run_benchmark_with_logging<S: BlobStore>(
     file_size: u64,
     verbose: bool,
 ) -> Result<Vec<BenchmarkResult>> {
-    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut rng = SeededRng::seed_from_u64(config.seed);
     let mut results = Vec::new();
 
-    let backend_name = S::backend_name();
+    let backend_name = store.backend_name();
+    // Run the whole benchmark as if the override were the global lookup count,
+    // so the sliding window and progress output scale with it too
+    let overridden;
+    let config = match config.backend_lookups.get(backend_name) {
+        Some(&num_lookups) => {
+            overridden = BenchmarkConfig {
+                num_lookups,
+                ..config.clone()
+            };
+            &overridden
+        }
+        None => config,
+    };
+
+    // A store that can't evict its data (In-Memory, or any store off Linux)
+    // would report warm reads as cold, so it is left out of a cold run
+    if config.cold && !store.drop_caches()? {
+        if verbose {
+            println!(
+                "  [{}] Skipped: --cold can't evict this store's data",
+                backend_name
+            );
+        }
+        return Ok(results);
+    }
 
     if verbose {
         println!("  [{}] Starting benchmark...", backend_name);
@@ -170,18 +869,7 @@ pub fn run_benchmark_with_logging<S: BlobStore>(
         let _ = io::stdout().flush();
     }
 
-    let warmup_start = Instant::now();
-    for i in 0..config.warmup_iterations {
-        if let Some(key) = keys.choose(&mut rng) {
-            let _ = store.get(key)?;
-        }
-        // Progress indicator every 25%
-        if verbose && config.warmup_iterations >= 100 && i % (config.warmup_iterations / 4) == 0 {
-            print!("{}%.. ", (i * 100) / config.warmup_iterations);
-            let _ = io::stdout().flush();
-        }
-    }
-    let warmup_duration = warmup_start.elapsed();
+    let (warmup_duration, _) = warm_up(store, keys, config, &mut rng, verbose)?;
 
     if verbose {
         println!("done ({:.2?})", warmup_duration);
@@ -201,6 +889,9 @@ pub fn run_benchmark_with_logging<S: BlobStore>(
 
     // Benchmark each size category separately
     for &size in BlobSize::all() {
+        if config.is_cancelled() {
+            break;
+        }
         if let Some(size_keys) = keys_by_size.get(&size) {
             if size_keys.is_empty() {
                 if verbose {
@@ -219,47 +910,159 @@ pub fn run_benchmark_with_logging<S: BlobStore>(
                 let _ = io::stdout().flush();
             }
 
-            let mut latencies = Vec::with_capacity(config.num_lookups);
+            let miss_keys: Vec<Vec<u8>> = if config.miss_ratio > 0.0 {
+                size_keys
+                    .iter()
+                    .take(MISS_KEY_POOL)
+                    .map(|key| [MISS_KEY_PREFIX, key].concat())
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            let mut miss_rng = SeededRng::seed_from_u64(config.seed ^ 0x6d69_7373);
+            let ordered_keys = config.access_order.ordered_keys(size_keys);
+            let planned = config
+                .shared_plan
+                .as_ref()
+                .and_then(|plan| plan.measured.get(&size))
+                .filter(|indices| !indices.is_empty());
+            let mut hit_key_for = |i: usize| match (&ordered_keys, planned) {
+                (Some(ordered), _) => ordered[i % ordered.len()],
+                (None, Some(indices)) => size_keys[indices[i % indices.len()]].as_slice(),
+                (None, None) => match config.access_pattern {
+                    AccessPattern::Uniform => size_keys.choose(&mut rng).unwrap().as_slice(),
+                    AccessPattern::SlidingWindow => {
+                        let idx = config.measured_index(&mut rng, i, size_keys.len());
+                        size_keys[idx].as_slice()
+                    }
+                },
+            };
+            let mut key_for = |i: usize| {
+                if !miss_keys.is_empty() && miss_rng.gen_bool(config.miss_ratio.min(1.0)) {
+                    miss_keys[miss_rng.gen_range(0..miss_keys.len())].as_slice()
+                } else {
+                    hit_key_for(i)
+                }
+            };
+            let batch_size = config.batch_size.max(1);
+            let mut batch = Vec::with_capacity(batch_size);
+            let mut latencies = config.new_samples(config.num_lookups / batch_size + 1);
+            let mut phases = PhaseLatencies::default();
+            if config.track_access {
+                store.start_access_tracking();
+            }
+            let mut returned_bytes = 0u64;
+            let mut misses = 0;
+            let mut miss_latencies = config.new_samples(0);
+            // One value buffer for the whole size category, so lookups don't
+            // measure a fresh allocation of the value size each time
+            let mut value_buf = Vec::new();
             let size_start = Instant::now();
 
-            for i in 0..config.num_lookups {
-                let key = size_keys.choose(&mut rng).unwrap();
+            let mut i = 0;
+            while i < config.num_lookups {
+                if config.is_cancelled() {
+                    break;
+                }
+                // Pick the batch's keys before starting the clock
+                let batch_len = batch_size.min(config.num_lookups - i);
+                batch.clear();
+                batch.extend((i..i + batch_len).map(&mut key_for));
+                if config.cold {
+                    store.drop_caches()?;
+                }
 
                 let start = Instant::now();
-                let _ = store.get(key)?;
+                let mut batch_misses = 0;
+                if config.phase_timing && batch_size == 1 {
+                    let (value, lookup_phases) = store.get_with_phases(batch[0])?;
+                    match value {
+                        Some(value) => returned_bytes += value.len() as u64,
+                        None => batch_misses += 1,
+                    }
+                    if let Some(lookup_phases) = lookup_phases {
+                        phases.index_ns.push(lookup_phases.index.as_nanos() as u64);
+                        phases.io_ns.push(lookup_phases.io.as_nanos() as u64);
+                    }
+                } else {
+                    for key in &batch {
+                        if store.get_into(key, &mut value_buf)? {
+                            returned_bytes += value_buf.len() as u64;
+                        } else {
+                            batch_misses += 1;
+                        }
+                    }
+                }
                 let elapsed = start.elapsed();
 
-                latencies.push(elapsed.as_nanos() as u64);
+                let latency = elapsed.as_nanos() as u64 / batch_len as u64;
+                latencies.record(latency);
+                misses += batch_misses;
+                if batch_misses == batch_len {
+                    miss_latencies.record(latency);
+                }
 
                 // Progress indicator every 25% for verbose mode
                 if verbose
                     && config.num_lookups >= 100
                     && i > 0
-                    && i % (config.num_lookups / 4) == 0
+                    && i % (config.num_lookups / 4) < batch_len
                 {
                     print!("{}%.. ", (i * 100) / config.num_lookups);
                     let _ = io::stdout().flush();
                 }
+                i += batch_len;
             }
 
             let size_duration = size_start.elapsed();
 
+            let interrupted = config.is_cancelled();
+            if interrupted && latencies.is_empty() {
+                break;
+            }
+
             let result = BenchmarkResult {
                 backend_name: backend_name.to_string(),
                 blob_size: size,
                 latencies_ns: latencies,
                 file_size,
                 memory_stats: memory_stats.clone(),
+                access_order: config.access_order,
+                access_pattern: config.access_pattern,
+                interrupted,
+                payload_bytes: None,
+                open_time: Duration::ZERO,
+                phase_ns: (!phases.index_ns.is_empty()).then_some(phases),
+                batch_size,
+                accessed_bytes: config
+                    .track_access
+                    .then(|| store.accessed_bytes())
+                    .flatten(),
+                read_amplification: config
+                    .track_access
+                    .then(|| store.bytes_read())
+                    .flatten()
+                    .filter(|_| returned_bytes > 0)
+                    .map(|read| read as f64 / returned_bytes as f64),
+                lookups: i,
+                misses,
+                miss_latencies_ns: miss_latencies,
+                wall_clock: size_duration,
+                fast_percentile: config.fast_percentile,
             };
 
             if verbose {
-                println!("done ({:.2?})", size_duration);
-                println!(
-                    "      -> P50: {:?}, P95: {:?}, P99: {:?}",
-                    result.p50(),
-                    result.p95(),
-                    result.p99()
-                );
+                if interrupted {
+                    println!(
+                        "interrupted after {} lookups ({:.2?})",
+                        result.latencies_ns.len(),
+                        size_duration
+                    );
+                } else {
+                    println!("done ({:.2?})", size_duration);
+                }
+                let p = result.percentiles(&[50.0, 95.0, 99.0]);
+                println!("      -> P50: {:?}, P95: {:?}, P99: {:?}", p[0], p[1], p[2]);
                 println!(
                     "      -> Min: {:?}, Max: {:?}, Mean: {:?}",
                     result.min(),
@@ -267,15 +1070,30 @@ pub fn run_benchmark_with_logging<S: BlobStore>(
                     result.mean()
                 );
                 println!(
-                    "      -> Throughput: {:.0} ops/sec",
-                    result.ops_per_second()
+                    "      -> Throughput: {:.0} ops/sec ({:.0} ops/sec wall clock)",
+                    result.ops_per_second(),
+                    result.ops_per_second_wall()
                 );
+                if result.misses > 0 {
+                    println!(
+                        "      -> Hits: {}, Misses: {}",
+                        result.hits(),
+                        result.misses
+                    );
+                }
             }
 
             results.push(result);
         }
     }
 
+    if config.report_index_size && !config.is_cancelled() {
+        let payload = store.total_value_bytes()?;
+        for result in &mut results {
+            result.payload_bytes = Some(payload);
+        }
+    }
+
     if verbose {
         println!();
         println!("  [{}] Benchmark complete!", backend_name);
@@ -284,11 +1102,97 @@ pub fn run_benchmark_with_logging<S: BlobStore>(
     Ok(results)
 }
 
-/// Print benchmark results to console
-pub fn print_results(results: &[BenchmarkResult]) {
+/// One `BenchmarkResult` as written by `export_json`: latency statistics in
+/// nanoseconds in place of the raw samples
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedResult {
+    pub backend_name: String,
+    pub blob_size: String,
+    pub file_size: u64,
+    pub memory_stats: MemoryStats,
+    pub samples: usize,
+    pub p50_ns: u64,
+    pub p90_ns: u64,
+    pub p95_ns: u64,
+    pub p99_ns: u64,
+    pub min_ns: u64,
+    pub max_ns: u64,
+    pub mean_ns: u64,
+    pub ops_per_second: f64,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl ExportedResult {
+    pub fn new(result: &BenchmarkResult) -> Self {
+        let ps = result.percentiles(&[50.0, 90.0, 95.0, 99.0]);
+        Self {
+            backend_name: result.backend_name.clone(),
+            blob_size: result.blob_size.name().to_string(),
+            file_size: result.file_size,
+            memory_stats: result.memory_stats.clone(),
+            samples: result.latencies_ns.len(),
+            p50_ns: ps[0].as_nanos() as u64,
+            p90_ns: ps[1].as_nanos() as u64,
+            p95_ns: ps[2].as_nanos() as u64,
+            p99_ns: ps[3].as_nanos() as u64,
+            min_ns: result.min().as_nanos() as u64,
+            max_ns: result.max().as_nanos() as u64,
+            mean_ns: result.mean().as_nanos() as u64,
+            ops_per_second: result.ops_per_second(),
+            hits: result.hits(),
+            misses: result.misses,
+        }
+    }
+}
+
+/// Write one JSON object per result (see `ExportedResult`) to `path` as an array
+pub fn export_json(results: &[BenchmarkResult], path: &Path) -> Result<()> {
+    let exported: Vec<ExportedResult> = results.iter().map(ExportedResult::new).collect();
+    let json = serde_json::to_string_pretty(&exported)?;
+    std::fs::write(path, json).context("Failed to write benchmark results JSON")
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break
+fn csv_field(field: &str) -> std::borrow::Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\"")).into()
+    } else {
+        field.into()
+    }
+}
+
+/// Write every latency sample to `path` as CSV rows of
+/// `backend_name,blob_size,latency_ns`, after a header row. Rows are streamed,
+/// since runs with many lookups produce large files.
+pub fn export_latencies_csv(results: &[BenchmarkResult], path: &Path) -> Result<()> {
+    let file = File::create(path).context("Failed to create latency CSV")?;
+    let mut writer = BufWriter::new(file);
+    writeln!(writer, "backend_name,blob_size,latency_ns")?;
+    for result in results {
+        let backend = csv_field(&result.backend_name);
+        let size = result.blob_size.name();
+        let Some(latencies) = result.latencies_ns.raw() else {
+            bail!(
+                "{} {} latencies were recorded into a histogram; rerun without --histogram to export them",
+                result.backend_name,
+                size
+            );
+        };
+        for latency in latencies {
+            writeln!(writer, "{},{},{}", backend, size, latency)?;
+        }
+    }
+    writer.flush().context("Failed to write latency CSV")
+}
+
+/// Print benchmark results to console, one latency column per entry of `percentiles`
+pub fn print_results(results: &[BenchmarkResult], percentiles: &[f64]) {
     println!("\n{:=<80}", "");
     println!("Benchmark Results");
-    println!("{:=<80}\n", "");
+    println!("{:=<80}", "");
+    println!("Build: {}", Provenance::current());
+    println!("Allocator: {}\n", allocator_name());
 
     // Group by backend
     let mut by_backend: std::collections::HashMap<&str, Vec<&BenchmarkResult>> =
@@ -313,45 +1217,574 @@ pub fn print_results(results: &[BenchmarkResult]) {
                 "  Memory (physical): {:.2} MB",
                 first.memory_stats.physical_mem as f64 / 1_048_576.0
             );
+            println!("  Open time: {:.2?}", first.open_time);
         }
 
+        print!("\n  {:>8}", "Size");
+        for &p in percentiles {
+            print!(" {:>12}", format!("P{}", p));
+        }
+        println!(" {:>12}", "Ops/sec");
         println!(
-            "\n  {:>8} {:>12} {:>12} {:>12} {:>12}",
-            "Size", "P50", "P95", "P99", "Ops/sec"
+            "  {:-<width$}",
+            "",
+            width = 8 + 13 * (percentiles.len() + 1)
         );
-        println!("  {:-<60}", "");
 
         for result in backend_results.iter() {
+            print!("  {:>8}", result.blob_size.name());
+            for p in result.percentiles(percentiles) {
+                print!(" {:>12.2?}", p);
+            }
             println!(
-                "  {:>8} {:>12.2?} {:>12.2?} {:>12.2?} {:>12.0}",
-                result.blob_size.name(),
-                result.p50(),
-                result.p95(),
-                result.p99(),
-                result.ops_per_second()
+                " {:>12.0}{}",
+                result.ops_per_second(),
+                if result.interrupted { " (partial)" } else { "" }
             );
         }
         println!();
     }
 }
 
-/// Aggregate results for comparison
-#[derive(Debug)]
-pub struct AggregateResults {
-    pub results: Vec<BenchmarkResult>,
-}
-
-impl AggregateResults {
-    pub fn new(results: Vec<BenchmarkResult>) -> Self {
-        Self { results }
+/// Print file size split into index overhead vs value payload, per backend
+pub fn print_size_breakdown(results: &[BenchmarkResult]) {
+    let mut seen = std::collections::HashSet::new();
+    let measured: Vec<&BenchmarkResult> = results
+        .iter()
+        .filter(|r| r.payload_bytes.is_some() && seen.insert(r.backend_name.as_str()))
+        .collect();
+    if measured.is_empty() {
+        return;
     }
 
-    /// Get results grouped by backend name
-    pub fn by_backend(&self) -> std::collections::HashMap<&str, Vec<&BenchmarkResult>> {
-        let mut map = std::collections::HashMap::new();
-        for result in &self.results {
-            map.entry(result.backend_name.as_str())
-                .or_insert_with(Vec::new)
+    println!("\nIndex Overhead vs Payload");
+    println!("{:-<80}", "");
+    println!(
+        "  {:<28} {:>12} {:>12} {:>12} {:>9}",
+        "Backend", "File (MB)", "Payload (MB)", "Index (MB)", "Index %"
+    );
+    for result in measured {
+        let payload = result.payload_bytes.unwrap_or(0);
+        let Some(index) = result.index_bytes() else {
+            println!(
+                "  {:<28} {:>12.2} {:>12.2}   payload exceeds file size (compressed values); no split",
+                result.backend_name,
+                result.file_size as f64 / 1_048_576.0,
+                payload as f64 / 1_048_576.0,
+            );
+            continue;
+        };
+        let index_pct = if result.file_size > 0 {
+            index as f64 * 100.0 / result.file_size as f64
+        } else {
+            0.0
+        };
+        println!(
+            "  {:<28} {:>12.2} {:>12.2} {:>12.2} {:>8.2}%",
+            result.backend_name,
+            result.file_size as f64 / 1_048_576.0,
+            payload as f64 / 1_048_576.0,
+            index as f64 / 1_048_576.0,
+            index_pct
+        );
+    }
+}
+
+/// Print the index vs IO split for results measured with phase timing
+pub fn print_phase_breakdown(results: &[BenchmarkResult]) {
+    let measured: Vec<(&BenchmarkResult, &PhaseLatencies)> = results
+        .iter()
+        .filter_map(|r| r.phase_ns.as_ref().map(|phases| (r, phases)))
+        .collect();
+    if measured.is_empty() {
+        return;
+    }
+
+    println!("\nLookup Phases (mean)");
+    println!("{:-<80}", "");
+    println!(
+        "  {:<28} {:>8} {:>12} {:>12} {:>12}",
+        "Backend", "Size", "Index", "IO", "Total"
+    );
+    for (result, phases) in measured {
+        println!(
+            "  {:<28} {:>8} {:>12.2?} {:>12.2?} {:>12.2?}",
+            result.backend_name,
+            result.blob_size.name(),
+            phases.mean_index(),
+            phases.mean_io(),
+            result.mean()
+        );
+    }
+}
+
+/// Print the share of each file the measured lookups touched (the hot set) and
+/// how many bytes were read per byte returned
+pub fn print_access_breakdown(results: &[BenchmarkResult]) {
+    let measured: Vec<(&BenchmarkResult, u64, f64)> = results
+        .iter()
+        .filter_map(|r| Some((r, r.accessed_bytes?, r.accessed_fraction()?)))
+        .collect();
+    if measured.is_empty() {
+        return;
+    }
+
+    println!("\nFile Bytes Accessed (Hash DAT only)");
+    println!("{:-<80}", "");
+    println!(
+        "  {:<28} {:>8} {:>14} {:>12} {:>10}",
+        "Backend", "Size", "Accessed (MB)", "Of file", "Read amp"
+    );
+    for (result, bytes, fraction) in measured {
+        let amplification = result
+            .read_amplification
+            .map(|amp| format!("{:.3}x", amp))
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "  {:<28} {:>8} {:>14.2} {:>11.2}% {:>10}",
+            result.backend_name,
+            result.blob_size.name(),
+            bytes as f64 / 1_048_576.0,
+            fraction * 100.0,
+            amplification
+        );
+    }
+}
+
+/// Latency of a key's first lookup and of the lookup immediately after it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColdWarmSample {
+    pub key: Vec<u8>,
+    pub cold_ns: u64,
+    pub warm_ns: u64,
+}
+
+impl ColdWarmSample {
+    /// How many times slower the cold lookup was than the warm one
+    pub fn ratio(&self) -> f64 {
+        self.cold_ns as f64 / self.warm_ns.max(1) as f64
+    }
+}
+
+/// Per-key cold/warm samples for one backend
+#[derive(Debug, Clone)]
+pub struct ColdWarmResult {
+    pub backend_name: String,
+    pub samples: Vec<ColdWarmSample>,
+}
+
+impl ColdWarmResult {
+    /// Percentile (0-100) of the per-key cold/warm ratios
+    pub fn ratio_percentile(&self, p: f64) -> f64 {
+        let mut ratios: Vec<f64> = self.samples.iter().map(|s| s.ratio()).collect();
+        if ratios.is_empty() {
+            return 0.0;
+        }
+        ratios.sort_unstable_by(f64::total_cmp);
+        ratios[percentile_index(p, ratios.len())]
+    }
+
+    fn median_ns(&self, latency: impl Fn(&ColdWarmSample) -> u64) -> Duration {
+        let mut samples: Vec<u64> = self.samples.iter().map(latency).collect();
+        if samples.is_empty() {
+            return Duration::ZERO;
+        }
+        samples.sort_unstable();
+        Duration::from_nanos(samples[percentile_index(50.0, samples.len())])
+    }
+
+    pub fn cold_p50(&self) -> Duration {
+        self.median_ns(|s| s.cold_ns)
+    }
+
+    pub fn warm_p50(&self) -> Duration {
+        self.median_ns(|s| s.warm_ns)
+    }
+}
+
+/// Keys spot-checked by `check_keys_present` before a backend is benchmarked
+pub const PREFLIGHT_SAMPLE_SIZE: usize = 100;
+
+/// Fraction of the spot-checked keys a store must contain to be benchmarked
+pub const PREFLIGHT_MIN_HIT_RATE: f64 = 0.9;
+
+/// Spot-check that `store` contains a random sample of `keys`, so a keys.json
+/// left over from a different build fails fast instead of timing lookups of
+/// keys that don't exist
+pub fn check_keys_present<S: crate::store::DynBlobStore + ?Sized>(
+    store: &S,
+    keys: &[Vec<u8>],
+    sample_size: usize,
+    seed: u64,
+) -> Result<()> {
+    let mut rng = SeededRng::seed_from_u64(seed);
+    let sample: Vec<&Vec<u8>> = keys.choose_multiple(&mut rng, sample_size).collect();
+    if sample.is_empty() {
+        return Ok(());
+    }
+    let mut found = 0;
+    for key in &sample {
+        if store.contains_key(key)? {
+            found += 1;
+        }
+    }
+    let hit_rate = found as f64 / sample.len() as f64;
+    if hit_rate < PREFLIGHT_MIN_HIT_RATE {
+        anyhow::bail!(
+            "keys.json appears stale for backend {} (only {:.0}% of sampled keys found); rebuild",
+            store.backend_name(),
+            hit_rate * 100.0
+        );
+    }
+    Ok(())
+}
+
+/// Issue `MADV_WILLNEED` if configured, then run the warmup lookups. Returns
+/// how long the warmup took and how many lookups it made.
+fn warm_up<S: crate::store::DynBlobStore + ?Sized, R: Rng>(
+    store: &S,
+    keys: &[Vec<u8>],
+    config: &BenchmarkConfig,
+    rng: &mut R,
+    verbose: bool,
+) -> Result<(Duration, usize)> {
+    if config.willneed {
+        let advised = store.advise_willneed()?;
+        if verbose {
+            let outcome = if advised {
+                "issued"
+            } else {
+                "skipped (not mmap-backed)"
+            };
+            println!("    MADV_WILLNEED: {}", outcome);
+        }
+    }
+
+    let warmup_start = Instant::now();
+    let mut iterations = 0;
+    let mut value_buf = Vec::new();
+    for i in 0..config.warmup_iterations {
+        if config.is_cancelled() {
+            break;
+        }
+        let key = match &config.shared_plan {
+            Some(plan) => plan.warmup.get(i).map(|&idx| keys[idx].as_slice()),
+            None => keys.choose(rng).map(|k| k.as_slice()),
+        };
+        if let Some(key) = key {
+            store.get_into(key, &mut value_buf)?;
+        }
+        iterations += 1;
+        // Progress indicator every 25%
+        if verbose && config.warmup_iterations >= 100 && i % (config.warmup_iterations / 4) == 0 {
+            print!("{}%.. ", (i * 100) / config.warmup_iterations);
+            let _ = io::stdout().flush();
+        }
+    }
+    Ok((warmup_start.elapsed(), iterations))
+}
+
+/// Cost of getting one backend ready to serve: open plus warmup, without any
+/// measured lookups
+#[derive(Debug, Clone)]
+pub struct WarmupResult {
+    pub backend_name: String,
+    /// Time taken by `BlobStore::open` (zero unless the caller measured it)
+    pub open_time: Duration,
+    pub warmup_time: Duration,
+    /// Warmup lookups performed (fewer than configured if interrupted)
+    pub iterations: usize,
+    /// Memory usage after the warmup
+    pub memory_stats: MemoryStats,
+}
+
+impl WarmupResult {
+    /// Open plus warmup: how long until the store is hot
+    pub fn ready_time(&self) -> Duration {
+        self.open_time + self.warmup_time
+    }
+}
+
+/// Run only the warmup phase of `run_benchmark_with_logging` (including
+/// `MADV_WILLNEED` if configured) against an already-open store
+pub fn run_warmup<S: crate::store::DynBlobStore + ?Sized>(
+    store: &S,
+    keys: &[Vec<u8>],
+    config: &BenchmarkConfig,
+) -> Result<WarmupResult> {
+    let mut rng = SeededRng::seed_from_u64(config.seed);
+    let (warmup_time, iterations) = warm_up(store, keys, config, &mut rng, false)?;
+    Ok(WarmupResult {
+        backend_name: store.backend_name().to_string(),
+        open_time: Duration::ZERO,
+        warmup_time,
+        iterations,
+        memory_stats: MemoryStats::capture(),
+    })
+}
+
+/// Print one row per backend with its open, warmup and total ready time
+pub fn print_warmup_results(results: &[WarmupResult]) {
+    println!("\nOpen + Warmup (no measured lookups):");
+    println!(
+        "  {:<28} {:>10} {:>12} {:>12} {:>12} {:>12}",
+        "Backend", "Lookups", "Open", "Warmup", "Ready", "Memory (MB)"
+    );
+    for result in results {
+        let open = format!("{:.2?}", result.open_time);
+        let warmup = format!("{:.2?}", result.warmup_time);
+        let ready = format!("{:.2?}", result.ready_time());
+        println!(
+            "  {:<28} {:>10} {:>12} {:>12} {:>12} {:>12.2}",
+            result.backend_name,
+            result.iterations,
+            open,
+            warmup,
+            ready,
+            result.memory_stats.physical_mem as f64 / 1_048_576.0
+        );
+    }
+}
+
+/// Look up `sample_size` distinct random keys twice in a row each, timing the
+/// first (cold) and second (warm) `get` separately. Run against a freshly
+/// opened store so no key has been read yet; the OS page cache is not dropped.
+pub fn run_cold_warm<S: crate::store::DynBlobStore + ?Sized>(
+    store: &S,
+    keys: &[Vec<u8>],
+    sample_size: usize,
+    seed: u64,
+) -> Result<ColdWarmResult> {
+    let mut rng = SeededRng::seed_from_u64(seed);
+    let mut samples = Vec::with_capacity(sample_size.min(keys.len()));
+    for key in keys.choose_multiple(&mut rng, sample_size) {
+        let start = Instant::now();
+        let _ = store.get(key)?;
+        let cold_ns = start.elapsed().as_nanos() as u64;
+
+        let start = Instant::now();
+        let _ = store.get(key)?;
+        let warm_ns = start.elapsed().as_nanos() as u64;
+
+        samples.push(ColdWarmSample {
+            key: key.clone(),
+            cold_ns,
+            warm_ns,
+        });
+    }
+    Ok(ColdWarmResult {
+        backend_name: store.backend_name().to_string(),
+        samples,
+    })
+}
+
+/// Print the cold/warm ratio distribution for each backend
+pub fn print_cold_warm(results: &[ColdWarmResult]) {
+    println!("\nCold vs Warm Lookups (per key, first get / immediate repeat):");
+    println!(
+        "  {:<28} {:>8} {:>12} {:>12} {:>9} {:>9} {:>9}",
+        "Backend", "Keys", "Cold P50", "Warm P50", "Ratio P50", "P90", "P99"
+    );
+    for result in results {
+        let cold = format!("{:.2?}", result.cold_p50());
+        let warm = format!("{:.2?}", result.warm_p50());
+        println!(
+            "  {:<28} {:>8} {:>12} {:>12} {:>8.2}x {:>8.2}x {:>8.2}x",
+            result.backend_name,
+            result.samples.len(),
+            cold,
+            warm,
+            result.ratio_percentile(50.0),
+            result.ratio_percentile(90.0),
+            result.ratio_percentile(99.0)
+        );
+    }
+}
+
+/// Lookups spread over several threads sharing one store
+#[derive(Debug, Clone)]
+pub struct ConcurrentResult {
+    pub backend_name: String,
+    pub threads: usize,
+    /// Every thread's per-lookup latencies, merged: raw samples, or one
+    /// histogram if the run asked for `BenchmarkConfig::histogram`
+    pub latencies_ns: LatencySamples,
+    /// Wall-clock time from starting the threads to the last one finishing
+    pub elapsed: Duration,
+}
+
+impl ConcurrentResult {
+    /// Lookups completed per second across all threads
+    pub fn ops_per_second(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.latencies_ns.len() as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    pub fn percentile(&self, p: f64) -> Duration {
+        self.latencies_ns.percentiles(&[p])[0]
+    }
+}
+
+/// Perform `config.num_lookups` uniformly random lookups over `keys`, split
+/// across `config.threads` threads that share `store`. Each thread draws keys
+/// from its own RNG (seeded from `config.seed` and the thread index) and times
+/// its lookups; the latencies are merged afterwards. With `config.histogram`
+/// each thread records into its own histogram, so memory stays flat however
+/// long the run.
+///
+/// The store must be `Sync`. Backends that read through one `RefCell`-held
+/// handle (Zip, MPH DAT) are not, and are left out of concurrent runs; SQLite
+/// is shared through its pooled store, which opens a connection per CPU.
+pub fn run_concurrent_benchmark<S: crate::store::DynBlobStore + Sync + ?Sized>(
+    store: &S,
+    keys: &[Vec<u8>],
+    config: &BenchmarkConfig,
+) -> Result<ConcurrentResult> {
+    let threads = config.threads.max(1);
+    let start = Instant::now();
+    let per_thread: Vec<Result<LatencySamples>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|t| {
+                // The first num_lookups % threads threads take one extra lookup
+                let lookups =
+                    config.num_lookups / threads + usize::from(t < config.num_lookups % threads);
+                scope.spawn(move || -> Result<LatencySamples> {
+                    let mut rng = SeededRng::seed_from_u64(config.seed.wrapping_add(t as u64));
+                    let mut value_buf = Vec::new();
+                    let mut latencies = config.new_samples(lookups);
+                    for _ in 0..lookups {
+                        if config.is_cancelled() {
+                            break;
+                        }
+                        let Some(key) = keys.choose(&mut rng) else {
+                            break;
+                        };
+                        let lookup_start = Instant::now();
+                        store.get_into(key, &mut value_buf)?;
+                        latencies.record(lookup_start.elapsed().as_nanos() as u64);
+                    }
+                    Ok(latencies)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("benchmark thread panicked"))
+            .collect()
+    });
+    let elapsed = start.elapsed();
+
+    let mut latencies_ns = config.new_samples(config.num_lookups);
+    for thread_latencies in per_thread {
+        latencies_ns.merge(thread_latencies?);
+    }
+    Ok(ConcurrentResult {
+        backend_name: store.backend_name().to_string(),
+        threads,
+        latencies_ns,
+        elapsed,
+    })
+}
+
+/// Print aggregate throughput and latency percentiles of each concurrent run
+pub fn print_concurrent_results(results: &[ConcurrentResult]) {
+    println!("\nConcurrent Lookups (one shared store per backend):");
+    println!(
+        "  {:<28} {:>8} {:>10} {:>14} {:>12} {:>12}",
+        "Backend", "Threads", "Lookups", "Ops/sec", "P50", "P99"
+    );
+    for result in results {
+        let p50 = format!("{:.2?}", result.percentile(50.0));
+        let p99 = format!("{:.2?}", result.percentile(99.0));
+        println!(
+            "  {:<28} {:>8} {:>10} {:>14.0} {:>12} {:>12}",
+            result.backend_name,
+            result.threads,
+            result.latencies_ns.len(),
+            result.ops_per_second(),
+            p50,
+            p99
+        );
+    }
+}
+
+/// Hash DAT lookup throughput with one synchronous `get` at a time versus
+/// io_uring batches of `batch_size` overlapped reads, over the same keys
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UringComparison {
+    pub lookups: usize,
+    pub batch_size: usize,
+    pub sync_ops_per_sec: f64,
+    pub uring_ops_per_sec: f64,
+}
+
+/// Look up `lookups` random keys in the Hash DAT file at `path` twice: with
+/// `HashDatStore::get` and with `HashDatUringStore::get_batch`. The file is
+/// evicted from the page cache before each pass so neither pass reads pages the
+/// other one warmed.
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub fn compare_uring_throughput(
+    path: &std::path::Path,
+    keys: &[Vec<u8>],
+    lookups: usize,
+    batch_size: usize,
+    seed: u64,
+) -> Result<UringComparison> {
+    use crate::backends::{HashDatStore, HashDatUringStore};
+
+    let mut rng = SeededRng::seed_from_u64(seed);
+    let sequence: Vec<&[u8]> = (0..lookups)
+        .filter_map(|_| keys.choose(&mut rng).map(|k| k.as_slice()))
+        .collect();
+    let ops_per_sec = |elapsed: Duration| sequence.len() as f64 / elapsed.as_secs_f64();
+
+    let sync = HashDatStore::open(path)?;
+    let uring = HashDatUringStore::open_with_queue_depth(path, batch_size as u32)?;
+
+    sync.drop_caches()?;
+    let start = Instant::now();
+    for key in &sequence {
+        let _ = sync.get(key)?;
+    }
+    let sync_ops_per_sec = ops_per_sec(start.elapsed());
+
+    sync.drop_caches()?;
+    let start = Instant::now();
+    for batch in sequence.chunks(batch_size) {
+        let _ = uring.get_batch(batch)?;
+    }
+    let uring_ops_per_sec = ops_per_sec(start.elapsed());
+
+    Ok(UringComparison {
+        lookups: sequence.len(),
+        batch_size,
+        sync_ops_per_sec,
+        uring_ops_per_sec,
+    })
+}
+
+/// Aggregate results for comparison
+#[derive(Debug)]
+pub struct AggregateResults {
+    pub results: Vec<BenchmarkResult>,
+}
+
+impl AggregateResults {
+    pub fn new(results: Vec<BenchmarkResult>) -> Self {
+        Self { results }
+    }
+
+    /// Get results grouped by backend name
+    pub fn by_backend(&self) -> std::collections::HashMap<&str, Vec<&BenchmarkResult>> {
+        let mut map = std::collections::HashMap::new();
+        for result in &self.results {
+            map.entry(result.backend_name.as_str())
+                .or_insert_with(Vec::new)
                 .push(result);
         }
         map
@@ -368,3 +1801,892 @@ impl AggregateResults {
         map
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::path::Path;
+
+    /// In-memory store that records every key passed to `get`
+    struct RecordingStore {
+        keys: Vec<Vec<u8>>,
+        requested: RefCell<Vec<Vec<u8>>>,
+    }
+
+    impl RecordingStore {
+        fn new(keys: Vec<Vec<u8>>) -> Self {
+            Self {
+                keys,
+                requested: RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    impl BlobStore for RecordingStore {
+        fn open(_path: &Path) -> Result<Self> {
+            anyhow::bail!("RecordingStore is in-memory only")
+        }
+
+        fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+            self.requested.borrow_mut().push(key.to_vec());
+            Ok(self.keys.iter().find(|k| k.as_slice() == key).cloned())
+        }
+
+        fn keys(&self) -> Result<Vec<Vec<u8>>> {
+            Ok(self.keys.clone())
+        }
+
+        fn len(&self) -> usize {
+            self.keys.len()
+        }
+
+        fn backend_name() -> &'static str {
+            "Recording"
+        }
+    }
+
+    /// Same recording behaviour under a different backend type
+    struct OtherRecordingStore(RecordingStore);
+
+    impl BlobStore for OtherRecordingStore {
+        fn open(_path: &Path) -> Result<Self> {
+            anyhow::bail!("OtherRecordingStore is in-memory only")
+        }
+
+        fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+            self.0.get(key)
+        }
+
+        fn keys(&self) -> Result<Vec<Vec<u8>>> {
+            self.0.keys()
+        }
+
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+
+        fn backend_name() -> &'static str {
+            "Other Recording"
+        }
+    }
+
+    fn synthetic_result(latencies_ns: Vec<u64>) -> BenchmarkResult {
+        let lookups = latencies_ns.len();
+        BenchmarkResult {
+            backend_name: "Synthetic".to_string(),
+            blob_size: BlobSize::Tiny,
+            latencies_ns: latencies_ns.into(),
+            lookups,
+            ..Default::default()
+        }
+    }
+
+    fn tiny_keys(keys: &[&[u8]]) -> (Vec<Vec<u8>>, HashMap<BlobSize, Vec<Vec<u8>>>) {
+        let keys: Vec<Vec<u8>> = keys.iter().map(|k| k.to_vec()).collect();
+        let mut by_size = HashMap::new();
+        by_size.insert(BlobSize::Tiny, keys.clone());
+        (keys, by_size)
+    }
+
+    /// Build a `B` store in a temp file with `n` keys `key000`, `key001`, ...
+    /// each holding `value_len` bytes of 5s
+    fn build_store<B: crate::store::BlobStoreBuilder>(
+        n: usize,
+        value_len: usize,
+    ) -> (tempfile::NamedTempFile, Vec<Vec<u8>>) {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let keys: Vec<Vec<u8>> = (0..n)
+            .map(|i| format!("key{:03}", i).into_bytes())
+            .collect();
+        let mut builder = B::create(temp_file.path()).unwrap();
+        for key in &keys {
+            builder.insert(key, &vec![5u8; value_len]).unwrap();
+        }
+        builder.finish().unwrap();
+        (temp_file, keys)
+    }
+
+    /// Deterministic samples cycling through 1000..=3000ns
+    fn spread_latencies(n: usize) -> Vec<u64> {
+        (0..n).map(|i| 1_000 + ((i * 37) % 2_001) as u64).collect()
+    }
+
+    #[test]
+    fn test_provenance_records_crate_version() {
+        let provenance = Provenance::current();
+        assert_eq!(provenance.version, env!("CARGO_PKG_VERSION"));
+        assert!(!provenance.git_sha.is_empty());
+    }
+
+    #[test]
+    fn test_throughput_ci_brackets_point_estimate() {
+        let result = synthetic_result(spread_latencies(1_000));
+        let (mean_low, mean_high) = result.mean_ci_ns();
+        let mean = result.mean().as_nanos() as f64;
+        assert!(mean_low <= mean && mean <= mean_high);
+
+        let (ops_low, ops_high) = result.throughput_ci();
+        let ops = result.ops_per_second();
+        assert!(ops_low <= ops && ops <= ops_high);
+    }
+
+    #[test]
+    fn test_wall_clock_throughput_counts_loop_overhead() {
+        // 1,000 lookups timed at 1μs each, but the loop took 2ms in total
+        let mut result = synthetic_result(vec![1_000; 1_000]);
+        assert_eq!(result.ops_per_second_wall(), 0.0);
+
+        result.wall_clock = Duration::from_millis(2);
+        assert!((result.ops_per_second() - 1_000_000.0).abs() < 1e-6);
+        assert!((result.ops_per_second_wall() - 500_000.0).abs() < 1e-6);
+
+        // An interrupted run divides only the lookups it performed
+        result.lookups = 500;
+        assert!((result.ops_per_second_wall() - 250_000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_throughput_ci_narrows_with_more_samples() {
+        let small = synthetic_result(spread_latencies(100));
+        let large = synthetic_result(spread_latencies(10_000));
+
+        let (small_low, small_high) = small.throughput_ci();
+        let (large_low, large_high) = large.throughput_ci();
+        assert!(large_high - large_low < small_high - small_low);
+    }
+
+    #[test]
+    fn test_p999_picks_expected_index() {
+        // Reversed so the sort has work to do; sorted index i holds i ns
+        let result = synthetic_result((0..10_000).rev().collect());
+        // 0.999 * 9_999 = 9_989.001, which rounds to index 9_989
+        assert_eq!(result.percentile(99.9), Duration::from_nanos(9_989));
+        assert_eq!(result.p999(), result.percentile(99.9));
+        assert_eq!(result.percentiles(&[99.9]), vec![result.p999()]);
+    }
+
+    #[test]
+    fn test_histogram_percentiles_track_raw_samples() {
+        let samples: Vec<u64> = (0..20_000u64)
+            .map(|i| 1_000 + (i * 7_919) % 500_000)
+            .collect();
+        let raw = synthetic_result(samples.clone());
+        let mut hist = synthetic_result(Vec::new());
+        hist.latencies_ns = LatencySamples::histogram();
+        for &ns in &samples {
+            hist.latencies_ns.record(ns);
+        }
+        assert_eq!(hist.latencies_ns.len(), samples.len());
+        assert!(hist.latencies_ns.raw().is_none());
+
+        let within = |actual: Duration, expected: Duration| {
+            let (a, e) = (actual.as_nanos() as f64, expected.as_nanos() as f64);
+            assert!((a - e).abs() <= e * 0.002, "{:?} vs {:?}", actual, expected);
+        };
+        within(hist.p50(), raw.p50());
+        within(hist.p99(), raw.p99());
+        within(hist.mean(), raw.mean());
+        within(hist.max(), raw.max());
+    }
+
+    #[test]
+    fn test_histogram_mode_records_every_lookup() {
+        let (keys, by_size) = tiny_keys(&[b"alpha", b"bravo", b"charlie"]);
+        let store = RecordingStore::new(keys.clone());
+        let config = BenchmarkConfig {
+            num_lookups: 40,
+            warmup_iterations: 0,
+            histogram: true,
+            ..Default::default()
+        };
+
+        let results = run_benchmark(&store, &keys, &by_size, &config, 0).unwrap();
+        assert!(matches!(results[0].latencies_ns, LatencySamples::Hist(_)));
+        assert_eq!(results[0].latencies_ns.len(), 40);
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let err = export_latencies_csv(&results, &dir.path().join("raw.csv")).unwrap_err();
+        assert!(err.to_string().contains("histogram"), "{}", err);
+    }
+
+    #[test]
+    fn test_percentiles_match_individual_methods() {
+        let mut latencies: Vec<u64> = (0..1001).map(|i| (i * 7919) % 10_007).collect();
+        latencies.reverse();
+        let result = synthetic_result(latencies);
+
+        let ps = result.percentiles(&[50.0, 90.0, 95.0, 99.0]);
+        assert_eq!(
+            ps,
+            vec![result.p50(), result.p90(), result.p95(), result.p99()]
+        );
+        let fast = BenchmarkResult {
+            fast_percentile: true,
+            ..result.clone()
+        };
+        for p in [0.0, 50.0, 99.0, 100.0] {
+            assert_eq!(result.percentile_select(p), result.percentile(p));
+            assert_eq!(fast.percentile(p), result.percentile(p));
+        }
+
+        let empty = synthetic_result(vec![]);
+        assert_eq!(empty.percentiles(&[50.0, 99.0]), vec![Duration::ZERO; 2]);
+        assert_eq!(empty.percentile_select(50.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_export_json_roundtrip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("results.json");
+
+        let mut fast = synthetic_result((1..=100).collect());
+        fast.backend_name = "Fast".to_string();
+        fast.file_size = 4096;
+        fast.memory_stats.physical_mem = 1 << 20;
+        let mut slow = synthetic_result(vec![5_000; 10]);
+        slow.backend_name = "Slow".to_string();
+        slow.blob_size = BlobSize::Large;
+        export_json(&[fast, slow], &path).unwrap();
+
+        let exported: Vec<ExportedResult> =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(exported.len(), 2);
+        assert_eq!(exported[0].backend_name, "Fast");
+        assert_eq!(exported[0].blob_size, BlobSize::Tiny.name());
+        assert_eq!(exported[0].file_size, 4096);
+        assert_eq!(exported[0].memory_stats.physical_mem, 1 << 20);
+        assert_eq!(exported[0].samples, 100);
+        assert_eq!((exported[0].min_ns, exported[0].max_ns), (1, 100));
+        assert_eq!(exported[0].p50_ns, 51);
+        assert_eq!(exported[1].blob_size, BlobSize::Large.name());
+        assert_eq!(exported[1].p99_ns, 5_000);
+        assert_eq!(exported[1].mean_ns, 5_000);
+        assert_eq!(exported[1].ops_per_second, 200_000.0);
+    }
+
+    #[test]
+    fn test_export_latencies_csv_writes_one_row_per_sample() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("latencies.csv");
+
+        let mut first = synthetic_result(spread_latencies(250));
+        first.backend_name = "SQLite (ROWID)".to_string();
+        let mut second = synthetic_result(vec![7; 40]);
+        second.backend_name = "Odd, \"name\"".to_string();
+        let empty = synthetic_result(vec![]);
+        let results = [first, second, empty];
+        export_latencies_csv(&results, &path).unwrap();
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("backend_name,blob_size,latency_ns"));
+        let rows: Vec<&str> = lines.collect();
+        let samples: usize = results.iter().map(|r| r.latencies_ns.len()).sum();
+        assert_eq!(rows.len(), samples);
+        assert_eq!(rows[0], "SQLite (ROWID),100B,1000");
+        assert_eq!(rows[250], "\"Odd, \"\"name\"\"\",100B,7");
+    }
+
+    #[test]
+    fn test_throughput_ci_degenerate_samples() {
+        assert_eq!(synthetic_result(vec![]).throughput_ci(), (0.0, 0.0));
+        assert_eq!(synthetic_result(vec![500]).mean_ci_ns(), (500.0, 500.0));
+    }
+
+    #[test]
+    fn test_sorted_access_order_is_nondecreasing() {
+        let (keys, by_size) = tiny_keys(&[b"delta", b"alpha", b"echo", b"charlie", b"bravo"]);
+        let store = RecordingStore::new(keys.clone());
+        let config = BenchmarkConfig {
+            num_lookups: keys.len(),
+            warmup_iterations: 0,
+            access_order: AccessOrder::Sorted,
+            ..Default::default()
+        };
+
+        let results = run_benchmark(&store, &keys, &by_size, &config, 0).unwrap();
+        assert_eq!(results[0].access_order, AccessOrder::Sorted);
+
+        let requested = store.requested.borrow();
+        assert_eq!(requested.len(), keys.len());
+        assert!(requested.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn test_ordered_access_wraps_around() {
+        let (keys, by_size) = tiny_keys(&[b"b", b"c", b"a"]);
+        let store = RecordingStore::new(keys.clone());
+        let config = BenchmarkConfig {
+            num_lookups: 7,
+            warmup_iterations: 0,
+            access_order: AccessOrder::Reverse,
+            ..Default::default()
+        };
+
+        run_benchmark(&store, &keys, &by_size, &config, 0).unwrap();
+
+        let expected: Vec<Vec<u8>> = [b"c", b"b", b"a", b"c", b"b", b"a", b"c"]
+            .iter()
+            .map(|k| k.to_vec())
+            .collect();
+        assert_eq!(*store.requested.borrow(), expected);
+    }
+
+    #[test]
+    fn test_memory_sampler_collects_timeline() {
+        let sampler = MemorySampler::start(Duration::from_millis(5));
+        std::thread::sleep(Duration::from_millis(60));
+        let samples = sampler.stop();
+
+        assert!(samples.len() >= 2, "only {} samples", samples.len());
+        assert!(samples.windows(2).all(|w| w[0].elapsed < w[1].elapsed));
+    }
+
+    #[test]
+    fn test_sliding_window_working_set_shifts() {
+        let names: Vec<Vec<u8>> = (0..100)
+            .map(|i| format!("key{:03}", i).into_bytes())
+            .collect();
+        let refs: Vec<&[u8]> = names.iter().map(|k| k.as_slice()).collect();
+        let (keys, by_size) = tiny_keys(&refs);
+        let store = RecordingStore::new(keys.clone());
+        let config = BenchmarkConfig {
+            num_lookups: 1000,
+            warmup_iterations: 0,
+            access_pattern: AccessPattern::SlidingWindow,
+            window_size: 10,
+            ..Default::default()
+        };
+
+        let results = run_benchmark(&store, &keys, &by_size, &config, 0).unwrap();
+        assert_eq!(results[0].access_pattern, AccessPattern::SlidingWindow);
+
+        let requested = store.requested.borrow();
+        let early: HashSet<&Vec<u8>> = requested[..100].iter().collect();
+        let late: HashSet<&Vec<u8>> = requested[900..].iter().collect();
+        assert!(early.is_disjoint(&late));
+        assert!(early.iter().all(|k| k.as_slice() < b"key020".as_slice()));
+        assert!(late.iter().all(|k| k.as_slice() >= b"key080".as_slice()));
+
+        // A shared plan draws from the same sliding window
+        let plan = AccessPlan::generate(&config, &keys, &by_size);
+        let measured = &plan.measured[&BlobSize::Tiny];
+        assert!(measured[..100].iter().all(|&idx| idx < 20));
+        assert!(measured[900..].iter().all(|&idx| idx >= 80));
+    }
+
+    /// Fails if the RNG changes; see `data_gen::SeededRng`
+    #[test]
+    fn test_seed_42_access_plan_is_pinned() {
+        let keys: Vec<Vec<u8>> = (0..1000)
+            .map(|i| format!("key{:04}", i).into_bytes())
+            .collect();
+        let mut by_size = HashMap::new();
+        by_size.insert(BlobSize::Tiny, keys.clone());
+        let config = BenchmarkConfig {
+            warmup_iterations: 8,
+            num_lookups: 0,
+            seed: 42,
+            ..Default::default()
+        };
+        let plan = AccessPlan::generate(&config, &keys, &by_size);
+        assert_eq!(plan.warmup, vec![681, 950, 427, 627, 288, 149, 308, 803]);
+    }
+
+    #[test]
+    fn test_benchmarks_stores_of_different_backends_through_dyn() {
+        use crate::backends::{
+            BTreeDatStore, BTreeDatStoreBuilder, HashDatStore, HashDatStoreBuilder, SqliteStore,
+            SqliteStoreBuilder,
+        };
+        use crate::store::{BlobStoreBuilder, DynBlobStore};
+
+        fn build<B: BlobStoreBuilder>(path: &Path, keys: &[Vec<u8>]) {
+            let mut builder = B::create(path).unwrap();
+            for key in keys {
+                builder.insert(key, &[7u8; 100]).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let (keys, by_size) = tiny_keys(&[b"alpha", b"bravo", b"charlie"]);
+        let (hash, btree, sqlite) = (
+            dir.path().join("hash.dat"),
+            dir.path().join("btree.dat"),
+            dir.path().join("index.sqlite"),
+        );
+        build::<HashDatStoreBuilder>(&hash, &keys);
+        build::<BTreeDatStoreBuilder>(&btree, &keys);
+        build::<SqliteStoreBuilder>(&sqlite, &keys);
+
+        let stores: Vec<Box<dyn DynBlobStore>> = vec![
+            Box::new(HashDatStore::open(&hash).unwrap()),
+            Box::new(BTreeDatStore::open(&btree).unwrap()),
+            Box::new(SqliteStore::open(&sqlite).unwrap()),
+        ];
+        let config = BenchmarkConfig {
+            num_lookups: 30,
+            warmup_iterations: 5,
+            ..Default::default()
+        };
+
+        let mut names = Vec::new();
+        for store in &stores {
+            assert_eq!(store.len(), keys.len());
+            let results = run_benchmark(store.as_ref(), &keys, &by_size, &config, 0).unwrap();
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].backend_name, store.backend_name());
+            assert_eq!(results[0].lookups, 30);
+            assert_eq!(results[0].misses, 0);
+            names.push(store.backend_name());
+        }
+        assert_eq!(
+            names,
+            [
+                "Custom Offset File Format",
+                "B-tree DAT",
+                "SQLite (WITHOUT ROWID)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_few_hot_keys_touch_small_fraction_of_file() {
+        use crate::backends::{HashDatStore, HashDatStoreBuilder};
+
+        let (temp_file, keys) = build_store::<HashDatStoreBuilder>(500, 1000);
+        let store = HashDatStore::open(temp_file.path()).unwrap();
+        let file_size = std::fs::metadata(temp_file.path()).unwrap().len();
+
+        // Only three keys are ever looked up
+        let hot: Vec<&[u8]> = keys[..3].iter().map(|k| k.as_slice()).collect();
+        let (hot_keys, by_size) = tiny_keys(&hot);
+        let config = BenchmarkConfig {
+            num_lookups: 200,
+            warmup_iterations: 0,
+            track_access: true,
+            ..Default::default()
+        };
+        let results = run_benchmark(&store, &hot_keys, &by_size, &config, file_size).unwrap();
+
+        // Each hot entry (header + key + value, 1022 bytes) spans at most two
+        // 4 KiB pages, and repeated lookups don't count them again
+        let accessed = results[0].accessed_bytes.unwrap();
+        assert_eq!(accessed % 4096, 0);
+        assert!(accessed >= 4096 && accessed <= 3 * 2 * 4096, "{}", accessed);
+        let fraction = results[0].accessed_fraction().unwrap();
+        assert!(fraction > 0.0 && fraction < 0.05, "{}", fraction);
+
+        // Untracked runs report nothing
+        let untracked = BenchmarkConfig {
+            track_access: false,
+            ..config
+        };
+        let results = run_benchmark(&store, &hot_keys, &by_size, &untracked, file_size).unwrap();
+        assert_eq!(results[0].accessed_bytes, None);
+    }
+
+    #[test]
+    fn test_cold_warm_samples_each_key_twice() {
+        let keys: Vec<Vec<u8>> = (0..10).map(|i| format!("k{}", i).into_bytes()).collect();
+        let store = RecordingStore::new(keys.clone());
+
+        let result = run_cold_warm(&store, &keys, 4, 7).unwrap();
+        assert_eq!(result.backend_name, "Recording");
+        assert_eq!(result.samples.len(), 4);
+
+        // Each sampled key is looked up cold, then warm, before the next key
+        let requested = store.requested.borrow();
+        assert_eq!(requested.len(), 8);
+        for (sample, pair) in result.samples.iter().zip(requested.chunks(2)) {
+            assert_eq!(pair, [sample.key.clone(), sample.key.clone()]);
+        }
+        let distinct: HashSet<&Vec<u8>> = result.samples.iter().map(|s| &s.key).collect();
+        assert_eq!(distinct.len(), 4);
+        assert!(result.samples.iter().any(|s| s.warm_ns > 0));
+
+        // Never samples more keys than exist
+        let all = run_cold_warm(&store, &keys, 50, 7).unwrap();
+        assert_eq!(all.samples.len(), keys.len());
+    }
+
+    #[test]
+    fn test_hash_read_amplification_near_one_for_large_values() {
+        use crate::backends::{HashDatStore, HashDatStoreBuilder};
+
+        let (temp_file, keys) = build_store::<HashDatStoreBuilder>(20, 100_000);
+        let store = HashDatStore::open(temp_file.path()).unwrap();
+        let refs: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+        let (keys, by_size) = tiny_keys(&refs);
+        let config = BenchmarkConfig {
+            num_lookups: 50,
+            warmup_iterations: 0,
+            track_access: true,
+            ..Default::default()
+        };
+        let results = run_benchmark(&store, &keys, &by_size, &config, 0).unwrap();
+
+        // Each get also reads the entry header and key alongside the value
+        let amplification = results[0].read_amplification.unwrap();
+        assert!(
+            amplification > 1.0 && amplification < 1.01,
+            "{}",
+            amplification
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_cold_runs_evict_between_lookups() {
+        use crate::backends::{HashDatMmapStore, HashDatStoreBuilder};
+
+        let (temp_file, keys) = build_store::<HashDatStoreBuilder>(20, 5_000);
+        let store = HashDatMmapStore::open(temp_file.path()).unwrap();
+        let refs: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+        let (keys, by_size) = tiny_keys(&refs);
+        let config = BenchmarkConfig {
+            num_lookups: 30,
+            warmup_iterations: 5,
+            cold: true,
+            ..Default::default()
+        };
+
+        let results = run_benchmark(&store, &keys, &by_size, &config, 0).unwrap();
+        assert_eq!(results[0].latencies_ns.len(), 30);
+        assert_eq!(results[0].misses, 0);
+        assert!(store.drop_caches().unwrap());
+        // Lookups still see every value after the eviction
+        assert_eq!(store.get(&keys[0]).unwrap(), Some(vec![5u8; 5_000]));
+    }
+
+    #[test]
+    fn test_cold_run_skips_store_that_cannot_evict() {
+        let (keys, by_size) = tiny_keys(&[b"a", b"b"]);
+        let store = RecordingStore::new(keys.clone());
+        let config = BenchmarkConfig {
+            num_lookups: 10,
+            warmup_iterations: 5,
+            cold: true,
+            ..Default::default()
+        };
+
+        let results = run_benchmark(&store, &keys, &by_size, &config, 0).unwrap();
+        assert!(results.is_empty());
+        assert!(store.requested.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_full_miss_ratio_looks_up_only_missing_keys() {
+        let (keys, by_size) = tiny_keys(&[b"alpha", b"bravo", b"charlie"]);
+        let store = RecordingStore::new(keys.clone());
+        let config = BenchmarkConfig {
+            num_lookups: 50,
+            warmup_iterations: 0,
+            miss_ratio: 1.0,
+            ..Default::default()
+        };
+
+        let results = run_benchmark(&store, &keys, &by_size, &config, 0).unwrap();
+        assert_eq!(results[0].misses, 50);
+        assert_eq!(results[0].hits(), 0);
+        assert_eq!(results[0].miss_latencies_ns.len(), 50);
+
+        // `get` records its key too, so check a copy of the requests
+        let requested = store.requested.borrow().clone();
+        assert_eq!(requested.len(), 50);
+        for key in &requested {
+            assert_eq!(store.get(key).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_concurrent_benchmark_shares_sqlite_store_across_threads() {
+        use crate::backends::{SqliteStoreBuilder, SqliteWithoutRowidPooledStore};
+
+        let (temp_file, keys) = build_store::<SqliteStoreBuilder>(100, 64);
+        let store = SqliteWithoutRowidPooledStore::open(temp_file.path()).unwrap();
+        let config = BenchmarkConfig {
+            num_lookups: 1_002,
+            threads: 4,
+            ..Default::default()
+        };
+
+        let result = run_concurrent_benchmark(&store, &keys, &config).unwrap();
+        assert_eq!(
+            result.backend_name,
+            SqliteWithoutRowidPooledStore::backend_name()
+        );
+        assert_eq!(result.threads, 4);
+        assert_eq!(result.latencies_ns.len(), 1_002);
+        assert!(result.ops_per_second() > 0.0);
+        assert!(result.percentile(50.0) <= result.percentile(99.0));
+        // The pooled connections still serve plain lookups afterwards
+        assert_eq!(store.get(b"key042").unwrap(), Some(vec![5u8; 64]));
+    }
+
+    #[test]
+    fn test_concurrent_benchmark_merges_thread_histograms() {
+        use crate::backends::{HashDatStore, HashDatStoreBuilder};
+
+        let (temp_file, keys) = build_store::<HashDatStoreBuilder>(50, 100);
+        let store = HashDatStore::open(temp_file.path()).unwrap();
+        let config = BenchmarkConfig {
+            num_lookups: 1_001,
+            threads: 3,
+            histogram: true,
+            ..Default::default()
+        };
+
+        let result = run_concurrent_benchmark(&store, &keys, &config).unwrap();
+        assert!(matches!(result.latencies_ns, LatencySamples::Hist(_)));
+        assert_eq!(result.latencies_ns.len(), 1_001);
+        assert!(result.percentile(50.0) <= result.percentile(99.0));
+    }
+
+    #[test]
+    fn test_batch_timing_records_nonzero_per_op_latency() {
+        let (keys, by_size) = tiny_keys(&[b"only"]);
+        let store = RecordingStore::new(keys.clone());
+        let config = BenchmarkConfig {
+            num_lookups: 1000,
+            warmup_iterations: 0,
+            batch_size: 100,
+            ..Default::default()
+        };
+
+        let results = run_benchmark(&store, &keys, &by_size, &config, 0).unwrap();
+        assert_eq!(results[0].batch_size, 100);
+        // One sample per batch, every lookup still performed
+        assert_eq!(results[0].latencies_ns.len(), 10);
+        assert_eq!(store.requested.borrow().len(), 1000);
+        assert!(results[0]
+            .latencies_ns
+            .raw()
+            .unwrap()
+            .iter()
+            .all(|&ns| ns > 0));
+
+        let calibration = TimerCalibration::measure();
+        assert!(!calibration.resolution.is_zero());
+        assert!(calibration.is_near_floor(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_shared_plan_gives_backends_identical_sequences() {
+        let (keys, by_size) = tiny_keys(&[b"a", b"b", b"c", b"d", b"e", b"f"]);
+        let mut config = BenchmarkConfig {
+            num_lookups: 40,
+            warmup_iterations: 30,
+            ..Default::default()
+        };
+        let plan = Arc::new(AccessPlan::generate(&config, &keys, &by_size));
+        assert_eq!(plan.warmup.len(), 30);
+        assert_eq!(plan.measured[&BlobSize::Tiny].len(), 40);
+        config.shared_plan = Some(plan.clone());
+
+        let first = RecordingStore::new(keys.clone());
+        let second = OtherRecordingStore(RecordingStore::new(keys.clone()));
+        run_benchmark(&first, &keys, &by_size, &config, 0).unwrap();
+        run_benchmark(&second, &keys, &by_size, &config, 0).unwrap();
+
+        let first_requested = first.requested.borrow();
+        let second_requested = second.0.requested.borrow();
+        assert_eq!(*first_requested, *second_requested);
+
+        let expected_warmup: Vec<Vec<u8>> =
+            plan.warmup.iter().map(|&idx| keys[idx].clone()).collect();
+        assert_eq!(first_requested[..30], expected_warmup[..]);
+    }
+
+    #[test]
+    fn test_warmup_only_makes_no_measured_lookups() {
+        let (keys, _) = tiny_keys(&[b"a", b"b", b"c"]);
+        let config = BenchmarkConfig {
+            num_lookups: 100,
+            warmup_iterations: 30,
+            ..Default::default()
+        };
+
+        let store = RecordingStore::new(keys.clone());
+        let mut result = run_warmup(&store, &keys, &config).unwrap();
+        result.open_time = Duration::from_millis(2);
+
+        assert_eq!(result.backend_name, "Recording");
+        assert_eq!(result.iterations, 30);
+        assert_eq!(store.requested.borrow().len(), 30);
+        assert!(result.warmup_time > Duration::ZERO);
+        assert_eq!(result.ready_time(), result.open_time + result.warmup_time);
+    }
+
+    #[test]
+    fn test_backend_lookups_override_only_named_backend() {
+        let (keys, by_size) = tiny_keys(&[b"a", b"b", b"c"]);
+        let config = BenchmarkConfig {
+            num_lookups: 20,
+            warmup_iterations: 0,
+            backend_lookups: HashMap::from([("Other Recording".to_string(), 7)]),
+            ..Default::default()
+        };
+
+        let first = RecordingStore::new(keys.clone());
+        let second = OtherRecordingStore(RecordingStore::new(keys.clone()));
+        let first_results = run_benchmark(&first, &keys, &by_size, &config, 0).unwrap();
+        let second_results = run_benchmark(&second, &keys, &by_size, &config, 0).unwrap();
+
+        assert_eq!(first.requested.borrow().len(), 20);
+        assert_eq!(first_results[0].lookups, 20);
+        assert_eq!(second.0.requested.borrow().len(), 7);
+        assert_eq!(second_results[0].lookups, 7);
+        assert_eq!(second_results[0].latencies_ns.len(), 7);
+    }
+
+    /// Store that raises the cancellation flag after a fixed number of gets
+    struct CancellingStore {
+        remaining: std::cell::Cell<usize>,
+        flag: Arc<AtomicBool>,
+    }
+
+    impl BlobStore for CancellingStore {
+        fn open(_path: &Path) -> Result<Self> {
+            anyhow::bail!("CancellingStore is in-memory only")
+        }
+
+        fn get(&self, _key: &[u8]) -> Result<Option<Vec<u8>>> {
+            let remaining = self.remaining.get().saturating_sub(1);
+            self.remaining.set(remaining);
+            if remaining == 0 {
+                self.flag.store(true, Ordering::Relaxed);
+            }
+            Ok(Some(Vec::new()))
+        }
+
+        fn keys(&self) -> Result<Vec<Vec<u8>>> {
+            Ok(Vec::new())
+        }
+
+        fn len(&self) -> usize {
+            0
+        }
+
+        fn backend_name() -> &'static str {
+            "Cancelling"
+        }
+    }
+
+    #[test]
+    fn test_cancellation_produces_partial_result() {
+        let (keys, by_size) = tiny_keys(&[b"a", b"b", b"c"]);
+        let flag = Arc::new(AtomicBool::new(false));
+        let store = CancellingStore {
+            remaining: std::cell::Cell::new(25),
+            flag: flag.clone(),
+        };
+        let config = BenchmarkConfig {
+            num_lookups: 100,
+            warmup_iterations: 0,
+            cancel: Some(flag),
+            ..Default::default()
+        };
+
+        let results = run_benchmark(&store, &keys, &by_size, &config, 0).unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].interrupted);
+        assert_eq!(results[0].latencies_ns.len(), 25);
+        assert!(results[0].latencies_ns.len() < config.num_lookups);
+    }
+
+    fn assert_size_split<S: BlobStore>(path: &Path, expected_payload: u64) {
+        let store = S::open(path).unwrap();
+        let file_size = std::fs::metadata(path).unwrap().len();
+        let (keys, by_size) = tiny_keys(&[b"k00"]);
+        let config = BenchmarkConfig {
+            num_lookups: 5,
+            warmup_iterations: 0,
+            report_index_size: true,
+            ..Default::default()
+        };
+
+        let results = run_benchmark(&store, &keys, &by_size, &config, file_size).unwrap();
+        let result = &results[0];
+        let payload = result.payload_bytes.unwrap();
+        let index = result.index_bytes().unwrap();
+
+        assert_eq!(payload, expected_payload);
+        assert!(index > 0 && index < file_size);
+        assert_eq!(index + payload, file_size);
+    }
+
+    /// Write 20 values of increasing size with `builder`, returning their total length
+    fn build_size_split_store<B: crate::store::BlobStoreBuilder>(mut builder: B) -> u64 {
+        let mut expected_payload = 0u64;
+        for i in 0..20 {
+            let key = format!("k{:02}", i);
+            let value = vec![i as u8; 100 * (i + 1)];
+            expected_payload += value.len() as u64;
+            builder.insert(key.as_bytes(), &value).unwrap();
+        }
+        builder.finish().unwrap();
+        expected_payload
+    }
+
+    #[test]
+    fn test_index_and_payload_sum_to_file_size() {
+        use crate::backends::{
+            BTreeDatStore, BTreeDatStoreBuilder, HashDatStore, HashDatStoreBuilder, SqliteStore,
+            SqliteStoreBuilder, ZipStore, ZipStoreBuilder,
+        };
+        use crate::store::BlobStoreBuilder;
+        use tempfile::TempDir;
+
+        let dir = TempDir::new().unwrap();
+        let hash_path = dir.path().join("index_hash.dat");
+        let btree_path = dir.path().join("index_btree.dat");
+        let sqlite_path = dir.path().join("index.sqlite");
+        let zip_path = dir.path().join("index.zip");
+
+        let payload = build_size_split_store(HashDatStoreBuilder::create(&hash_path).unwrap());
+        assert_size_split::<HashDatStore>(&hash_path, payload);
+        let payload = build_size_split_store(BTreeDatStoreBuilder::create(&btree_path).unwrap());
+        assert_size_split::<BTreeDatStore>(&btree_path, payload);
+        let payload = build_size_split_store(SqliteStoreBuilder::create(&sqlite_path).unwrap());
+        assert_size_split::<SqliteStore>(&sqlite_path, payload);
+        let payload = build_size_split_store(ZipStoreBuilder::create(&zip_path).unwrap());
+        assert_size_split::<ZipStore>(&zip_path, payload);
+    }
+
+    #[test]
+    fn test_compressed_payload_has_no_index_split() {
+        use crate::backends::{ZipCompression, ZipStore, ZipStoreBuilder};
+        use crate::store::BlobStoreBuilder;
+        use tempfile::TempDir;
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index_deflate.zip");
+        let builder = ZipStoreBuilder::create(&path)
+            .unwrap()
+            .with_compression(ZipCompression {
+                method: zip::CompressionMethod::Deflated,
+                level: Some(9),
+            });
+        let expected_payload = build_size_split_store(builder);
+
+        let store = ZipStore::open(&path).unwrap();
+        let file_size = std::fs::metadata(&path).unwrap().len();
+        let (keys, by_size) = tiny_keys(&[b"k00"]);
+        let config = BenchmarkConfig {
+            num_lookups: 5,
+            warmup_iterations: 0,
+            report_index_size: true,
+            ..Default::default()
+        };
+
+        let results = run_benchmark(&store, &keys, &by_size, &config, file_size).unwrap();
+        assert_eq!(results[0].payload_bytes, Some(expected_payload));
+        assert!(expected_payload > file_size);
+        assert_eq!(results[0].index_bytes(), None);
+    }
+}
@@ -0,0 +1,398 @@
+use crate::data_gen::{BlobSize, DataGenConfig, InsertOrder, KeyShape};
+use crate::store::BlobStore;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// File written next to `keys.json` by `build`
+pub const BUILD_STATS_FILE: &str = "build_stats.json";
+
+/// File written next to `keys.json` by `build`, holding each backend's build time
+pub const BUILD_TIMES_FILE: &str = "build_times.json";
+
+/// File written next to `keys.json` by `build`, describing the generated dataset
+pub const DATASET_MANIFEST_FILE: &str = "manifest.json";
+
+/// The generator settings and contents of the dataset the indices in a
+/// directory were built from, so `bench` can say what it measured and notice
+/// when the directory is incomplete
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatasetManifest {
+    pub seed: u64,
+    pub entries_per_size: usize,
+    /// Entry counts that replace `entries_per_size`, keyed by size name
+    pub entries_override: BTreeMap<String, usize>,
+    /// Total entries across every size category
+    pub entries: usize,
+    /// Share of each value made of repeated runs (`DataGenConfig::compressibility`)
+    #[serde(default)]
+    pub compressibility: f64,
+    /// Relative spread of value lengths (`DataGenConfig::size_jitter`)
+    #[serde(default)]
+    pub size_jitter: f64,
+    /// `KeyShape::name` of the generated keys
+    #[serde(default = "default_key_shape")]
+    pub key_shape: String,
+    /// `InsertOrder::name` the stores were built with
+    #[serde(default = "default_insert_order")]
+    pub insert_order: String,
+    /// Hex `DataDigest` of the generated entries; equals the `data_digest` of
+    /// every store built from them
+    pub dataset_hash: String,
+    /// Index files and directories built from the dataset, relative to the manifest
+    pub stores: Vec<String>,
+}
+
+/// Manifests written before key shapes existed only hold synthetic keys
+fn default_key_shape() -> String {
+    KeyShape::default().name().to_string()
+}
+
+/// Manifests written before insert orders were recorded were built in natural order
+fn default_insert_order() -> String {
+    InsertOrder::default().name().to_string()
+}
+
+impl DatasetManifest {
+    /// The generator settings that reproduce the dataset
+    pub fn data_gen_config(&self) -> Result<DataGenConfig> {
+        let entries_override = self
+            .entries_override
+            .iter()
+            .map(|(name, &count)| {
+                let size = BlobSize::all()
+                    .iter()
+                    .find(|size| size.name() == name)
+                    .with_context(|| format!("Unknown blob size `{}` in dataset manifest", name))?;
+                Ok((*size, count))
+            })
+            .collect::<Result<_>>()?;
+        let key_shape = KeyShape::value_variants()
+            .iter()
+            .find(|shape| shape.name() == self.key_shape)
+            .with_context(|| {
+                format!("Unknown key shape `{}` in dataset manifest", self.key_shape)
+            })?;
+        Ok(DataGenConfig {
+            entries_per_size: self.entries_per_size,
+            entries_override,
+            seed: self.seed,
+            compressibility: self.compressibility,
+            size_jitter: self.size_jitter,
+            key_shape: *key_shape,
+        })
+    }
+
+    /// Write `manifest.json` into `dir`
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(dir.join(DATASET_MANIFEST_FILE), json)
+            .context("Failed to write dataset manifest")
+    }
+
+    /// Load `manifest.json` from `dir`, or `None` if it was never written
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(DATASET_MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = std::fs::read_to_string(&path).context("Failed to read dataset manifest")?;
+        let manifest = serde_json::from_str(&json).context("Failed to parse dataset manifest")?;
+        Ok(Some(manifest))
+    }
+
+    /// Listed stores that no longer exist in `dir`
+    pub fn missing_stores(&self, dir: &Path) -> Vec<&str> {
+        self.stores
+            .iter()
+            .filter(|store| !dir.join(store).exists())
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Facts about a single backend's index captured at build time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackendBuildStats {
+    /// Uncompressed value bytes / stored value bytes (1.0 when values are stored as-is)
+    pub compression_ratio: f64,
+}
+
+/// Build-time stats for every backend, keyed by backend display name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BuildStats {
+    pub backends: BTreeMap<String, BackendBuildStats>,
+    /// `--insert-order` the indices were built with
+    #[serde(default)]
+    pub insert_order: Option<String>,
+}
+
+impl BuildStats {
+    /// Open the freshly built store at `path` and record its stats
+    pub fn record<S: BlobStore>(&mut self, path: &Path) -> Result<&BackendBuildStats> {
+        let store = S::open(path)?;
+        let stats = BackendBuildStats {
+            compression_ratio: store.compression_ratio()?,
+        };
+        let name = S::backend_name().to_string();
+        self.backends.insert(name.clone(), stats);
+        Ok(&self.backends[&name])
+    }
+
+    /// Write `build_stats.json` into `dir`
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(dir.join(BUILD_STATS_FILE), json).context("Failed to write build stats")
+    }
+
+    /// Load `build_stats.json` from `dir`, or `None` if it was never written
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(BUILD_STATS_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = std::fs::read_to_string(&path).context("Failed to read build stats")?;
+        let stats = serde_json::from_str(&json).context("Failed to parse build stats")?;
+        Ok(Some(stats))
+    }
+}
+
+/// How long each backend's index took to build (create, every insert and
+/// finish), keyed by backend display name. Backends skipped as unchanged keep
+/// the time from the build that produced them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BuildTimes {
+    /// Build time in milliseconds
+    pub backends: BTreeMap<String, f64>,
+}
+
+impl BuildTimes {
+    pub fn record<S: BlobStore>(&mut self, elapsed: Duration) {
+        self.backends.insert(
+            S::backend_name().to_string(),
+            elapsed.as_secs_f64() * 1000.0,
+        );
+    }
+
+    /// Write `build_times.json` into `dir`
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(dir.join(BUILD_TIMES_FILE), json).context("Failed to write build times")
+    }
+
+    /// Load `build_times.json` from `dir`, or `None` if it was never written
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(BUILD_TIMES_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = std::fs::read_to_string(&path).context("Failed to read build times")?;
+        let times = serde_json::from_str(&json).context("Failed to parse build times")?;
+        Ok(Some(times))
+    }
+}
+
+/// Distribution of value lengths across every entry of a store
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueSizeStats {
+    /// Every value length, ascending
+    sizes: Vec<u64>,
+}
+
+impl ValueSizeStats {
+    /// Record the length of every value in the store, without reading values
+    /// where the backend can size them from its index
+    pub fn collect<S: crate::store::DynBlobStore + ?Sized>(store: &S) -> Result<Self> {
+        let mut sizes = Vec::with_capacity(store.len());
+        for key in store.keys()? {
+            let len = store
+                .value_len(&key)?
+                .context("Key listed by keys() has no value")?;
+            sizes.push(len as u64);
+        }
+        Ok(Self::from_sizes(sizes))
+    }
+
+    pub fn from_sizes(mut sizes: Vec<u64>) -> Self {
+        sizes.sort_unstable();
+        Self { sizes }
+    }
+
+    pub fn count(&self) -> usize {
+        self.sizes.len()
+    }
+
+    pub fn min(&self) -> u64 {
+        self.sizes.first().copied().unwrap_or(0)
+    }
+
+    pub fn max(&self) -> u64 {
+        self.sizes.last().copied().unwrap_or(0)
+    }
+
+    /// Nearest-rank percentile, `p` in 0..=100
+    pub fn percentile(&self, p: f64) -> u64 {
+        if self.sizes.is_empty() {
+            return 0;
+        }
+        let rank = ((p / 100.0) * self.sizes.len() as f64).ceil() as usize;
+        self.sizes[rank.clamp(1, self.sizes.len()) - 1]
+    }
+
+    /// Power-of-two bucket holding `len`: 0 for empty values, otherwise k for
+    /// lengths in [2^(k-1), 2^k)
+    fn bucket(len: u64) -> usize {
+        (u64::BITS - len.leading_zeros()) as usize
+    }
+
+    /// (smallest length in the bucket, count) for each power-of-two bucket from
+    /// the smallest value's bucket to the largest's, including empty ones between
+    pub fn histogram(&self) -> Vec<(u64, usize)> {
+        if self.sizes.is_empty() {
+            return Vec::new();
+        }
+        let first = Self::bucket(self.min());
+        let mut counts = vec![0usize; Self::bucket(self.max()) - first + 1];
+        for &len in &self.sizes {
+            counts[Self::bucket(len) - first] += 1;
+        }
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| {
+                let bucket = first + i;
+                let lower = if bucket == 0 { 0 } else { 1u64 << (bucket - 1) };
+                (lower, count)
+            })
+            .collect()
+    }
+
+    /// ASCII bar chart of `histogram`, the largest bucket `width` characters wide
+    pub fn render_histogram(&self, width: usize) -> String {
+        let histogram = self.histogram();
+        let largest = histogram.iter().map(|&(_, count)| count).max().unwrap_or(0);
+        let mut out = String::new();
+        for (lower, count) in histogram {
+            let bar = if largest == 0 {
+                0
+            } else {
+                (count * width).div_ceil(largest)
+            };
+            let upper = if lower == 0 { 1 } else { lower * 2 };
+            out.push_str(&format!(
+                "  {:>12} .. {:<12} {:>8} |{}\n",
+                lower,
+                upper,
+                count,
+                "#".repeat(bar)
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::{ZipStore, ZipStoreBuilder};
+    use crate::store::BlobStoreBuilder;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_build_stats_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(BuildStats::load(dir.path()).unwrap(), None);
+
+        let zip_path = dir.path().join("index.zip");
+        let mut builder = ZipStoreBuilder::create(&zip_path).unwrap();
+        builder.insert(b"key", &[7u8; 1000]).unwrap();
+        builder.finish().unwrap();
+
+        let mut stats = BuildStats::default();
+        let recorded = stats.record::<ZipStore>(&zip_path).unwrap();
+        assert_eq!(recorded.compression_ratio, 1.0);
+        stats.save(dir.path()).unwrap();
+
+        assert_eq!(BuildStats::load(dir.path()).unwrap(), Some(stats));
+    }
+
+    #[test]
+    fn test_build_times_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(BuildTimes::load(dir.path()).unwrap(), None);
+
+        let mut times = BuildTimes::default();
+        times.record::<ZipStore>(Duration::from_millis(1_500));
+        times.save(dir.path()).unwrap();
+
+        let loaded = BuildTimes::load(dir.path()).unwrap().unwrap();
+        assert_eq!(loaded.backends[ZipStore::backend_name()], 1_500.0);
+        assert_eq!(loaded, times);
+    }
+
+    #[test]
+    fn test_older_dataset_manifest_gets_default_generator_settings() {
+        let dir = TempDir::new().unwrap();
+        let json = r#"{"seed": 7, "entries_per_size": 4, "entries_override": {"1MB": 2},
+            "entries": 18, "dataset_hash": "00", "stores": ["index.zip"]}"#;
+        std::fs::write(dir.path().join(DATASET_MANIFEST_FILE), json).unwrap();
+
+        let manifest = DatasetManifest::load(dir.path()).unwrap().unwrap();
+        assert_eq!(manifest.key_shape, "synthetic");
+        assert_eq!(manifest.insert_order, "natural");
+        let config = manifest.data_gen_config().unwrap();
+        assert_eq!((config.seed, config.entries_per_size), (7, 4));
+        assert_eq!(config.entries_for_size(BlobSize::Huge), 2);
+        assert_eq!((config.compressibility, config.size_jitter), (0.0, 0.0));
+        assert_eq!(config.key_shape, KeyShape::Synthetic);
+
+        let bad = DatasetManifest {
+            key_shape: "spiral".to_string(),
+            ..manifest
+        };
+        assert!(bad.data_gen_config().is_err());
+    }
+
+    #[test]
+    fn test_value_size_histogram_buckets() {
+        let dir = TempDir::new().unwrap();
+        let zip_path = dir.path().join("index.zip");
+        let mut builder = ZipStoreBuilder::create(&zip_path).unwrap();
+        let sizes = [0usize, 10, 12, 15, 1000, 1023, 1024, 100_000];
+        for (i, &len) in sizes.iter().enumerate() {
+            builder
+                .insert(format!("key{}", i).as_bytes(), &vec![1u8; len])
+                .unwrap();
+        }
+        builder.finish().unwrap();
+
+        let stats = ValueSizeStats::collect(&ZipStore::open(&zip_path).unwrap()).unwrap();
+        assert_eq!(stats.count(), sizes.len());
+        assert_eq!(stats.min(), 0);
+        assert_eq!(stats.max(), 100_000);
+        assert_eq!(stats.percentile(50.0), 15);
+        assert_eq!(stats.percentile(99.0), 100_000);
+
+        let histogram = stats.histogram();
+        // Buckets run from the empty value's bucket up to [65536, 131072)
+        assert_eq!(histogram.len(), 18);
+        let count_at = |lower: u64| histogram.iter().find(|&&(l, _)| l == lower).unwrap().1;
+        assert_eq!(count_at(0), 1);
+        assert_eq!(count_at(8), 3); // 10, 12, 15
+        assert_eq!(count_at(512), 2); // 1000, 1023
+        assert_eq!(count_at(1024), 1);
+        assert_eq!(count_at(65536), 1);
+        assert_eq!(count_at(16), 0);
+        assert_eq!(
+            histogram.iter().map(|&(_, count)| count).sum::<usize>(),
+            sizes.len()
+        );
+
+        let rendered = stats.render_histogram(30);
+        assert_eq!(rendered.lines().count(), histogram.len());
+        assert!(rendered.contains(&format!("|{}", "#".repeat(30))));
+    }
+}
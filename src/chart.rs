@@ -1,8 +1,12 @@
-use crate::benchmark::{AggregateResults, BenchmarkResult};
+use crate::benchmark::{AggregateResults, BenchmarkResult, MemorySample};
+use crate::build_stats::{BuildStats, BuildTimes};
+use crate::compression::CompressionResult;
 use crate::data_gen::BlobSize;
 use anyhow::{Context, Result};
+use plotters::coord::Shift;
 use plotters::prelude::*;
 use plotters::style::text_anchor::{HPos, Pos, VPos};
+use std::collections::HashMap;
 use std::path::Path;
 
 // Font sizes
@@ -18,34 +22,284 @@ const DATA_LABEL_FONT_SIZE: u32 = 16;
 const DEFAULT_MARGIN_BOTTOM: u32 = 55;
 const DEFAULT_X_LABEL_AREA_SIZE: u32 = 60;
 
-/// Color palette for different backends
+/// Percentiles the percentile charts draw when none are requested
+const DEFAULT_PERCENTILES: [f64; 3] = [50.0, 95.0, 99.0];
+
+/// Image formats generated charts are written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ChartFormat {
+    #[default]
+    Svg,
+    Png,
+    /// An `.svg` and a `.png` of every chart
+    Both,
+}
+
+/// A single image file format
+#[derive(Debug, Clone, Copy)]
+enum ImageFormat {
+    Svg,
+    Png,
+}
+
+impl ImageFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Svg => "svg",
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+impl ChartFormat {
+    /// Files to write per chart
+    fn images(self) -> &'static [ImageFormat] {
+        match self {
+            ChartFormat::Svg => &[ImageFormat::Svg],
+            ChartFormat::Png => &[ImageFormat::Png],
+            ChartFormat::Both => &[ImageFormat::Svg, ImageFormat::Png],
+        }
+    }
+}
+
+/// Drawing area for one image format. Plotters backends are separate types,
+/// so `render_chart` callers match on it to call their generic `draw_*`
+/// function with each.
+enum ChartArea<'a> {
+    Svg(DrawingArea<SVGBackend<'a>, Shift>),
+    Png(DrawingArea<BitMapBackend<'a>, Shift>),
+}
+
+/// Draw a chart once per image format in `options.format`, into
+/// `<output_dir>/<stem>.<ext>`, passing `draw` the drawing area and the
+/// chart's `ChartLayout` for that format (from `default_size`).
+fn render_chart(
+    options: &ChartOptions,
+    output_dir: &Path,
+    stem: &str,
+    default_size: (u32, u32),
+    draw: impl Fn(ChartArea<'_>, &ChartLayout) -> Result<()>,
+) -> Result<()> {
+    for &image in options.format.images() {
+        let path = output_dir.join(format!("{}.{}", stem, image.extension()));
+        let layout = options.layout(default_size, image);
+        let area = match image {
+            ImageFormat::Svg => {
+                ChartArea::Svg(SVGBackend::new(&path, layout.size).into_drawing_area())
+            }
+            ImageFormat::Png => {
+                ChartArea::Png(BitMapBackend::new(&path, layout.size).into_drawing_area())
+            }
+        };
+        draw(area, &layout)?;
+        println!("Generated: {}", path.display());
+    }
+    Ok(())
+}
+
+/// Output dimensions, format and drawn percentiles for generated charts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChartOptions {
+    /// Width in pixels; each chart keeps its own default when unset
+    pub width: Option<u32>,
+    /// Height in pixels; each chart keeps its own default when unset
+    pub height: Option<u32>,
+    /// Percentiles (0-100) drawn by the percentile charts; P50/P95/P99 when empty
+    pub percentiles: Vec<f64>,
+    /// SVG, PNG or both
+    pub format: ChartFormat,
+    /// Pixel multiplier for PNG output (e.g. 2 for high-DPI displays), applied
+    /// on top of width/height; 1 when unset. SVGs are resolution-independent
+    /// and ignore it.
+    pub scale: Option<f64>,
+}
+
+impl ChartOptions {
+    /// Percentiles to draw, falling back to `DEFAULT_PERCENTILES`
+    fn percentiles(&self) -> &[f64] {
+        if self.percentiles.is_empty() {
+            &DEFAULT_PERCENTILES
+        } else {
+            &self.percentiles
+        }
+    }
+
+    /// Largest percentile drawn, which sets the top of the latency axis
+    fn highest_percentile(&self) -> f64 {
+        self.percentiles().iter().copied().fold(0.0, f64::max)
+    }
+
+    /// Resolve a chart's final size from its default. Fonts and margins scale with
+    /// the smaller of the two axis ratios so text stays proportionate; PNGs are
+    /// then multiplied by `scale` as a whole, text included.
+    fn layout(&self, default_size: (u32, u32), image: ImageFormat) -> ChartLayout {
+        let size = (
+            self.width.unwrap_or(default_size.0),
+            self.height.unwrap_or(default_size.1),
+        );
+        let factor =
+            (size.0 as f64 / default_size.0 as f64).min(size.1 as f64 / default_size.1 as f64);
+        let scale = match image {
+            ImageFormat::Svg => 1.0,
+            ImageFormat::Png => self.scale.unwrap_or(1.0),
+        };
+        let scaled = |px: u32| ((px as f64 * scale).round() as u32).max(1);
+        ChartLayout {
+            size: (scaled(size.0), scaled(size.1)),
+            factor: factor * scale,
+        }
+    }
+}
+
+/// Resolved size and scale factor for a single chart
+struct ChartLayout {
+    size: (u32, u32),
+    factor: f64,
+}
+
+impl ChartLayout {
+    fn font(&self, base: u32) -> u32 {
+        ((base as f64 * self.factor).round() as u32).max(1)
+    }
+
+    fn px(&self, base: u32) -> u32 {
+        (base as f64 * self.factor).round() as u32
+    }
+}
+
+/// Color palette for backends, assigned in sorted-name order by `backend_indices`
 const COLORS: &[RGBColor] = &[
-    RGBColor(66, 133, 244),  // Blue (SQLite WITHOUT ROWID)
-    RGBColor(129, 180, 255), // Light blue (SQLite ROWID)
-    RGBColor(251, 188, 5),   // Yellow (Hash DAT)
-    RGBColor(52, 168, 83),   // Green (Zip)
+    RGBColor(66, 133, 244),  // Blue
+    RGBColor(129, 180, 255), // Light blue
+    RGBColor(251, 188, 5),   // Yellow
+    RGBColor(52, 168, 83),   // Green
+    RGBColor(234, 67, 53),   // Red
+    RGBColor(142, 68, 173),  // Purple
+    RGBColor(121, 85, 72),   // Brown
+    RGBColor(0, 150, 136),   // Teal
+    RGBColor(255, 112, 67),  // Orange
+    RGBColor(233, 30, 99),   // Pink
 ];
 
-fn get_backend_color(backend_name: &str) -> RGBColor {
-    match backend_name {
-        "SQLite (WITHOUT ROWID)" => COLORS[0],
-        "SQLite (ROWID)" => COLORS[1],
-        "Custom Offset File Format" => COLORS[2],
-        "Zip" => COLORS[3],
-        _ => RGBColor(128, 128, 128),
+/// Palette position of each backend, keyed by backend name
+pub type BackendIndices<'a> = HashMap<&'a str, usize>;
+
+/// Number the distinct backend names in sorted order. Charts order bars and
+/// legends by this index and color each backend `COLORS[index]`, cycling the
+/// palette once there are more backends than colors, so a new backend never
+/// shares a catch-all color with another.
+pub fn backend_indices<'a>(names: impl IntoIterator<Item = &'a str>) -> BackendIndices<'a> {
+    let mut names: Vec<&str> = names.into_iter().collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| (name, i))
+        .collect()
+}
+
+/// `backend_indices` over every backend in `results`
+pub fn result_backend_indices(results: &AggregateResults) -> BackendIndices<'_> {
+    backend_indices(results.results.iter().map(|r| r.backend_name.as_str()))
+}
+
+/// Backends missing from `indices` sort last
+fn backend_index(indices: &BackendIndices, backend_name: &str) -> usize {
+    indices.get(backend_name).copied().unwrap_or(usize::MAX)
+}
+
+/// Backends missing from `indices` are drawn gray
+fn backend_color(indices: &BackendIndices, backend_name: &str) -> RGBColor {
+    match indices.get(backend_name) {
+        Some(&idx) => COLORS[idx % COLORS.len()],
+        None => RGBColor(128, 128, 128),
     }
 }
 
-fn get_backend_index(backend_name: &str) -> usize {
-    match backend_name {
-        "SQLite (WITHOUT ROWID)" => 0,
-        "SQLite (ROWID)" => 1,
-        "Custom Offset File Format" => 2,
-        "Zip" => 3,
-        _ => 4,
+/// Unit for a byte-valued axis, chosen from the largest value in the series so
+/// labels stay readable whether the values are a few KB or many GB
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ByteUnit {
+    name: &'static str,
+    bytes: f64,
+}
+
+impl ByteUnit {
+    const ALL: [ByteUnit; 4] = [
+        ByteUnit {
+            name: "B",
+            bytes: 1.0,
+        },
+        ByteUnit {
+            name: "KB",
+            bytes: 1024.0,
+        },
+        ByteUnit {
+            name: "MB",
+            bytes: 1_048_576.0,
+        },
+        ByteUnit {
+            name: "GB",
+            bytes: 1_073_741_824.0,
+        },
+    ];
+
+    /// Largest unit that keeps `max_bytes` at or above 1
+    fn for_max(max_bytes: f64) -> Self {
+        Self::ALL
+            .iter()
+            .rev()
+            .find(|unit| max_bytes >= unit.bytes)
+            .copied()
+            .unwrap_or(Self::ALL[0])
+    }
+
+    /// Convert a byte count into this unit
+    fn scale(&self, bytes: f64) -> f64 {
+        bytes / self.bytes
+    }
+
+    /// Label for a value already expressed in this unit
+    fn format(&self, value: f64) -> String {
+        if self.bytes == 1.0 {
+            format!("{:.0} {}", value, self.name)
+        } else {
+            format!("{:.1} {}", value, self.name)
+        }
     }
 }
 
+/// Lowest log-axis bound used when the data gives no usable positive minimum
+const LOG_AXIS_FLOOR: f64 = 0.1;
+
+/// Turn computed axis bounds into a finite, strictly increasing range. NaN or
+/// infinite bounds, inverted bounds (e.g. a `fold(f64::MAX, min)` over no samples)
+/// and all-zero data fall back to a small valid range; log axes keep `min > 0`.
+fn sanitize_range(min: f64, max: f64, log_scale: bool) -> (f64, f64) {
+    let max = if max.is_finite() { max } else { 0.0 };
+    let fallback_min = if !log_scale {
+        max.min(0.0)
+    } else if max > 0.0 {
+        max / 10.0
+    } else {
+        LOG_AXIS_FLOOR
+    };
+    let min = if min.is_finite() && min <= max && (!log_scale || min > 0.0) {
+        min
+    } else {
+        fallback_min
+    };
+    let max = if max > min {
+        max
+    } else if log_scale {
+        min * 10.0
+    } else {
+        min + 1.0
+    };
+    (min, max)
+}
+
 /// Format latency for display
 fn format_latency(micros: f64) -> String {
     if micros >= 1000.0 {
@@ -69,30 +323,87 @@ fn format_log_latency_tick(micros: f64) -> String {
     }
 }
 
+/// Legend label for a percentile, e.g. "P50" or "P99.9"
+fn percentile_label(p: f64) -> String {
+    format!("P{}", p)
+}
+
+/// Bar color for the `idx`th of `count` drawn percentiles, spread along a
+/// green-yellow-red ramp (three percentiles get exactly those three colors)
+fn percentile_color(idx: usize, count: usize) -> RGBColor {
+    const STOPS: [(f64, f64, f64); 3] = [
+        (100.0, 180.0, 100.0), // green
+        (200.0, 180.0, 80.0),  // yellow
+        (200.0, 100.0, 100.0), // red
+    ];
+    let t = if count > 1 {
+        idx as f64 / (count - 1) as f64 * 2.0
+    } else {
+        0.0
+    };
+    let lower = (t.floor() as usize).min(1);
+    let frac = t - lower as f64;
+    let (from, to) = (STOPS[lower], STOPS[lower + 1]);
+    let mix = |a: f64, b: f64| (a + (b - a) * frac).round() as u8;
+    RGBColor(mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+}
+
 /// Generate all benchmark charts
-pub fn generate_charts(results: &AggregateResults, output_dir: &Path) -> Result<()> {
+pub fn generate_charts(
+    results: &AggregateResults,
+    indices: &BackendIndices,
+    output_dir: &Path,
+    options: &ChartOptions,
+) -> Result<()> {
     std::fs::create_dir_all(output_dir).context("Failed to create output directory")?;
 
-    generate_latency_by_size_chart(results, output_dir)?;
-    generate_throughput_chart(results, output_dir)?;
-    generate_percentile_chart(results, output_dir)?;
-    generate_percentile_1mb_linear_chart(results, output_dir)?;
-    generate_p90_chart(results, output_dir)?;
-    generate_memory_chart(results, output_dir)?;
-    generate_file_size_chart(results, output_dir)?;
+    generate_latency_by_size_chart(results, indices, output_dir, options)?;
+    generate_throughput_chart(results, indices, output_dir, options)?;
+    generate_percentile_chart(results, indices, output_dir, options)?;
+    generate_percentile_1mb_linear_chart(results, indices, output_dir, options)?;
+    generate_p90_chart(results, indices, output_dir, options)?;
+    generate_memory_chart(results, indices, output_dir, options)?;
+    generate_file_size_chart(results, indices, output_dir, options)?;
+    if results.results.iter().any(|r| r.payload_bytes.is_some()) {
+        generate_index_overhead_chart(results, indices, output_dir, options)?;
+    }
 
     Ok(())
 }
 
 /// Generate grouped bar chart showing P50 latency by blob size for each backend
-fn generate_latency_by_size_chart(results: &AggregateResults, output_dir: &Path) -> Result<()> {
-    let path = output_dir.join("latency_by_size.svg");
-    let root = SVGBackend::new(&path, (1000, 600)).into_drawing_area();
+fn generate_latency_by_size_chart(
+    results: &AggregateResults,
+    indices: &BackendIndices,
+    output_dir: &Path,
+    options: &ChartOptions,
+) -> Result<()> {
+    render_chart(
+        options,
+        output_dir,
+        "latency_by_size",
+        (1000, 600),
+        |area, layout| match area {
+            ChartArea::Svg(root) => draw_latency_by_size(root, layout, results, indices),
+            ChartArea::Png(root) => draw_latency_by_size(root, layout, results, indices),
+        },
+    )
+}
+
+fn draw_latency_by_size<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    layout: &ChartLayout,
+    results: &AggregateResults,
+    indices: &BackendIndices,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
     root.fill(&WHITE)?;
 
     let by_backend = results.by_backend();
     let mut backends: Vec<&str> = by_backend.keys().copied().collect();
-    backends.sort_by_key(|b| get_backend_index(b));
+    backends.sort_by_key(|b| backend_index(indices, b));
 
     let num_backends = backends.len();
     let num_sizes = BlobSize::all().len();
@@ -112,16 +423,17 @@ fn generate_latency_by_size_chart(results: &AggregateResults, output_dir: &Path)
         .map(|r| r.p50().as_micros() as f64)
         .fold(0.0_f64, |a, b| a.max(b))
         * 2.0;
+    let (min_latency, max_latency) = sanitize_range(min_latency, max_latency, true);
 
     let mut chart = ChartBuilder::on(&root)
         .caption(
             "P50 Latency by Blob Size (log scale)",
-            ("sans-serif", TITLE_FONT_SIZE),
+            ("sans-serif", layout.font(TITLE_FONT_SIZE)),
         )
-        .margin(20)
-        .margin_bottom(DEFAULT_MARGIN_BOTTOM)
-        .x_label_area_size(DEFAULT_X_LABEL_AREA_SIZE)
-        .y_label_area_size(90)
+        .margin(layout.px(20))
+        .margin_bottom(layout.px(DEFAULT_MARGIN_BOTTOM))
+        .x_label_area_size(layout.px(DEFAULT_X_LABEL_AREA_SIZE))
+        .y_label_area_size(layout.px(90))
         .build_cartesian_2d(
             -0.5..(num_sizes as f64 - 0.5),
             (min_latency..max_latency).log_scale(),
@@ -144,16 +456,17 @@ fn generate_latency_by_size_chart(results: &AggregateResults, output_dir: &Path)
         })
         .y_desc("Latency (μs)")
         .x_desc("Blob Size")
-        .label_style(("sans-serif", TICK_LABEL_FONT_SIZE))
-        .axis_desc_style(("sans-serif", AXIS_LABEL_FONT_SIZE))
+        .label_style(("sans-serif", layout.font(TICK_LABEL_FONT_SIZE)))
+        .axis_desc_style(("sans-serif", layout.font(AXIS_LABEL_FONT_SIZE)))
         .draw()?;
 
-    // Draw grouped bars
+    // Draw grouped bars, outlining and starring the fastest one in each group
     let group_width = 0.8;
     let bar_width = group_width / num_backends as f64;
+    let fastest = fastest_per_size(&results.results);
 
     for (backend_idx, backend) in backends.iter().enumerate() {
-        let color = get_backend_color(backend);
+        let color = backend_color(indices, backend);
 
         if let Some(backend_results) = by_backend.get(backend) {
             for result in backend_results.iter() {
@@ -176,13 +489,28 @@ fn generate_latency_by_size_chart(results: &AggregateResults, output_dir: &Path)
                     [(x_left, min_latency), (x_right, latency)],
                     color.filled(),
                 )))?;
+
+                if fastest.get(&result.blob_size) == Some(backend) {
+                    chart.draw_series(std::iter::once(Rectangle::new(
+                        [(x_left, min_latency), (x_right, latency)],
+                        BLACK.stroke_width(2),
+                    )))?;
+                    chart.draw_series(std::iter::once(Text::new(
+                        "★",
+                        (x_center + x_offset, latency * 1.1),
+                        ("sans-serif", layout.font(DATA_LABEL_FONT_SIZE + 4))
+                            .into_font()
+                            .color(&BLACK)
+                            .pos(Pos::new(HPos::Center, VPos::Bottom)),
+                    )))?;
+                }
             }
         }
     }
 
     // Draw legend
     for backend in &backends {
-        let color = get_backend_color(backend);
+        let color = backend_color(indices, backend);
         chart
             .draw_series(std::iter::once(Circle::new(
                 (num_sizes as f64 - 1.0, max_latency),
@@ -198,51 +526,104 @@ fn generate_latency_by_size_chart(results: &AggregateResults, output_dir: &Path)
         .position(SeriesLabelPosition::UpperLeft)
         .background_style(WHITE.mix(0.8))
         .border_style(BLACK)
-        .label_font(("sans-serif", LEGEND_FONT_SIZE))
+        .label_font(("sans-serif", layout.font(LEGEND_FONT_SIZE)))
         .draw()?;
 
     root.present()?;
-    println!("Generated: {}", path.display());
     Ok(())
 }
 
+/// Backend that serves lookups from RAM (`MemoryStore`). Its bars are drawn as
+/// the latency floor but never starred as the fastest.
+pub const LATENCY_FLOOR_BACKEND: &str = "In-Memory";
+
+/// Backend with the lowest P50 among the bars drawn in each size group of the
+/// latency-by-size chart (bars under 1μs aren't drawn), not counting the
+/// `LATENCY_FLOOR_BACKEND`. Ties go to the backend drawn first.
+fn fastest_per_size(results: &[BenchmarkResult]) -> HashMap<BlobSize, &str> {
+    let mut fastest: HashMap<BlobSize, (&str, f64)> = HashMap::new();
+    for result in results {
+        let latency = result.p50().as_micros() as f64;
+        if latency <= 0.0 || result.backend_name == LATENCY_FLOOR_BACKEND {
+            continue;
+        }
+        let name = result.backend_name.as_str();
+        fastest
+            .entry(result.blob_size)
+            .and_modify(|best| {
+                if (latency, name) < (best.1, best.0) {
+                    *best = (name, latency);
+                }
+            })
+            .or_insert((name, latency));
+    }
+    fastest
+        .into_iter()
+        .map(|(size, (name, _))| (size, name))
+        .collect()
+}
+
 /// Generate line chart showing throughput (ops/sec) vs blob size with log scale
-fn generate_throughput_chart(results: &AggregateResults, output_dir: &Path) -> Result<()> {
-    let path = output_dir.join("throughput.svg");
-    let root = SVGBackend::new(&path, (1000, 600)).into_drawing_area();
+fn generate_throughput_chart(
+    results: &AggregateResults,
+    indices: &BackendIndices,
+    output_dir: &Path,
+    options: &ChartOptions,
+) -> Result<()> {
+    render_chart(
+        options,
+        output_dir,
+        "throughput",
+        (1000, 600),
+        |area, layout| match area {
+            ChartArea::Svg(root) => draw_throughput(root, layout, results, indices),
+            ChartArea::Png(root) => draw_throughput(root, layout, results, indices),
+        },
+    )
+}
+
+fn draw_throughput<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    layout: &ChartLayout,
+    results: &AggregateResults,
+    indices: &BackendIndices,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
     root.fill(&WHITE)?;
 
     let by_backend = results.by_backend();
     let mut backends: Vec<&str> = by_backend.keys().copied().collect();
-    backends.sort_by_key(|b| get_backend_index(b));
+    backends.sort_by_key(|b| backend_index(indices, b));
 
     let num_sizes = BlobSize::all().len();
 
-    // Find throughput range for log scale
-    let min_throughput = results
-        .results
-        .iter()
-        .map(|r| r.ops_per_second())
+    // Find throughput range for log scale, covering both the wall-clock
+    // points and the mean-latency estimates their whiskers center on
+    let throughputs = || {
+        results
+            .results
+            .iter()
+            .flat_map(|r| [r.ops_per_second_wall(), r.ops_per_second()])
+    };
+    let min_throughput = throughputs()
         .filter(|&v| v > 0.0)
         .fold(f64::MAX, |a, b| a.min(b))
         .max(1.0);
 
-    let max_throughput = results
-        .results
-        .iter()
-        .map(|r| r.ops_per_second())
-        .fold(0.0_f64, |a, b| a.max(b))
-        * 2.0;
+    let max_throughput = throughputs().fold(0.0_f64, |a, b| a.max(b)) * 2.0;
+    let (min_throughput, max_throughput) = sanitize_range(min_throughput, max_throughput, true);
 
     let mut chart = ChartBuilder::on(&root)
         .caption(
-            "Throughput by Blob Size - Mean Latency (log scale)",
-            ("sans-serif", TITLE_FONT_SIZE),
+            "Throughput by Blob Size - Wall Clock (log scale)",
+            ("sans-serif", layout.font(TITLE_FONT_SIZE)),
         )
-        .margin(20)
-        .margin_bottom(DEFAULT_MARGIN_BOTTOM)
-        .x_label_area_size(DEFAULT_X_LABEL_AREA_SIZE)
-        .y_label_area_size(110)
+        .margin(layout.px(20))
+        .margin_bottom(layout.px(DEFAULT_MARGIN_BOTTOM))
+        .x_label_area_size(layout.px(DEFAULT_X_LABEL_AREA_SIZE))
+        .y_label_area_size(layout.px(110))
         .build_cartesian_2d(
             -0.5..(num_sizes as f64 - 0.5),
             (min_throughput..max_throughput).log_scale(),
@@ -263,14 +644,14 @@ fn generate_throughput_chart(results: &AggregateResults, output_dir: &Path) -> R
                 String::new()
             }
         })
-        .y_desc("Operations/sec")
+        .y_desc("Operations/sec (whiskers: mean-latency 95% CI)")
         .x_desc("Blob Size")
-        .label_style(("sans-serif", TICK_LABEL_FONT_SIZE))
-        .axis_desc_style(("sans-serif", AXIS_LABEL_FONT_SIZE))
+        .label_style(("sans-serif", layout.font(TICK_LABEL_FONT_SIZE)))
+        .axis_desc_style(("sans-serif", layout.font(AXIS_LABEL_FONT_SIZE)))
         .draw()?;
 
     for backend in &backends {
-        let color = get_backend_color(backend);
+        let color = backend_color(indices, backend);
 
         if let Some(backend_results) = by_backend.get(backend) {
             let mut data: Vec<(f64, f64)> = backend_results
@@ -280,7 +661,7 @@ fn generate_throughput_chart(results: &AggregateResults, output_dir: &Path) -> R
                         .iter()
                         .position(|&s| s == r.blob_size)
                         .unwrap_or(0);
-                    (size_idx as f64, r.ops_per_second())
+                    (size_idx as f64, r.ops_per_second_wall())
                 })
                 .filter(|(_, ops)| *ops > 0.0)
                 .collect();
@@ -302,6 +683,29 @@ fn generate_throughput_chart(results: &AggregateResults, output_dir: &Path) -> R
                         EmptyElement::at(coord) + Circle::new((0, 0), size, style)
                     },
                 ))?;
+
+                // 95% confidence interval of the mean-latency estimate, for comparison
+                // with the wall-clock line; clamped to the visible axis range
+                chart.draw_series(
+                    backend_results
+                        .iter()
+                        .filter(|r| r.ops_per_second() > 0.0)
+                        .map(|r| {
+                            let size_idx = BlobSize::all()
+                                .iter()
+                                .position(|&s| s == r.blob_size)
+                                .unwrap_or(0);
+                            let (low, high) = r.throughput_ci();
+                            ErrorBar::new_vertical(
+                                size_idx as f64,
+                                low.clamp(min_throughput, max_throughput),
+                                r.ops_per_second(),
+                                high.clamp(min_throughput, max_throughput),
+                                color.stroke_width(2),
+                                12,
+                            )
+                        }),
+                )?;
             }
         }
     }
@@ -311,24 +715,48 @@ fn generate_throughput_chart(results: &AggregateResults, output_dir: &Path) -> R
         .position(SeriesLabelPosition::UpperLeft)
         .background_style(WHITE.mix(0.8))
         .border_style(BLACK)
-        .label_font(("sans-serif", LEGEND_FONT_SIZE))
+        .label_font(("sans-serif", layout.font(LEGEND_FONT_SIZE)))
         .draw()?;
 
     root.present()?;
-    println!("Generated: {}", path.display());
     Ok(())
 }
 
-/// Generate chart showing P50, P95, P99 for each backend (10KB, log scale)
-fn generate_percentile_chart(results: &AggregateResults, output_dir: &Path) -> Result<()> {
-    let path = output_dir.join("percentiles.svg");
-    let root = SVGBackend::new(&path, (1000, 600)).into_drawing_area();
+/// Generate chart showing the requested percentiles for each backend (10KB, log scale)
+fn generate_percentile_chart(
+    results: &AggregateResults,
+    indices: &BackendIndices,
+    output_dir: &Path,
+    options: &ChartOptions,
+) -> Result<()> {
+    render_chart(
+        options,
+        output_dir,
+        "percentiles",
+        (1000, 600),
+        |area, layout| match area {
+            ChartArea::Svg(root) => draw_percentile(root, layout, results, indices, options),
+            ChartArea::Png(root) => draw_percentile(root, layout, results, indices, options),
+        },
+    )
+}
+
+fn draw_percentile<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    layout: &ChartLayout,
+    results: &AggregateResults,
+    indices: &BackendIndices,
+    options: &ChartOptions,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
     root.fill(&WHITE)?;
 
     let target_sizes = [BlobSize::Medium];
     let by_backend = results.by_backend();
     let mut backends: Vec<&str> = by_backend.keys().copied().collect();
-    backends.sort_by_key(|b| get_backend_index(b));
+    backends.sort_by_key(|b| backend_index(indices, b));
 
     let relevant_results: Vec<&BenchmarkResult> = results
         .results
@@ -342,18 +770,25 @@ fn generate_percentile_chart(results: &AggregateResults, output_dir: &Path) -> R
     }
 
     // Find latency range for log scale
+    let lowest = options
+        .percentiles()
+        .iter()
+        .copied()
+        .fold(f64::MAX, f64::min);
     let min_latency = relevant_results
         .iter()
-        .map(|r| r.p50().as_micros() as f64)
+        .map(|r| r.percentile(lowest).as_micros() as f64)
         .filter(|&v| v > 0.0)
         .fold(f64::MAX, |a, b| a.min(b))
         .max(0.1);
 
+    let highest = options.highest_percentile();
     let max_latency = relevant_results
         .iter()
-        .map(|r| r.p99().as_micros() as f64)
+        .map(|r| r.percentile(highest).as_micros() as f64)
         .fold(0.0_f64, |a, b| a.max(b))
         * 2.5;
+    let (min_latency, max_latency) = sanitize_range(min_latency, max_latency, true);
 
     for target_size in target_sizes.iter() {
         let caption = format!(
@@ -363,11 +798,11 @@ fn generate_percentile_chart(results: &AggregateResults, output_dir: &Path) -> R
 
         let num_backends = backends.len();
         let mut chart = ChartBuilder::on(&root)
-            .caption(caption, ("sans-serif", TITLE_FONT_SIZE))
-            .margin(20)
-            .margin_bottom(DEFAULT_MARGIN_BOTTOM)
-            .x_label_area_size(DEFAULT_X_LABEL_AREA_SIZE)
-            .y_label_area_size(90)
+            .caption(caption, ("sans-serif", layout.font(TITLE_FONT_SIZE)))
+            .margin(layout.px(20))
+            .margin_bottom(layout.px(DEFAULT_MARGIN_BOTTOM))
+            .x_label_area_size(layout.px(DEFAULT_X_LABEL_AREA_SIZE))
+            .y_label_area_size(layout.px(90))
             .build_cartesian_2d(
                 -0.5..(num_backends as f64 - 0.5),
                 (min_latency..max_latency).log_scale(),
@@ -389,18 +824,13 @@ fn generate_percentile_chart(results: &AggregateResults, output_dir: &Path) -> R
             .y_label_formatter(&|y| format_log_latency_tick(*y))
             .y_desc("Latency")
             .x_desc("Backend")
-            .label_style(("sans-serif", TICK_LABEL_FONT_SIZE))
-            .axis_desc_style(("sans-serif", AXIS_LABEL_FONT_SIZE))
+            .label_style(("sans-serif", layout.font(TICK_LABEL_FONT_SIZE)))
+            .axis_desc_style(("sans-serif", layout.font(AXIS_LABEL_FONT_SIZE)))
             .draw()?;
 
-        // Draw grouped bars for P50, P95, P99
-        let percentile_colors = [
-            RGBColor(100, 180, 100), // P50 - green
-            RGBColor(200, 180, 80),  // P95 - yellow
-            RGBColor(200, 100, 100), // P99 - red
-        ];
-        let percentile_names = ["P50", "P95", "P99"];
-        let bar_width = 0.25;
+        // Draw grouped bars, one per requested percentile
+        let percentiles = options.percentiles();
+        let bar_width = 0.75 / percentiles.len() as f64;
 
         for (backend_idx, backend) in backends.iter().enumerate() {
             let maybe_result = results
@@ -410,19 +840,20 @@ fn generate_percentile_chart(results: &AggregateResults, output_dir: &Path) -> R
 
             let Some(result) = maybe_result else { continue };
 
-            let p50 = result.p50().as_micros() as f64;
-            let p95 = result.p95().as_micros() as f64;
-            let p99 = result.p99().as_micros() as f64;
-            let values = [p50, p95, p99];
+            let values: Vec<f64> = result
+                .percentiles(percentiles)
+                .iter()
+                .map(|d| d.as_micros() as f64)
+                .collect();
 
             for (p_idx, &value) in values.iter().enumerate() {
                 if value <= 0.0 {
                     continue;
                 }
 
-                let color = percentile_colors[p_idx];
+                let color = percentile_color(p_idx, percentiles.len());
                 let x_center = backend_idx as f64;
-                let x_offset = (p_idx as f64 - 1.0) * bar_width;
+                let x_offset = (p_idx as f64 - (percentiles.len() - 1) as f64 / 2.0) * bar_width;
                 let x_left = x_center + x_offset - bar_width / 2.0 + 0.02;
                 let x_right = x_center + x_offset + bar_width / 2.0 - 0.02;
                 let x_mid = (x_left + x_right) / 2.0;
@@ -436,7 +867,7 @@ fn generate_percentile_chart(results: &AggregateResults, output_dir: &Path) -> R
                 chart.draw_series(std::iter::once(Text::new(
                     format_latency(value),
                     (x_mid, value * 1.15),
-                    ("sans-serif", DATA_LABEL_FONT_SIZE)
+                    ("sans-serif", layout.font(DATA_LABEL_FONT_SIZE))
                         .into_font()
                         .color(&BLACK)
                         .pos(Pos::new(HPos::Center, VPos::Bottom)),
@@ -445,15 +876,15 @@ fn generate_percentile_chart(results: &AggregateResults, output_dir: &Path) -> R
         }
 
         // Add percentile legend (put it upper-left to avoid overlapping bars on the right)
-        for (idx, name) in percentile_names.iter().enumerate() {
-            let color = percentile_colors[idx];
+        for (idx, &p) in percentiles.iter().enumerate() {
+            let color = percentile_color(idx, percentiles.len());
             chart
                 .draw_series(std::iter::once(Circle::new(
                     (num_backends as f64 - 1.0, max_latency),
                     0,
                     color.filled(),
                 )))?
-                .label(*name)
+                .label(percentile_label(p))
                 .legend(move |(x, y)| {
                     Rectangle::new([(x, y - 5), (x + 20, y + 5)], color.filled())
                 });
@@ -464,28 +895,53 @@ fn generate_percentile_chart(results: &AggregateResults, output_dir: &Path) -> R
             .position(SeriesLabelPosition::UpperLeft)
             .background_style(WHITE.mix(0.85))
             .border_style(BLACK)
-            .label_font(("sans-serif", LEGEND_FONT_SIZE))
+            .label_font(("sans-serif", layout.font(LEGEND_FONT_SIZE)))
             .draw()?;
     }
 
     root.present()?;
-    println!("Generated: {}", path.display());
     Ok(())
 }
 
-/// Generate chart showing P50, P95, P99 for each backend (1MB, linear scale)
+/// Generate chart showing the requested percentiles for each backend (1MB, linear scale)
 fn generate_percentile_1mb_linear_chart(
     results: &AggregateResults,
+    indices: &BackendIndices,
     output_dir: &Path,
+    options: &ChartOptions,
 ) -> Result<()> {
+    render_chart(
+        options,
+        output_dir,
+        "percentiles_1mb_linear",
+        (1000, 600),
+        |area, layout| match area {
+            ChartArea::Svg(root) => {
+                draw_percentile_1mb_linear(root, layout, results, indices, options)
+            }
+            ChartArea::Png(root) => {
+                draw_percentile_1mb_linear(root, layout, results, indices, options)
+            }
+        },
+    )
+}
+
+fn draw_percentile_1mb_linear<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    layout: &ChartLayout,
+    results: &AggregateResults,
+    indices: &BackendIndices,
+    options: &ChartOptions,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
     let target_size = BlobSize::Huge; // 1MB
-    let path = output_dir.join("percentiles_1mb_linear.svg");
-    let root = SVGBackend::new(&path, (1000, 600)).into_drawing_area();
     root.fill(&WHITE)?;
 
     let by_backend = results.by_backend();
     let mut backends: Vec<&str> = by_backend.keys().copied().collect();
-    backends.sort_by_key(|b| get_backend_index(b));
+    backends.sort_by_key(|b| backend_index(indices, b));
     let num_backends = backends.len();
 
     let size_results: Vec<&BenchmarkResult> = results
@@ -500,11 +956,13 @@ fn generate_percentile_1mb_linear_chart(
     }
 
     // Linear scale bounds
+    let highest = options.highest_percentile();
     let max_latency = size_results
         .iter()
-        .map(|r| r.p99().as_micros() as f64)
+        .map(|r| r.percentile(highest).as_micros() as f64)
         .fold(0.0_f64, |a, b| a.max(b))
         * 1.25;
+    let (_, max_latency) = sanitize_range(0.0, max_latency, false);
 
     let mut chart = ChartBuilder::on(&root)
         .caption(
@@ -512,13 +970,13 @@ fn generate_percentile_1mb_linear_chart(
                 "Latency Percentiles - {} blobs (linear)",
                 target_size.name()
             ),
-            ("sans-serif", TITLE_FONT_SIZE),
+            ("sans-serif", layout.font(TITLE_FONT_SIZE)),
         )
-        .margin(20)
-        .margin_bottom(DEFAULT_MARGIN_BOTTOM)
-        .x_label_area_size(DEFAULT_X_LABEL_AREA_SIZE)
-        .y_label_area_size(90)
-        .build_cartesian_2d(-0.5..(num_backends as f64 - 0.5), 0.0..max_latency.max(1.0))?;
+        .margin(layout.px(20))
+        .margin_bottom(layout.px(DEFAULT_MARGIN_BOTTOM))
+        .x_label_area_size(layout.px(DEFAULT_X_LABEL_AREA_SIZE))
+        .y_label_area_size(layout.px(90))
+        .build_cartesian_2d(-0.5..(num_backends as f64 - 0.5), 0.0..max_latency)?;
 
     chart
         .configure_mesh()
@@ -536,18 +994,13 @@ fn generate_percentile_1mb_linear_chart(
         .y_label_formatter(&|y| format_latency(*y))
         .y_desc("Latency")
         .x_desc("Backend")
-        .label_style(("sans-serif", TICK_LABEL_FONT_SIZE))
-        .axis_desc_style(("sans-serif", AXIS_LABEL_FONT_SIZE))
+        .label_style(("sans-serif", layout.font(TICK_LABEL_FONT_SIZE)))
+        .axis_desc_style(("sans-serif", layout.font(AXIS_LABEL_FONT_SIZE)))
         .draw()?;
 
-    // Draw grouped bars for P50, P95, P99
-    let percentile_colors = [
-        RGBColor(100, 180, 100), // P50 - green
-        RGBColor(200, 180, 80),  // P95 - yellow
-        RGBColor(200, 100, 100), // P99 - red
-    ];
-    let percentile_names = ["P50", "P95", "P99"];
-    let bar_width = 0.25;
+    // Draw grouped bars, one per requested percentile
+    let percentiles = options.percentiles();
+    let bar_width = 0.75 / percentiles.len() as f64;
 
     for (backend_idx, backend) in backends.iter().enumerate() {
         let maybe_result = results
@@ -557,19 +1010,20 @@ fn generate_percentile_1mb_linear_chart(
 
         let Some(result) = maybe_result else { continue };
 
-        let p50 = result.p50().as_micros() as f64;
-        let p95 = result.p95().as_micros() as f64;
-        let p99 = result.p99().as_micros() as f64;
-        let values = [p50, p95, p99];
+        let values: Vec<f64> = result
+            .percentiles(percentiles)
+            .iter()
+            .map(|d| d.as_micros() as f64)
+            .collect();
 
         for (p_idx, &value) in values.iter().enumerate() {
             if value <= 0.0 {
                 continue;
             }
 
-            let color = percentile_colors[p_idx];
+            let color = percentile_color(p_idx, percentiles.len());
             let x_center = backend_idx as f64;
-            let x_offset = (p_idx as f64 - 1.0) * bar_width;
+            let x_offset = (p_idx as f64 - (percentiles.len() - 1) as f64 / 2.0) * bar_width;
             let x_left = x_center + x_offset - bar_width / 2.0 + 0.02;
             let x_right = x_center + x_offset + bar_width / 2.0 - 0.02;
             let x_mid = (x_left + x_right) / 2.0;
@@ -582,7 +1036,7 @@ fn generate_percentile_1mb_linear_chart(
             chart.draw_series(std::iter::once(Text::new(
                 format_latency(value),
                 (x_mid, value + max_latency * 0.02),
-                ("sans-serif", DATA_LABEL_FONT_SIZE)
+                ("sans-serif", layout.font(DATA_LABEL_FONT_SIZE))
                     .into_font()
                     .color(&BLACK)
                     .pos(Pos::new(HPos::Center, VPos::Bottom)),
@@ -590,15 +1044,15 @@ fn generate_percentile_1mb_linear_chart(
         }
     }
 
-    for (idx, name) in percentile_names.iter().enumerate() {
-        let color = percentile_colors[idx];
+    for (idx, &p) in percentiles.iter().enumerate() {
+        let color = percentile_color(idx, percentiles.len());
         chart
             .draw_series(std::iter::once(Circle::new(
                 (num_backends as f64 - 1.0, max_latency),
                 0,
                 color.filled(),
             )))?
-            .label(*name)
+            .label(percentile_label(p))
             .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], color.filled()));
     }
 
@@ -607,23 +1061,46 @@ fn generate_percentile_1mb_linear_chart(
         .position(SeriesLabelPosition::UpperLeft)
         .background_style(WHITE.mix(0.85))
         .border_style(BLACK)
-        .label_font(("sans-serif", LEGEND_FONT_SIZE))
+        .label_font(("sans-serif", layout.font(LEGEND_FONT_SIZE)))
         .draw()?;
 
     root.present()?;
-    println!("Generated: {}", path.display());
     Ok(())
 }
 
 /// Generate P90 latency chart across all blob sizes
-fn generate_p90_chart(results: &AggregateResults, output_dir: &Path) -> Result<()> {
-    let path = output_dir.join("p90_latency.svg");
-    let root = SVGBackend::new(&path, (1000, 600)).into_drawing_area();
+fn generate_p90_chart(
+    results: &AggregateResults,
+    indices: &BackendIndices,
+    output_dir: &Path,
+    options: &ChartOptions,
+) -> Result<()> {
+    render_chart(
+        options,
+        output_dir,
+        "p90_latency",
+        (1000, 600),
+        |area, layout| match area {
+            ChartArea::Svg(root) => draw_p90(root, layout, results, indices),
+            ChartArea::Png(root) => draw_p90(root, layout, results, indices),
+        },
+    )
+}
+
+fn draw_p90<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    layout: &ChartLayout,
+    results: &AggregateResults,
+    indices: &BackendIndices,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
     root.fill(&WHITE)?;
 
     let by_backend = results.by_backend();
     let mut backends: Vec<&str> = by_backend.keys().copied().collect();
-    backends.sort_by_key(|b| get_backend_index(b));
+    backends.sort_by_key(|b| backend_index(indices, b));
 
     let num_sizes = BlobSize::all().len();
 
@@ -642,16 +1119,17 @@ fn generate_p90_chart(results: &AggregateResults, output_dir: &Path) -> Result<(
         .map(|r| r.p90().as_micros() as f64)
         .fold(0.0_f64, |a, b| a.max(b))
         * 2.0;
+    let (min_latency, max_latency) = sanitize_range(min_latency, max_latency, true);
 
     let mut chart = ChartBuilder::on(&root)
         .caption(
             "P90 Latency by Blob Size (log scale)",
-            ("sans-serif", TITLE_FONT_SIZE),
+            ("sans-serif", layout.font(TITLE_FONT_SIZE)),
         )
-        .margin(20)
-        .margin_bottom(DEFAULT_MARGIN_BOTTOM)
-        .x_label_area_size(DEFAULT_X_LABEL_AREA_SIZE)
-        .y_label_area_size(90)
+        .margin(layout.px(20))
+        .margin_bottom(layout.px(DEFAULT_MARGIN_BOTTOM))
+        .x_label_area_size(layout.px(DEFAULT_X_LABEL_AREA_SIZE))
+        .y_label_area_size(layout.px(90))
         .build_cartesian_2d(
             -0.5..(num_sizes as f64 - 0.5),
             (min_latency..max_latency).log_scale(),
@@ -674,12 +1152,12 @@ fn generate_p90_chart(results: &AggregateResults, output_dir: &Path) -> Result<(
         })
         .y_desc("Latency (μs)")
         .x_desc("Blob Size")
-        .label_style(("sans-serif", TICK_LABEL_FONT_SIZE))
-        .axis_desc_style(("sans-serif", AXIS_LABEL_FONT_SIZE))
+        .label_style(("sans-serif", layout.font(TICK_LABEL_FONT_SIZE)))
+        .axis_desc_style(("sans-serif", layout.font(AXIS_LABEL_FONT_SIZE)))
         .draw()?;
 
     for backend in &backends {
-        let color = get_backend_color(backend);
+        let color = backend_color(indices, backend);
 
         if let Some(backend_results) = by_backend.get(backend) {
             let mut data: Vec<(f64, f64)> = backend_results
@@ -720,36 +1198,64 @@ fn generate_p90_chart(results: &AggregateResults, output_dir: &Path) -> Result<(
         .position(SeriesLabelPosition::UpperLeft)
         .background_style(WHITE.mix(0.8))
         .border_style(BLACK)
-        .label_font(("sans-serif", LEGEND_FONT_SIZE))
+        .label_font(("sans-serif", layout.font(LEGEND_FONT_SIZE)))
         .draw()?;
 
     root.present()?;
-    println!("Generated: {}", path.display());
     Ok(())
 }
 
 /// Generate memory usage comparison chart
-fn generate_memory_chart(results: &AggregateResults, output_dir: &Path) -> Result<()> {
-    let path = output_dir.join("memory_usage.svg");
-    let root = SVGBackend::new(&path, (800, 500)).into_drawing_area();
+fn generate_memory_chart(
+    results: &AggregateResults,
+    indices: &BackendIndices,
+    output_dir: &Path,
+    options: &ChartOptions,
+) -> Result<()> {
+    render_chart(
+        options,
+        output_dir,
+        "memory_usage",
+        (800, 500),
+        |area, layout| match area {
+            ChartArea::Svg(root) => draw_memory(root, layout, results, indices),
+            ChartArea::Png(root) => draw_memory(root, layout, results, indices),
+        },
+    )
+}
+
+fn draw_memory<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    layout: &ChartLayout,
+    results: &AggregateResults,
+    indices: &BackendIndices,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
     root.fill(&WHITE)?;
 
     let by_backend = results.by_backend();
     let mut backends: Vec<&str> = by_backend.keys().copied().collect();
-    backends.sort_by_key(|b| get_backend_index(b));
+    backends.sort_by_key(|b| backend_index(indices, b));
     let num_backends = backends.len();
 
     // Collect memory data
-    let memory_data: Vec<(&str, f64)> = backends
+    let memory_bytes: Vec<(&str, f64)> = backends
         .iter()
         .filter_map(|backend| {
             by_backend.get(backend).and_then(|results| {
                 results
                     .first()
-                    .map(|r| (*backend, r.memory_stats.physical_mem as f64 / 1_048_576.0))
+                    .map(|r| (*backend, r.memory_stats.physical_mem as f64))
             })
         })
         .collect();
+    let unit = ByteUnit::for_max(memory_bytes.iter().map(|(_, b)| *b).fold(0.0, f64::max));
+    let memory_data: Vec<(&str, f64)> = memory_bytes
+        .iter()
+        .map(|(backend, bytes)| (*backend, unit.scale(*bytes)))
+        .collect();
 
     if memory_data.is_empty() {
         root.present()?;
@@ -761,14 +1267,18 @@ fn generate_memory_chart(results: &AggregateResults, output_dir: &Path) -> Resul
         .map(|(_, mem)| *mem)
         .fold(0.0_f64, |a, b| a.max(b))
         * 1.3;
+    let (_, max_memory) = sanitize_range(0.0, max_memory, false);
 
     let mut chart = ChartBuilder::on(&root)
-        .caption("Memory Usage by Backend", ("sans-serif", TITLE_FONT_SIZE))
-        .margin(20)
-        .margin_bottom(DEFAULT_MARGIN_BOTTOM)
-        .x_label_area_size(DEFAULT_X_LABEL_AREA_SIZE)
-        .y_label_area_size(90)
-        .build_cartesian_2d(-0.5..(num_backends as f64 - 0.5), 0.0..max_memory.max(1.0))?;
+        .caption(
+            "Memory Usage by Backend",
+            ("sans-serif", layout.font(TITLE_FONT_SIZE)),
+        )
+        .margin(layout.px(20))
+        .margin_bottom(layout.px(DEFAULT_MARGIN_BOTTOM))
+        .x_label_area_size(layout.px(DEFAULT_X_LABEL_AREA_SIZE))
+        .y_label_area_size(layout.px(90))
+        .build_cartesian_2d(-0.5..(num_backends as f64 - 0.5), 0.0..max_memory)?;
 
     chart
         .configure_mesh()
@@ -782,32 +1292,32 @@ fn generate_memory_chart(results: &AggregateResults, output_dir: &Path) -> Resul
                 String::new()
             }
         })
-        .y_desc("Memory (MB)")
+        .y_desc(format!("Memory ({})", unit.name))
         .x_desc("Backend")
-        .label_style(("sans-serif", TICK_LABEL_FONT_SIZE))
-        .axis_desc_style(("sans-serif", AXIS_LABEL_FONT_SIZE))
+        .label_style(("sans-serif", layout.font(TICK_LABEL_FONT_SIZE)))
+        .axis_desc_style(("sans-serif", layout.font(AXIS_LABEL_FONT_SIZE)))
         .draw()?;
 
     let bar_width = 0.6;
 
     for (idx, backend) in backends.iter().enumerate() {
-        let color = get_backend_color(backend);
+        let color = backend_color(indices, backend);
 
-        if let Some((_, mem_mb)) = memory_data.iter().find(|(b, _)| b == backend) {
+        if let Some((_, mem)) = memory_data.iter().find(|(b, _)| b == backend) {
             let x_center = idx as f64;
             let x_left = x_center - bar_width / 2.0;
             let x_right = x_center + bar_width / 2.0;
 
             chart.draw_series(std::iter::once(Rectangle::new(
-                [(x_left, 0.0), (x_right, *mem_mb)],
+                [(x_left, 0.0), (x_right, *mem)],
                 color.filled(),
             )))?;
 
             // Add value label on top of bar
             chart.draw_series(std::iter::once(Text::new(
-                format!("{:.1} MB", mem_mb),
-                (x_center, *mem_mb + max_memory * 0.03),
-                ("sans-serif", DATA_LABEL_FONT_SIZE + 2)
+                unit.format(*mem),
+                (x_center, *mem + max_memory * 0.03),
+                ("sans-serif", layout.font(DATA_LABEL_FONT_SIZE + 2))
                     .into_font()
                     .color(&BLACK)
                     .pos(Pos::new(HPos::Center, VPos::Bottom)),
@@ -816,32 +1326,58 @@ fn generate_memory_chart(results: &AggregateResults, output_dir: &Path) -> Resul
     }
 
     root.present()?;
-    println!("Generated: {}", path.display());
     Ok(())
 }
 
 /// Generate file size comparison chart
-fn generate_file_size_chart(results: &AggregateResults, output_dir: &Path) -> Result<()> {
-    let path = output_dir.join("file_sizes.svg");
-    let root = SVGBackend::new(&path, (800, 500)).into_drawing_area();
+fn generate_file_size_chart(
+    results: &AggregateResults,
+    indices: &BackendIndices,
+    output_dir: &Path,
+    options: &ChartOptions,
+) -> Result<()> {
+    render_chart(
+        options,
+        output_dir,
+        "file_sizes",
+        (800, 500),
+        |area, layout| match area {
+            ChartArea::Svg(root) => draw_file_size(root, layout, results, indices),
+            ChartArea::Png(root) => draw_file_size(root, layout, results, indices),
+        },
+    )
+}
+
+fn draw_file_size<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    layout: &ChartLayout,
+    results: &AggregateResults,
+    indices: &BackendIndices,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
     root.fill(&WHITE)?;
 
     let by_backend = results.by_backend();
     let mut backends: Vec<&str> = by_backend.keys().copied().collect();
-    backends.sort_by_key(|b| get_backend_index(b));
+    backends.sort_by_key(|b| backend_index(indices, b));
     let num_backends = backends.len();
 
     // Collect file size data
-    let size_data: Vec<(&str, f64)> = backends
+    let size_bytes: Vec<(&str, f64)> = backends
         .iter()
         .filter_map(|backend| {
-            by_backend.get(backend).and_then(|results| {
-                results
-                    .first()
-                    .map(|r| (*backend, r.file_size as f64 / 1_048_576.0))
-            })
+            by_backend
+                .get(backend)
+                .and_then(|results| results.first().map(|r| (*backend, r.file_size as f64)))
         })
         .collect();
+    let unit = ByteUnit::for_max(size_bytes.iter().map(|(_, b)| *b).fold(0.0, f64::max));
+    let size_data: Vec<(&str, f64)> = size_bytes
+        .iter()
+        .map(|(backend, bytes)| (*backend, unit.scale(*bytes)))
+        .collect();
 
     if size_data.is_empty() {
         root.present()?;
@@ -853,18 +1389,19 @@ fn generate_file_size_chart(results: &AggregateResults, output_dir: &Path) -> Re
         .map(|(_, size)| *size)
         .fold(0.0_f64, |a, b| a.max(b))
         * 1.3;
+    let (_, max_size) = sanitize_range(0.0, max_size, false);
 
     let mut chart = ChartBuilder::on(&root)
         .caption(
             "Index File Size by Backend",
-            ("sans-serif", TITLE_FONT_SIZE),
+            ("sans-serif", layout.font(TITLE_FONT_SIZE)),
         )
-        .margin(20)
+        .margin(layout.px(20))
         // Give the x-axis title ("Backend") more breathing room from the axis line.
-        .margin_bottom(DEFAULT_MARGIN_BOTTOM)
-        .x_label_area_size(DEFAULT_X_LABEL_AREA_SIZE)
-        .y_label_area_size(90)
-        .build_cartesian_2d(-0.5..(num_backends as f64 - 0.5), 0.0..max_size.max(1.0))?;
+        .margin_bottom(layout.px(DEFAULT_MARGIN_BOTTOM))
+        .x_label_area_size(layout.px(DEFAULT_X_LABEL_AREA_SIZE))
+        .y_label_area_size(layout.px(90))
+        .build_cartesian_2d(-0.5..(num_backends as f64 - 0.5), 0.0..max_size)?;
 
     chart
         .configure_mesh()
@@ -878,32 +1415,574 @@ fn generate_file_size_chart(results: &AggregateResults, output_dir: &Path) -> Re
                 String::new()
             }
         })
-        .y_desc("File Size (MB)")
+        .y_desc(format!("File Size ({})", unit.name))
         .x_desc("Backend")
-        .label_style(("sans-serif", TICK_LABEL_FONT_SIZE))
-        .axis_desc_style(("sans-serif", AXIS_LABEL_FONT_SIZE))
+        .label_style(("sans-serif", layout.font(TICK_LABEL_FONT_SIZE)))
+        .axis_desc_style(("sans-serif", layout.font(AXIS_LABEL_FONT_SIZE)))
         .draw()?;
 
     let bar_width = 0.6;
 
     for (idx, backend) in backends.iter().enumerate() {
-        let color = get_backend_color(backend);
+        let color = backend_color(indices, backend);
 
-        if let Some((_, size_mb)) = size_data.iter().find(|(b, _)| b == backend) {
+        if let Some((_, size)) = size_data.iter().find(|(b, _)| b == backend) {
             let x_center = idx as f64;
             let x_left = x_center - bar_width / 2.0;
             let x_right = x_center + bar_width / 2.0;
 
             chart.draw_series(std::iter::once(Rectangle::new(
-                [(x_left, 0.0), (x_right, *size_mb)],
+                [(x_left, 0.0), (x_right, *size)],
                 color.filled(),
             )))?;
 
             // Add value label on top of bar
             chart.draw_series(std::iter::once(Text::new(
-                format!("{:.1} MB", size_mb),
-                (x_center, *size_mb + max_size * 0.03),
-                ("sans-serif", DATA_LABEL_FONT_SIZE + 2)
+                unit.format(*size),
+                (x_center, *size + max_size * 0.03),
+                ("sans-serif", layout.font(DATA_LABEL_FONT_SIZE + 2))
+                    .into_font()
+                    .color(&BLACK)
+                    .pos(Pos::new(HPos::Center, VPos::Bottom)),
+            )))?;
+        }
+    }
+
+    root.present()?;
+    Ok(())
+}
+
+/// Generate bar chart of the value compression ratio recorded at build time
+pub fn generate_compression_ratio_chart(
+    stats: &BuildStats,
+    indices: &BackendIndices,
+    output_dir: &Path,
+    options: &ChartOptions,
+) -> Result<()> {
+    std::fs::create_dir_all(output_dir).context("Failed to create output directory")?;
+    render_chart(
+        options,
+        output_dir,
+        "compression_ratio",
+        (800, 500),
+        |area, layout| match area {
+            ChartArea::Svg(root) => draw_compression_ratio(root, layout, stats, indices),
+            ChartArea::Png(root) => draw_compression_ratio(root, layout, stats, indices),
+        },
+    )
+}
+
+fn draw_compression_ratio<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    layout: &ChartLayout,
+    stats: &BuildStats,
+    indices: &BackendIndices,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let mut backends: Vec<&str> = stats.backends.keys().map(|b| b.as_str()).collect();
+    backends.sort_by_key(|b| backend_index(indices, b));
+    let num_backends = backends.len();
+
+    if backends.is_empty() {
+        root.present()?;
+        return Ok(());
+    }
+
+    let max_ratio = stats
+        .backends
+        .values()
+        .map(|s| s.compression_ratio)
+        .fold(1.0_f64, |a, b| a.max(b))
+        * 1.3;
+    let (_, max_ratio) = sanitize_range(0.0, max_ratio, false);
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(
+            "Compression Ratio by Backend",
+            ("sans-serif", layout.font(TITLE_FONT_SIZE)),
+        )
+        .margin(layout.px(20))
+        .margin_bottom(layout.px(DEFAULT_MARGIN_BOTTOM))
+        .x_label_area_size(layout.px(DEFAULT_X_LABEL_AREA_SIZE))
+        .y_label_area_size(layout.px(90))
+        .build_cartesian_2d(-0.5..(num_backends as f64 - 0.5), 0.0..max_ratio)?;
+
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_labels(num_backends)
+        .x_label_formatter(&|x| {
+            let idx = x.round() as usize;
+            if idx < num_backends && (x - idx as f64).abs() < 0.3 {
+                backends.get(idx).map(|s| s.to_string()).unwrap_or_default()
+            } else {
+                String::new()
+            }
+        })
+        .y_desc("Uncompressed / Stored")
+        .x_desc("Backend")
+        .label_style(("sans-serif", layout.font(TICK_LABEL_FONT_SIZE)))
+        .axis_desc_style(("sans-serif", layout.font(AXIS_LABEL_FONT_SIZE)))
+        .draw()?;
+
+    let bar_width = 0.6;
+
+    for (idx, backend) in backends.iter().enumerate() {
+        let color = backend_color(indices, backend);
+        let ratio = stats.backends[*backend].compression_ratio;
+        let x_center = idx as f64;
+        let x_left = x_center - bar_width / 2.0;
+        let x_right = x_center + bar_width / 2.0;
+
+        chart.draw_series(std::iter::once(Rectangle::new(
+            [(x_left, 0.0), (x_right, ratio)],
+            color.filled(),
+        )))?;
+
+        chart.draw_series(std::iter::once(Text::new(
+            format!("{:.2}x", ratio),
+            (x_center, ratio + max_ratio * 0.03),
+            ("sans-serif", layout.font(DATA_LABEL_FONT_SIZE + 2))
+                .into_font()
+                .color(&BLACK)
+                .pos(Pos::new(HPos::Center, VPos::Bottom)),
+        )))?;
+    }
+
+    root.present()?;
+    Ok(())
+}
+
+/// Generate bar chart of each backend's index build time, in milliseconds or,
+/// once any build takes 10s or more, seconds
+pub fn generate_build_time_chart(
+    times: &BuildTimes,
+    indices: &BackendIndices,
+    output_dir: &Path,
+    options: &ChartOptions,
+) -> Result<()> {
+    std::fs::create_dir_all(output_dir).context("Failed to create output directory")?;
+    render_chart(
+        options,
+        output_dir,
+        "build_time",
+        (800, 500),
+        |area, layout| match area {
+            ChartArea::Svg(root) => draw_build_time(root, layout, times, indices),
+            ChartArea::Png(root) => draw_build_time(root, layout, times, indices),
+        },
+    )
+}
+
+fn draw_build_time<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    layout: &ChartLayout,
+    times: &BuildTimes,
+    indices: &BackendIndices,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let mut backends: Vec<&str> = times.backends.keys().map(|b| b.as_str()).collect();
+    backends.sort_by_key(|b| backend_index(indices, b));
+    let num_backends = backends.len();
+
+    if backends.is_empty() {
+        root.present()?;
+        return Ok(());
+    }
+
+    let longest_ms = times.backends.values().fold(0.0_f64, |a, &b| a.max(b));
+    let (unit, divisor) = if longest_ms >= 10_000.0 {
+        ("s", 1000.0)
+    } else {
+        ("ms", 1.0)
+    };
+    let (_, max_time) = sanitize_range(0.0, longest_ms / divisor * 1.2, false);
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(
+            "Index Build Time by Backend",
+            ("sans-serif", layout.font(TITLE_FONT_SIZE)),
+        )
+        .margin(layout.px(20))
+        .margin_bottom(layout.px(DEFAULT_MARGIN_BOTTOM))
+        .x_label_area_size(layout.px(DEFAULT_X_LABEL_AREA_SIZE))
+        .y_label_area_size(layout.px(90))
+        .build_cartesian_2d(-0.5..(num_backends as f64 - 0.5), 0.0..max_time)?;
+
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_labels(num_backends)
+        .x_label_formatter(&|x| {
+            let idx = x.round() as usize;
+            if idx < num_backends && (x - idx as f64).abs() < 0.3 {
+                backends.get(idx).map(|s| s.to_string()).unwrap_or_default()
+            } else {
+                String::new()
+            }
+        })
+        .y_desc(format!("Build time ({})", unit))
+        .x_desc("Backend")
+        .label_style(("sans-serif", layout.font(TICK_LABEL_FONT_SIZE)))
+        .axis_desc_style(("sans-serif", layout.font(AXIS_LABEL_FONT_SIZE)))
+        .draw()?;
+
+    let bar_width = 0.6;
+
+    for (idx, backend) in backends.iter().enumerate() {
+        let color = backend_color(indices, backend);
+        let time = times.backends[*backend] / divisor;
+        let x_center = idx as f64;
+        let x_left = x_center - bar_width / 2.0;
+        let x_right = x_center + bar_width / 2.0;
+
+        chart.draw_series(std::iter::once(Rectangle::new(
+            [(x_left, 0.0), (x_right, time)],
+            color.filled(),
+        )))?;
+
+        chart.draw_series(std::iter::once(Text::new(
+            format!("{:.1} {}", time, unit),
+            (x_center, time + max_time * 0.03),
+            ("sans-serif", layout.font(DATA_LABEL_FONT_SIZE + 2))
+                .into_font()
+                .color(&BLACK)
+                .pos(Pos::new(HPos::Center, VPos::Bottom)),
+        )))?;
+    }
+
+    root.present()?;
+    Ok(())
+}
+
+/// Generate bar chart of Zip file size per compression setting, labelled with
+/// each setting's median lookup latency
+pub fn generate_compression_comparison_chart(
+    results: &[CompressionResult],
+    indices: &BackendIndices,
+    output_dir: &Path,
+    options: &ChartOptions,
+) -> Result<()> {
+    std::fs::create_dir_all(output_dir).context("Failed to create output directory")?;
+    render_chart(
+        options,
+        output_dir,
+        "compression_comparison",
+        (800, 500),
+        |area, layout| match area {
+            ChartArea::Svg(root) => draw_compression_comparison(root, layout, results, indices),
+            ChartArea::Png(root) => draw_compression_comparison(root, layout, results, indices),
+        },
+    )
+}
+
+fn draw_compression_comparison<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    layout: &ChartLayout,
+    results: &[CompressionResult],
+    indices: &BackendIndices,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    if results.is_empty() {
+        root.present()?;
+        return Ok(());
+    }
+
+    let num_settings = results.len();
+    let max_bytes = results.iter().map(|r| r.file_size).max().unwrap_or(0);
+    let unit = ByteUnit::for_max(max_bytes as f64);
+    let (_, max_size) = sanitize_range(0.0, unit.scale(max_bytes as f64) * 1.3, false);
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(
+            "Zip File Size by Compression",
+            ("sans-serif", layout.font(TITLE_FONT_SIZE)),
+        )
+        .margin(layout.px(20))
+        .margin_bottom(layout.px(DEFAULT_MARGIN_BOTTOM))
+        .x_label_area_size(layout.px(DEFAULT_X_LABEL_AREA_SIZE))
+        .y_label_area_size(layout.px(90))
+        .build_cartesian_2d(-0.5..(num_settings as f64 - 0.5), 0.0..max_size)?;
+
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_labels(num_settings)
+        .x_label_formatter(&|x| {
+            let idx = x.round() as usize;
+            if idx < num_settings && (x - idx as f64).abs() < 0.3 {
+                results
+                    .get(idx)
+                    .map(|r| r.setting.clone())
+                    .unwrap_or_default()
+            } else {
+                String::new()
+            }
+        })
+        .y_desc(format!("File Size ({})", unit.name))
+        .x_desc("Compression")
+        .label_style(("sans-serif", layout.font(TICK_LABEL_FONT_SIZE)))
+        .axis_desc_style(("sans-serif", layout.font(AXIS_LABEL_FONT_SIZE)))
+        .draw()?;
+
+    let bar_width = 0.6;
+    let color = backend_color(indices, "Zip");
+
+    for (idx, result) in results.iter().enumerate() {
+        let size = unit.scale(result.file_size as f64);
+        let x_center = idx as f64;
+
+        chart.draw_series(std::iter::once(Rectangle::new(
+            [
+                (x_center - bar_width / 2.0, 0.0),
+                (x_center + bar_width / 2.0, size),
+            ],
+            color.filled(),
+        )))?;
+
+        chart.draw_series(std::iter::once(Text::new(
+            format!("p50 {:.1?}", result.p50),
+            (x_center, size + max_size * 0.03),
+            ("sans-serif", layout.font(DATA_LABEL_FONT_SIZE + 2))
+                .into_font()
+                .color(&BLACK)
+                .pos(Pos::new(HPos::Center, VPos::Bottom)),
+        )))?;
+    }
+
+    root.present()?;
+    Ok(())
+}
+
+/// Generate line chart of process memory sampled over the whole benchmark run
+pub fn generate_memory_timeline_chart(
+    samples: &[MemorySample],
+    output_dir: &Path,
+    options: &ChartOptions,
+) -> Result<()> {
+    std::fs::create_dir_all(output_dir).context("Failed to create output directory")?;
+    render_chart(
+        options,
+        output_dir,
+        "memory_timeline",
+        (1000, 500),
+        |area, layout| match area {
+            ChartArea::Svg(root) => draw_memory_timeline(root, layout, samples),
+            ChartArea::Png(root) => draw_memory_timeline(root, layout, samples),
+        },
+    )
+}
+
+fn draw_memory_timeline<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    layout: &ChartLayout,
+    samples: &[MemorySample],
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    if samples.is_empty() {
+        root.present()?;
+        return Ok(());
+    }
+
+    let unit = ByteUnit::for_max(
+        samples
+            .iter()
+            .map(|s| s.stats.physical_mem as f64)
+            .fold(0.0, f64::max),
+    );
+    let points: Vec<(f64, f64)> = samples
+        .iter()
+        .map(|s| {
+            (
+                s.elapsed.as_secs_f64(),
+                unit.scale(s.stats.physical_mem as f64),
+            )
+        })
+        .collect();
+    let (_, max_secs) = sanitize_range(0.0, points.last().map(|(t, _)| *t).unwrap_or(0.0), false);
+    let max_memory = points.iter().map(|(_, mb)| *mb).fold(0.0_f64, f64::max) * 1.3;
+    let (_, max_memory) = sanitize_range(0.0, max_memory, false);
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(
+            "Memory Over Time",
+            ("sans-serif", layout.font(TITLE_FONT_SIZE)),
+        )
+        .margin(layout.px(20))
+        .margin_bottom(layout.px(DEFAULT_MARGIN_BOTTOM))
+        .x_label_area_size(layout.px(DEFAULT_X_LABEL_AREA_SIZE))
+        .y_label_area_size(layout.px(90))
+        .build_cartesian_2d(0.0..max_secs, 0.0..max_memory)?;
+
+    chart
+        .configure_mesh()
+        .x_label_formatter(&|x| format!("{:.1}s", x))
+        .y_desc(format!("Physical Memory ({})", unit.name))
+        .x_desc("Elapsed")
+        .label_style(("sans-serif", layout.font(TICK_LABEL_FONT_SIZE)))
+        .axis_desc_style(("sans-serif", layout.font(AXIS_LABEL_FONT_SIZE)))
+        .draw()?;
+
+    chart.draw_series(LineSeries::new(
+        points,
+        COLORS[0].stroke_width(layout.px(3).max(1)),
+    ))?;
+
+    root.present()?;
+    Ok(())
+}
+
+/// Generate stacked bar chart splitting file size into value payload and index overhead
+fn generate_index_overhead_chart(
+    results: &AggregateResults,
+    indices: &BackendIndices,
+    output_dir: &Path,
+    options: &ChartOptions,
+) -> Result<()> {
+    render_chart(
+        options,
+        output_dir,
+        "index_overhead",
+        (800, 500),
+        |area, layout| match area {
+            ChartArea::Svg(root) => draw_index_overhead(root, layout, results, indices),
+            ChartArea::Png(root) => draw_index_overhead(root, layout, results, indices),
+        },
+    )
+}
+
+fn draw_index_overhead<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    layout: &ChartLayout,
+    results: &AggregateResults,
+    indices: &BackendIndices,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let by_backend = results.by_backend();
+    let mut backends: Vec<&str> = by_backend
+        .iter()
+        .filter(|(_, rs)| rs.iter().any(|r| r.index_bytes().is_some()))
+        .map(|(b, _)| *b)
+        .collect();
+    backends.sort_by_key(|b| backend_index(indices, b));
+    let num_backends = backends.len();
+
+    // (backend, payload bytes, index bytes)
+    let split_bytes: Vec<(&str, f64, f64)> = backends
+        .iter()
+        .filter_map(|backend| {
+            by_backend.get(backend).and_then(|results| {
+                results.first().and_then(|r| {
+                    let payload = r.payload_bytes? as f64;
+                    let index = r.index_bytes()? as f64;
+                    Some((*backend, payload, index))
+                })
+            })
+        })
+        .collect();
+    let unit = ByteUnit::for_max(
+        split_bytes
+            .iter()
+            .map(|(_, payload, index)| payload + index)
+            .fold(0.0, f64::max),
+    );
+    let split_data: Vec<(&str, f64, f64)> = split_bytes
+        .iter()
+        .map(|(backend, payload, index)| (*backend, unit.scale(*payload), unit.scale(*index)))
+        .collect();
+
+    if split_data.is_empty() {
+        root.present()?;
+        return Ok(());
+    }
+
+    let max_size = split_data
+        .iter()
+        .map(|(_, payload, index)| payload + index)
+        .fold(0.0_f64, |a, b| a.max(b))
+        * 1.3;
+    let (_, max_size) = sanitize_range(0.0, max_size, false);
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(
+            "Index Overhead vs Payload",
+            ("sans-serif", layout.font(TITLE_FONT_SIZE)),
+        )
+        .margin(layout.px(20))
+        .margin_bottom(layout.px(DEFAULT_MARGIN_BOTTOM))
+        .x_label_area_size(layout.px(DEFAULT_X_LABEL_AREA_SIZE))
+        .y_label_area_size(layout.px(90))
+        .build_cartesian_2d(-0.5..(num_backends as f64 - 0.5), 0.0..max_size)?;
+
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_labels(num_backends)
+        .x_label_formatter(&|x| {
+            let idx = x.round() as usize;
+            if idx < num_backends && (x - idx as f64).abs() < 0.3 {
+                backends.get(idx).map(|s| s.to_string()).unwrap_or_default()
+            } else {
+                String::new()
+            }
+        })
+        .y_desc(format!("Size ({})", unit.name))
+        .x_desc("Backend")
+        .label_style(("sans-serif", layout.font(TICK_LABEL_FONT_SIZE)))
+        .axis_desc_style(("sans-serif", layout.font(AXIS_LABEL_FONT_SIZE)))
+        .draw()?;
+
+    let payload_color = RGBColor(160, 160, 160);
+    let bar_width = 0.6;
+
+    for (idx, backend) in backends.iter().enumerate() {
+        let color = backend_color(indices, backend);
+
+        if let Some((_, payload, index)) = split_data.iter().find(|(b, _, _)| b == backend) {
+            let x_center = idx as f64;
+            let x_left = x_center - bar_width / 2.0;
+            let x_right = x_center + bar_width / 2.0;
+            let total = payload + index;
+
+            chart.draw_series(std::iter::once(Rectangle::new(
+                [(x_left, 0.0), (x_right, *payload)],
+                payload_color.filled(),
+            )))?;
+            chart.draw_series(std::iter::once(Rectangle::new(
+                [(x_left, *payload), (x_right, total)],
+                color.filled(),
+            )))?;
+
+            // Label the index share on top of the stack
+            let index_pct = if total > 0.0 {
+                index * 100.0 / total
+            } else {
+                0.0
+            };
+            chart.draw_series(std::iter::once(Text::new(
+                format!("{} index ({:.1}%)", unit.format(*index), index_pct),
+                (x_center, total + max_size * 0.03),
+                ("sans-serif", layout.font(DATA_LABEL_FONT_SIZE))
                     .into_font()
                     .color(&BLACK)
                     .pos(Pos::new(HPos::Center, VPos::Bottom)),
@@ -911,7 +1990,520 @@ fn generate_file_size_chart(results: &AggregateResults, output_dir: &Path) -> Re
         }
     }
 
+    chart
+        .draw_series(std::iter::once(Circle::new(
+            (0.0, 0.0),
+            0,
+            payload_color.filled(),
+        )))?
+        .label("Payload (values)")
+        .legend(move |(x, y)| {
+            Rectangle::new([(x, y - 5), (x + 20, y + 5)], payload_color.filled())
+        });
+
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(WHITE.mix(0.85))
+        .border_style(BLACK)
+        .label_font(("sans-serif", layout.font(LEGEND_FONT_SIZE)))
+        .draw()?;
+
     root.present()?;
+    Ok(())
+}
+
+/// Write `index.html` to `output_dir`: every chart image already there (SVG
+/// preferred when a chart was also written as PNG) followed by a table of
+/// file size, physical memory and P50/P99 per backend and blob size. Call it
+/// after the charts are generated.
+pub fn generate_dashboard(results: &AggregateResults, output_dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(output_dir).context("Failed to create output directory")?;
+
+    let mut images: Vec<String> = std::fs::read_dir(output_dir)
+        .with_context(|| format!("Failed to read {}", output_dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".svg") || name.ends_with(".png"))
+        .collect();
+    images.sort();
+    let svg_stems: std::collections::HashSet<String> = images
+        .iter()
+        .filter_map(|name| name.strip_suffix(".svg").map(str::to_string))
+        .collect();
+    images.retain(|name| {
+        !name
+            .strip_suffix(".png")
+            .is_some_and(|stem| svg_stems.contains(stem))
+    });
+
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Benchmark Dashboard</title>\n<style>\n\
+         body { font-family: sans-serif; margin: 2em; }\n\
+         table { border-collapse: collapse; margin-bottom: 2em; }\n\
+         th, td { border: 1px solid #ccc; padding: 4px 10px; text-align: right; }\n\
+         th:first-child, td:first-child { text-align: left; }\n\
+         img { display: block; max-width: 100%; margin-bottom: 2em; }\n\
+         </style>\n</head>\n<body>\n<h1>Benchmark Dashboard</h1>\n",
+    );
+
+    html.push_str("<h2>Summary</h2>\n<table>\n");
+    html.push_str(
+        "<tr><th>Backend</th><th>Size</th><th>File size</th>\
+         <th>Memory (physical)</th><th>P50</th><th>P99</th></tr>\n",
+    );
+    let by_backend = results.by_backend();
+    let mut backends: Vec<&str> = by_backend.keys().copied().collect();
+    backends.sort_unstable();
+    for backend in backends {
+        let mut rows = by_backend[backend].clone();
+        rows.sort_by_key(|r| BlobSize::all().iter().position(|&s| s == r.blob_size));
+        for r in rows {
+            let file_size = r.file_size as f64;
+            let memory = r.memory_stats.physical_mem as f64;
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(backend),
+                r.blob_size.name(),
+                ByteUnit::for_max(file_size).format(ByteUnit::for_max(file_size).scale(file_size)),
+                ByteUnit::for_max(memory).format(ByteUnit::for_max(memory).scale(memory)),
+                format_latency(r.p50().as_nanos() as f64 / 1000.0),
+                format_latency(r.p99().as_nanos() as f64 / 1000.0),
+            ));
+        }
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Charts</h2>\n");
+    for image in &images {
+        let name = escape_html(image);
+        html.push_str(&format!("<img src=\"{}\" alt=\"{}\">\n", name, name));
+    }
+    html.push_str("</body>\n</html>\n");
+
+    let path = output_dir.join("index.html");
+    std::fs::write(&path, html).with_context(|| format!("Failed to write {}", path.display()))?;
     println!("Generated: {}", path.display());
     Ok(())
 }
+
+/// Escape text for an HTML element body or quoted attribute
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_stats::BackendBuildStats;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_chart_uses_configured_dimensions() {
+        let dir = TempDir::new().unwrap();
+        let mut stats = BuildStats::default();
+        stats.backends.insert(
+            "Zip".to_string(),
+            BackendBuildStats {
+                compression_ratio: 1.0,
+            },
+        );
+        let options = ChartOptions {
+            width: Some(400),
+            height: Some(250),
+            format: ChartFormat::Both,
+            scale: Some(2.0),
+            ..Default::default()
+        };
+
+        generate_compression_ratio_chart(&stats, &BackendIndices::new(), dir.path(), &options)
+            .unwrap();
+
+        let svg = std::fs::read_to_string(dir.path().join("compression_ratio.svg")).unwrap();
+        assert!(svg.contains(r#"width="400" height="250""#));
+        assert!(svg.contains(r#"viewBox="0 0 400 250""#));
+
+        // The PNG's IHDR chunk holds its big-endian width and height
+        let png = std::fs::read(dir.path().join("compression_ratio.png")).unwrap();
+        let dimension = |at: usize| u32::from_be_bytes(png[at..at + 4].try_into().unwrap());
+        assert_eq!((dimension(16), dimension(20)), (800, 500));
+    }
+
+    #[test]
+    fn test_build_time_chart_switches_to_seconds() {
+        let dir = TempDir::new().unwrap();
+        let mut times = BuildTimes::default();
+        times.backends.insert("Zip".to_string(), 250.0);
+        times.backends.insert("B+tree".to_string(), 800.0);
+
+        generate_build_time_chart(
+            &times,
+            &backend_indices(times.backends.keys().map(String::as_str)),
+            dir.path(),
+            &ChartOptions::default(),
+        )
+        .unwrap();
+        let svg = std::fs::read_to_string(dir.path().join("build_time.svg")).unwrap();
+        assert!(svg.contains("800.0 ms"), "{}", svg);
+        assert!(svg.contains("Build time (ms)"));
+
+        times
+            .backends
+            .insert("SQLite (ROWID)".to_string(), 42_000.0);
+        generate_build_time_chart(
+            &times,
+            &backend_indices(times.backends.keys().map(String::as_str)),
+            dir.path(),
+            &ChartOptions::default(),
+        )
+        .unwrap();
+        let svg = std::fs::read_to_string(dir.path().join("build_time.svg")).unwrap();
+        assert!(svg.contains("42.0 s"));
+        assert!(svg.contains("0.8 s"));
+    }
+
+    #[test]
+    fn test_charts_written_in_requested_formats() {
+        let results: Vec<BenchmarkResult> = BlobSize::all()
+            .iter()
+            .map(|&blob_size| BenchmarkResult {
+                backend_name: "Hash DAT".to_string(),
+                blob_size,
+                latencies_ns: vec![1_000, 2_000, 3_000].into(),
+                file_size: 4_096,
+                lookups: 3,
+                ..Default::default()
+            })
+            .collect();
+        let aggregate = AggregateResults::new(results);
+        let charts = [
+            "latency_by_size",
+            "throughput",
+            "percentiles",
+            "percentiles_1mb_linear",
+            "p90_latency",
+            "memory_usage",
+            "file_sizes",
+        ];
+
+        for (format, extensions) in [
+            (ChartFormat::Svg, &["svg"][..]),
+            (ChartFormat::Png, &["png"][..]),
+            (ChartFormat::Both, &["svg", "png"][..]),
+        ] {
+            let dir = TempDir::new().unwrap();
+            let options = ChartOptions {
+                format,
+                ..Default::default()
+            };
+            generate_charts(
+                &aggregate,
+                &result_backend_indices(&aggregate),
+                dir.path(),
+                &options,
+            )
+            .unwrap();
+
+            let mut written: Vec<String> = std::fs::read_dir(dir.path())
+                .unwrap()
+                .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+                .collect();
+            written.sort();
+            let mut expected: Vec<String> = charts
+                .iter()
+                .flat_map(|chart| {
+                    extensions
+                        .iter()
+                        .map(move |ext| format!("{}.{}", chart, ext))
+                })
+                .collect();
+            expected.sort();
+            assert_eq!(written, expected, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_sanitize_range_is_always_valid() {
+        for log_scale in [false, true] {
+            for (min, max) in [
+                (f64::MAX, 0.0),
+                (0.0, 0.0),
+                (f64::NAN, f64::NAN),
+                (5.0, f64::INFINITY),
+                (3.0, 3.0),
+                (-1.0, 2.0),
+            ] {
+                let (lo, hi) = sanitize_range(min, max, log_scale);
+                assert!(
+                    lo.is_finite() && hi.is_finite() && lo < hi,
+                    "{}..{}",
+                    lo,
+                    hi
+                );
+                if log_scale {
+                    assert!(lo > 0.0);
+                }
+            }
+        }
+        assert_eq!(sanitize_range(2.0, 50.0, true), (2.0, 50.0));
+    }
+
+    #[test]
+    fn test_charts_render_all_zero_latencies() {
+        let dir = TempDir::new().unwrap();
+        let results: Vec<BenchmarkResult> = BlobSize::all()
+            .iter()
+            .map(|&blob_size| BenchmarkResult {
+                backend_name: "Zip".to_string(),
+                blob_size,
+                latencies_ns: vec![0; 10].into(),
+                payload_bytes: Some(0),
+                lookups: 10,
+                ..Default::default()
+            })
+            .collect();
+
+        let aggregate = AggregateResults::new(results);
+        generate_charts(
+            &aggregate,
+            &result_backend_indices(&aggregate),
+            dir.path(),
+            &ChartOptions::default(),
+        )
+        .unwrap();
+        for chart in ["latency_by_size.svg", "throughput.svg", "p90_latency.svg"] {
+            let svg = std::fs::read_to_string(dir.path().join(chart)).unwrap();
+            assert!(!svg.contains("NaN"), "{} has NaN coordinates", chart);
+        }
+    }
+
+    #[test]
+    fn test_percentile_charts_draw_requested_percentiles() {
+        let dir = TempDir::new().unwrap();
+        let results: Vec<BenchmarkResult> = [BlobSize::Medium, BlobSize::Huge]
+            .iter()
+            .map(|&blob_size| BenchmarkResult {
+                backend_name: "Hash DAT".to_string(),
+                blob_size,
+                latencies_ns: (1..=1_000).map(|i| i * 1_000).collect::<Vec<u64>>().into(),
+                lookups: 1_000,
+                ..Default::default()
+            })
+            .collect();
+        let options = ChartOptions {
+            percentiles: vec![50.0, 90.0, 99.0, 99.9],
+            ..Default::default()
+        };
+
+        let aggregate = AggregateResults::new(results);
+        generate_charts(
+            &aggregate,
+            &result_backend_indices(&aggregate),
+            dir.path(),
+            &options,
+        )
+        .unwrap();
+        for chart in ["percentiles.svg", "percentiles_1mb_linear.svg"] {
+            let svg = std::fs::read_to_string(dir.path().join(chart)).unwrap();
+            for label in ["P50", "P90", "P99", "P99.9"] {
+                assert!(svg.contains(label), "{} is missing {}", chart, label);
+            }
+            assert!(!svg.contains("P95"), "{} draws an unrequested P95", chart);
+        }
+
+        // The default three keep their green/yellow/red bars
+        assert_eq!(percentile_color(0, 3), RGBColor(100, 180, 100));
+        assert_eq!(percentile_color(1, 3), RGBColor(200, 180, 80));
+        assert_eq!(percentile_color(2, 3), RGBColor(200, 100, 100));
+    }
+
+    #[test]
+    fn test_fastest_per_size_picks_lowest_drawn_p50() {
+        let result = |backend: &str, blob_size, latency_ns: u64| BenchmarkResult {
+            backend_name: backend.to_string(),
+            blob_size,
+            latencies_ns: vec![latency_ns; 5].into(),
+            lookups: 5,
+            ..Default::default()
+        };
+        let results = vec![
+            result("Zip", BlobSize::Tiny, 40_000),
+            result("B+tree", BlobSize::Tiny, 3_000),
+            result("SQLite (ROWID)", BlobSize::Tiny, 9_000),
+            result("Zip", BlobSize::Large, 80_000),
+            result("B+tree", BlobSize::Large, 700_000),
+            // Tied with Zip; SQLite is drawn first so it keeps the star
+            result("SQLite (ROWID)", BlobSize::Large, 80_000),
+            // Sub-microsecond bars aren't drawn, so can't win
+            result("Zip", BlobSize::Huge, 500),
+            result("B+tree", BlobSize::Huge, 2_000_000),
+            // The in-memory floor is faster still but never starred
+            result(LATENCY_FLOOR_BACKEND, BlobSize::Tiny, 1_000),
+            result(LATENCY_FLOOR_BACKEND, BlobSize::Small, 1_000),
+        ];
+
+        let fastest = fastest_per_size(&results);
+        assert_eq!(fastest[&BlobSize::Tiny], "B+tree");
+        assert_eq!(fastest[&BlobSize::Large], "SQLite (ROWID)");
+        assert_eq!(fastest[&BlobSize::Huge], "B+tree");
+        assert!(!fastest.contains_key(&BlobSize::Small));
+    }
+
+    #[test]
+    fn test_latency_floor_is_the_memory_backend() {
+        use crate::backends::MemoryStore;
+        use crate::store::BlobStore;
+
+        assert_eq!(LATENCY_FLOOR_BACKEND, MemoryStore::backend_name());
+    }
+
+    #[test]
+    fn test_backend_indices_are_distinct_and_sorted() {
+        let names = [
+            "Zip",
+            "LMDB",
+            "Brand New Backend",
+            "B+tree",
+            "Another New Backend",
+        ];
+        let results: Vec<BenchmarkResult> = names
+            .iter()
+            .flat_map(|name| {
+                [BlobSize::Tiny, BlobSize::Huge].map(|blob_size| BenchmarkResult {
+                    backend_name: name.to_string(),
+                    blob_size,
+                    latencies_ns: vec![1_000; 5].into(),
+                    lookups: 5,
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let aggregate = AggregateResults::new(results);
+
+        let indices = result_backend_indices(&aggregate);
+        assert_eq!(indices.len(), 5);
+        assert_eq!(indices["Another New Backend"], 0);
+        assert_eq!(indices["B+tree"], 1);
+        assert_eq!(indices["Brand New Backend"], 2);
+        assert_eq!(indices["LMDB"], 3);
+        assert_eq!(indices["Zip"], 4);
+        let colors: std::collections::HashSet<_> = names
+            .iter()
+            .map(|name| {
+                let RGBColor(r, g, b) = backend_color(&indices, name);
+                (r, g, b)
+            })
+            .collect();
+        assert_eq!(colors.len(), 5);
+
+        // Past the palette's end colors cycle rather than collapsing to gray
+        let many: Vec<String> = (0..COLORS.len() + 1)
+            .map(|i| format!("b{:02}", i))
+            .collect();
+        let indices = backend_indices(many.iter().map(String::as_str));
+        assert_eq!(backend_color(&indices, "b10"), COLORS[0]);
+        assert_eq!(backend_color(&indices, "unknown"), RGBColor(128, 128, 128));
+    }
+
+    #[test]
+    fn test_dashboard_embeds_charts_and_backends() {
+        let names = ["Hash DAT", "SQLite (ROWID)", "B+tree"];
+        let results: Vec<BenchmarkResult> = names
+            .iter()
+            .map(|name| BenchmarkResult {
+                backend_name: name.to_string(),
+                blob_size: BlobSize::Small,
+                latencies_ns: vec![2_000; 10].into(),
+                file_size: 3 * 1_048_576,
+                lookups: 10,
+                ..Default::default()
+            })
+            .collect();
+        let aggregate = AggregateResults::new(results);
+        let dir = TempDir::new().unwrap();
+        let options = ChartOptions {
+            format: ChartFormat::Both,
+            ..Default::default()
+        };
+        generate_charts(
+            &aggregate,
+            &result_backend_indices(&aggregate),
+            dir.path(),
+            &options,
+        )
+        .unwrap();
+
+        generate_dashboard(&aggregate, dir.path()).unwrap();
+
+        let html = std::fs::read_to_string(dir.path().join("index.html")).unwrap();
+        for name in names {
+            assert!(
+                html.contains(&format!("<td>{}</td>", escape_html(name))),
+                "{}",
+                name
+            );
+        }
+        assert!(html.contains("<td>3.0 MB</td>"));
+        assert!(html.contains("<td>2µs</td>"));
+        assert!(html.contains(r#"<img src="latency_by_size.svg""#));
+        // PNG copies of charts that also have an SVG aren't embedded twice
+        assert!(!html.contains(".png"));
+    }
+
+    #[test]
+    fn test_byte_unit_follows_series_max() {
+        let kb = ByteUnit::for_max(2.0 * 1024.0);
+        assert_eq!(kb.name, "KB");
+        assert_eq!(kb.format(kb.scale(2048.0)), "2.0 KB");
+
+        let gb = ByteUnit::for_max(5.0 * 1_073_741_824.0);
+        assert_eq!(gb.name, "GB");
+        assert_eq!(gb.format(gb.scale(5.0 * 1_073_741_824.0)), "5.0 GB");
+
+        assert_eq!(ByteUnit::for_max(0.0).format(0.0), "0 B");
+    }
+
+    #[test]
+    fn test_layout_scales_fonts_with_size() {
+        let defaults = ChartOptions::default().layout((800, 500), ImageFormat::Svg);
+        assert_eq!(defaults.size, (800, 500));
+        assert_eq!(defaults.font(TITLE_FONT_SIZE), TITLE_FONT_SIZE);
+
+        let half = ChartOptions {
+            width: Some(400),
+            height: None,
+            ..Default::default()
+        }
+        .layout((800, 500), ImageFormat::Svg);
+        assert_eq!(half.size, (400, 500));
+        assert_eq!(half.font(TITLE_FONT_SIZE), TITLE_FONT_SIZE / 2);
+    }
+
+    #[test]
+    fn test_scale_multiplies_png_but_not_svg() {
+        let options = ChartOptions {
+            width: Some(400),
+            scale: Some(2.0),
+            ..Default::default()
+        };
+
+        let svg = options.layout((800, 500), ImageFormat::Svg);
+        assert_eq!(svg.size, (400, 500));
+        assert_eq!(svg.font(TITLE_FONT_SIZE), TITLE_FONT_SIZE / 2);
+
+        let png = options.layout((800, 500), ImageFormat::Png);
+        assert_eq!(png.size, (800, 1000));
+        assert_eq!(png.font(TITLE_FONT_SIZE), TITLE_FONT_SIZE);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::backends::{ZipCompression, ZipStore, ZipStoreBuilder};
+use crate::benchmark::{run_benchmark, BenchmarkConfig};
+use crate::data_gen::{BlobSize, Entry};
+use crate::store::{BlobStore, BlobStoreBuilder};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Build and lookup cost of the Zip backend at one compression setting
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressionResult {
+    /// Setting name, e.g. `deflate:9`
+    pub setting: String,
+    pub file_size: u64,
+    pub build_time: Duration,
+    /// Uncompressed / stored value bytes
+    pub compression_ratio: f64,
+    /// Median lookup latency across every blob size
+    pub p50: Duration,
+    pub mean: Duration,
+}
+
+/// Build a Zip index from the same `entries` once per setting (as
+/// `index_<setting>.zip` in `output_dir`) and benchmark lookups against each
+pub fn compare_zip_compression(
+    entries: &[Entry],
+    settings: &[ZipCompression],
+    output_dir: &Path,
+    config: &BenchmarkConfig,
+) -> Result<Vec<CompressionResult>> {
+    std::fs::create_dir_all(output_dir).context("Failed to create output directory")?;
+
+    let mut keys_by_size: HashMap<BlobSize, Vec<Vec<u8>>> = HashMap::new();
+    for entry in entries {
+        keys_by_size
+            .entry(entry.size_category)
+            .or_default()
+            .push(entry.key.clone());
+    }
+    let all_keys: Vec<Vec<u8>> = entries.iter().map(|e| e.key.clone()).collect();
+
+    let mut results = Vec::with_capacity(settings.len());
+    for setting in settings {
+        let name = setting.name();
+        println!("\nCompression {}...", name);
+        let path = output_dir.join(format!("index_{}.zip", name.replace(':', "_")));
+
+        let build_start = Instant::now();
+        let mut builder = ZipStoreBuilder::create(&path)?.with_compression(*setting);
+        for entry in entries {
+            builder.insert(&entry.key, &entry.value)?;
+        }
+        builder.finish()?;
+        let build_time = build_start.elapsed();
+        let file_size = std::fs::metadata(&path)
+            .context("Failed to read zip file size")?
+            .len();
+
+        let store = ZipStore::open(&path)?;
+        let size_results = run_benchmark(&store, &all_keys, &keys_by_size, config, file_size)?;
+        // Fold every size's samples into one distribution for the setting
+        let merged = size_results.into_iter().reduce(|mut merged, result| {
+            merged.latencies_ns.merge(result.latencies_ns);
+            merged
+        });
+
+        results.push(CompressionResult {
+            setting: name,
+            file_size,
+            build_time,
+            compression_ratio: store.compression_ratio()?,
+            p50: merged.as_ref().map(|r| r.p50()).unwrap_or_default(),
+            mean: merged.as_ref().map(|r| r.mean()).unwrap_or_default(),
+        });
+    }
+    Ok(results)
+}
+
+/// Print one row per compression setting
+pub fn print_compression_comparison(results: &[CompressionResult]) {
+    println!("\n{:=<80}", "");
+    println!("Zip Compression Comparison");
+    println!("{:=<80}", "");
+    println!(
+        "{:<12} {:>12} {:>8} {:>12} {:>12} {:>12}",
+        "Setting", "Size (MB)", "Ratio", "Build", "P50", "Mean"
+    );
+    for r in results {
+        let build = format!("{:.2?}", r.build_time);
+        let p50 = format!("{:.2?}", r.p50);
+        let mean = format!("{:.2?}", r.mean);
+        println!(
+            "{:<12} {:>12.2} {:>7.2}x {:>12} {:>12} {:>12}",
+            r.setting,
+            r.file_size as f64 / 1_048_576.0,
+            r.compression_ratio,
+            build,
+            p50,
+            mean
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_compression_settings_give_one_row_each() {
+        let dir = TempDir::new().unwrap();
+        // Repetitive values so compression visibly shrinks the file
+        let entries: Vec<Entry> = (0..20)
+            .map(|i| Entry {
+                key: format!("100B_{:08}", i).into_bytes(),
+                value: vec![b'a' + (i % 3) as u8; 2_000],
+                size_category: BlobSize::Tiny,
+            })
+            .collect();
+        let settings: Vec<ZipCompression> = ["stored", "deflate:9"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        let config = BenchmarkConfig {
+            num_lookups: 50,
+            warmup_iterations: 5,
+            ..Default::default()
+        };
+
+        let results = compare_zip_compression(&entries, &settings, dir.path(), &config).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].setting, "stored");
+        assert_eq!(results[1].setting, "deflate:9");
+        assert!(results[1].file_size < results[0].file_size);
+        assert!(results[1].compression_ratio > results[0].compression_ratio);
+        assert!(dir.path().join("index_deflate_9.zip").exists());
+    }
+}
@@ -1,8 +1,15 @@
-use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
 use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use rayon::prelude::*;
 use std::io::{self, Write};
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// RNG behind every seeded choice: generated keys and values, insert order and
+/// benchmark access order. ChaCha8's output is fixed by the algorithm, whereas
+/// `StdRng` may change between `rand` releases and silently alter what a seed means.
+pub type SeededRng = ChaCha8Rng;
 
 /// Blob size categories for benchmarking
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -40,6 +47,17 @@ impl BlobSize {
         }
     }
 
+    /// Position in `all()`
+    fn ordinal(&self) -> u64 {
+        match self {
+            BlobSize::Tiny => 0,
+            BlobSize::Small => 1,
+            BlobSize::Medium => 2,
+            BlobSize::Large => 3,
+            BlobSize::Huge => 4,
+        }
+    }
+
     pub fn name(&self) -> &'static str {
         match self {
             BlobSize::Tiny => "100B",
@@ -60,9 +78,33 @@ pub struct DataGenConfig {
     pub entries_override: std::collections::HashMap<BlobSize, usize>,
     /// Random seed for reproducibility
     pub seed: u64,
+    /// Share of each value (0.0-1.0) made of runs of a repeated pattern rather
+    /// than random bytes; 0.0 keeps values incompressible
+    pub compressibility: f64,
+    /// Relative spread of value lengths around the category's `byte_size()`
+    /// (0.2 = ±20%); 0.0 makes every value in a category the same length
+    pub size_jitter: f64,
+    /// Format of the generated keys
+    pub key_shape: KeyShape,
 }
 
 impl DataGenConfig {
+    /// `entries_per_size` entries of each size from `seed`, except at most 100
+    /// huge (1MB) ones, which are slow to generate; random values and keys
+    pub fn new(entries_per_size: usize, seed: u64) -> Self {
+        let mut entries_override = std::collections::HashMap::new();
+        entries_override.insert(BlobSize::Huge, entries_per_size.min(100));
+
+        Self {
+            entries_per_size,
+            entries_override,
+            seed,
+            compressibility: 0.0,
+            size_jitter: 0.0,
+            key_shape: KeyShape::default(),
+        }
+    }
+
     /// Get the number of entries for a specific size
     pub fn entries_for_size(&self, size: BlobSize) -> usize {
         self.entries_override
@@ -74,18 +116,43 @@ impl DataGenConfig {
 
 impl Default for DataGenConfig {
     fn default() -> Self {
-        let mut entries_override = std::collections::HashMap::new();
-        // Default to fewer huge entries since they're slow to generate
-        entries_override.insert(BlobSize::Huge, 100);
+        Self::new(1_000, 42)
+    }
+}
 
-        Self {
-            entries_per_size: 1_000,
-            entries_override,
-            seed: 42,
+/// Format of generated keys. Every shape is unique across size categories and
+/// fixed by seed + index; they differ in how clustered their sorted order is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum KeyShape {
+    /// `{size}_{index:08}_{random hex}`: size-clustered, random suffix
+    #[default]
+    Synthetic,
+    /// Random version 4 UUIDs in hyphenated form, with no clustering at all
+    Uuid,
+    /// Zero-padded integers, ascending in generation order
+    Sequential,
+    /// `/{size}/{xx}/{yy}/{index:08}` with random two-hex-digit directories,
+    /// clustered under a few shared prefixes
+    Path,
+}
+
+impl KeyShape {
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyShape::Synthetic => "synthetic",
+            KeyShape::Uuid => "uuid",
+            KeyShape::Sequential => "sequential",
+            KeyShape::Path => "path",
         }
     }
 }
 
+/// Value bytes `generate_streaming` generates ahead of the consumer at once
+const STREAM_CHUNK_BYTES: usize = 64 * 1_048_576;
+
+/// Length of the runs `generate_value` makes random or repetitive
+const COMPRESSIBLE_RUN_LEN: usize = 64;
+
 /// A generated key-value entry
 #[derive(Debug, Clone)]
 pub struct Entry {
@@ -94,6 +161,37 @@ pub struct Entry {
     pub size_category: BlobSize,
 }
 
+/// Order in which `build` inserts generated entries into each backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum InsertOrder {
+    /// Generation order: grouped by size category, then by index
+    #[default]
+    Natural,
+    /// Ascending key order
+    Sorted,
+    /// Seeded random permutation
+    Shuffled,
+}
+
+impl InsertOrder {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InsertOrder::Natural => "natural",
+            InsertOrder::Sorted => "sorted",
+            InsertOrder::Shuffled => "shuffled",
+        }
+    }
+
+    /// Reorder `entries` in place; `seed` only affects `Shuffled`
+    pub fn apply(&self, entries: &mut [Entry], seed: u64) {
+        match self {
+            InsertOrder::Natural => {}
+            InsertOrder::Sorted => entries.sort_unstable_by(|a, b| a.key.cmp(&b.key)),
+            InsertOrder::Shuffled => entries.shuffle(&mut SeededRng::seed_from_u64(seed)),
+        }
+    }
+}
+
 /// Progress information for data generation
 #[derive(Debug, Clone)]
 pub struct Progress {
@@ -125,37 +223,127 @@ impl Progress {
 /// Data generator for benchmarking
 pub struct DataGenerator {
     config: DataGenConfig,
+    /// Dedicated pool for generation; `None` uses rayon's global pool
+    pool: Option<rayon::ThreadPool>,
 }
 
 impl DataGenerator {
     pub fn new(config: DataGenConfig) -> Self {
-        Self { config }
+        Self { config, pool: None }
+    }
+
+    pub fn config(&self) -> &DataGenConfig {
+        &self.config
+    }
+
+    /// Generate on a dedicated pool of `threads` workers instead of every core.
+    /// Output is identical for any thread count since each entry depends only on its index.
+    pub fn with_threads(mut self, threads: usize) -> Result<Self, rayon::ThreadPoolBuildError> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("data-gen-{}", i))
+            .build()?;
+        self.pool = Some(pool);
+        Ok(self)
+    }
+
+    /// Run `op` on the generation pool, if one was configured
+    fn install<T: Send>(&self, op: impl FnOnce() -> T + Send) -> T {
+        match &self.pool {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
     }
 
     /// Generate a key for a given size category and index (deterministic based on seed + index)
-    fn generate_key(seed: u64, size: BlobSize, index: usize) -> Vec<u8> {
-        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(index as u64));
-        format!("{}_{:08}_{:016x}", size.name(), index, rng.gen::<u64>()).into_bytes()
+    fn generate_key(seed: u64, size: BlobSize, index: usize, shape: KeyShape) -> Vec<u8> {
+        let mut rng = SeededRng::seed_from_u64(seed.wrapping_add(index as u64));
+        match shape {
+            KeyShape::Synthetic => {
+                format!("{}_{:08}_{:016x}", size.name(), index, rng.gen::<u64>()).into_bytes()
+            }
+            KeyShape::Uuid => {
+                // The same index in each size category shares a seed, so give
+                // each category its own stream to keep the UUIDs apart
+                rng.set_stream(size.ordinal() + 1);
+                let mut bytes: [u8; 16] = rng.gen();
+                bytes[6] = (bytes[6] & 0x0f) | 0x40; // version 4
+                bytes[8] = (bytes[8] & 0x3f) | 0x80; // RFC 4122 variant
+                let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+                format!(
+                    "{}-{}-{}-{}-{}",
+                    &hex[..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..]
+                )
+                .into_bytes()
+            }
+            KeyShape::Sequential => {
+                format!("{:020}", size.ordinal() * 1_000_000_000_000 + index as u64).into_bytes()
+            }
+            KeyShape::Path => {
+                rng.set_stream(size.ordinal() + 1);
+                let (dir, subdir) = (rng.gen::<u8>(), rng.gen::<u8>());
+                format!("/{}/{:02x}/{:02x}/{:08}", size.name(), dir, subdir, index).into_bytes()
+            }
+        }
     }
 
-    /// Generate random blob data of the specified size (deterministic based on seed + index)
-    fn generate_value(seed: u64, size: BlobSize, index: usize) -> Vec<u8> {
+    /// Generate random blob data of the specified size (deterministic based on seed + index).
+    /// With `compressibility` above zero, each `COMPRESSIBLE_RUN_LEN`-byte run is
+    /// overwritten with a repeated 16-byte pattern with that probability. With
+    /// `size_jitter` above zero the length is drawn uniformly from
+    /// `byte_size() * (1 ± size_jitter)`. Values with both at 0.0 are unchanged
+    /// from before the options existed.
+    fn generate_value(
+        seed: u64,
+        size: BlobSize,
+        index: usize,
+        compressibility: f64,
+        size_jitter: f64,
+    ) -> Vec<u8> {
         // Use a different seed offset for value to avoid correlation with key
-        let mut rng = StdRng::seed_from_u64(
+        let mut rng = SeededRng::seed_from_u64(
             seed.wrapping_add(index as u64)
                 .wrapping_add(0x1234567890abcdef),
         );
-        let byte_size = size.byte_size();
+        let mut byte_size = size.byte_size();
+        if size_jitter > 0.0 {
+            let spread = (byte_size as f64 * size_jitter.min(1.0)).round() as usize;
+            byte_size = rng
+                .gen_range(byte_size.saturating_sub(spread)..=byte_size + spread)
+                .max(1);
+        }
         let mut data = vec![0u8; byte_size];
         rng.fill(&mut data[..]);
+        if compressibility > 0.0 {
+            let pattern: [u8; 16] = rng.gen();
+            let compressibility = compressibility.min(1.0);
+            for run in data.chunks_mut(COMPRESSIBLE_RUN_LEN) {
+                if rng.gen_bool(compressibility) {
+                    for (byte, &p) in run.iter_mut().zip(pattern.iter().cycle()) {
+                        *byte = p;
+                    }
+                }
+            }
+        }
         data
     }
 
     /// Generate a single entry (can be called in parallel)
-    fn generate_entry(seed: u64, size: BlobSize, index: usize) -> Entry {
+    fn generate_entry(config: &DataGenConfig, size: BlobSize, index: usize) -> Entry {
+        let seed = config.seed;
         Entry {
-            key: Self::generate_key(seed, size, index),
-            value: Self::generate_value(seed, size, index),
+            key: Self::generate_key(seed, size, index, config.key_shape),
+            value: Self::generate_value(
+                seed,
+                size,
+                index,
+                config.compressibility,
+                config.size_jitter,
+            ),
             size_category: size,
         }
     }
@@ -171,13 +359,15 @@ impl DataGenerator {
 
         for &size in BlobSize::all() {
             let count = self.config.entries_for_size(size);
-            let seed = self.config.seed;
+            let config = &self.config;
 
             // Generate entries for this size in parallel
-            let size_entries: Vec<Entry> = (0..count)
-                .into_par_iter()
-                .map(|i| Self::generate_entry(seed, size, i))
-                .collect();
+            let size_entries: Vec<Entry> = self.install(|| {
+                (0..count)
+                    .into_par_iter()
+                    .map(|i| Self::generate_entry(config, size, i))
+                    .collect()
+            });
 
             entries.extend(size_entries);
         }
@@ -209,7 +399,7 @@ impl DataGenerator {
 
         for &size in BlobSize::all() {
             let count = self.config.entries_for_size(size);
-            let seed = self.config.seed;
+            let config = &self.config;
 
             print!("  Generating {} blobs ({} each)... ", size.name(), count);
             let _ = io::stdout().flush();
@@ -219,22 +409,24 @@ impl DataGenerator {
             let total = count;
 
             // Generate entries in parallel
-            let entries: Vec<Entry> = (0..count)
-                .into_par_iter()
-                .map(|i| {
-                    let entry = Self::generate_entry(seed, size, i);
+            let entries: Vec<Entry> = self.install(|| {
+                (0..count)
+                    .into_par_iter()
+                    .map(|i| {
+                        let entry = Self::generate_entry(config, size, i);
 
-                    // Update progress counter
-                    let done = progress_counter.fetch_add(1, Ordering::Relaxed) + 1;
+                        // Update progress counter
+                        let done = progress_counter.fetch_add(1, Ordering::Relaxed) + 1;
 
-                    // Print progress every 10% for large batches
-                    if total >= 100 && done % (total / 10) == 0 {
-                        eprint!("{}%.. ", (done * 100) / total);
-                    }
+                        // Print progress every 10% for large batches
+                        if total >= 100 && done % (total / 10) == 0 {
+                            eprint!("{}%.. ", (done * 100) / total);
+                        }
 
-                    entry
-                })
-                .collect();
+                        entry
+                    })
+                    .collect()
+            });
 
             let size_bytes = count * size.byte_size();
             bytes_generated += size_bytes;
@@ -253,15 +445,49 @@ impl DataGenerator {
         all_entries
     }
 
+    /// Pass every entry to `f` in `generate_all` order without holding them all:
+    /// entries are generated in parallel chunks of about `STREAM_CHUNK_BYTES`
+    /// of values, and each chunk is dropped once `f` has seen it. Stops at the
+    /// first error `f` returns. Returns the time spent generating chunks, not
+    /// counting `f`, so a caller timing `f`'s work can leave it out.
+    pub fn generate_streaming(
+        &self,
+        mut f: impl FnMut(Entry) -> anyhow::Result<()>,
+    ) -> anyhow::Result<Duration> {
+        let config = &self.config;
+        let mut generating = Duration::ZERO;
+        for &size in BlobSize::all() {
+            let count = config.entries_for_size(size);
+            let chunk_len = (STREAM_CHUNK_BYTES / size.byte_size()).max(1);
+            for start in (0..count).step_by(chunk_len) {
+                let end = (start + chunk_len).min(count);
+                let started = Instant::now();
+                let chunk: Vec<Entry> = self.install(|| {
+                    (start..end)
+                        .into_par_iter()
+                        .map(|i| Self::generate_entry(config, size, i))
+                        .collect()
+                });
+                generating += started.elapsed();
+                for entry in chunk {
+                    f(entry)?;
+                }
+            }
+        }
+        Ok(generating)
+    }
+
     /// Generate entries for a specific size category (parallel)
     pub fn generate_for_size(&self, size: BlobSize) -> Vec<Entry> {
         let count = self.config.entries_for_size(size);
-        let seed = self.config.seed;
+        let config = &self.config;
 
-        (0..count)
-            .into_par_iter()
-            .map(|i| Self::generate_entry(seed, size, i))
-            .collect()
+        self.install(|| {
+            (0..count)
+                .into_par_iter()
+                .map(|i| Self::generate_entry(config, size, i))
+                .collect()
+        })
     }
 }
 
@@ -279,11 +505,7 @@ mod tests {
 
     #[test]
     fn test_data_generation() {
-        let config = DataGenConfig {
-            entries_per_size: 10,
-            entries_override: std::collections::HashMap::new(),
-            seed: 42,
-        };
+        let config = DataGenConfig::new(10, 42);
         let gen = DataGenerator::new(config);
         let entries = gen.generate_all();
 
@@ -301,13 +523,155 @@ mod tests {
         }
     }
 
+    /// Fails if the RNG or how it is used changes; historical results for a
+    /// seed are only comparable while these hold
     #[test]
-    fn test_reproducibility() {
+    fn test_seed_42_output_is_pinned() {
+        assert_eq!(
+            DataGenerator::generate_key(42, BlobSize::Tiny, 0, KeyShape::Synthetic),
+            b"100B_00000000_ae90bfb5395d5ba1".to_vec()
+        );
+        assert_eq!(
+            DataGenerator::generate_key(42, BlobSize::Small, 7, KeyShape::Synthetic),
+            b"1KB_00000007_87003c452f7df2bc".to_vec()
+        );
+        let value = DataGenerator::generate_value(42, BlobSize::Tiny, 0, 0.0, 0.0);
+        assert_eq!(value[..8], [129, 137, 227, 195, 43, 1, 216, 55]);
+        let value = DataGenerator::generate_value(42, BlobSize::Small, 7, 0.0, 0.0);
+        assert_eq!(value[..8], [84, 224, 208, 161, 9, 20, 106, 179]);
+    }
+
+    #[test]
+    fn test_key_shapes_are_deterministic_and_unique() {
+        for shape in [
+            KeyShape::Synthetic,
+            KeyShape::Uuid,
+            KeyShape::Sequential,
+            KeyShape::Path,
+        ] {
+            let config = DataGenConfig {
+                key_shape: shape,
+                ..DataGenConfig::new(20, 5)
+            };
+            let first = DataGenerator::new(config.clone()).generate_all();
+            let second = DataGenerator::new(config).generate_all();
+            let keys: Vec<&[u8]> = first.iter().map(|e| e.key.as_slice()).collect();
+            assert_eq!(
+                keys,
+                second.iter().map(|e| e.key.as_slice()).collect::<Vec<_>>(),
+                "{:?}",
+                shape
+            );
+
+            let unique: std::collections::HashSet<&[u8]> = keys.iter().copied().collect();
+            assert_eq!(unique.len(), keys.len(), "{:?} keys collide", shape);
+        }
+
+        let uuid = DataGenerator::generate_key(5, BlobSize::Tiny, 0, KeyShape::Uuid);
+        let uuid = String::from_utf8(uuid).unwrap();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(uuid.as_bytes()[14], b'4');
+        assert_eq!(
+            DataGenerator::generate_key(5, BlobSize::Small, 7, KeyShape::Sequential),
+            b"00000001000000000007".to_vec()
+        );
+        let path = DataGenerator::generate_key(5, BlobSize::Medium, 3, KeyShape::Path);
+        let path = String::from_utf8(path).unwrap();
+        assert!(
+            path.starts_with("/10KB/") && path.ends_with("/00000003"),
+            "{}",
+            path
+        );
+    }
+
+    #[test]
+    fn test_streaming_matches_generate_all() {
+        let mut entries_override = std::collections::HashMap::new();
+        entries_override.insert(BlobSize::Huge, 65);
         let config = DataGenConfig {
-            entries_per_size: 5,
-            entries_override: std::collections::HashMap::new(),
-            seed: 123,
+            entries_override,
+            size_jitter: 0.1,
+            ..DataGenConfig::new(12, 21)
         };
+        let gen = DataGenerator::new(config);
+        let all = gen.generate_all();
+
+        let mut streamed = Vec::new();
+        gen.generate_streaming(|entry| {
+            streamed.push(entry);
+            Ok(())
+        })
+        .unwrap();
+
+        // 65 huge entries span two chunks
+        assert!(65 * BlobSize::Huge.byte_size() > STREAM_CHUNK_BYTES);
+        assert_eq!(streamed.len(), all.len());
+        for (a, b) in all.iter().zip(streamed.iter()) {
+            assert_eq!(a.key, b.key);
+            assert_eq!(a.value, b.value);
+            assert_eq!(a.size_category, b.size_category);
+        }
+
+        let mut seen = 0;
+        let err = gen
+            .generate_streaming(|_| {
+                seen += 1;
+                anyhow::ensure!(seen < 3, "stop");
+                Ok(())
+            })
+            .unwrap_err();
+        assert_eq!(err.to_string(), "stop");
+        assert_eq!(seen, 3);
+    }
+
+    #[test]
+    fn test_size_jitter_varies_lengths_reproducibly() {
+        let config = DataGenConfig {
+            size_jitter: 0.2,
+            ..DataGenConfig::new(50, 11)
+        };
+        let first = DataGenerator::new(config.clone()).generate_for_size(BlobSize::Small);
+        let second = DataGenerator::new(config).generate_for_size(BlobSize::Small);
+
+        let lengths: std::collections::HashSet<usize> =
+            first.iter().map(|e| e.value.len()).collect();
+        assert!(
+            lengths.len() > 10,
+            "only {} distinct lengths",
+            lengths.len()
+        );
+        assert!(lengths.iter().all(|&len| (819..=1229).contains(&len)));
+        for (a, b) in first.iter().zip(second.iter()) {
+            assert_eq!(a.value, b.value);
+        }
+    }
+
+    #[test]
+    fn test_compressibility_shrinks_zstd_output() {
+        let random = DataGenerator::generate_value(42, BlobSize::Large, 3, 0.0, 0.0);
+        let repetitive = DataGenerator::generate_value(42, BlobSize::Large, 3, 0.9, 0.0);
+        assert_eq!(random.len(), repetitive.len());
+
+        let random_zstd = zstd::encode_all(&random[..], 3).unwrap();
+        let repetitive_zstd = zstd::encode_all(&repetitive[..], 3).unwrap();
+        assert!(random_zstd.len() > random.len() * 99 / 100);
+        assert!(
+            repetitive_zstd.len() < random_zstd.len() / 4,
+            "{} vs {}",
+            repetitive_zstd.len(),
+            random_zstd.len()
+        );
+
+        // Still deterministic per seed and index
+        assert_eq!(
+            repetitive,
+            DataGenerator::generate_value(42, BlobSize::Large, 3, 0.9, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_reproducibility() {
+        let config = DataGenConfig::new(5, 123);
 
         let gen1 = DataGenerator::new(config.clone());
         let gen2 = DataGenerator::new(config);
@@ -321,6 +685,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_thread_count_does_not_change_output() {
+        let config = DataGenConfig::new(8, 9);
+
+        let single = DataGenerator::new(config.clone())
+            .with_threads(1)
+            .unwrap()
+            .generate_all();
+        let four = DataGenerator::new(config)
+            .with_threads(4)
+            .unwrap()
+            .generate_all();
+
+        assert_eq!(single.len(), four.len());
+        for (a, b) in single.iter().zip(four.iter()) {
+            assert_eq!(a.key, b.key);
+            assert_eq!(a.value, b.value);
+            assert_eq!(a.size_category, b.size_category);
+        }
+    }
+
     #[test]
     fn test_entries_override() {
         let mut entries_override = std::collections::HashMap::new();
@@ -328,9 +713,8 @@ mod tests {
         entries_override.insert(BlobSize::Large, 20);
 
         let config = DataGenConfig {
-            entries_per_size: 10,
             entries_override,
-            seed: 42,
+            ..DataGenConfig::new(10, 42)
         };
         let gen = DataGenerator::new(config);
         let entries = gen.generate_all();
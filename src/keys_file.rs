@@ -0,0 +1,193 @@
+//! Binary alternative to keys.json. It holds the same per-size key lists, but
+//! as length-prefixed raw keys instead of base64 strings in pretty-printed
+//! JSON, so it is smaller and much faster to load for large datasets.
+
+use crate::data_gen::BlobSize;
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Key lists written by `build` with `--keys-format json`
+pub const JSON_KEYS_FILE: &str = "keys.json";
+/// Key lists written by `build` with `--keys-format binary`
+pub const BINARY_KEYS_FILE: &str = "keys.bin";
+
+const MAGIC: &[u8; 8] = b"KEYSBIN1";
+
+/// File layout:
+/// - magic: 8 bytes
+/// - group_count: 4 bytes (u32)
+/// - per size category:
+///   - name_len: 1 byte
+///   - name: variable (`BlobSize::name`)
+///   - key_count: 8 bytes (u64)
+///   - per key: key_len (4 bytes, u32) then the key
+
+/// Format `build` writes the benchmark keys in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum KeysFormat {
+    /// keys.json: base64 keys grouped by size name
+    #[default]
+    Json,
+    /// keys.bin: length-prefixed raw keys grouped by size
+    Binary,
+}
+
+impl KeysFormat {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            KeysFormat::Json => JSON_KEYS_FILE,
+            KeysFormat::Binary => BINARY_KEYS_FILE,
+        }
+    }
+}
+
+/// Whether `data` is in the binary format rather than JSON
+pub fn is_binary(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Write `keys_by_size` in the binary format, sizes in `BlobSize::all()` order
+pub fn write_binary(
+    writer: &mut impl Write,
+    keys_by_size: &HashMap<BlobSize, Vec<Vec<u8>>>,
+) -> Result<()> {
+    let groups: Vec<_> = BlobSize::all()
+        .iter()
+        .filter_map(|size| Some((size, keys_by_size.get(size)?)))
+        .collect();
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(groups.len() as u32).to_le_bytes())?;
+    for (size, keys) in groups {
+        let name = size.name().as_bytes();
+        writer.write_all(&[name.len() as u8])?;
+        writer.write_all(name)?;
+        writer.write_all(&(keys.len() as u64).to_le_bytes())?;
+        for key in keys {
+            if key.len() > u32::MAX as usize {
+                bail!("Key of {} bytes is too long", key.len());
+            }
+            writer.write_all(&(key.len() as u32).to_le_bytes())?;
+            writer.write_all(key)?;
+        }
+    }
+    Ok(())
+}
+
+/// Take the next `len` bytes at `*pos`, advancing it
+fn take<'a>(data: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8]> {
+    let bytes = pos
+        .checked_add(len)
+        .and_then(|end| data.get(*pos..end))
+        .context("Binary keys file is truncated")?;
+    *pos += len;
+    Ok(bytes)
+}
+
+/// Read key lists written by `write_binary`. Every size category is present in
+/// the result; groups for unknown size names are skipped.
+pub fn read_binary(data: &[u8]) -> Result<HashMap<BlobSize, Vec<Vec<u8>>>> {
+    if !is_binary(data) {
+        bail!("Not a binary keys file (bad magic)");
+    }
+    let mut keys_by_size: HashMap<BlobSize, Vec<Vec<u8>>> = BlobSize::all()
+        .iter()
+        .map(|&size| (size, Vec::new()))
+        .collect();
+
+    let mut pos = MAGIC.len();
+    let group_count = u32::from_le_bytes(take(data, &mut pos, 4)?.try_into().unwrap());
+    for _ in 0..group_count {
+        let name_len = take(data, &mut pos, 1)?[0] as usize;
+        let name = take(data, &mut pos, name_len)?;
+        let size = BlobSize::all()
+            .iter()
+            .find(|size| size.name().as_bytes() == name);
+        let key_count = u64::from_le_bytes(take(data, &mut pos, 8)?.try_into().unwrap());
+
+        let mut keys = Vec::new();
+        for _ in 0..key_count {
+            let key_len = u32::from_le_bytes(take(data, &mut pos, 4)?.try_into().unwrap());
+            keys.push(take(data, &mut pos, key_len as usize)?.to_vec());
+        }
+        if let Some(size) = size {
+            keys_by_size.insert(*size, keys);
+        }
+    }
+    if pos != data.len() {
+        bail!("Binary keys file has {} trailing bytes", data.len() - pos);
+    }
+    Ok(keys_by_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec as prop_vec;
+    use proptest::prelude::*;
+
+    fn sample_keys() -> HashMap<BlobSize, Vec<Vec<u8>>> {
+        BlobSize::all()
+            .iter()
+            .enumerate()
+            .map(|(i, &size)| {
+                let keys = (0..i * 3)
+                    .map(|j| format!("{}_{:04}", size.name(), j).into_bytes())
+                    .collect();
+                (size, keys)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_binary_keys_roundtrip() {
+        let mut keys_by_size = sample_keys();
+        keys_by_size.get_mut(&BlobSize::Huge).unwrap().extend([
+            Vec::new(),
+            vec![0, 0xff, b'\n'],
+            vec![7; 70_000],
+        ]);
+
+        let mut data = Vec::new();
+        write_binary(&mut data, &keys_by_size).unwrap();
+        assert!(is_binary(&data));
+        assert_eq!(read_binary(&data).unwrap(), keys_by_size);
+    }
+
+    #[test]
+    fn test_binary_keys_fill_in_missing_sizes() {
+        let keys_by_size = HashMap::from([(BlobSize::Small, vec![b"only".to_vec()])]);
+        let mut data = Vec::new();
+        write_binary(&mut data, &keys_by_size).unwrap();
+
+        let read = read_binary(&data).unwrap();
+        assert_eq!(read.len(), BlobSize::all().len());
+        assert_eq!(read[&BlobSize::Small], vec![b"only".to_vec()]);
+        assert!(read[&BlobSize::Tiny].is_empty());
+    }
+
+    #[test]
+    fn test_binary_keys_reject_truncated_and_json_files() {
+        let mut data = Vec::new();
+        write_binary(&mut data, &sample_keys()).unwrap();
+        for len in [0, 4, MAGIC.len() + 2, data.len() - 1] {
+            assert!(read_binary(&data[..len]).is_err(), "length {}", len);
+        }
+        assert!(!is_binary(b"{\"tiny\": []}"));
+        assert!(read_binary(b"{\"tiny\": []}").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_binary_keys_roundtrip(
+            groups in prop_vec(prop_vec(prop_vec(any::<u8>(), 0..40), 0..20), 5)
+        ) {
+            let keys_by_size: HashMap<BlobSize, Vec<Vec<u8>>> =
+                BlobSize::all().iter().copied().zip(groups).collect();
+            let mut data = Vec::new();
+            write_binary(&mut data, &keys_by_size).unwrap();
+            prop_assert_eq!(read_binary(&data).unwrap(), keys_by_size);
+        }
+    }
+}
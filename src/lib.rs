@@ -1,8 +1,12 @@
 pub mod backends;
 pub mod benchmark;
+pub mod build_stats;
 pub mod chart;
+pub mod compression;
 pub mod data_gen;
+pub mod keys_file;
 pub mod store;
+pub mod summary;
+pub mod util;
 
-pub use store::{BlobStore, BlobStoreBuilder};
-
+pub use store::{BlobStore, BlobStoreBuilder, DataDigest, DynBlobStore, LookupPhases};
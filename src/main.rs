@@ -1,17 +1,66 @@
 use anyhow::{Context, Result};
 use build_an_index::backends::{
-    HashDatStore, HashDatStoreBuilder, SqliteRowidStore, SqliteRowidStoreBuilder,
-    SqliteWithoutRowidStore, SqliteWithoutRowidStoreBuilder, ZipStore, ZipStoreBuilder,
+    open_any, read_manifest, BPlusTreeStore, BPlusTreeStoreBuilder, BTreeDatStore,
+    BTreeDatStoreBuilder, HashAlgo, HashDatMmapStore, HashDatStore, HashDatStoreBuilder,
+    MemoryStore, MemoryStoreBuilder, SqliteRowidPooledStore, SqliteRowidStore,
+    SqliteRowidStoreBuilder, SqliteWithoutRowidPooledStore, SqliteWithoutRowidStore,
+    SqliteWithoutRowidStoreBuilder, SstableStore, SstableStoreBuilder, StoreFormat, ZipCompression,
+    ZipStore, ZipStoreBuilder, ZstdDatStore, ZstdDatStoreBuilder,
 };
+#[cfg(feature = "lmdb")]
+use build_an_index::backends::{LmdbStore, LmdbStoreBuilder};
+#[cfg(feature = "mph")]
+use build_an_index::backends::{MphDatStore, MphDatStoreBuilder};
+#[cfg(feature = "rocksdb")]
+use build_an_index::backends::{RocksDbStore, RocksDbStoreBuilder};
 use build_an_index::benchmark::{
-    print_results, run_benchmark_with_logging, AggregateResults, BenchmarkConfig,
+    allocator_name, check_keys_present, export_json, export_latencies_csv, print_access_breakdown,
+    print_cold_warm, print_concurrent_results, print_phase_breakdown, print_results,
+    print_size_breakdown, print_warmup_results, run_benchmark_with_logging, run_cold_warm,
+    run_concurrent_benchmark, run_warmup, AccessOrder, AccessPattern, AccessPlan, AggregateResults,
+    BenchmarkConfig, ColdWarmResult, ConcurrentResult, MemorySampler, Provenance, TimerCalibration,
+    WarmupResult, PREFLIGHT_SAMPLE_SIZE,
 };
-use build_an_index::chart::generate_charts;
-use build_an_index::data_gen::{BlobSize, DataGenConfig, DataGenerator};
-use build_an_index::store::{BlobStore, BlobStoreBuilder};
+use build_an_index::build_stats::{
+    BuildStats, BuildTimes, DatasetManifest, ValueSizeStats, DATASET_MANIFEST_FILE,
+};
+use build_an_index::chart::{
+    backend_indices, generate_build_time_chart, generate_charts,
+    generate_compression_comparison_chart, generate_compression_ratio_chart, generate_dashboard,
+    generate_memory_timeline_chart, ChartFormat, ChartOptions,
+};
+use build_an_index::compression::{compare_zip_compression, print_compression_comparison};
+use build_an_index::data_gen::{
+    BlobSize, DataGenConfig, DataGenerator, Entry, InsertOrder, KeyShape, SeededRng,
+};
+use build_an_index::keys_file::{self, KeysFormat, BINARY_KEYS_FILE, JSON_KEYS_FILE};
+use build_an_index::store::{
+    verify_streamed, BlobStore, BlobStoreBuilder, DataDigest, VerifyFailure, VerifyReport,
+};
+use build_an_index::summary::{write_prometheus, write_run_summary, RunSummary};
+use build_an_index::util::{base64, hex};
 use clap::{Parser, Subcommand};
-use std::collections::HashMap;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, OnceCell};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::{Bound, RangeBounds};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Exit code used when a benchmark is stopped with Ctrl-C (128 + SIGINT)
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+#[cfg(feature = "mimalloc")]
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+#[cfg(all(feature = "jemalloc", not(feature = "mimalloc")))]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
 #[derive(Parser)]
 #[command(name = "build-an-index")]
@@ -36,6 +85,83 @@ enum Commands {
         /// Random seed for data generation
         #[arg(short, long, default_value = "42")]
         seed: u64,
+
+        /// Only regenerate the key file (keys.json or keys.bin); skip building
+        /// and verifying the indices
+        #[arg(long, default_value = "false")]
+        keys_only: bool,
+
+        /// Hash function used by the Hash DAT index
+        #[arg(long, value_enum, default_value = "sip-hash")]
+        hash_algo: HashAlgo,
+
+        /// Write a bloom filter into the Hash DAT index so lookups of absent
+        /// keys usually skip the bucket probe
+        #[arg(long, default_value = "false")]
+        hash_bloom: bool,
+
+        /// Record CRC32s of the Hash DAT index's bucket table and blob heap, so
+        /// every open (including the post-build verification) checks them
+        #[arg(long, default_value = "false")]
+        verify_checksum: bool,
+
+        /// Compression for the Zip index entries as `method[:level]` (stored,
+        /// deflate, bzip2, zstd)
+        #[arg(long, default_value = "stored")]
+        zip_compression: ZipCompression,
+
+        /// Rebuild every index even if build_manifest.json says it is up to date
+        #[arg(long, default_value = "false")]
+        force: bool,
+
+        /// Threads used to generate data (defaults to all cores)
+        #[arg(long)]
+        gen_threads: Option<usize>,
+
+        /// Build SQLite, Hash, Zstd, B-tree and B+tree DAT, Zip, In-Memory and
+        /// SSTable twice more into scratch files and fail unless the two builds
+        /// match (byte-for-byte, or by data digest for SQLite and Zip)
+        #[arg(long, default_value = "false")]
+        verify_deterministic: bool,
+
+        /// Order entries are inserted into each index (shuffled uses --seed)
+        #[arg(long, value_enum, default_value = "natural")]
+        insert_order: InsertOrder,
+
+        /// Append a JSON manifest (format, version, entry count, build time and
+        /// config fingerprint) to the Hash DAT and B+tree files; see `inspect`
+        #[arg(long, default_value = "false")]
+        embed_manifest: bool,
+
+        /// Share of each generated value (0.0-1.0) made of a repeated pattern
+        /// instead of random bytes, so compressing backends have something to
+        /// compress
+        #[arg(
+            long,
+            default_value = "0.0",
+            value_parser = parse_f64_in("compressibility", 0.0..=1.0)
+        )]
+        compressibility: f64,
+
+        /// Vary each value's length by up to this fraction (0.0-1.0, exclusive)
+        /// of its size category, e.g. 0.2 for ±20%
+        #[arg(long, default_value = "0.0", value_parser = parse_f64_in("jitter", 0.0..1.0))]
+        jitter: f64,
+
+        /// Format of the generated keys
+        #[arg(long, value_enum, default_value = "synthetic")]
+        key_shape: KeyShape,
+
+        /// Regenerate entries chunk by chunk for each backend and verification
+        /// pass instead of holding them all in memory (natural insert order
+        /// only). Backends that buffer the whole data set still do
+        #[arg(long, default_value = "false")]
+        stream: bool,
+
+        /// Write the benchmark keys as keys.json (base64 in JSON) or keys.bin
+        /// (length-prefixed raw keys); `bench` reads whichever is present
+        #[arg(long, value_enum, default_value = "json")]
+        keys_format: KeysFormat,
     },
 
     /// Run benchmarks on existing index files
@@ -56,9 +182,347 @@ enum Commands {
         #[arg(short, long, default_value = "42")]
         seed: u64,
 
+        /// Override --lookups for individual backends, e.g. `hash=100000,zstd=5000,bplustree=1000`
+        #[arg(long, value_delimiter = ',', value_parser = parse_backend_lookups)]
+        backend_lookups: Vec<(String, usize)>,
+
         /// Enable verbose logging during benchmark
         #[arg(short, long, default_value = "false")]
         verbose: bool,
+
+        /// Order in which measured lookups visit keys
+        #[arg(long, value_enum, default_value = "random")]
+        access_order: AccessOrder,
+
+        /// Distribution of random lookups over each size category's keys
+        #[arg(long, value_enum, default_value = "uniform")]
+        access_pattern: AccessPattern,
+
+        /// Keys in the working set for `--access-pattern sliding-window`
+        #[arg(long, default_value = "100")]
+        window_size: usize,
+
+        /// Report file size split into index overhead vs value payload
+        #[arg(long, default_value = "false")]
+        index_only_size: bool,
+
+        /// Precompute one warmup/lookup key sequence and replay it for every backend
+        #[arg(long, default_value = "false")]
+        warmup_shared: bool,
+
+        /// Time the index probe and value read of each lookup separately (Hash DAT only)
+        #[arg(long, default_value = "false")]
+        phase_timing: bool,
+
+        /// Time K consecutive lookups together and record their mean, for lookups
+        /// faster than the timer resolution
+        #[arg(
+            long,
+            default_value = "1",
+            value_parser = clap::value_parser!(u64).range(1..),
+            conflicts_with = "phase_timing"
+        )]
+        batch_timing: u64,
+
+        /// Report the fraction of each index file the measured lookups read and
+        /// their read amplification. Only Hash DAT tracks its reads; other
+        /// backends are left out of the report.
+        #[arg(long, default_value = "false")]
+        track_access: bool,
+
+        /// Only open each backend and run the warmup, reporting open + warmup
+        /// time per backend (no measured lookups, charts or summary)
+        #[arg(long, default_value = "false")]
+        warmup_only: bool,
+
+        /// Advise MADV_WILLNEED over mmap-backed indices right after open, so
+        /// pages load ahead of the lookups (no-op for other backends)
+        #[arg(long, default_value = "false")]
+        willneed: bool,
+
+        /// Evict each store's file from the page cache (and SQLite's own cache)
+        /// before every measured lookup, to time cold reads. Linux only; stores
+        /// that can't evict (In-Memory, and every store elsewhere) are skipped
+        #[arg(long, default_value = "false", conflicts_with = "willneed")]
+        cold: bool,
+
+        /// Chart width in pixels (defaults to each chart's built-in width)
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        chart_width: Option<u32>,
+
+        /// Chart height in pixels (defaults to each chart's built-in height)
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        chart_height: Option<u32>,
+
+        /// Pixel multiplier for PNG charts (e.g. 2 for high-DPI displays);
+        /// SVGs are unaffected
+        #[arg(long, value_parser = parse_f64_in("chart scale", CHART_SCALE_RANGE))]
+        chart_scale: Option<f64>,
+
+        /// Image format of the generated charts
+        #[arg(long, value_enum, default_value = "svg")]
+        format: ChartFormat,
+
+        /// Also write an index.html in the output directory showing every chart
+        /// and a per-backend table of file size, memory and P50/P99
+        #[arg(long, default_value = "false")]
+        dashboard: bool,
+
+        /// Write a machine-readable summary of the whole run to this JSON file
+        #[arg(long)]
+        output_json_summary: Option<PathBuf>,
+
+        /// Write every (backend, size) result's file size, memory stats and
+        /// latency percentiles to this file as a JSON array
+        #[arg(long)]
+        json: Option<PathBuf>,
+
+        /// Write every individual lookup latency to this CSV file, one
+        /// `backend_name,blob_size,latency_ns` row per sample
+        #[arg(long)]
+        raw_csv: Option<PathBuf>,
+
+        /// Write per-(backend, size) metrics to this file in Prometheus text format
+        #[arg(long)]
+        prometheus: Option<PathBuf>,
+
+        /// Sample process memory every N milliseconds and chart it over time
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        memory_report_interval: Option<u64>,
+
+        /// Also run N lookup bursts per backend, each in a fresh child process
+        /// (timed end to end, including process startup and store open)
+        #[arg(long, default_value = "0")]
+        process_bursts: usize,
+
+        /// Lookups performed by each child process burst
+        #[arg(long, default_value = "100")]
+        burst_lookups: usize,
+
+        /// Also time the first and an immediately repeated lookup of N random
+        /// keys per backend, on a freshly opened store, and report cold/warm ratios
+        #[arg(long, default_value = "0")]
+        cold_warm_samples: usize,
+
+        /// Also run the lookups again split across N threads sharing one store
+        /// per backend, and report aggregate throughput (backends that can't be
+        /// shared across threads are skipped)
+        #[arg(long, default_value = "1", value_parser = clap::value_parser!(u64).range(1..))]
+        threads: u64,
+
+        /// Fraction (0.0-1.0) of measured lookups made with keys that don't
+        /// exist, to time negative lookups
+        #[arg(long, default_value = "0.0", value_parser = parse_f64_in("miss ratio", 0.0..=1.0))]
+        miss_ratio: f64,
+
+        /// Latency percentiles (0-100) shown in the results table and drawn
+        /// by the percentile charts, e.g. `50,90,99,99.9`
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_values_t = [50.0, 95.0, 99.0],
+            value_parser = parse_f64_in("percentile", 0.0..=100.0)
+        )]
+        percentiles: Vec<f64>,
+
+        /// Record latencies into an HDR histogram (0.1% precision) instead of
+        /// keeping every sample, bounding memory for very long runs
+        #[arg(long, default_value = "false", conflicts_with = "raw_csv")]
+        histogram: bool,
+
+        /// Compute single percentiles (P90 charts, pXX columns) by quickselect
+        /// instead of sorting every sample; same values, faster on huge runs
+        #[arg(long, default_value = "false")]
+        fast_percentile: bool,
+
+        /// Only benchmark the keys listed in this file (keys.json format),
+        /// e.g. the keys ingested since a baseline build
+        #[arg(long, conflicts_with = "baseline_keys")]
+        new_keys_file: Option<PathBuf>,
+
+        /// Only benchmark keys in the input's keys.json that are absent from
+        /// this older keys.json
+        #[arg(long)]
+        baseline_keys: Option<PathBuf>,
+
+        /// Only load the first N keys of each size category, bounding the key
+        /// universe (unlike --lookups, which bounds the number of operations)
+        #[arg(long, value_parser = clap::value_parser!(usize).range(1..))]
+        limit_entries: Option<usize>,
+
+        /// Fail instead of warning when the input has no manifest.json or
+        /// stores it lists are missing
+        #[arg(long, default_value = "false")]
+        strict: bool,
+    },
+
+    /// Open one store and run a burst of lookups (spawned by `bench --process-bursts`)
+    #[command(hide = true)]
+    BenchChild {
+        /// Directory containing index files and keys.json
+        #[arg(long)]
+        input: PathBuf,
+
+        /// Backend to open (any `--backend-lookups` name)
+        #[arg(long, value_parser = BenchBackend::named)]
+        backend: BenchBackend,
+
+        /// Number of random lookups
+        #[arg(long)]
+        lookups: usize,
+
+        /// Random seed for key selection
+        #[arg(long)]
+        seed: u64,
+    },
+
+    /// Report the distribution of value sizes in one index
+    Stats {
+        /// Directory containing index files
+        #[arg(short, long, default_value = "./data")]
+        input: PathBuf,
+
+        /// Backend whose index to inspect (any `--backend-lookups` name)
+        #[arg(long, value_parser = BenchBackend::named)]
+        backend: BenchBackend,
+
+        /// Also print a power-of-two histogram of value sizes
+        #[arg(long, default_value = "false")]
+        histogram: bool,
+    },
+
+    /// Compact a SQLite index file in place (runs VACUUM)
+    Compact {
+        /// Path to the index file
+        path: PathBuf,
+    },
+
+    /// Print the manifest embedded in a .dat file by `build --embed-manifest`
+    Inspect {
+        /// Path to the .dat file
+        path: PathBuf,
+    },
+
+    /// Rebuild a Hash DAT file's bucket table from its blob heap
+    Repair {
+        /// Path to the damaged Hash DAT file
+        path: PathBuf,
+
+        /// Where to write the repaired file
+        out: PathBuf,
+    },
+
+    /// Copy every entry of an index into a new index of another format
+    Migrate {
+        /// Index to read; its format is detected from its header
+        #[arg(long)]
+        from: PathBuf,
+
+        /// Path of the index to write
+        #[arg(long)]
+        to: PathBuf,
+
+        /// Format of the index to write
+        #[arg(long, value_enum)]
+        to_format: StoreFormat,
+    },
+
+    /// Check an existing index against regenerated data without rebuilding it.
+    /// The data options must match the ones the index was built with
+    Verify {
+        /// Index to check; its format is detected from its header
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Random seed the index was built with. This and the other dataset
+        /// options default to the manifest.json `build` wrote next to the
+        /// index, or to `build`'s defaults without one
+        #[arg(short, long)]
+        seed: Option<u64>,
+
+        /// Number of entries per blob size category the index was built with
+        #[arg(short, long)]
+        entries: Option<usize>,
+
+        /// Key shape the index was built with
+        #[arg(long, value_enum)]
+        key_shape: Option<KeyShape>,
+
+        /// Value compressibility the index was built with
+        #[arg(long, value_parser = parse_f64_in("compressibility", 0.0..=1.0))]
+        compressibility: Option<f64>,
+
+        /// Size jitter the index was built with
+        #[arg(long, value_parser = parse_f64_in("jitter", 0.0..1.0))]
+        jitter: Option<f64>,
+    },
+
+    /// Print an index's contents: one `<hex key>\t<value length>` line per key
+    /// in key order, or with --key the raw value of that key
+    Dump {
+        /// Index to read; its format is detected from its header
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Key to print the value of, encoded as --key-format says
+        #[arg(long)]
+        key: Option<String>,
+
+        /// Encoding of --key
+        #[arg(long, value_enum, default_value = "hex")]
+        key_format: KeyFormat,
+
+        /// List at most this many keys
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+
+    /// Build the Zip index from the same data at several compression settings
+    /// and compare file size, build time and lookup latency
+    CompareCompression {
+        /// Directory for the Zip files and the comparison chart
+        #[arg(short, long, default_value = "./output/compression")]
+        output: PathBuf,
+
+        /// Number of entries per blob size category
+        #[arg(short, long, default_value = "200")]
+        entries: usize,
+
+        /// Random seed for data generation and lookups
+        #[arg(long, default_value = "42")]
+        seed: u64,
+
+        /// Comma-separated `method[:level]` settings (stored, deflate, bzip2, zstd)
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_value = "stored,deflate:1,deflate:9,zstd:3,bzip2:9"
+        )]
+        settings: Vec<ZipCompression>,
+
+        /// Number of lookups per blob size for each setting
+        #[arg(short, long, default_value = "1000")]
+        lookups: usize,
+    },
+
+    /// Compare Hash DAT throughput of synchronous gets against io_uring batch gets
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    BenchUring {
+        /// Directory containing index_hash.dat and keys.json
+        #[arg(short, long, default_value = "./data")]
+        input: PathBuf,
+
+        /// Number of random lookups (across all sizes)
+        #[arg(short, long, default_value = "10000")]
+        lookups: usize,
+
+        /// Reads in flight per io_uring batch
+        #[arg(long, default_value = "64", value_parser = clap::value_parser!(u32).range(1..))]
+        batch: u32,
+
+        /// Random seed for key selection
+        #[arg(short, long, default_value = "42")]
+        seed: u64,
     },
 }
 
@@ -70,389 +534,2723 @@ fn main() -> Result<()> {
             output,
             entries,
             seed,
+            keys_only,
+            hash_algo,
+            hash_bloom,
+            verify_checksum,
+            zip_compression,
+            force,
+            gen_threads,
+            verify_deterministic,
+            insert_order,
+            embed_manifest,
+            compressibility,
+            jitter,
+            key_shape,
+            stream,
+            keys_format,
         } => {
-            build_indices(&output, entries, seed)?;
+            let options = BuildOptions {
+                entries_per_size: entries,
+                seed,
+                keys_only,
+                hash_algo,
+                hash_bloom,
+                verify_checksum,
+                zip_compression,
+                force,
+                gen_threads,
+                verify_deterministic,
+                insert_order,
+                embed_manifest,
+                compressibility,
+                size_jitter: jitter,
+                key_shape,
+                stream,
+                keys_format,
+            };
+            build_indices(&output, &options)?;
         }
         Commands::Bench {
             input,
             output,
             lookups,
             seed,
+            backend_lookups,
             verbose,
+            access_order,
+            access_pattern,
+            window_size,
+            index_only_size,
+            warmup_shared,
+            phase_timing,
+            batch_timing,
+            track_access,
+            warmup_only,
+            willneed,
+            cold,
+            chart_width,
+            chart_height,
+            chart_scale,
+            format,
+            dashboard,
+            output_json_summary,
+            json,
+            raw_csv,
+            prometheus,
+            memory_report_interval,
+            process_bursts,
+            burst_lookups,
+            cold_warm_samples,
+            threads,
+            miss_ratio,
+            percentiles,
+            histogram,
+            fast_percentile,
+            new_keys_file,
+            baseline_keys,
+            limit_entries,
+            strict,
+        } => {
+            let cancel = Arc::new(AtomicBool::new(false));
+            let handler_flag = cancel.clone();
+            ctrlc::set_handler(move || {
+                eprintln!(
+                    "\nInterrupted; finishing current measurement and saving partial results..."
+                );
+                handler_flag.store(true, Ordering::Relaxed);
+            })
+            .context("Failed to install Ctrl-C handler")?;
+
+            let config = BenchmarkConfig {
+                num_lookups: lookups,
+                warmup_iterations: 1000,
+                seed,
+                access_order,
+                access_pattern,
+                window_size,
+                cancel: Some(cancel),
+                report_index_size: index_only_size,
+                shared_plan: None,
+                phase_timing,
+                batch_size: batch_timing as usize,
+                track_access,
+                willneed,
+                cold,
+                backend_lookups: backend_lookups.into_iter().collect(),
+                threads: threads as usize,
+                miss_ratio,
+                percentiles: percentiles.clone(),
+                histogram,
+                fast_percentile,
+            };
+            let chart_options = ChartOptions {
+                width: chart_width,
+                height: chart_height,
+                percentiles,
+                format,
+                scale: chart_scale,
+            };
+            ensure_writable_dir(&output)?;
+            if let Some(summary_dir) = output_json_summary
+                .iter()
+                .chain(&json)
+                .chain(&raw_csv)
+                .chain(&prometheus)
+                .filter_map(|path| path.parent())
+                .find(|dir| !dir.as_os_str().is_empty())
+            {
+                ensure_writable_dir(summary_dir)?;
+            }
+            check_dataset_manifest(&input, strict)?;
+            let keys_by_size = load_bench_keys(
+                &input,
+                new_keys_file.as_deref(),
+                baseline_keys.as_deref(),
+                limit_entries,
+            )?;
+            // Flatten in a fixed order so a seed always samples the same keys
+            let cold_warm_keys: Vec<Vec<u8>> = BlobSize::all()
+                .iter()
+                .filter_map(|size| keys_by_size.get(size))
+                .flatten()
+                .cloned()
+                .collect();
+            if warmup_only {
+                let results = run_warmup_pass(&input, &cold_warm_keys, &config)?;
+                print_warmup_results(&results);
+            } else {
+                run_benchmarks(
+                    &input,
+                    keys_by_size,
+                    &output,
+                    &config,
+                    &chart_options,
+                    dashboard,
+                    output_json_summary.as_deref(),
+                    json.as_deref(),
+                    raw_csv.as_deref(),
+                    prometheus.as_deref(),
+                    memory_report_interval.map(Duration::from_millis),
+                    warmup_shared,
+                    verbose,
+                )?;
+            }
+
+            if config.is_cancelled() {
+                std::process::exit(INTERRUPTED_EXIT_CODE);
+            }
+
+            if process_bursts > 0 {
+                run_process_bursts(&input, process_bursts, burst_lookups, seed)?;
+            }
+            if cold_warm_samples > 0 {
+                let results = run_cold_warm_pass(&input, &cold_warm_keys, cold_warm_samples, seed)?;
+                print_cold_warm(&results);
+            }
+            if config.threads > 1 {
+                let results = run_concurrent_pass(&input, &cold_warm_keys, &config)?;
+                print_concurrent_results(&results);
+            }
+        }
+        Commands::BenchChild {
+            input,
+            backend,
+            lookups,
+            seed,
+        } => {
+            let burst = run_child_burst(&input, backend, lookups, seed)?;
+            println!("{}", serde_json::to_string(&burst)?);
+        }
+        Commands::Stats {
+            input,
+            backend,
+            histogram,
+        } => {
+            let path = input.join(backend.file_name);
+            let stats = ValueSizeStats::collect((backend.open)(&path)?.as_ref())?;
+            print_value_size_stats(&path, &backend, &stats, histogram);
+        }
+        Commands::Compact { path } => {
+            compact_index(&path)?;
+        }
+        Commands::Inspect { path } => {
+            print!("{}", inspect_report(&path)?);
+        }
+        Commands::Repair { path, out } => {
+            repair_index(&path, &out)?;
+        }
+        Commands::Migrate {
+            from,
+            to,
+            to_format,
+        } => {
+            migrate_index(&from, &to, to_format)?;
+        }
+        Commands::Verify {
+            input,
+            seed,
+            entries,
+            key_shape,
+            compressibility,
+            jitter,
+        } => {
+            let mut config = recorded_data_gen_config(&input)?;
+            if let Some(entries) = entries {
+                let counts = DataGenConfig::new(entries, config.seed);
+                config.entries_per_size = counts.entries_per_size;
+                config.entries_override = counts.entries_override;
+            }
+            config.seed = seed.unwrap_or(config.seed);
+            config.key_shape = key_shape.unwrap_or(config.key_shape);
+            config.compressibility = compressibility.unwrap_or(config.compressibility);
+            config.size_jitter = jitter.unwrap_or(config.size_jitter);
+            verify_index(&input, config)?;
+        }
+        Commands::Dump {
+            input,
+            key,
+            key_format,
+            limit,
+        } => {
+            dump_index(&input, key.as_deref(), key_format, limit)?;
+        }
+        Commands::CompareCompression {
+            output,
+            entries,
+            seed,
+            settings,
+            lookups,
         } => {
-            run_benchmarks(&input, &output, lookups, seed, verbose)?;
+            let generated = DataGenerator::new(DataGenConfig::new(entries, seed)).generate_all();
+            let config = BenchmarkConfig {
+                num_lookups: lookups,
+                seed,
+                ..Default::default()
+            };
+            let results = compare_zip_compression(&generated, &settings, &output, &config)?;
+            print_compression_comparison(&results);
+            generate_compression_comparison_chart(
+                &results,
+                &backend_indices(["Zip"]),
+                &output,
+                &ChartOptions::default(),
+            )?;
+        }
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        Commands::BenchUring {
+            input,
+            lookups,
+            batch,
+            seed,
+        } => {
+            let keys_by_size = load_keys(&input)?;
+            // Flatten in a fixed order so a seed always picks the same keys
+            let keys: Vec<Vec<u8>> = BlobSize::all()
+                .iter()
+                .flat_map(|size| keys_by_size[size].iter().cloned())
+                .collect();
+            let comparison = build_an_index::benchmark::compare_uring_throughput(
+                &input.join("index_hash.dat"),
+                &keys,
+                lookups,
+                batch as usize,
+                seed,
+            )?;
+            println!("\nHash DAT throughput ({} lookups):", comparison.lookups);
+            println!(
+                "  sync get:            {:>12.0} ops/s",
+                comparison.sync_ops_per_sec
+            );
+            println!(
+                "  io_uring batch ({:>3}): {:>12.0} ops/s ({:.2}x)",
+                comparison.batch_size,
+                comparison.uring_ops_per_sec,
+                comparison.uring_ops_per_sec / comparison.sync_ops_per_sec
+            );
         }
     }
 
     Ok(())
 }
 
-fn build_indices(output_dir: &Path, entries_per_size: usize, seed: u64) -> Result<()> {
-    std::fs::create_dir_all(output_dir).context("Failed to create output directory")?;
+/// Options for the `build` command
+struct BuildOptions {
+    entries_per_size: usize,
+    seed: u64,
+    keys_only: bool,
+    hash_algo: HashAlgo,
+    hash_bloom: bool,
+    verify_checksum: bool,
+    zip_compression: ZipCompression,
+    force: bool,
+    gen_threads: Option<usize>,
+    verify_deterministic: bool,
+    insert_order: InsertOrder,
+    embed_manifest: bool,
+    compressibility: f64,
+    size_jitter: f64,
+    key_shape: KeyShape,
+    stream: bool,
+    keys_format: KeysFormat,
+}
 
-    // Use fewer entries for huge blobs (1MB) to speed up generation
-    let mut entries_override = std::collections::HashMap::new();
-    entries_override.insert(BlobSize::Huge, std::cmp::min(entries_per_size, 100));
+impl Default for BuildOptions {
+    fn default() -> Self {
+        Self {
+            entries_per_size: 1000,
+            seed: 42,
+            keys_only: false,
+            hash_algo: HashAlgo::default(),
+            hash_bloom: false,
+            verify_checksum: false,
+            zip_compression: ZipCompression::default(),
+            force: false,
+            gen_threads: None,
+            verify_deterministic: false,
+            insert_order: InsertOrder::default(),
+            embed_manifest: false,
+            compressibility: 0.0,
+            size_jitter: 0.0,
+            key_shape: KeyShape::default(),
+            stream: false,
+            keys_format: KeysFormat::default(),
+        }
+    }
+}
 
+/// Returns the files that were (re)written; unchanged indices are skipped.
+fn build_indices(output_dir: &Path, options: &BuildOptions) -> Result<Vec<PathBuf>> {
+    ensure_writable_dir(output_dir)?;
+    let entries_per_size = options.entries_per_size;
     let config = DataGenConfig {
-        entries_per_size,
-        entries_override,
-        seed,
+        compressibility: options.compressibility,
+        size_jitter: options.size_jitter,
+        key_shape: options.key_shape,
+        ..DataGenConfig::new(entries_per_size, options.seed)
+    };
+    let expected_len: usize = BlobSize::all()
+        .iter()
+        .map(|size| config.entries_for_size(*size))
+        .sum();
+
+    if options.stream && options.insert_order != InsertOrder::Natural {
+        anyhow::bail!(
+            "--stream generates entries in natural order; it can't be combined with \
+             --insert-order {}",
+            options.insert_order.name()
+        );
+    }
+
+    // Only generate data once something actually needs rebuilding
+    let mut generator = DataGenerator::new(config.clone());
+    if let Some(threads) = options.gen_threads {
+        generator = generator
+            .with_threads(threads)
+            .context("Failed to create data generation thread pool")?;
+    }
+    let generated = OnceCell::new();
+    let (generator, generated) = (&generator, &generated);
+    let (insert_order, seed) = (options.insert_order, options.seed);
+    let entries = move || {
+        generated.get_or_init(|| {
+            let mut entries = generator.generate_all_with_logging();
+            insert_order.apply(&mut entries, seed);
+            entries
+        })
+    };
+    let streamed = &StreamedEntries::new(generator);
+    let stream = options.stream;
+    let source = move || -> &dyn EntrySource {
+        if stream {
+            streamed
+        } else {
+            entries()
+        }
     };
 
-    let generator = DataGenerator::new(config.clone());
-    let entries = generator.generate_all_with_logging();
+    if options.keys_only {
+        println!(
+            "\nWARNING: --keys-only skips index builds. Existing indices in {} must have been \
+             built with the same seed and entry counts.",
+            output_dir.display()
+        );
+        let keys_path = write_keys(output_dir, source(), options.keys_format)?;
+        println!("\nBuild complete!");
+        return Ok(vec![keys_path]);
+    }
+
+    let manifest = BuildManifest::load(output_dir)?;
+    let mut fingerprint = format!(
+        // Data generated by the same seed under the old StdRng is different
+        "seed={} rng=chacha8 entries={} huge={} order={}",
+        options.seed,
+        entries_per_size,
+        config.entries_for_size(BlobSize::Huge),
+        options.insert_order.name()
+    );
+    // Random values keep the plain fingerprint so existing manifests stay valid
+    if options.compressibility > 0.0 {
+        fingerprint += &format!(" compressibility={}", options.compressibility);
+    }
+    if options.size_jitter > 0.0 {
+        fingerprint += &format!(" jitter={}", options.size_jitter);
+    }
+    if options.key_shape != KeyShape::Synthetic {
+        fingerprint += &format!(" keys={}", options.key_shape.name());
+    }
+    // Toggling the manifest changes the .dat file bytes, so it must force a rebuild
+    let dat_fingerprint = if options.embed_manifest {
+        format!("{} manifest", fingerprint)
+    } else {
+        fingerprint.clone()
+    };
+    let mut builds = IndexBuilds {
+        manifest,
+        build_times: BuildTimes::load(output_dir)?.unwrap_or_default(),
+        build_stats: BuildStats {
+            insert_order: Some(options.insert_order.name().to_string()),
+            ..Default::default()
+        },
+        rebuilt: Vec::new(),
+        stores: Vec::new(),
+        expected_len,
+        force: options.force,
+    };
+    println!("\nInsert order: {}", options.insert_order.name());
 
     // Build SQLite indices
     println!("\nBuilding SQLite index (WITHOUT ROWID)...");
-    let sqlite_without_rowid_path = output_dir.join("index_sqlite_without_rowid.sqlite");
-    build_store::<SqliteWithoutRowidStoreBuilder>(&sqlite_without_rowid_path, &entries)?;
-    println!(
-        "  Created: {} ({:.2} MB)",
-        sqlite_without_rowid_path.display(),
-        file_size_mb(&sqlite_without_rowid_path)?
-    );
-    verify_store::<SqliteWithoutRowidStore>(&sqlite_without_rowid_path, &entries)?;
+    builds.build::<SqliteWithoutRowidStore, _>(
+        &output_dir.join("index_sqlite_without_rowid.sqlite"),
+        &fingerprint,
+        &source,
+        SqliteWithoutRowidStoreBuilder::create,
+    )?;
 
     println!("\nBuilding SQLite index (ROWID)...");
-    let sqlite_rowid_path = output_dir.join("index_sqlite_rowid.sqlite");
-    build_store::<SqliteRowidStoreBuilder>(&sqlite_rowid_path, &entries)?;
-    println!(
-        "  Created: {} ({:.2} MB)",
-        sqlite_rowid_path.display(),
-        file_size_mb(&sqlite_rowid_path)?
-    );
-    verify_store::<SqliteRowidStore>(&sqlite_rowid_path, &entries)?;
+    builds.build::<SqliteRowidStore, _>(
+        &output_dir.join("index_sqlite_rowid.sqlite"),
+        &fingerprint,
+        &source,
+        SqliteRowidStoreBuilder::create,
+    )?;
 
     // Build Hash DAT index
-    println!("\nBuilding Hash DAT index...");
-    let hash_path = output_dir.join("index_hash.dat");
-    build_store::<HashDatStoreBuilder>(&hash_path, &entries)?;
     println!(
-        "  Created: {} ({:.2} MB)",
-        hash_path.display(),
-        file_size_mb(&hash_path)?
+        "\nBuilding Hash DAT index ({})...",
+        options.hash_algo.name()
     );
-    verify_store::<HashDatStore>(&hash_path, &entries)?;
+    let mut hash_fingerprint =
+        format!("{} hash_algo={}", dat_fingerprint, options.hash_algo.name());
+    if options.hash_bloom {
+        hash_fingerprint += " bloom";
+    }
+    if options.verify_checksum {
+        hash_fingerprint += " checksums";
+    }
+    builds.build::<HashDatStore, _>(
+        &output_dir.join("index_hash.dat"),
+        &hash_fingerprint,
+        &source,
+        |path| {
+            let mut builder = HashDatStoreBuilder::create(path)?.with_hash_algo(options.hash_algo);
+            if options.hash_bloom {
+                builder = builder.with_bloom_filter();
+            }
+            if options.verify_checksum {
+                builder = builder.with_checksums();
+            }
+            if options.embed_manifest {
+                builder = builder.with_manifest(Some(&hash_fingerprint));
+            }
+            Ok(builder)
+        },
+    )?;
+
+    // Build zstd-compressed Hash DAT index
+    println!("\nBuilding Zstd DAT index...");
+    builds.build::<ZstdDatStore, _>(
+        &output_dir.join("index_zstd.dat"),
+        &fingerprint,
+        &source,
+        ZstdDatStoreBuilder::create,
+    )?;
+
+    // Build the in-memory baseline (a plain dump of the entries, loaded whole on open)
+    println!("\nBuilding In-Memory baseline...");
+    builds.build::<MemoryStore, _>(
+        &output_dir.join("index_memory.bin"),
+        &fingerprint,
+        &source,
+        MemoryStoreBuilder::create,
+    )?;
+
+    #[cfg(feature = "mph")]
+    {
+        println!("\nBuilding MPH DAT index...");
+        builds.build::<MphDatStore, _>(
+            &output_dir.join("index_mph.dat"),
+            &fingerprint,
+            &source,
+            MphDatStoreBuilder::create,
+        )?;
+    }
+
+    // Build paged B+tree index
+    println!("\nBuilding B+tree index...");
+    builds.build::<BPlusTreeStore, _>(
+        &output_dir.join("index_bplustree.dat"),
+        &dat_fingerprint,
+        &source,
+        |path| {
+            let mut builder = BPlusTreeStoreBuilder::create(path)?;
+            if options.embed_manifest {
+                builder = builder.with_manifest(Some(&dat_fingerprint));
+            }
+            Ok(builder)
+        },
+    )?;
+
+    // Build block-indexed SSTable
+    println!("\nBuilding SSTable index...");
+    builds.build::<SstableStore, _>(
+        &output_dir.join("index_sstable.sst"),
+        &fingerprint,
+        &source,
+        SstableStoreBuilder::create,
+    )?;
 
     // Build Zip index
-    println!("\nBuilding Zip index...");
-    let zip_path = output_dir.join("index.zip");
-    build_store::<ZipStoreBuilder>(&zip_path, &entries)?;
     println!(
-        "  Created: {} ({:.2} MB)",
-        zip_path.display(),
-        file_size_mb(&zip_path)?
+        "\nBuilding Zip index ({})...",
+        options.zip_compression.name()
     );
-    verify_store::<ZipStore>(&zip_path, &entries)?;
+    // Stored archives keep the plain fingerprint so existing manifests stay valid
+    let zip_fingerprint = if options.zip_compression == ZipCompression::STORED {
+        fingerprint.clone()
+    } else {
+        format!("{} zip={}", fingerprint, options.zip_compression.name())
+    };
+    builds.build::<ZipStore, _>(
+        &output_dir.join("index.zip"),
+        &zip_fingerprint,
+        &source,
+        |path| Ok(ZipStoreBuilder::create(path)?.with_compression(options.zip_compression)),
+    )?;
+
+    #[cfg(feature = "rocksdb")]
+    {
+        println!("\nBuilding RocksDB index...");
+        builds.build::<RocksDbStore, _>(
+            &output_dir.join("index_rocksdb"),
+            &fingerprint,
+            &source,
+            RocksDbStoreBuilder::create,
+        )?;
+    }
 
-    // Save keys for benchmarking
-    println!("\nSaving key index...");
-    let keys_path = output_dir.join("keys.json");
-    let keys_by_size: HashMap<String, Vec<String>> = BlobSize::all()
-        .iter()
-        .map(|size| {
-            let size_keys: Vec<String> = entries
-                .iter()
-                .filter(|e| e.size_category == *size)
-                .map(|e| base64_encode(&e.key))
-                .collect();
-            (size.name().to_string(), size_keys)
-        })
-        .collect();
+    #[cfg(feature = "lmdb")]
+    {
+        println!("\nBuilding LMDB index...");
+        builds.build::<LmdbStore, _>(
+            &output_dir.join("index_lmdb"),
+            &fingerprint,
+            &source,
+            LmdbStoreBuilder::create,
+        )?;
+    }
 
-    let keys_json = serde_json::to_string_pretty(&keys_by_size)?;
-    std::fs::write(&keys_path, keys_json)?;
-    println!("  Created: {}", keys_path.display());
+    let IndexBuilds {
+        mut manifest,
+        build_times,
+        build_stats,
+        mut rebuilt,
+        stores,
+        ..
+    } = builds;
+
+    let keys_path = output_dir.join(options.keys_format.file_name());
+    let keys_current = !options.force
+        && keys_path.exists()
+        && manifest.fingerprint(&keys_path) == Some(fingerprint.as_str());
+    if !keys_current {
+        write_keys(output_dir, source(), options.keys_format)?;
+        manifest.record(&keys_path, &fingerprint);
+        rebuilt.push(keys_path);
+    }
+    build_stats.save(output_dir)?;
+    build_times.save(output_dir)?;
+    manifest.save(output_dir)?;
+    if !rebuilt.is_empty() || DatasetManifest::load(output_dir)?.is_none() {
+        write_dataset_manifest(output_dir, &config, options.insert_order, source(), stores)?;
+    }
+
+    if options.verify_deterministic {
+        verify_determinism(output_dir, source(), options)?;
+    }
 
+    if rebuilt.is_empty() {
+        println!("\nAll indices up to date (use --force to rebuild)");
+    }
     println!("\nBuild complete!");
-    Ok(())
+    Ok(rebuilt)
 }
 
-fn build_store<B: BlobStoreBuilder>(
-    path: &Path,
-    entries: &[build_an_index::data_gen::Entry],
+/// Write manifest.json describing the dataset in `entries` and the stores built from it
+fn write_dataset_manifest(
+    output_dir: &Path,
+    config: &DataGenConfig,
+    insert_order: InsertOrder,
+    entries: &(impl EntrySource + ?Sized),
+    stores: Vec<String>,
 ) -> Result<()> {
-    let mut builder = B::create(path)?;
-    for entry in entries {
-        builder.insert(&entry.key, &entry.value)?;
+    let mut digest = DataDigest::default();
+    entries.for_each(&mut |entry| {
+        digest.update(&entry.key, &entry.value);
+        Ok(())
+    })?;
+    let manifest = DatasetManifest {
+        seed: config.seed,
+        entries_per_size: config.entries_per_size,
+        entries_override: config
+            .entries_override
+            .iter()
+            .map(|(size, &count)| (size.name().to_string(), count))
+            .collect(),
+        entries: entries.len(),
+        compressibility: config.compressibility,
+        size_jitter: config.size_jitter,
+        key_shape: config.key_shape.name().to_string(),
+        insert_order: insert_order.name().to_string(),
+        dataset_hash: digest
+            .finish()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect(),
+        stores,
+    };
+    manifest.save(output_dir)
+}
+
+/// Print the dataset `bench` is about to measure, as recorded in the input's
+/// manifest.json. A missing manifest or missing stores are warnings, or errors
+/// under `strict`.
+fn check_dataset_manifest(input_dir: &Path, strict: bool) -> Result<()> {
+    let problem = |message: String| -> Result<()> {
+        if strict {
+            anyhow::bail!("{} (--strict)", message);
+        }
+        println!("WARNING: {}", message);
+        Ok(())
+    };
+    let Some(manifest) = DatasetManifest::load(input_dir)? else {
+        return problem(format!(
+            "no {} in {}; rebuild to record which dataset the indices hold",
+            DATASET_MANIFEST_FILE,
+            input_dir.display()
+        ));
+    };
+    println!(
+        "Dataset: seed {}, {} entries, hash {}",
+        manifest.seed,
+        manifest.entries,
+        &manifest.dataset_hash[..manifest.dataset_hash.len().min(16)]
+    );
+    let missing = manifest.missing_stores(input_dir);
+    if !missing.is_empty() {
+        return problem(format!(
+            "stores listed in {} are missing: {}",
+            DATASET_MANIFEST_FILE,
+            missing.join(", ")
+        ));
     }
-    builder.finish()?;
     Ok(())
 }
 
-fn file_size_mb(path: &Path) -> Result<f64> {
-    let metadata = std::fs::metadata(path)?;
-    Ok(metadata.len() as f64 / 1_048_576.0)
+/// What `build_indices` tracks across backends: the manifest deciding which
+/// files to skip, build times and stats, and which files were (re)built
+struct IndexBuilds {
+    manifest: BuildManifest,
+    build_times: BuildTimes,
+    build_stats: BuildStats,
+    rebuilt: Vec<PathBuf>,
+    /// File names of every store, rebuilt or not, for the dataset manifest
+    stores: Vec<String>,
+    expected_len: usize,
+    force: bool,
 }
 
-/// Verify that all entries can be read back correctly from a store
-fn verify_store<S: BlobStore>(
-    path: &Path,
-    entries: &[build_an_index::data_gen::Entry],
-) -> Result<()> {
-    use std::io::Write;
+impl IndexBuilds {
+    /// Build the `S` store at `path` with the builder `create` returns, unless
+    /// the manifest shows it's already current for `fingerprint`; a fresh build
+    /// is verified against `source` and recorded in the manifest
+    fn build<'s, S: BlobStore, B: BlobStoreBuilder>(
+        &mut self,
+        path: &Path,
+        fingerprint: &str,
+        source: &dyn Fn() -> &'s dyn EntrySource,
+        create: impl FnOnce(&Path) -> Result<B>,
+    ) -> Result<()> {
+        if self
+            .manifest
+            .is_current::<S>(path, fingerprint, self.expected_len, self.force)
+        {
+            println!("  Skipped (unchanged): {}", path.display());
+        } else {
+            // Generated before timing starts, or for --stream, excluded from it
+            let source = source();
+            let elapsed = time_build(|| create(path), source)?;
+            self.build_times.record::<S>(elapsed);
+            println!(
+                "  Created: {} ({:.2} MB)",
+                path.display(),
+                file_size_mb(path)?
+            );
+            verify_store::<S>(path, source)?;
+            self.manifest.record(path, fingerprint);
+            self.rebuilt.push(path.to_path_buf());
+        }
+        record_build_stats::<S>(&mut self.build_stats, path)?;
+        self.stores.push(BuildManifest::file_key(path));
+        Ok(())
+    }
+}
 
-    print!("  Verifying {} entries... ", entries.len());
-    std::io::stdout().flush()?;
+fn record_build_stats<S: BlobStore>(build_stats: &mut BuildStats, path: &Path) -> Result<()> {
+    let stats = build_stats.record::<S>(path)?;
+    println!("  Compression ratio: {:.2}x", stats.compression_ratio);
+    Ok(())
+}
 
-    let store = S::open(path)?;
+/// Input fingerprints of each built file, persisted as `build_manifest.json` so
+/// an unchanged rebuild can skip it
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BuildManifest {
+    /// File name -> fingerprint of the config it was built from
+    files: BTreeMap<String, String>,
+}
 
-    // Verify entry count
-    if store.len() != entries.len() {
-        anyhow::bail!(
-            "Entry count mismatch: expected {}, got {}",
-            entries.len(),
-            store.len()
-        );
-    }
+impl BuildManifest {
+    const FILE_NAME: &'static str = "build_manifest.json";
 
-    // Verify each entry can be retrieved with correct value
-    let mut errors = 0;
-    let mut verified = 0;
-    let total = entries.len();
-    let check_interval = (total / 10).max(1);
-
-    for entry in entries.iter() {
-        match store.get(&entry.key)? {
-            Some(value) => {
-                if value != entry.value {
-                    if errors < 5 {
-                        eprintln!(
-                            "\n    Value mismatch for key {:?}: expected {} bytes, got {} bytes",
-                            String::from_utf8_lossy(&entry.key[..entry.key.len().min(32)]),
-                            entry.value.len(),
-                            value.len()
-                        );
-                    }
-                    errors += 1;
-                }
-            }
-            None => {
-                if errors < 5 {
-                    eprintln!(
-                        "\n    Missing key: {:?}",
-                        String::from_utf8_lossy(&entry.key[..entry.key.len().min(32)])
-                    );
-                }
-                errors += 1;
-            }
+    fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(Self::FILE_NAME);
+        if !path.exists() {
+            return Ok(Self::default());
         }
-        verified += 1;
+        let json = std::fs::read_to_string(&path).context("Failed to read build manifest")?;
+        serde_json::from_str(&json).context("Failed to parse build manifest")
+    }
 
-        // Progress indicator
-        if verified % check_interval == 0 {
-            print!("{}%.. ", (verified * 100) / total);
-            std::io::stdout().flush()?;
-        }
+    fn save(&self, dir: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(dir.join(Self::FILE_NAME), json).context("Failed to write build manifest")
     }
 
-    if errors > 0 {
-        println!("FAILED");
-        anyhow::bail!("{} verification errors out of {} entries", errors, total);
+    fn file_key(path: &Path) -> String {
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
     }
 
-    println!("OK");
-    Ok(())
+    fn fingerprint(&self, path: &Path) -> Option<&str> {
+        self.files.get(&Self::file_key(path)).map(|f| f.as_str())
+    }
+
+    fn record(&mut self, path: &Path, fingerprint: &str) {
+        self.files
+            .insert(Self::file_key(path), fingerprint.to_string());
+    }
+
+    /// True if `path` was built from `fingerprint` and still opens with the
+    /// expected entry count
+    fn is_current<S: BlobStore>(
+        &self,
+        path: &Path,
+        fingerprint: &str,
+        expected_len: usize,
+        force: bool,
+    ) -> bool {
+        !force
+            && self.fingerprint(path) == Some(fingerprint)
+            && S::open(path).is_ok_and(|store| store.len() == expected_len)
+    }
 }
 
-fn run_benchmarks(
-    input_dir: &Path,
+/// Save keys (grouped by size category) for benchmarking in `format`, removing
+/// a key file of the other format so `bench` can't pick up a stale one.
+/// Returns the path written.
+fn write_keys(
     output_dir: &Path,
-    num_lookups: usize,
-    seed: u64,
-    verbose: bool,
-) -> Result<()> {
-    // Load keys
-    let keys_path = input_dir.join("keys.json");
-    let keys_json = std::fs::read_to_string(&keys_path)
-        .context("Failed to read keys.json. Did you run 'build' first?")?;
-    let keys_by_size_str: HashMap<String, Vec<String>> = serde_json::from_str(&keys_json)?;
-
-    let keys_by_size: HashMap<BlobSize, Vec<Vec<u8>>> = BlobSize::all()
+    entries: &(impl EntrySource + ?Sized),
+    format: KeysFormat,
+) -> Result<PathBuf> {
+    println!("\nSaving key index...");
+    let keys_path = output_dir.join(format.file_name());
+    let mut keys_by_size: HashMap<BlobSize, Vec<Vec<u8>>> = BlobSize::all()
         .iter()
-        .map(|size| {
-            let keys = keys_by_size_str
-                .get(size.name())
-                .map(|ks| ks.iter().filter_map(|k| base64_decode(k)).collect())
-                .unwrap_or_default();
-            (*size, keys)
-        })
+        .map(|&size| (size, Vec::new()))
         .collect();
+    entries.for_each(&mut |e| {
+        if let Some(size_keys) = keys_by_size.get_mut(&e.size_category) {
+            size_keys.push(e.key.clone());
+        }
+        Ok(())
+    })?;
 
-    let all_keys: Vec<Vec<u8>> = keys_by_size.values().flatten().cloned().collect();
-
-    println!("\nBenchmark Configuration:");
-    println!("  Lookups per size: {}", num_lookups);
-    println!("  Warmup iterations: 1000");
-    println!("  Random seed: {}", seed);
-    println!("  Total keys loaded: {}", all_keys.len());
-    for size in BlobSize::all() {
-        if let Some(keys) = keys_by_size.get(size) {
-            println!("    {}: {} keys", size.name(), keys.len());
+    match format {
+        KeysFormat::Json => {
+            let keys_by_name: HashMap<&str, Vec<String>> = keys_by_size
+                .iter()
+                .map(|(size, keys)| {
+                    (
+                        size.name(),
+                        keys.iter().map(|k| base64::encode(k)).collect(),
+                    )
+                })
+                .collect();
+            let keys_json = serde_json::to_string_pretty(&keys_by_name)?;
+            std::fs::write(&keys_path, keys_json)?;
+        }
+        KeysFormat::Binary => {
+            let mut data = Vec::new();
+            keys_file::write_binary(&mut data, &keys_by_size)?;
+            std::fs::write(&keys_path, data)?;
+        }
+    }
+    for other in [JSON_KEYS_FILE, BINARY_KEYS_FILE] {
+        let other_path = output_dir.join(other);
+        if other_path != keys_path && other_path.exists() {
+            std::fs::remove_file(&other_path)
+                .with_context(|| format!("Failed to remove stale {}", other_path.display()))?;
         }
     }
+    println!("  Created: {}", keys_path.display());
+    Ok(keys_path)
+}
 
-    let config = BenchmarkConfig {
-        num_lookups,
-        warmup_iterations: 1000,
-        seed,
-    };
+/// Entries `build` inserts, verifies and lists in keys.json, visited one at a
+/// time so a source need not hold them all
+trait EntrySource {
+    fn len(&self) -> usize;
+    fn for_each(&self, f: &mut dyn FnMut(&Entry) -> Result<()>) -> Result<()>;
 
-    let mut all_results = Vec::new();
+    /// Time spent generating entries inside `for_each` so far, which timed
+    /// builds leave out; zero for entries generated up front
+    fn generation_time(&self) -> Duration {
+        Duration::ZERO
+    }
+}
 
-    // Benchmark SQLite (WITHOUT ROWID)
-    println!("\nBenchmarking SQLite (WITHOUT ROWID)...");
-    let sqlite_without_rowid_path = input_dir.join("index_sqlite_without_rowid.sqlite");
-    if sqlite_without_rowid_path.exists() {
-        let results = benchmark_store::<SqliteWithoutRowidStore>(
-            &sqlite_without_rowid_path,
-            &all_keys,
-            &keys_by_size,
-            &config,
-            verbose,
-        )?;
-        all_results.extend(results);
-    } else {
-        println!("  Skipped (file not found)");
-    }
-
-    // Benchmark SQLite (ROWID)
-    println!("\nBenchmarking SQLite (ROWID)...");
-    let sqlite_rowid_path = input_dir.join("index_sqlite_rowid.sqlite");
-    if sqlite_rowid_path.exists() {
-        let results = benchmark_store::<SqliteRowidStore>(
-            &sqlite_rowid_path,
-            &all_keys,
-            &keys_by_size,
-            &config,
-            verbose,
-        )?;
-        all_results.extend(results);
-    } else {
-        println!("  Skipped (file not found)");
-    }
-
-    // Benchmark Hash DAT
-    println!("\nBenchmarking Hash DAT...");
-    let hash_path = input_dir.join("index_hash.dat");
-    if hash_path.exists() {
-        let results = benchmark_store::<HashDatStore>(
-            &hash_path,
-            &all_keys,
-            &keys_by_size,
-            &config,
-            verbose,
-        )?;
-        all_results.extend(results);
+impl EntrySource for [Entry] {
+    fn len(&self) -> usize {
+        <[Entry]>::len(self)
+    }
+
+    fn for_each(&self, f: &mut dyn FnMut(&Entry) -> Result<()>) -> Result<()> {
+        self.iter().try_for_each(f)
+    }
+}
+
+impl EntrySource for Vec<Entry> {
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn for_each(&self, f: &mut dyn FnMut(&Entry) -> Result<()>) -> Result<()> {
+        self.as_slice().for_each(f)
+    }
+}
+
+/// Entries regenerated chunk by chunk on every pass (`build --stream`), so
+/// generated values never all sit in memory at once. Each pass repeats the
+/// generation work. Builders that sort or hash the whole data set before
+/// writing (Hash DAT, Zstd DAT, MPH DAT, B+tree, BTree DAT, SSTable, LMDB,
+/// In-Memory) still buffer every entry themselves; SQLite, Zip and RocksDB write as entries arrive.
+struct StreamedEntries<'a> {
+    generator: &'a DataGenerator,
+    generating: Cell<Duration>,
+}
+
+impl<'a> StreamedEntries<'a> {
+    fn new(generator: &'a DataGenerator) -> Self {
+        Self {
+            generator,
+            generating: Cell::new(Duration::ZERO),
+        }
+    }
+}
+
+impl EntrySource for StreamedEntries<'_> {
+    fn len(&self) -> usize {
+        let config = self.generator.config();
+        BlobSize::all()
+            .iter()
+            .map(|&size| config.entries_for_size(size))
+            .sum()
+    }
+
+    fn for_each(&self, f: &mut dyn FnMut(&Entry) -> Result<()>) -> Result<()> {
+        let generating = self.generator.generate_streaming(|entry| f(&entry))?;
+        self.generating.set(self.generating.get() + generating);
+        Ok(())
+    }
+
+    fn generation_time(&self) -> Duration {
+        self.generating.get()
+    }
+}
+
+fn build_store<B: BlobStoreBuilder>(
+    path: &Path,
+    entries: &(impl EntrySource + ?Sized),
+) -> Result<Duration> {
+    time_build(|| B::create(path), entries)
+}
+
+/// Create a builder and populate it, timing both but not the generation of
+/// streamed entries (see `EntrySource::generation_time`)
+fn time_build<B: BlobStoreBuilder>(
+    create: impl FnOnce() -> Result<B>,
+    entries: &(impl EntrySource + ?Sized),
+) -> Result<Duration> {
+    let generated_before = entries.generation_time();
+    let started = Instant::now();
+    populate_store(create()?, entries)?;
+    let elapsed = started.elapsed();
+    Ok(elapsed.saturating_sub(entries.generation_time() - generated_before))
+}
+
+/// Insert all entries into an already-configured builder and finish it
+fn populate_store<B: BlobStoreBuilder>(
+    mut builder: B,
+    entries: &(impl EntrySource + ?Sized),
+) -> Result<()> {
+    entries.for_each(&mut |entry| builder.insert(&entry.key, &entry.value))?;
+    builder.finish()?;
+    Ok(())
+}
+
+/// Build every single-file backend except MPH twice into a scratch directory
+/// under `output_dir`, with the Hash and B-tree DAT options `build` used, and
+/// fail if any pair differs. SQLite's page layout and Zip's entry timestamps
+/// vary between builds, so those are compared by `data_digest` instead of bytes.
+fn verify_determinism(
+    output_dir: &Path,
+    entries: &(impl EntrySource + ?Sized),
+    options: &BuildOptions,
+) -> Result<()> {
+    println!("\nVerifying deterministic builds...");
+    let scratch = output_dir.join(".determinism");
+    let checks = [
+        builds_match::<SqliteWithoutRowidStore>(&scratch, false, |path| {
+            build_store::<SqliteWithoutRowidStoreBuilder>(path, entries)
+        }),
+        builds_match::<SqliteRowidStore>(&scratch, false, |path| {
+            build_store::<SqliteRowidStoreBuilder>(path, entries)
+        }),
+        builds_match::<HashDatStore>(&scratch, true, |path| {
+            let mut builder = HashDatStoreBuilder::create(path)?.with_hash_algo(options.hash_algo);
+            if options.hash_bloom {
+                builder = builder.with_bloom_filter();
+            }
+            if options.verify_checksum {
+                builder = builder.with_checksums();
+            }
+            populate_store(builder, entries)
+        }),
+        builds_match::<ZstdDatStore>(&scratch, true, |path| {
+            build_store::<ZstdDatStoreBuilder>(path, entries)
+        }),
+        builds_match::<BTreeDatStore>(&scratch, true, |path| {
+            let mut builder = BTreeDatStoreBuilder::create(path)?;
+            if options.verify_checksum {
+                builder = builder.with_checksums();
+            }
+            populate_store(builder, entries)
+        }),
+        builds_match::<BPlusTreeStore>(&scratch, true, |path| {
+            build_store::<BPlusTreeStoreBuilder>(path, entries)
+        }),
+        builds_match::<ZipStore>(&scratch, false, |path| {
+            build_store::<ZipStoreBuilder>(path, entries)
+        }),
+        builds_match::<MemoryStore>(&scratch, true, |path| {
+            build_store::<MemoryStoreBuilder>(path, entries)
+        }),
+        builds_match::<SstableStore>(&scratch, true, |path| {
+            build_store::<SstableStoreBuilder>(path, entries)
+        }),
+    ];
+    let _ = std::fs::remove_dir_all(&scratch);
+
+    let mut differing = Vec::new();
+    for check in checks {
+        let (backend, matched) = check?;
+        if !matched {
+            differing.push(backend);
+        }
+    }
+    if !differing.is_empty() {
+        anyhow::bail!("Non-deterministic builds: {}", differing.join(", "));
+    }
+    Ok(())
+}
+
+/// Build one backend twice into `scratch` and report (backend name, whether
+/// the builds match). Compares raw bytes when `byte_identical`, otherwise the
+/// stores' logical `data_digest`.
+fn builds_match<S: BlobStore, T>(
+    scratch: &Path,
+    byte_identical: bool,
+    build: impl Fn(&Path) -> Result<T>,
+) -> Result<(&'static str, bool)> {
+    std::fs::create_dir_all(scratch).context("Failed to create determinism scratch directory")?;
+    let first = scratch.join("first");
+    let second = scratch.join("second");
+    for path in [&first, &second] {
+        // SQLite appends to an existing database, so always start fresh
+        let _ = std::fs::remove_file(path);
+        build(path)?;
+    }
+
+    let matched = if byte_identical {
+        std::fs::read(&first)? == std::fs::read(&second)?
     } else {
-        println!("  Skipped (file not found)");
+        S::open(&first)?.data_digest()? == S::open(&second)?.data_digest()?
+    };
+    println!(
+        "  {}: {} ({})",
+        S::backend_name(),
+        if matched { "deterministic" } else { "DIFFERS" },
+        if byte_identical {
+            "bytes"
+        } else {
+            "data digest"
+        }
+    );
+    Ok((S::backend_name(), matched))
+}
+
+/// Create `dir` if needed and prove it accepts new files, so an unwritable
+/// output fails up front instead of after data generation or a benchmark run
+fn ensure_writable_dir(dir: &Path) -> Result<()> {
+    let not_writable = || format!("output directory is not writable: {}", dir.display());
+    std::fs::create_dir_all(dir).with_context(not_writable)?;
+    let probe = dir.join(format!(".write_test_{}", std::process::id()));
+    std::fs::File::create(&probe).with_context(not_writable)?;
+    std::fs::remove_file(&probe).with_context(not_writable)
+}
+
+fn file_size_mb(path: &Path) -> Result<f64> {
+    Ok(path_size(path)? as f64 / 1_048_576.0)
+}
+
+/// Size in bytes of a file, or of every file under a directory (for
+/// directory-based stores like RocksDB)
+fn path_size(path: &Path) -> Result<u64> {
+    let metadata = std::fs::metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
     }
+    let mut total = 0;
+    for entry in std::fs::read_dir(path)? {
+        total += path_size(&entry?.path())?;
+    }
+    Ok(total)
+}
 
-    // Benchmark Zip
-    println!("\nBenchmarking Zip...");
-    let zip_path = input_dir.join("index.zip");
-    if zip_path.exists() {
-        let results =
-            benchmark_store::<ZipStore>(&zip_path, &all_keys, &keys_by_size, &config, verbose)?;
-        all_results.extend(results);
+/// Verify that all entries can be read back correctly from a store.
+/// Backends that keep one entry per key may report fewer entries than were
+/// inserted when keys repeat; that is reported rather than treated as an error.
+/// Only keys and value hashes are held, so a streamed source is checked
+/// without materializing its values.
+fn verify_store<S: BlobStore>(
+    path: &Path,
+    entries: &(impl EntrySource + ?Sized),
+) -> Result<VerifyReport> {
+    use std::io::Write;
+
+    print!("  Verifying {} entries... ", entries.len());
+    std::io::stdout().flush()?;
+
+    let store = S::open(path)?;
+    let report = verify_streamed(&store, |f| entries.for_each(f))?;
+    check_verify_report(&report)?;
+    println!("OK");
+    Ok(report)
+}
+
+/// Generator settings from the manifest.json next to `input`, if it lists
+/// `input` among its stores, or else `build`'s defaults
+fn recorded_data_gen_config(input: &Path) -> Result<DataGenConfig> {
+    let dir = input.parent().unwrap_or(Path::new("."));
+    let lists_input = |manifest: &DatasetManifest| {
+        let name = input.file_name();
+        manifest
+            .stores
+            .iter()
+            .any(|store| name == Some(std::ffi::OsStr::new(store)))
+    };
+    match DatasetManifest::load(dir)? {
+        Some(manifest) if lists_input(&manifest) => {
+            println!(
+                "Dataset settings from {}",
+                dir.join(DATASET_MANIFEST_FILE).display()
+            );
+            manifest.data_gen_config()
+        }
+        _ => Ok(DataGenConfig::new(
+            BuildOptions::default().entries_per_size,
+            BuildOptions::default().seed,
+        )),
+    }
+}
+
+/// Check an existing index of any format against entries regenerated from
+/// `config`, chunk by chunk so the data set is never held in memory
+fn verify_index(input: &Path, config: DataGenConfig) -> Result<VerifyReport> {
+    use std::io::Write;
+
+    let store = open_any(input)?;
+    println!("Verifying {} ({})", input.display(), store.backend_name());
+    let generator = DataGenerator::new(config);
+    let entries = StreamedEntries::new(&generator);
+    print!("  Checking {} entries... ", entries.len());
+    std::io::stdout().flush()?;
+
+    let report = verify_streamed(store.as_ref(), |f| entries.for_each(f))?;
+    check_verify_report(&report)?;
+    println!("OK");
+    println!(
+        "  {} of {} entries matched ({} stored)",
+        report.matched, report.inserted, report.stored
+    );
+    Ok(report)
+}
+
+/// Print what `report` found and fail if the store lost or corrupted entries
+fn check_verify_report(report: &VerifyReport) -> Result<()> {
+    let show_key = |key: &[u8]| format!("{:?}", String::from_utf8_lossy(&key[..key.len().min(32)]));
+
+    if report.count_mismatch() {
+        println!("FAILED");
+        anyhow::bail!(
+            "Entry count mismatch: expected {} ({} distinct keys), got {}",
+            report.inserted,
+            report.distinct_keys,
+            report.stored
+        );
+    }
+    if report.deduplicated() > 0 {
+        let examples: Vec<String> = report
+            .duplicate_keys
+            .iter()
+            .map(|key| show_key(key))
+            .collect();
+        eprintln!(
+            "\n    Store deduplicated {} entries ({} stored of {} inserted); \
+             duplicate keys include {}",
+            report.deduplicated(),
+            report.stored,
+            report.inserted,
+            examples.join(", ")
+        );
+    }
+    for failure in &report.failures {
+        match failure {
+            VerifyFailure::Missing { key } => eprintln!("\n    Missing key: {}", show_key(key)),
+            VerifyFailure::Mismatch {
+                key,
+                expected_len,
+                actual_len,
+            } => eprintln!(
+                "\n    Value mismatch for key {}: expected {} bytes, got {} bytes",
+                show_key(key),
+                expected_len,
+                actual_len
+            ),
+        }
+    }
+    if report.errors() > 0 {
+        println!("FAILED");
+        anyhow::bail!(
+            "{} verification errors out of {} entries ({} missing, {} mismatched)",
+            report.errors(),
+            report.inserted,
+            report.missing,
+            report.mismatched
+        );
+    }
+    Ok(())
+}
+
+/// Reclaim free pages in a SQLite index. Other backends are written compactly already.
+fn compact_index(path: &Path) -> Result<()> {
+    if !build_an_index::backends::sqlite::is_sqlite_file(path)? {
+        println!(
+            "Compaction not supported for {} (only SQLite indices can be compacted)",
+            path.display()
+        );
+        return Ok(());
+    }
+
+    println!("Compacting {}...", path.display());
+    let before = file_size_mb(path)?;
+    build_an_index::backends::sqlite::compact(path)?;
+    let after = file_size_mb(path)?;
+    println!("  Before: {:.2} MB", before);
+    println!("  After:  {:.2} MB", after);
+    Ok(())
+}
+
+fn print_value_size_stats(
+    path: &Path,
+    backend: &BenchBackend,
+    stats: &ValueSizeStats,
+    histogram: bool,
+) {
+    println!("{} ({})", path.display(), (backend.backend_name)());
+    println!("  Entries: {}", stats.count());
+    println!(
+        "  Value size: min {} B, median {} B, p99 {} B, max {} B",
+        stats.min(),
+        stats.percentile(50.0),
+        stats.percentile(99.0),
+        stats.max()
+    );
+    if histogram {
+        println!("\n  Value sizes (bytes, power-of-two buckets):");
+        print!("{}", stats.render_histogram(50));
+    }
+}
+
+fn repair_index(path: &Path, out: &Path) -> Result<()> {
+    println!("Repairing {} -> {}...", path.display(), out.display());
+    let recovered = build_an_index::backends::dat_hash::repair(path, out)?;
+    let store = HashDatStore::open(out).context("Repaired file failed to open")?;
+    println!("  Recovered {} entries", recovered);
+    println!("  Repaired index holds {} entries", store.len());
+    Ok(())
+}
+
+/// Describe the manifest embedded in a .dat file, reading only its trailer
+fn inspect_report(path: &Path) -> Result<String> {
+    let mut report = format!("{}\n", path.display());
+    match read_manifest(path)? {
+        Some(manifest) => {
+            report += &format!("  Format: {}\n", manifest.format);
+            report += &format!("  Version: {}\n", manifest.version);
+            report += &format!("  Entries: {}\n", manifest.entry_count);
+            report += &format!("  Built at: {} (Unix seconds)\n", manifest.built_at);
+            if let Some(hash_algo) = &manifest.hash_algo {
+                report += &format!("  Hash algorithm: {}\n", hash_algo);
+            }
+            if let Some(fingerprint) = &manifest.fingerprint {
+                report += &format!("  Config fingerprint: {}\n", fingerprint);
+            }
+        }
+        None => report += "  No embedded manifest (build with --embed-manifest)\n",
+    }
+    Ok(report)
+}
+
+/// Copy every entry of the index at `from` into a new `to_format` index at `to`
+fn migrate_index(from: &Path, to: &Path, to_format: StoreFormat) -> Result<()> {
+    let source = open_any(from)?;
+    println!(
+        "Migrating {} ({}) -> {} ({:?})...",
+        from.display(),
+        source.backend_name(),
+        to.display(),
+        to_format
+    );
+    let source = source.as_ref();
+    let migrated = match to_format {
+        StoreFormat::Sqlite => copy_entries(source, SqliteWithoutRowidStoreBuilder::create(to)?),
+        StoreFormat::Hash => copy_entries(source, HashDatStoreBuilder::create(to)?),
+        StoreFormat::Zstd => copy_entries(source, ZstdDatStoreBuilder::create(to)?),
+        StoreFormat::BTree => copy_entries(source, BTreeDatStoreBuilder::create(to)?),
+        StoreFormat::BPlusTree => copy_entries(source, BPlusTreeStoreBuilder::create(to)?),
+        StoreFormat::Zip => copy_entries(source, ZipStoreBuilder::create(to)?),
+        StoreFormat::Memory => copy_entries(source, MemoryStoreBuilder::create(to)?),
+        StoreFormat::Sstable => copy_entries(source, SstableStoreBuilder::create(to)?),
+        #[cfg(feature = "mph")]
+        StoreFormat::Mph => copy_entries(source, MphDatStoreBuilder::create(to)?),
+    }?;
+    println!("  Migrated {} entries", migrated);
+    Ok(())
+}
+
+/// Encoding of a key given on the command line. Explicit because many base64
+/// strings are also valid hex
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum KeyFormat {
+    Hex,
+    Base64,
+}
+
+/// Decode a key given on the command line
+fn parse_key_arg(key: &str, format: KeyFormat) -> Result<Vec<u8>> {
+    match format {
+        KeyFormat::Hex => hex::decode(key),
+        KeyFormat::Base64 => base64::decode(key),
+    }
+    .with_context(|| format!("Key '{}' is not valid {:?}", key, format))
+}
+
+/// Write the value of `key` to stdout as raw bytes, or list the keys (sorted,
+/// up to `limit`) one per line as `<hex key>\t<value length>`
+fn dump_index(
+    input: &Path,
+    key: Option<&str>,
+    key_format: KeyFormat,
+    limit: Option<usize>,
+) -> Result<()> {
+    use std::io::Write;
+
+    let store = open_any(input)?;
+    let mut stdout = std::io::stdout().lock();
+    if let Some(key) = key {
+        let value = store
+            .get(&parse_key_arg(key, key_format)?)?
+            .with_context(|| format!("Key '{}' not found in {}", key, input.display()))?;
+        stdout.write_all(&value)?;
+        return Ok(());
+    }
+
+    let mut keys = store.keys()?;
+    keys.sort_unstable();
+    for key in keys.iter().take(limit.unwrap_or(usize::MAX)) {
+        let len = store
+            .value_len(key)?
+            .with_context(|| format!("Listed key {} has no value", hex::encode(key)))?;
+        writeln!(stdout, "{}\t{}", hex::encode(key), len)?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Insert every entry of `source` into `builder` and finish it
+fn copy_entries<B: BlobStoreBuilder>(
+    source: &dyn build_an_index::store::DynBlobStore,
+    mut builder: B,
+) -> Result<usize> {
+    let keys = source.keys()?;
+    for key in &keys {
+        let value = source.get(key)?.with_context(|| {
+            format!(
+                "Key {} listed by the source index has no value",
+                base64::encode(key)
+            )
+        })?;
+        builder.insert(key, &value)?;
+    }
+    builder.finish()?;
+    Ok(keys.len())
+}
+
+/// Load the per-size key lists written by `build`, from keys.bin if present
+/// and keys.json otherwise
+fn load_keys(input_dir: &Path) -> Result<HashMap<BlobSize, Vec<Vec<u8>>>> {
+    let binary_path = input_dir.join(BINARY_KEYS_FILE);
+    let path = if binary_path.exists() {
+        binary_path
     } else {
-        println!("  Skipped (file not found)");
+        input_dir.join(JSON_KEYS_FILE)
+    };
+    load_keys_file(&path).with_context(|| {
+        format!(
+            "Failed to read {} or {}. Did you run 'build' first?",
+            JSON_KEYS_FILE, BINARY_KEYS_FILE
+        )
+    })
+}
+
+/// Load per-size key lists from a file in keys.json or keys.bin format,
+/// detected from its magic bytes
+fn load_keys_file(path: &Path) -> Result<HashMap<BlobSize, Vec<Vec<u8>>>> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if keys_file::is_binary(&data) {
+        return keys_file::read_binary(&data)
+            .with_context(|| format!("Failed to parse {}", path.display()));
+    }
+    let keys_by_size_str: HashMap<String, Vec<String>> = serde_json::from_slice(&data)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    BlobSize::all()
+        .iter()
+        .map(|size| {
+            let keys = keys_by_size_str
+                .get(size.name())
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .map(|k| {
+                    base64::decode(k).with_context(|| {
+                        format!(
+                            "Key '{}' for size {} in {} is not valid base64",
+                            k,
+                            size.name(),
+                            path.display()
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok((*size, keys))
+        })
+        .collect()
+}
+
+/// Keys in `current` that are absent from `baseline`, per size, in `current`'s order
+fn diff_keys(
+    current: &HashMap<BlobSize, Vec<Vec<u8>>>,
+    baseline: &HashMap<BlobSize, Vec<Vec<u8>>>,
+) -> HashMap<BlobSize, Vec<Vec<u8>>> {
+    let known: std::collections::HashSet<&[u8]> = baseline
+        .values()
+        .flatten()
+        .map(|key| key.as_slice())
+        .collect();
+    current
+        .iter()
+        .map(|(size, keys)| {
+            let new_keys = keys
+                .iter()
+                .filter(|key| !known.contains(key.as_slice()))
+                .cloned()
+                .collect();
+            (*size, new_keys)
+        })
+        .collect()
+}
+
+/// Keys the benchmark should look up: everything in keys.json by default, or
+/// only the newly-ingested keys when a new-keys file or baseline is given,
+/// truncated to the first `limit_entries` keys of each size
+fn load_bench_keys(
+    input_dir: &Path,
+    new_keys_file: Option<&Path>,
+    baseline_keys: Option<&Path>,
+    limit_entries: Option<usize>,
+) -> Result<HashMap<BlobSize, Vec<Vec<u8>>>> {
+    let mut keys_by_size = match (new_keys_file, baseline_keys) {
+        (Some(new_keys_file), _) => load_keys_file(new_keys_file)?,
+        (None, Some(baseline_keys)) => {
+            diff_keys(&load_keys(input_dir)?, &load_keys_file(baseline_keys)?)
+        }
+        (None, None) => load_keys(input_dir)?,
+    };
+    if new_keys_file.is_some() || baseline_keys.is_some() {
+        let total: usize = keys_by_size.values().map(|keys| keys.len()).sum();
+        println!("Restricting lookups to {} new keys", total);
+        if total == 0 {
+            anyhow::bail!("No new keys to benchmark");
+        }
+    }
+    if let Some(limit) = limit_entries {
+        for keys in keys_by_size.values_mut() {
+            keys.truncate(limit);
+        }
+        let total: usize = keys_by_size.values().map(|keys| keys.len()).sum();
+        println!(
+            "Limiting to the first {} keys per size ({} keys)",
+            limit, total
+        );
+    }
+    Ok(keys_by_size)
+}
+
+/// One backend `bench` measures: the name it goes by on the command line, the
+/// index file `build` writes for it, and how to open that file
+#[derive(Debug, Clone, Copy)]
+struct BenchBackend {
+    /// Name accepted by `--backend-lookups`, e.g. `hash-mmap`
+    arg: &'static str,
+    /// Short name for progress output
+    label: &'static str,
+    file_name: &'static str,
+    backend_name: fn() -> &'static str,
+    open: fn(&Path) -> Result<Box<dyn build_an_index::store::DynBlobStore>>,
+    /// Open a store the `--threads` pass can share between threads, if the
+    /// backend has a `Sync` one
+    open_shared: Option<fn(&Path) -> Result<Box<dyn build_an_index::store::DynBlobStore + Sync>>>,
+}
+
+impl BenchBackend {
+    fn of<S: BlobStore + 'static>(
+        arg: &'static str,
+        label: &'static str,
+        file_name: &'static str,
+    ) -> Self {
+        Self {
+            arg,
+            label,
+            file_name,
+            backend_name: S::backend_name,
+            open: |path| Ok(Box::new(S::open(path)?)),
+            open_shared: None,
+        }
+    }
+
+    /// A backend whose store `S` can also be shared between threads as `P`
+    fn shared<S: BlobStore + 'static, P: BlobStore + Sync + 'static>(
+        arg: &'static str,
+        label: &'static str,
+        file_name: &'static str,
+    ) -> Self {
+        Self {
+            open_shared: Some(|path| Ok(Box::new(P::open(path)?))),
+            ..Self::of::<S>(arg, label, file_name)
+        }
+    }
+
+    /// Every backend `bench` measures, in the order it measures them
+    fn all() -> Vec<BenchBackend> {
+        let mut backends = vec![
+            BenchBackend::shared::<SqliteWithoutRowidStore, SqliteWithoutRowidPooledStore>(
+                "sqlite-without-rowid",
+                "SQLite (WITHOUT ROWID)",
+                "index_sqlite_without_rowid.sqlite",
+            ),
+            BenchBackend::shared::<SqliteRowidStore, SqliteRowidPooledStore>(
+                "sqlite-rowid",
+                "SQLite (ROWID)",
+                "index_sqlite_rowid.sqlite",
+            ),
+            BenchBackend::shared::<HashDatStore, HashDatStore>(
+                "hash",
+                "Hash DAT",
+                "index_hash.dat",
+            ),
+            BenchBackend::shared::<HashDatMmapStore, HashDatMmapStore>(
+                "hash-mmap",
+                "Hash DAT (mmap)",
+                "index_hash.dat",
+            ),
+            BenchBackend::shared::<ZstdDatStore, ZstdDatStore>(
+                "zstd",
+                "Zstd DAT",
+                "index_zstd.dat",
+            ),
+            BenchBackend::shared::<MemoryStore, MemoryStore>(
+                "memory",
+                "In-Memory",
+                "index_memory.bin",
+            ),
+        ];
+        #[cfg(feature = "mph")]
+        backends.push(BenchBackend::of::<MphDatStore>(
+            "mph",
+            "MPH DAT",
+            "index_mph.dat",
+        ));
+        backends.extend([
+            BenchBackend::shared::<BPlusTreeStore, BPlusTreeStore>(
+                "bplustree",
+                "B+tree",
+                "index_bplustree.dat",
+            ),
+            BenchBackend::shared::<SstableStore, SstableStore>(
+                "sstable",
+                "SSTable",
+                "index_sstable.sst",
+            ),
+            BenchBackend::of::<ZipStore>("zip", "Zip", "index.zip"),
+        ]);
+        #[cfg(feature = "rocksdb")]
+        backends.push(BenchBackend::shared::<RocksDbStore, RocksDbStore>(
+            "rocksdb",
+            "RocksDB",
+            "index_rocksdb",
+        ));
+        #[cfg(feature = "lmdb")]
+        backends.push(BenchBackend::shared::<LmdbStore, LmdbStore>(
+            "lmdb",
+            "LMDB",
+            "index_lmdb",
+        ));
+        backends
+    }
+
+    /// Look a backend up by its command-line name, ignoring case
+    fn named(arg: &str) -> Result<BenchBackend, String> {
+        let backends = Self::all();
+        backends
+            .iter()
+            .find(|backend| backend.arg.eq_ignore_ascii_case(arg))
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = backends.iter().map(|backend| backend.arg).collect();
+                format!(
+                    "unknown backend `{}` (expected one of: {})",
+                    arg,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Parse one `--backend-lookups` entry of the form `<backend>=<lookups>` into
+/// the backend's display name (as results report it) and the lookup count
+fn parse_backend_lookups(s: &str) -> Result<(String, usize), String> {
+    let (name, count) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected <backend>=<lookups>, got `{}`", s))?;
+    let backend = BenchBackend::named(name.trim())?;
+    let count = count
+        .trim()
+        .parse::<usize>()
+        .map_err(|e| format!("invalid lookup count `{}`: {}", count, e))?;
+    if count == 0 {
+        return Err(format!("lookup count for `{}` must be at least 1", name));
+    }
+    Ok(((backend.backend_name)().to_string(), count))
+}
+
+/// `--chart-scale` must be above 0.0 and at most 16.0
+const CHART_SCALE_RANGE: (Bound<f64>, Bound<f64>) = (Bound::Excluded(0.0), Bound::Included(16.0));
+
+/// Value parser for a float option that must lie in `range`, e.g. `--miss-ratio`
+/// (`0.0..=1.0`). `name` is the option as error messages call it.
+fn parse_f64_in(
+    name: &'static str,
+    range: impl RangeBounds<f64> + Clone + Send + Sync + 'static,
+) -> impl Fn(&str) -> Result<f64, String> + Clone + Send + Sync + 'static {
+    move |s| {
+        let value = s
+            .trim()
+            .parse::<f64>()
+            .map_err(|e| format!("invalid {} `{}`: {}", name, s, e))?;
+        if !range.contains(&value) {
+            let lower = match range.start_bound() {
+                Bound::Included(start) => Some(format!("at least {:?}", start)),
+                Bound::Excluded(start) => Some(format!("above {:?}", start)),
+                Bound::Unbounded => None,
+            };
+            let upper = match range.end_bound() {
+                Bound::Included(end) => Some(format!("at most {:?}", end)),
+                Bound::Excluded(end) => Some(format!("below {:?}", end)),
+                Bound::Unbounded => None,
+            };
+            let limits: Vec<String> = lower.into_iter().chain(upper).collect();
+            return Err(format!(
+                "{} must be {}, got {:?}",
+                name,
+                limits.join(" and "),
+                value
+            ));
+        }
+        Ok(value)
+    }
+}
+
+/// Timings a `bench-child` process reports on stdout
+#[derive(Debug, Serialize, Deserialize)]
+struct ChildBurst {
+    open_ns: u64,
+    lookup_ns: u64,
+    lookups: usize,
+}
+
+/// Body of the `bench-child` command: open the store and time a burst of lookups
+fn run_child_burst(
+    input_dir: &Path,
+    backend: BenchBackend,
+    lookups: usize,
+    seed: u64,
+) -> Result<ChildBurst> {
+    let keys_by_size = load_keys(input_dir)?;
+    // Flatten in a fixed order so a seed always picks the same keys
+    let all_keys: Vec<Vec<u8>> = BlobSize::all()
+        .iter()
+        .flat_map(|size| keys_by_size[size].iter().cloned())
+        .collect();
+    let path = input_dir.join(backend.file_name);
+
+    let open_start = Instant::now();
+    let store = (backend.open)(&path)?;
+    let open_ns = open_start.elapsed().as_nanos() as u64;
+
+    let mut rng = SeededRng::seed_from_u64(seed);
+    let lookup_start = Instant::now();
+    for _ in 0..lookups {
+        if let Some(key) = all_keys.choose(&mut rng) {
+            store.get(key)?;
+        }
+    }
+    let lookup_ns = lookup_start.elapsed().as_nanos() as u64;
+
+    Ok(ChildBurst {
+        open_ns,
+        lookup_ns,
+        lookups,
+    })
+}
+
+/// Spawn this binary as a `bench-child` and time it end to end
+fn spawn_child_burst(
+    input_dir: &Path,
+    backend: &BenchBackend,
+    lookups: usize,
+    seed: u64,
+) -> Result<(Duration, ChildBurst)> {
+    let exe = std::env::current_exe().context("Failed to locate benchmark binary")?;
+    let start = Instant::now();
+    let output = std::process::Command::new(exe)
+        .arg("bench-child")
+        .arg("--input")
+        .arg(input_dir)
+        .args(["--backend", backend.arg])
+        .args(["--lookups", &lookups.to_string()])
+        .args(["--seed", &seed.to_string()])
+        .output()
+        .context("Failed to spawn bench-child process")?;
+    let wall = start.elapsed();
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "bench-child for {} failed ({}): {}",
+            (backend.backend_name)(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let burst = serde_json::from_str(stdout.lines().last().unwrap_or_default())
+        .context("Failed to parse bench-child output")?;
+    Ok((wall, burst))
+}
+
+/// Run `bursts` child-process bursts per backend and print each burst's wall time
+fn run_process_bursts(input_dir: &Path, bursts: usize, lookups: usize, seed: u64) -> Result<()> {
+    println!("\nCross-process bursts ({} lookups per process):", lookups);
+    for backend in BenchBackend::all() {
+        if !input_dir.join(backend.file_name).exists() {
+            continue;
+        }
+        println!("  {}:", (backend.backend_name)());
+        let mut total = Duration::ZERO;
+        for i in 0..bursts {
+            let (wall, burst) = spawn_child_burst(input_dir, &backend, lookups, seed + i as u64)?;
+            total += wall;
+            println!(
+                "    burst {}: {:.2?} wall (open {:.2?}, lookups {:.2?})",
+                i + 1,
+                wall,
+                Duration::from_nanos(burst.open_ns),
+                Duration::from_nanos(burst.lookup_ns)
+            );
+        }
+        println!("    mean: {:.2?} wall", total.div_f64(bursts as f64));
+    }
+    Ok(())
+}
+
+/// Open each backend afresh and sample per-key cold/warm lookup latency
+fn run_cold_warm_pass(
+    input_dir: &Path,
+    keys: &[Vec<u8>],
+    samples: usize,
+    seed: u64,
+) -> Result<Vec<ColdWarmResult>> {
+    let mut results = Vec::new();
+    for backend in BenchBackend::all() {
+        let path = input_dir.join(backend.file_name);
+        if !path.exists() {
+            continue;
+        }
+        let store = (backend.open)(&path)?;
+        let result = run_cold_warm(store.as_ref(), keys, samples, seed)?;
+        results.push(result);
+    }
+    Ok(results)
+}
+
+/// Run `config.num_lookups` lookups per backend across `config.threads` threads
+/// sharing one store. Backends without a `Sync` store are skipped.
+fn run_concurrent_pass(
+    input_dir: &Path,
+    keys: &[Vec<u8>],
+    config: &BenchmarkConfig,
+) -> Result<Vec<ConcurrentResult>> {
+    let mut results = Vec::new();
+    for backend in BenchBackend::all() {
+        let path = input_dir.join(backend.file_name);
+        if !path.exists() {
+            continue;
+        }
+        let Some(open_shared) = backend.open_shared else {
+            println!(
+                "  {}: skipped for concurrent lookups (store is not Sync)",
+                (backend.backend_name)()
+            );
+            continue;
+        };
+        let store = open_shared(&path)?;
+        results.push(run_concurrent_benchmark(store.as_ref(), keys, config)?);
+    }
+    Ok(results)
+}
+
+/// Open each backend and run only the warmup, timing both
+fn run_warmup_pass(
+    input_dir: &Path,
+    keys: &[Vec<u8>],
+    config: &BenchmarkConfig,
+) -> Result<Vec<WarmupResult>> {
+    let mut results = Vec::new();
+    for backend in BenchBackend::all() {
+        let path = input_dir.join(backend.file_name);
+        if !path.exists() || config.is_cancelled() {
+            continue;
+        }
+        let result = warmup_store(&backend, &path, keys, config)?;
+        results.push(result);
+    }
+    Ok(results)
+}
+
+/// Open one store, timing the open, and run the warmup against it
+fn warmup_store(
+    backend: &BenchBackend,
+    path: &Path,
+    keys: &[Vec<u8>],
+    config: &BenchmarkConfig,
+) -> Result<WarmupResult> {
+    let open_start = Instant::now();
+    let store = (backend.open)(path)?;
+    let open_time = open_start.elapsed();
+    let store = store.as_ref();
+    check_keys_present(store, keys, PREFLIGHT_SAMPLE_SIZE, config.seed)?;
+    let mut result = run_warmup(store, keys, config)?;
+    result.open_time = open_time;
+    Ok(result)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_benchmarks(
+    input_dir: &Path,
+    keys_by_size: HashMap<BlobSize, Vec<Vec<u8>>>,
+    output_dir: &Path,
+    config: &BenchmarkConfig,
+    chart_options: &ChartOptions,
+    dashboard: bool,
+    summary_path: Option<&Path>,
+    json_path: Option<&Path>,
+    raw_csv_path: Option<&Path>,
+    prometheus_path: Option<&Path>,
+    memory_interval: Option<Duration>,
+    warmup_shared: bool,
+    verbose: bool,
+) -> Result<()> {
+    let all_keys: Vec<Vec<u8>> = keys_by_size.values().flatten().cloned().collect();
+
+    let mut config = config.clone();
+    if warmup_shared {
+        let plan = AccessPlan::generate(&config, &all_keys, &keys_by_size);
+        config.shared_plan = Some(Arc::new(plan));
+    }
+    let config = &config;
+
+    println!("\nBenchmark Configuration:");
+    println!("  Build: {}", Provenance::current());
+    println!("  Lookups per size: {}", config.num_lookups);
+    let mut overrides: Vec<_> = config.backend_lookups.iter().collect();
+    overrides.sort();
+    for (backend, lookups) in overrides {
+        println!("    {}: {}", backend, lookups);
+    }
+    println!("  Warmup iterations: {}", config.warmup_iterations);
+    println!("  Access order: {}", config.access_order.name());
+    match config.access_pattern {
+        AccessPattern::SlidingWindow => println!(
+            "  Access pattern: {} ({} keys)",
+            config.access_pattern.name(),
+            config.window_size
+        ),
+        AccessPattern::Uniform => println!("  Access pattern: {}", config.access_pattern.name()),
+    }
+    println!("  Random seed: {}", config.seed);
+    if config.miss_ratio > 0.0 {
+        println!("  Miss ratio: {:.0}%", config.miss_ratio * 100.0);
+    }
+    let calibration = TimerCalibration::measure();
+    println!(
+        "  Timer: {:?} per call, {:?} resolution",
+        calibration.overhead, calibration.resolution
+    );
+    if config.batch_size > 1 {
+        println!("  Batch timing: {} lookups per sample", config.batch_size);
+    }
+    if config.willneed {
+        println!("  MADV_WILLNEED: before warmup (mmap-backed indices)");
+    }
+    if config.histogram {
+        println!("  Latency samples: HDR histogram (3 significant figures)");
+    }
+    if config.cold {
+        println!("  Cold cache: page cache evicted before every measured lookup");
+    }
+    println!(
+        "  Shared access plan: {}",
+        if config.shared_plan.is_some() {
+            "yes"
+        } else {
+            "no"
+        }
+    );
+    println!("  Allocator: {}", allocator_name());
+    println!("  Total keys loaded: {}", all_keys.len());
+    for size in BlobSize::all() {
+        if let Some(keys) = keys_by_size.get(size) {
+            println!("    {}: {} keys", size.name(), keys.len());
+        }
+    }
+
+    let sampler = memory_interval.map(MemorySampler::start);
+    let mut all_results = Vec::new();
+
+    for backend in BenchBackend::all() {
+        println!("\nBenchmarking {}...", backend.label);
+        let path = input_dir.join(backend.file_name);
+        if path.exists() {
+            let results =
+                benchmark_store(&backend, &path, &all_keys, &keys_by_size, config, verbose)?;
+            all_results.extend(results);
+        } else {
+            println!("  Skipped (file not found)");
+        }
+    }
+
+    let memory_timeline = sampler.map(MemorySampler::stop);
+
+    let near_floor: Vec<String> = all_results
+        .iter()
+        .filter(|r| r.batch_size == 1 && calibration.is_near_floor(r.p50()))
+        .map(|r| format!("{} {}", r.backend_name, r.blob_size.name()))
+        .collect();
+    if !near_floor.is_empty() {
+        println!(
+            "\nWARNING: median latency is within 10x of the timer resolution for: {}. \
+             Consider --batch-timing to average over several lookups.",
+            near_floor.join(", ")
+        );
     }
 
     // Print results
-    print_results(&all_results);
+    print_results(&all_results, &config.percentiles);
+    print_size_breakdown(&all_results);
+    print_phase_breakdown(&all_results);
+    print_access_breakdown(&all_results);
 
     // Generate charts
     println!("\nGenerating charts...");
     let aggregate = AggregateResults::new(all_results);
-    generate_charts(&aggregate, output_dir)?;
+    let build_stats = BuildStats::load(input_dir)?;
+    let build_times = BuildTimes::load(input_dir)?;
+    // One mapping for every chart, so a backend keeps its color across them
+    // even when it was built but not benchmarked
+    let indices = backend_indices(
+        aggregate
+            .results
+            .iter()
+            .map(|r| r.backend_name.as_str())
+            .chain(
+                build_stats
+                    .iter()
+                    .flat_map(|s| s.backends.keys().map(String::as_str)),
+            )
+            .chain(
+                build_times
+                    .iter()
+                    .flat_map(|t| t.backends.keys().map(String::as_str)),
+            ),
+    );
+    generate_charts(&aggregate, &indices, output_dir, chart_options)?;
+    if let Some(build_stats) = &build_stats {
+        generate_compression_ratio_chart(build_stats, &indices, output_dir, chart_options)?;
+    }
+    // Older index directories have no build times; the chart is skipped for them
+    if let Some(build_times) = &build_times {
+        generate_build_time_chart(build_times, &indices, output_dir, chart_options)?;
+    }
+    if let Some(memory_timeline) = &memory_timeline {
+        generate_memory_timeline_chart(memory_timeline, output_dir, chart_options)?;
+    }
+    if dashboard {
+        generate_dashboard(&aggregate, output_dir)?;
+    }
 
-    println!("\nBenchmark complete!");
+    let summary = RunSummary::new(config, &aggregate.results, build_stats.as_ref())
+        .with_key_count(all_keys.len());
+    if let Some(summary_path) = summary_path {
+        write_run_summary(summary_path, &summary)?;
+        println!("Generated: {}", summary_path.display());
+    }
+    if let Some(json_path) = json_path {
+        export_json(&aggregate.results, json_path)?;
+        println!("Generated: {}", json_path.display());
+    }
+    if let Some(raw_csv_path) = raw_csv_path {
+        export_latencies_csv(&aggregate.results, raw_csv_path)?;
+        println!("Generated: {}", raw_csv_path.display());
+    }
+    if let Some(prometheus_path) = prometheus_path {
+        write_prometheus(prometheus_path, &summary)?;
+        println!("Generated: {}", prometheus_path.display());
+    }
+
+    if config.is_cancelled() {
+        println!("\nBenchmark interrupted; partial results saved.");
+    } else {
+        println!("\nBenchmark complete!");
+    }
     Ok(())
 }
 
-fn benchmark_store<S: BlobStore>(
+fn benchmark_store(
+    backend: &BenchBackend,
     path: &Path,
     all_keys: &[Vec<u8>],
     keys_by_size: &HashMap<BlobSize, Vec<Vec<u8>>>,
     config: &BenchmarkConfig,
     verbose: bool,
 ) -> Result<Vec<build_an_index::benchmark::BenchmarkResult>> {
-    let store = S::open(path)?;
-    let file_size = std::fs::metadata(path)?.len();
-    run_benchmark_with_logging(&store, all_keys, keys_by_size, config, file_size, verbose)
+    if config.is_cancelled() {
+        println!("  Skipped (interrupted)");
+        return Ok(Vec::new());
+    }
+    let open_start = Instant::now();
+    let store = (backend.open)(path)?;
+    let open_time = open_start.elapsed();
+    let store = store.as_ref();
+    check_keys_present(store, all_keys, PREFLIGHT_SAMPLE_SIZE, config.seed)?;
+    let file_size = path_size(path)?;
+    let mut results =
+        run_benchmark_with_logging(store, all_keys, keys_by_size, config, file_size, verbose)?;
+    for result in &mut results {
+        result.open_time = open_time;
+    }
+    Ok(results)
 }
 
-// Simple base64 encoding for storing keys in JSON
-fn base64_encode(data: &[u8]) -> String {
-    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_backend_lookups_name_every_benchmarked_backend() {
+        for backend in BenchBackend::all() {
+            let (name, lookups) =
+                parse_backend_lookups(&format!("{}=7", backend.arg.to_uppercase())).unwrap();
+            assert_eq!(name, (backend.backend_name)());
+            assert_eq!(lookups, 7);
+        }
+        assert_eq!(
+            parse_backend_lookups("zstd=5").unwrap(),
+            (ZstdDatStore::backend_name().to_string(), 5)
+        );
+        assert!(parse_backend_lookups("nosuchbackend=5").is_err());
+        assert!(parse_backend_lookups("hash=0").is_err());
+    }
+
+    #[test]
+    fn test_parse_f64_in_checks_each_bound() {
+        let ratio = parse_f64_in("miss ratio", 0.0..=1.0);
+        assert_eq!(ratio(" 1.0 "), Ok(1.0));
+        assert_eq!(
+            ratio("1.5"),
+            Err("miss ratio must be at least 0.0 and at most 1.0, got 1.5".to_string())
+        );
+        assert!(ratio("half")
+            .unwrap_err()
+            .starts_with("invalid miss ratio `half`"));
+
+        let jitter = parse_f64_in("jitter", 0.0..1.0);
+        assert_eq!(jitter("0.0"), Ok(0.0));
+        assert!(jitter("1.0").unwrap_err().contains("below 1.0"));
+
+        let scale = parse_f64_in("chart scale", CHART_SCALE_RANGE);
+        assert_eq!(scale("16"), Ok(16.0));
+        assert_eq!(
+            scale("0"),
+            Err("chart scale must be above 0.0 and at most 16.0, got 0.0".to_string())
+        );
+        assert!(scale("NaN").is_err());
+    }
 
-    let mut result = String::new();
-    let mut i = 0;
+    #[test]
+    fn test_chart_dimensions_must_be_positive() {
+        let parse =
+            |args: &[&str]| Cli::try_parse_from([&["build-an-index", "bench"], args].concat());
+        assert!(parse(&["--chart-width", "0"]).is_err());
+        assert!(parse(&["--chart-height", "0"]).is_err());
+        assert!(parse(&["--chart-scale", "0"]).is_err());
+        assert!(parse(&["--chart-scale", "-1.5"]).is_err());
+        assert!(parse(&["--chart-width", "640", "--chart-scale", "2"]).is_ok());
+    }
 
-    while i < data.len() {
-        let b0 = data[i];
-        let b1 = if i + 1 < data.len() { data[i + 1] } else { 0 };
-        let b2 = if i + 2 < data.len() { data[i + 2] } else { 0 };
+    #[test]
+    fn test_btree_builds_are_deterministic() {
+        let dir = TempDir::new().unwrap();
+        let entries = DataGenerator::new(DataGenConfig::new(5, 3)).generate_all();
+        let (backend, matched) = builds_match::<BTreeDatStore>(dir.path(), true, |path| {
+            build_store::<BTreeDatStoreBuilder>(path, &entries)
+        })
+        .unwrap();
+        assert_eq!(backend, BTreeDatStore::backend_name());
+        assert!(matched);
+
+        // A different build is caught
+        let calls = std::cell::Cell::new(0);
+        let (_, matched) = builds_match::<BTreeDatStore>(dir.path(), true, |path| {
+            calls.set(calls.get() + 1);
+            build_store::<BTreeDatStoreBuilder>(path, &entries[..entries.len() - calls.get()])
+        })
+        .unwrap();
+        assert!(!matched);
+    }
 
-        result.push(ALPHABET[(b0 >> 2) as usize] as char);
-        result.push(ALPHABET[(((b0 & 0x03) << 4) | (b1 >> 4)) as usize] as char);
+    #[test]
+    fn test_verify_determinism_covers_dat_variants() {
+        let dir = TempDir::new().unwrap();
+        let entries = DataGenerator::new(DataGenConfig::new(3, 9)).generate_all();
+        let options = BuildOptions {
+            hash_bloom: true,
+            verify_checksum: true,
+            ..Default::default()
+        };
+        verify_determinism(dir.path(), &entries, &options).unwrap();
+        assert!(!dir.path().join(".determinism").exists());
+    }
 
-        if i + 1 < data.len() {
-            result.push(ALPHABET[(((b1 & 0x0f) << 2) | (b2 >> 6)) as usize] as char);
-        } else {
-            result.push('=');
+    #[test]
+    fn test_inspect_reads_embedded_manifest() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index_hash.dat");
+        let entries = DataGenerator::new(DataGenConfig::new(5, 1)).generate_all();
+        populate_store(
+            HashDatStoreBuilder::create(&path)
+                .unwrap()
+                .with_manifest(Some("seed=1")),
+            &entries,
+        )
+        .unwrap();
+
+        let report = inspect_report(&path).unwrap();
+        assert!(report.contains(&format!("Format: {}", HashDatStore::backend_name())));
+        assert!(report.contains(&format!("Entries: {}", entries.len())));
+        assert!(report.contains("Config fingerprint: seed=1"));
+        // The trailer doesn't get in the way of lookups
+        verify_store::<HashDatStore>(&path, &entries).unwrap();
+
+        let plain = dir.path().join("index_bplustree.dat");
+        build_store::<BPlusTreeStoreBuilder>(&plain, &entries).unwrap();
+        assert!(inspect_report(&plain)
+            .unwrap()
+            .contains("No embedded manifest"));
+    }
+
+    #[test]
+    fn test_warmup_only_records_open_and_warmup_time() {
+        let dir = TempDir::new().unwrap();
+        let entries = DataGenerator::new(DataGenConfig::new(5, 1)).generate_all();
+        build_store::<HashDatStoreBuilder>(&dir.path().join("index_hash.dat"), &entries).unwrap();
+        let keys: Vec<Vec<u8>> = entries.iter().map(|e| e.key.clone()).collect();
+        let config = BenchmarkConfig {
+            warmup_iterations: 50,
+            ..Default::default()
+        };
+
+        let results = run_warmup_pass(dir.path(), &keys, &config).unwrap();
+        // Hash DAT is read both with seeks and through mmap; the rest weren't built
+        let names: Vec<&str> = results.iter().map(|r| r.backend_name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                HashDatStore::backend_name(),
+                HashDatMmapStore::backend_name()
+            ]
+        );
+        for result in &results {
+            assert_eq!(result.iterations, 50);
+            assert!(result.open_time > Duration::ZERO);
+            assert!(result.ready_time() > result.warmup_time);
         }
+    }
 
-        if i + 2 < data.len() {
-            result.push(ALPHABET[(b2 & 0x3f) as usize] as char);
-        } else {
-            result.push('=');
+    #[test]
+    fn test_stale_keys_fail_preflight() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index_hash.dat");
+        let built = DataGenerator::new(DataGenConfig::new(5, 1)).generate_all();
+        build_store::<HashDatStoreBuilder>(&path, &built).unwrap();
+
+        // keys.json from a build with a different seed shares none of its keys
+        let stale = DataGenerator::new(DataGenConfig::new(5, 2)).generate_all();
+        let mut keys_by_size: HashMap<BlobSize, Vec<Vec<u8>>> = HashMap::new();
+        for entry in &stale {
+            keys_by_size
+                .entry(entry.size_category)
+                .or_default()
+                .push(entry.key.clone());
+        }
+        let all_keys: Vec<Vec<u8>> = stale.iter().map(|e| e.key.clone()).collect();
+        let config = BenchmarkConfig {
+            num_lookups: 10,
+            warmup_iterations: 0,
+            ..Default::default()
+        };
+
+        let hash = BenchBackend::named("hash").unwrap();
+        let err =
+            benchmark_store(&hash, &path, &all_keys, &keys_by_size, &config, false).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("keys.json appears stale for backend"));
+
+        let fresh: Vec<Vec<u8>> = built.iter().map(|e| e.key.clone()).collect();
+        let store = HashDatStore::open(&path).unwrap();
+        check_keys_present(&store, &fresh, PREFLIGHT_SAMPLE_SIZE, 42).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unwritable_output_dir_fails_early() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        let readonly = dir.path().join("readonly");
+        std::fs::create_dir(&readonly).unwrap();
+        std::fs::set_permissions(&readonly, std::fs::Permissions::from_mode(0o555)).unwrap();
+        // Root ignores directory permissions, so there is nothing to check
+        if std::fs::File::create(readonly.join("probe")).is_ok() {
+            return;
         }
 
-        i += 3;
+        let options = BuildOptions {
+            entries_per_size: 2,
+            ..Default::default()
+        };
+        let err = build_indices(&readonly, &options).unwrap_err();
+        assert!(
+            format!("{:#}", err).starts_with("output directory is not writable"),
+            "{:#}",
+            err
+        );
+        assert!(ensure_writable_dir(&readonly.join("nested")).is_err());
+        assert!(ensure_writable_dir(dir.path()).is_ok());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
     }
 
-    result
-}
+    #[test]
+    fn test_sorted_insert_order_inserts_ascending_keys() {
+        let dir = TempDir::new().unwrap();
+        let options = BuildOptions {
+            entries_per_size: 5,
+            insert_order: InsertOrder::Sorted,
+            ..Default::default()
+        };
+        build_indices(dir.path(), &options).unwrap();
+
+        // Zip lists entries in the order they were written
+        let zip = ZipStore::open(&dir.path().join("index.zip")).unwrap();
+        let inserted = zip.keys().unwrap();
+        assert_eq!(inserted.len(), 25);
+        assert!(inserted.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        let hash = HashDatStore::open(&dir.path().join("index_hash.dat")).unwrap();
+        for key in load_keys(dir.path()).unwrap().values().flatten() {
+            assert!(zip.get(key).unwrap().is_some());
+            assert!(hash.get(key).unwrap().is_some());
+        }
+
+        let stats = BuildStats::load(dir.path()).unwrap().unwrap();
+        assert_eq!(stats.insert_order.as_deref(), Some("sorted"));
+    }
 
-fn base64_decode(s: &str) -> Option<Vec<u8>> {
-    fn char_to_val(c: char) -> Option<u8> {
-        match c {
-            'A'..='Z' => Some(c as u8 - b'A'),
-            'a'..='z' => Some(c as u8 - b'a' + 26),
-            '0'..='9' => Some(c as u8 - b'0' + 52),
-            '+' => Some(62),
-            '/' => Some(63),
-            '=' => Some(0),
-            _ => None,
+    #[test]
+    fn test_verify_index_checks_an_existing_build() {
+        let dir = TempDir::new().unwrap();
+        let options = BuildOptions {
+            entries_per_size: 3,
+            seed: 9,
+            ..Default::default()
+        };
+        build_indices(dir.path(), &options).unwrap();
+
+        for file in ["index_hash.dat", "index_sstable.sst", "index.zip"] {
+            let path = dir.path().join(file);
+            let report = verify_index(&path, DataGenConfig::new(3, 9)).unwrap();
+            assert!(report.is_ok());
+            assert_eq!(report.matched, report.inserted);
+
+            // Data from another seed shares none of the index's keys
+            let err = verify_index(&path, DataGenConfig::new(3, 10)).unwrap_err();
+            assert!(err.to_string().contains("verification errors"), "{}", err);
         }
     }
 
-    let chars: Vec<char> = s.chars().collect();
-    if chars.len() % 4 != 0 {
-        return None;
+    #[test]
+    fn test_verify_reads_dataset_settings_from_the_manifest() {
+        let dir = TempDir::new().unwrap();
+        let options = BuildOptions {
+            entries_per_size: 3,
+            seed: 5,
+            compressibility: 0.5,
+            size_jitter: 0.1,
+            key_shape: KeyShape::Uuid,
+            insert_order: InsertOrder::Sorted,
+            ..Default::default()
+        };
+        build_indices(dir.path(), &options).unwrap();
+        let manifest = DatasetManifest::load(dir.path()).unwrap().unwrap();
+        assert_eq!(manifest.insert_order, "sorted");
+
+        let path = dir.path().join("index_hash.dat");
+        let config = recorded_data_gen_config(&path).unwrap();
+        assert_eq!(config.seed, 5);
+        assert_eq!(config.compressibility, 0.5);
+        assert_eq!(config.size_jitter, 0.1);
+        assert_eq!(config.key_shape, KeyShape::Uuid);
+        assert!(verify_index(&path, config).unwrap().is_ok());
+
+        // A store the manifest doesn't list falls back to build's defaults
+        let other = dir.path().join("other.dat");
+        std::fs::copy(&path, &other).unwrap();
+        assert_eq!(recorded_data_gen_config(&other).unwrap().seed, 42);
     }
 
-    let mut result = Vec::new();
+    #[test]
+    fn test_keys_only_build_writes_no_indices() {
+        let dir = TempDir::new().unwrap();
+        let options = BuildOptions {
+            entries_per_size: 2,
+            keys_only: true,
+            ..Default::default()
+        };
+        build_indices(dir.path(), &options).unwrap();
+
+        let files: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(files, vec!["keys.json".to_string()]);
+
+        let keys_json = std::fs::read_to_string(dir.path().join("keys.json")).unwrap();
+        let keys: HashMap<String, Vec<String>> = serde_json::from_str(&keys_json).unwrap();
+        for size in BlobSize::all() {
+            assert_eq!(keys[size.name()].len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_binary_keys_format_loads_the_same_keys() {
+        let json_dir = TempDir::new().unwrap();
+        let binary_dir = TempDir::new().unwrap();
+        let options = BuildOptions {
+            entries_per_size: 3,
+            keys_only: true,
+            ..Default::default()
+        };
+        build_indices(json_dir.path(), &options).unwrap();
+        // A stale keys.json is replaced rather than left for bench to find
+        std::fs::copy(
+            json_dir.path().join("keys.json"),
+            binary_dir.path().join("keys.json"),
+        )
+        .unwrap();
+        let binary_options = BuildOptions {
+            keys_format: KeysFormat::Binary,
+            ..options
+        };
+        let written = build_indices(binary_dir.path(), &binary_options).unwrap();
+
+        assert_eq!(written, vec![binary_dir.path().join("keys.bin")]);
+        assert!(!binary_dir.path().join("keys.json").exists());
+        let json_keys = load_keys(json_dir.path()).unwrap();
+        assert_eq!(load_keys(binary_dir.path()).unwrap(), json_keys);
+        assert_eq!(
+            load_keys_file(&binary_dir.path().join("keys.bin")).unwrap(),
+            json_keys
+        );
+    }
 
-    for chunk in chars.chunks(4) {
-        let v0 = char_to_val(chunk[0])?;
-        let v1 = char_to_val(chunk[1])?;
-        let v2 = char_to_val(chunk[2])?;
-        let v3 = char_to_val(chunk[3])?;
+    #[cfg(feature = "mph")]
+    #[test]
+    fn test_migrate_to_mph() {
+        let dir = TempDir::new().unwrap();
+        let from = dir.path().join("index_hash.dat");
+        let to = dir.path().join("index_mph.dat");
+        let mut builder = HashDatStoreBuilder::create(&from).unwrap();
+        for i in 0..20u8 {
+            builder.insert(&[b'k', i], &vec![i; i as usize]).unwrap();
+        }
+        builder.finish().unwrap();
 
-        result.push((v0 << 2) | (v1 >> 4));
+        migrate_index(&from, &to, StoreFormat::Mph).unwrap();
 
-        if chunk[2] != '=' {
-            result.push((v1 << 4) | (v2 >> 2));
+        let migrated = open_any(&to).unwrap();
+        assert_eq!(migrated.backend_name(), "MPH DAT");
+        assert_eq!(migrated.len(), 20);
+        for i in 0..20u8 {
+            assert_eq!(migrated.get(&[b'k', i]).unwrap(), Some(vec![i; i as usize]));
         }
+    }
+
+    #[test]
+    fn test_key_arg_format_is_explicit() {
+        // Valid hex and valid base64, with different bytes
+        assert_eq!(
+            parse_key_arg("abcd", KeyFormat::Hex).unwrap(),
+            vec![0xab, 0xcd]
+        );
+        assert_eq!(
+            parse_key_arg("abcd", KeyFormat::Base64).unwrap(),
+            vec![0x69, 0xb7, 0x1d]
+        );
+        assert!(parse_key_arg("a2V5MQ==", KeyFormat::Hex).is_err());
+        assert!(parse_key_arg("abc", KeyFormat::Base64).is_err());
+    }
+
+    #[test]
+    fn test_keys_file_with_invalid_base64_is_an_error() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("keys.json");
+        std::fs::write(&path, r#"{"1KB": ["a2V5MQ==", "not base64!"]}"#).unwrap();
 
-        if chunk[3] != '=' {
-            result.push((v2 << 6) | v3);
+        let err = format!("{:#}", load_keys_file(&path).unwrap_err());
+        assert!(err.contains("not base64!"), "{}", err);
+        assert!(err.contains("1KB"), "{}", err);
+    }
+
+    #[test]
+    fn test_verify_reports_deduplicated_keys() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index_btree.dat");
+        let entry = |key: &[u8], value: &[u8]| Entry {
+            key: key.to_vec(),
+            value: value.to_vec(),
+            size_category: BlobSize::Tiny,
+        };
+        let entries = vec![
+            entry(b"a", b"first"),
+            entry(b"b", b"only"),
+            entry(b"a", b"second"),
+            entry(b"c", b"one"),
+            entry(b"c", b"two"),
+        ];
+        build_store::<BTreeDatStoreBuilder>(&path, &entries).unwrap();
+
+        let report = verify_store::<BTreeDatStore>(&path, &entries).unwrap();
+        assert_eq!(report.inserted, 5);
+        assert_eq!(report.stored, 3);
+        assert_eq!(report.deduplicated(), 2);
+        assert_eq!(report.duplicate_keys, vec![b"a".to_vec(), b"c".to_vec()]);
+    }
+
+    #[test]
+    fn test_build_time_leaves_out_streamed_generation() {
+        /// Takes 200ms to "generate" its one entry, and says so
+        struct SlowSource(Cell<Duration>);
+
+        impl EntrySource for SlowSource {
+            fn len(&self) -> usize {
+                1
+            }
+
+            fn for_each(&self, f: &mut dyn FnMut(&Entry) -> Result<()>) -> Result<()> {
+                std::thread::sleep(Duration::from_millis(200));
+                self.0.set(self.0.get() + Duration::from_millis(200));
+                f(&Entry {
+                    key: b"key".to_vec(),
+                    value: b"value".to_vec(),
+                    size_category: BlobSize::Tiny,
+                })
+            }
+
+            fn generation_time(&self) -> Duration {
+                self.0.get()
+            }
         }
+
+        let dir = TempDir::new().unwrap();
+        let source = SlowSource(Cell::new(Duration::from_secs(5)));
+        let elapsed =
+            build_store::<MemoryStoreBuilder>(&dir.path().join("index.mem"), &source).unwrap();
+        assert!(elapsed < Duration::from_millis(200), "{:?}", elapsed);
     }
 
-    Some(result)
+    #[test]
+    fn test_baseline_restricts_benchmark_to_new_keys() {
+        let dir = TempDir::new().unwrap();
+        let baseline_dir = dir.path().join("baseline");
+        std::fs::create_dir_all(&baseline_dir).unwrap();
+
+        let mut small = BuildOptions {
+            entries_per_size: 2,
+            keys_only: true,
+            ..Default::default()
+        };
+        build_indices(&baseline_dir, &small).unwrap();
+        small.entries_per_size = 4;
+        build_indices(dir.path(), &small).unwrap();
+
+        let baseline_path = baseline_dir.join("keys.json");
+        let keys = load_bench_keys(dir.path(), None, Some(&baseline_path), None).unwrap();
+        let current = load_keys(dir.path()).unwrap();
+        let baseline = load_keys(&baseline_dir).unwrap();
+        for size in BlobSize::all() {
+            // Generation is deterministic per index, so the first two keys are shared
+            assert_eq!(keys[size], current[size][2..].to_vec());
+            assert!(keys[size].iter().all(|k| !baseline[size].contains(k)));
+        }
+
+        // Identical key sets leave nothing to benchmark
+        assert!(
+            load_bench_keys(dir.path(), None, Some(&dir.path().join("keys.json")), None).is_err()
+        );
+    }
+
+    #[test]
+    fn test_path_size_sums_directories_recursively() {
+        let dir = TempDir::new().unwrap();
+        let store_dir = dir.path().join("index_rocksdb");
+        std::fs::create_dir_all(store_dir.join("nested")).unwrap();
+        std::fs::write(store_dir.join("a.sst"), vec![0u8; 1000]).unwrap();
+        std::fs::write(store_dir.join("nested").join("b.sst"), vec![0u8; 24]).unwrap();
+        let file = dir.path().join("index.zip");
+        std::fs::write(&file, vec![0u8; 7]).unwrap();
+
+        assert_eq!(path_size(&store_dir).unwrap(), 1024);
+        assert_eq!(path_size(&file).unwrap(), 7);
+    }
+
+    #[test]
+    fn test_limit_entries_bounds_keys_per_size() {
+        let dir = TempDir::new().unwrap();
+        let options = BuildOptions {
+            entries_per_size: 12,
+            keys_only: true,
+            ..Default::default()
+        };
+        build_indices(dir.path(), &options).unwrap();
+
+        let all = load_bench_keys(dir.path(), None, None, None).unwrap();
+        let limited = load_bench_keys(dir.path(), None, None, Some(10)).unwrap();
+        for size in BlobSize::all() {
+            assert_eq!(all[size].len(), 12);
+            assert_eq!(limited[size], all[size][..10].to_vec());
+        }
+    }
+
+    #[test]
+    fn test_build_writes_dataset_manifest() {
+        let dir = TempDir::new().unwrap();
+        let options = BuildOptions {
+            entries_per_size: 3,
+            seed: 17,
+            ..Default::default()
+        };
+        build_indices(dir.path(), &options).unwrap();
+
+        let manifest = DatasetManifest::load(dir.path()).unwrap().unwrap();
+        assert_eq!(manifest.seed, 17);
+        assert_eq!(manifest.entries_per_size, 3);
+        assert_eq!(manifest.entries_override["1MB"], 3);
+        assert_eq!(manifest.entries, 15);
+        assert_eq!(manifest.key_shape, "synthetic");
+        assert_eq!(manifest.insert_order, "natural");
+        assert!(manifest.stores.contains(&"index_hash.dat".to_string()));
+        assert!(manifest.missing_stores(dir.path()).is_empty());
+        check_dataset_manifest(dir.path(), true).unwrap();
+
+        // The dataset hash is the data digest of every store built from it
+        let digest = HashDatStore::open(&dir.path().join("index_hash.dat"))
+            .unwrap()
+            .data_digest()
+            .unwrap();
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(manifest.dataset_hash, hex);
+
+        std::fs::remove_file(dir.path().join("index.zip")).unwrap();
+        assert_eq!(manifest.missing_stores(dir.path()), vec!["index.zip"]);
+        assert!(check_dataset_manifest(dir.path(), true).is_err());
+        check_dataset_manifest(dir.path(), false).unwrap();
+
+        std::fs::remove_file(dir.path().join(DATASET_MANIFEST_FILE)).unwrap();
+        assert!(check_dataset_manifest(dir.path(), true).is_err());
+    }
+
+    #[test]
+    fn test_streamed_build_matches_materialized_build() {
+        let (materialized, streamed) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let options = BuildOptions {
+            entries_per_size: 3,
+            ..Default::default()
+        };
+        build_indices(materialized.path(), &options).unwrap();
+        let stream = BuildOptions {
+            stream: true,
+            ..options
+        };
+        build_indices(streamed.path(), &stream).unwrap();
+
+        let keys = |dir: &TempDir| -> HashMap<String, Vec<String>> {
+            serde_json::from_slice(&std::fs::read(dir.path().join("keys.json")).unwrap()).unwrap()
+        };
+        assert_eq!(keys(&materialized), keys(&streamed));
+        for file in ["index_hash.dat", "index_bplustree.dat"] {
+            assert_eq!(
+                std::fs::read(materialized.path().join(file)).unwrap(),
+                std::fs::read(streamed.path().join(file)).unwrap(),
+                "{} differs",
+                file
+            );
+        }
+
+        let shuffled = BuildOptions {
+            insert_order: InsertOrder::Shuffled,
+            ..stream
+        };
+        let err = build_indices(streamed.path(), &shuffled).unwrap_err();
+        assert!(err.to_string().contains("--stream"), "{}", err);
+    }
+
+    #[test]
+    fn test_unchanged_rebuild_is_skipped_unless_forced() {
+        let dir = TempDir::new().unwrap();
+        let options = BuildOptions {
+            entries_per_size: 2,
+            ..Default::default()
+        };
+
+        // keys.json plus one file or directory per backend
+        let all_files = 9
+            + cfg!(feature = "rocksdb") as usize
+            + cfg!(feature = "lmdb") as usize
+            + cfg!(feature = "mph") as usize;
+        let first = build_indices(dir.path(), &options).unwrap();
+        assert_eq!(first.len(), all_files);
+        assert!(dir.path().join("build_manifest.json").exists());
+
+        let times = BuildTimes::load(dir.path()).unwrap().unwrap();
+        assert_eq!(times.backends.len(), all_files - 1);
+
+        let second = build_indices(dir.path(), &options).unwrap();
+        assert!(second.is_empty(), "rebuilt {:?}", second);
+        // Skipped builds keep the times from the build that made them
+        assert_eq!(BuildTimes::load(dir.path()).unwrap().unwrap(), times);
+
+        // A different seed invalidates every fingerprint
+        let reseeded = BuildOptions {
+            seed: options.seed + 1,
+            ..options
+        };
+        assert_eq!(
+            build_indices(dir.path(), &reseeded).unwrap().len(),
+            all_files
+        );
+
+        let forced = BuildOptions {
+            force: true,
+            ..reseeded
+        };
+        assert_eq!(build_indices(dir.path(), &forced).unwrap().len(), all_files);
+    }
+
+    #[cfg(any(feature = "mimalloc", feature = "jemalloc"))]
+    #[test]
+    fn test_benchmark_runs_with_alternate_allocator() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index_hash.dat");
+        let config = DataGenConfig::new(3, 7);
+        let entries = DataGenerator::new(config).generate_all();
+        build_store::<HashDatStoreBuilder>(&path, &entries).unwrap();
+        verify_store::<HashDatStore>(&path, &entries).unwrap();
+
+        let mut keys_by_size: HashMap<BlobSize, Vec<Vec<u8>>> = HashMap::new();
+        for entry in &entries {
+            keys_by_size
+                .entry(entry.size_category)
+                .or_default()
+                .push(entry.key.clone());
+        }
+        let all_keys: Vec<Vec<u8>> = entries.iter().map(|e| e.key.clone()).collect();
+        let bench_config = BenchmarkConfig {
+            num_lookups: 20,
+            warmup_iterations: 10,
+            seed: 1,
+            ..Default::default()
+        };
+        let hash = BenchBackend::named("hash").unwrap();
+        let results =
+            benchmark_store(&hash, &path, &all_keys, &keys_by_size, &bench_config, false).unwrap();
+
+        assert_eq!(results.len(), BlobSize::all().len());
+        assert_ne!(allocator_name(), "system");
+    }
 }
@@ -1,5 +1,63 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
 use std::path::Path;
+use std::time::Duration;
+
+/// Time spent in each phase of a single lookup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LookupPhases {
+    /// Locating the entry (probing the index)
+    pub index: Duration,
+    /// Reading the value bytes once the entry is located
+    pub io: Duration,
+}
+
+/// Smallest key greater than every key starting with `prefix`, for turning a
+/// prefix into a half-open range. `None` if no such bound exists (empty prefix
+/// or all `0xff` bytes).
+pub fn prefix_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut bound = prefix.to_vec();
+    while let Some(last) = bound.pop() {
+        if last < 0xff {
+            bound.push(last + 1);
+            return Some(bound);
+        }
+    }
+    None
+}
+
+/// Order-independent SHA-256 digest over (key, value) pairs, behind
+/// `BlobStore::data_digest`. Also usable on entries that aren't in a store yet.
+#[derive(Debug, Clone, Default)]
+pub struct DataDigest {
+    combined: [u8; 32],
+    count: u64,
+}
+
+impl DataDigest {
+    pub fn update(&mut self, key: &[u8], value: &[u8]) {
+        let mut hasher = Sha256::new();
+        hasher.update((key.len() as u64).to_le_bytes());
+        hasher.update(key);
+        hasher.update(value);
+
+        // XOR keeps the combination independent of iteration order
+        for (acc, byte) in self.combined.iter_mut().zip(hasher.finalize()) {
+            *acc ^= byte;
+        }
+        self.count += 1;
+    }
+
+    pub fn finish(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.count.to_le_bytes());
+        hasher.update(self.combined);
+        hasher.finalize().into()
+    }
+}
+
+/// Values streamed by `BlobStore::values`
+pub type ValueIter<'a> = Box<dyn Iterator<Item = Result<Vec<u8>>> + 'a>;
 
 /// Trait for read-only access to a blob store.
 /// All implementations are optimized for read-only access at runtime.
@@ -10,9 +68,116 @@ pub trait BlobStore: Sized {
     /// Get a blob by its key. Returns None if the key doesn't exist.
     fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
 
+    /// Like `get`, but writes the value into `buf` (cleared first) so one buffer
+    /// can be reused across lookups. Returns whether the key was found; `buf`
+    /// is left empty if it wasn't.
+    fn get_into(&self, key: &[u8], buf: &mut Vec<u8>) -> Result<bool> {
+        buf.clear();
+        match self.get(key)? {
+            Some(value) => {
+                buf.extend_from_slice(&value);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Whether `key` exists in the store.
+    fn contains_key(&self, key: &[u8]) -> Result<bool> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// Length of the value stored under `key`, if any.
+    fn value_len(&self, key: &[u8]) -> Result<Option<usize>> {
+        Ok(self.get(key)?.map(|value| value.len()))
+    }
+
+    /// Like `get`, but also reports how long the index and IO phases took.
+    /// Backends that can't separate the two return `None` for the phases.
+    fn get_with_phases(&self, key: &[u8]) -> Result<(Option<Vec<u8>>, Option<LookupPhases>)> {
+        Ok((self.get(key)?, None))
+    }
+
+    /// Start recording which bytes of the file lookups read, discarding any
+    /// earlier record. Backends without read tracking ignore this.
+    fn start_access_tracking(&self) {}
+
+    /// Unique file bytes read since `start_access_tracking`, or `None` if the
+    /// backend doesn't track reads.
+    fn accessed_bytes(&self) -> Option<u64> {
+        None
+    }
+
+    /// Total file bytes read since `start_access_tracking`, counting bytes read
+    /// more than once each time, or `None` if the backend doesn't track reads.
+    fn bytes_read(&self) -> Option<u64> {
+        None
+    }
+
+    /// Ask the OS to start reading the whole mapping into the page cache
+    /// (`MADV_WILLNEED`). Returns whether the advice was issued; backends that
+    /// don't mmap their file return `false`.
+    fn advise_willneed(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Evict the store's file from the OS page cache, and drop any cache of its
+    /// own, so the next lookup reads from disk. Returns whether anything was
+    /// evicted; a no-op returning `false` on platforms without the needed
+    /// advice (everything but Linux) and for backends that don't implement it.
+    fn drop_caches(&self) -> Result<bool> {
+        Ok(false)
+    }
+
     /// Get all keys in the store.
     fn keys(&self) -> Result<Vec<Vec<u8>>>;
 
+    /// Stream every value, in `keys()` order, fetching one at a time.
+    fn values(&self) -> Result<ValueIter<'_>> {
+        let keys = self.keys()?;
+        Ok(Box::new(keys.into_iter().map(move |key| {
+            self.get(&key)?.context("Key listed by keys() has no value")
+        })))
+    }
+
+    /// Sum of every value's length.
+    /// The default sums `value_len` over `keys()`, so backends that override
+    /// `value_len` avoid reading the values; backends that record value
+    /// lengths in their index override this directly.
+    fn total_value_bytes(&self) -> Result<u64> {
+        let mut total = 0u64;
+        for key in self.keys()? {
+            total += self
+                .value_len(&key)?
+                .context("Key listed by keys() has no value")? as u64;
+        }
+        Ok(total)
+    }
+
+    /// Order-independent SHA-256 digest over every (key, value) pair.
+    /// Stores with the same contents produce the same digest regardless of
+    /// backend or on-disk layout.
+    fn data_digest(&self) -> Result<[u8; 32]> {
+        let mut digest = DataDigest::default();
+        for key in self.keys()? {
+            let value = self
+                .get(&key)?
+                .context("Key listed by keys() has no value")?;
+            digest.update(&key, &value);
+        }
+        Ok(digest.finish())
+    }
+
+    /// Count the keys that start with `prefix`.
+    /// The default scans every key; sorted backends override it with a range count.
+    fn prefix_count(&self, prefix: &[u8]) -> Result<usize> {
+        Ok(self
+            .keys()?
+            .iter()
+            .filter(|key| key.starts_with(prefix))
+            .count())
+    }
+
     /// Get the number of entries in the store.
     fn len(&self) -> usize;
 
@@ -21,10 +186,124 @@ pub trait BlobStore: Sized {
         self.len() == 0
     }
 
+    /// Ratio of uncompressed to stored value bytes.
+    /// Backends that store values as-is report 1.0.
+    fn compression_ratio(&self) -> Result<f64> {
+        Ok(1.0)
+    }
+
     /// Get the name of this backend for display purposes.
     fn backend_name() -> &'static str;
 }
 
+/// Object-safe view of a `BlobStore`, so stores of different backends can sit
+/// behind one `Box<dyn DynBlobStore>`. Every `BlobStore` implements it, and
+/// each method behaves as the `BlobStore` method of the same name.
+pub trait DynBlobStore {
+    /// Get a blob by its key. Returns None if the key doesn't exist.
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    fn get_into(&self, key: &[u8], buf: &mut Vec<u8>) -> Result<bool>;
+
+    fn contains_key(&self, key: &[u8]) -> Result<bool>;
+
+    fn value_len(&self, key: &[u8]) -> Result<Option<usize>>;
+
+    fn get_with_phases(&self, key: &[u8]) -> Result<(Option<Vec<u8>>, Option<LookupPhases>)>;
+
+    fn start_access_tracking(&self);
+
+    fn accessed_bytes(&self) -> Option<u64>;
+
+    fn bytes_read(&self) -> Option<u64>;
+
+    fn advise_willneed(&self) -> Result<bool>;
+
+    fn drop_caches(&self) -> Result<bool>;
+
+    /// Get all keys in the store.
+    fn keys(&self) -> Result<Vec<Vec<u8>>>;
+
+    fn total_value_bytes(&self) -> Result<u64>;
+
+    /// Get the number of entries in the store.
+    fn len(&self) -> usize;
+
+    /// Check if the store is empty.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the name of this backend for display purposes.
+    fn backend_name(&self) -> &'static str;
+}
+
+impl<S: BlobStore> DynBlobStore for S {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        BlobStore::get(self, key)
+    }
+
+    fn get_into(&self, key: &[u8], buf: &mut Vec<u8>) -> Result<bool> {
+        BlobStore::get_into(self, key, buf)
+    }
+
+    fn contains_key(&self, key: &[u8]) -> Result<bool> {
+        BlobStore::contains_key(self, key)
+    }
+
+    fn value_len(&self, key: &[u8]) -> Result<Option<usize>> {
+        BlobStore::value_len(self, key)
+    }
+
+    fn get_with_phases(&self, key: &[u8]) -> Result<(Option<Vec<u8>>, Option<LookupPhases>)> {
+        BlobStore::get_with_phases(self, key)
+    }
+
+    fn start_access_tracking(&self) {
+        BlobStore::start_access_tracking(self)
+    }
+
+    fn accessed_bytes(&self) -> Option<u64> {
+        BlobStore::accessed_bytes(self)
+    }
+
+    fn bytes_read(&self) -> Option<u64> {
+        BlobStore::bytes_read(self)
+    }
+
+    fn advise_willneed(&self) -> Result<bool> {
+        BlobStore::advise_willneed(self)
+    }
+
+    fn drop_caches(&self) -> Result<bool> {
+        BlobStore::drop_caches(self)
+    }
+
+    fn keys(&self) -> Result<Vec<Vec<u8>>> {
+        BlobStore::keys(self)
+    }
+
+    fn total_value_bytes(&self) -> Result<u64> {
+        BlobStore::total_value_bytes(self)
+    }
+
+    fn len(&self) -> usize {
+        BlobStore::len(self)
+    }
+
+    fn backend_name(&self) -> &'static str {
+        S::backend_name()
+    }
+}
+
+/// Blob store that keeps its keys sorted and can enumerate a key range.
+/// Hash-based and unordered backends don't implement it.
+pub trait OrderedBlobStore: BlobStore {
+    /// Entries with `start <= key < end`, in key order. Empty when `start >= end`.
+    /// Pass a prefix and its `prefix_upper_bound` to get every key with that prefix.
+    fn range(&self, start: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>>;
+}
+
 /// Trait for building a blob store.
 /// Used during the build phase to create the index files.
 pub trait BlobStoreBuilder: Sized {
@@ -37,3 +316,492 @@ pub trait BlobStoreBuilder: Sized {
     /// Finish building the store and flush to disk.
     fn finish(self) -> Result<()>;
 }
+
+/// Most failures `verify` keeps details of
+const MAX_REPORTED_FAILURES: usize = 5;
+
+/// A key `verify` could not read back as inserted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyFailure {
+    /// The store has no value for the key
+    Missing { key: Vec<u8> },
+    /// The store's value matches none of the values inserted under the key
+    Mismatch {
+        key: Vec<u8>,
+        expected_len: usize,
+        actual_len: usize,
+    },
+}
+
+/// Outcome of `verify`: how a store compares with the entries it was built from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Entries passed to the builder
+    pub inserted: usize,
+    /// Distinct keys among them
+    pub distinct_keys: usize,
+    /// Entries the store reports holding
+    pub stored: usize,
+    /// Entries read back with a correct value
+    pub matched: usize,
+    /// Entries read back with a wrong value
+    pub mismatched: usize,
+    /// Entries whose key the store doesn't have
+    pub missing: usize,
+    /// A few of the keys that were inserted more than once, sorted
+    pub duplicate_keys: Vec<Vec<u8>>,
+    /// The first few missing or mismatched entries
+    pub failures: Vec<VerifyFailure>,
+}
+
+impl VerifyReport {
+    /// Inserted entries the backend collapsed into an existing key
+    pub fn deduplicated(&self) -> usize {
+        self.inserted.saturating_sub(self.stored)
+    }
+
+    /// Whether `stored` is neither the inserted nor the distinct entry count.
+    /// Backends that keep one entry per key hold the distinct count.
+    pub fn count_mismatch(&self) -> bool {
+        self.stored != self.inserted && self.stored != self.distinct_keys
+    }
+
+    /// Missing plus mismatched entries
+    pub fn errors(&self) -> usize {
+        self.mismatched + self.missing
+    }
+
+    pub fn is_ok(&self) -> bool {
+        !self.count_mismatch() && self.errors() == 0
+    }
+}
+
+/// Check that every entry reads back from `store` with its value. With
+/// repeated keys, any value inserted under the key counts as correct, since
+/// backends differ in which one wins.
+pub fn verify<S: DynBlobStore + ?Sized>(
+    store: &S,
+    entries: &[crate::data_gen::Entry],
+) -> Result<VerifyReport> {
+    verify_streamed(store, |f| entries.iter().try_for_each(f))
+}
+
+/// `verify` for entries visited by `for_each` rather than held in a slice. It
+/// is called twice and must visit the same entries both times. Only keys and
+/// value hashes are held, so a streamed source is checked without
+/// materializing its values.
+pub fn verify_streamed<S, F>(store: &S, for_each: F) -> Result<VerifyReport>
+where
+    S: DynBlobStore + ?Sized,
+    F: Fn(&mut dyn FnMut(&crate::data_gen::Entry) -> Result<()>) -> Result<()>,
+{
+    use std::collections::HashMap;
+    use std::hash::{Hash, Hasher};
+
+    let value_hash = |value: &[u8]| {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    };
+
+    // Hash of every value inserted under each key, in insertion order
+    let mut values_by_key: HashMap<Vec<u8>, Vec<u64>> = HashMap::new();
+    let mut inserted = 0;
+    for_each(&mut |entry| {
+        values_by_key
+            .entry(entry.key.clone())
+            .or_default()
+            .push(value_hash(&entry.value));
+        inserted += 1;
+        Ok(())
+    })?;
+    let mut duplicate_keys: Vec<Vec<u8>> = values_by_key
+        .iter()
+        .filter(|(_, values)| values.len() > 1)
+        .map(|(key, _)| key.clone())
+        .collect();
+    duplicate_keys.sort();
+    duplicate_keys.truncate(MAX_REPORTED_FAILURES);
+
+    let mut report = VerifyReport {
+        inserted,
+        distinct_keys: values_by_key.len(),
+        stored: store.len(),
+        duplicate_keys,
+        ..Default::default()
+    };
+    for_each(&mut |entry| {
+        let failure = match store.get(&entry.key)? {
+            Some(value)
+                if value == entry.value
+                    || values_by_key[entry.key.as_slice()].contains(&value_hash(&value)) =>
+            {
+                report.matched += 1;
+                return Ok(());
+            }
+            Some(value) => {
+                report.mismatched += 1;
+                VerifyFailure::Mismatch {
+                    key: entry.key.clone(),
+                    expected_len: entry.value.len(),
+                    actual_len: value.len(),
+                }
+            }
+            None => {
+                report.missing += 1;
+                VerifyFailure::Missing {
+                    key: entry.key.clone(),
+                }
+            }
+        };
+        if report.failures.len() < MAX_REPORTED_FAILURES {
+            report.failures.push(failure);
+        }
+        Ok(())
+    })?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::{
+        BPlusTreeStore, BPlusTreeStoreBuilder, BTreeDatStore, BTreeDatStoreBuilder,
+        HashDatMmapStore, HashDatStore, HashDatStoreBuilder, SqliteStore, SqliteStoreBuilder,
+        ZipStore, ZipStoreBuilder,
+    };
+    use crate::data_gen::{BlobSize, DataGenConfig, DataGenerator};
+    use tempfile::TempDir;
+
+    fn build<B: BlobStoreBuilder>(path: &Path, entries: &[(Vec<u8>, Vec<u8>)]) {
+        let mut builder = B::create(path).unwrap();
+        for (key, value) in entries {
+            builder.insert(key, value).unwrap();
+        }
+        builder.finish().unwrap();
+    }
+
+    fn entries_for_verify() -> Vec<crate::data_gen::Entry> {
+        (0..20)
+            .map(|i| crate::data_gen::Entry {
+                key: format!("key_{:02}", i).into_bytes(),
+                value: vec![i as u8; i * 7],
+                size_category: BlobSize::Tiny,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_verify_correct_store_has_no_errors() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index_hash.dat");
+        let entries = entries_for_verify();
+        let pairs: Vec<_> = entries
+            .iter()
+            .map(|e| (e.key.clone(), e.value.clone()))
+            .collect();
+        build::<HashDatStoreBuilder>(&path, &pairs);
+
+        let report = verify(&HashDatStore::open(&path).unwrap(), &entries).unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!(report.matched, entries.len());
+        assert_eq!(report.errors(), 0);
+        assert_eq!(report.deduplicated(), 0);
+        assert!(report.failures.is_empty());
+    }
+
+    #[test]
+    fn test_verify_reports_doctored_store() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index_btree.dat");
+        let entries = entries_for_verify();
+        // Drop key_03, change key_05's value and add a key nobody inserted
+        let mut pairs: Vec<_> = entries
+            .iter()
+            .filter(|e| e.key != b"key_03")
+            .map(|e| (e.key.clone(), e.value.clone()))
+            .collect();
+        pairs[4].1 = b"doctored".to_vec();
+        pairs.push((b"extra".to_vec(), b"x".to_vec()));
+        build::<BTreeDatStoreBuilder>(&path, &pairs);
+
+        let store = BTreeDatStore::open(&path).unwrap();
+        let report = verify(&store, &entries).unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.matched, entries.len() - 2);
+        assert_eq!(report.missing, 1);
+        assert_eq!(report.mismatched, 1);
+        assert!(!report.count_mismatch());
+        assert_eq!(
+            report.failures,
+            vec![
+                VerifyFailure::Missing {
+                    key: b"key_03".to_vec()
+                },
+                VerifyFailure::Mismatch {
+                    key: b"key_05".to_vec(),
+                    expected_len: 35,
+                    actual_len: 8,
+                },
+            ]
+        );
+
+        // Works the same through a trait object
+        let boxed: Box<dyn DynBlobStore> = Box::new(store);
+        assert_eq!(verify(boxed.as_ref(), &entries).unwrap(), report);
+    }
+
+    #[test]
+    fn test_data_digest_matches_across_backends() {
+        let dir = TempDir::new().unwrap();
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = (0..100)
+            .map(|i| (format!("key_{:03}", i).into_bytes(), vec![i as u8; i * 10]))
+            .collect();
+        let mut reversed = entries.clone();
+        reversed.reverse();
+
+        let hash_path = dir.path().join("index_hash.dat");
+        let btree_path = dir.path().join("index_btree.dat");
+        build::<HashDatStoreBuilder>(&hash_path, &entries);
+        build::<BTreeDatStoreBuilder>(&btree_path, &reversed);
+
+        let hash_digest = HashDatStore::open(&hash_path)
+            .unwrap()
+            .data_digest()
+            .unwrap();
+        let btree_digest = BTreeDatStore::open(&btree_path)
+            .unwrap()
+            .data_digest()
+            .unwrap();
+        assert_eq!(hash_digest, btree_digest);
+
+        // Digesting the entries directly agrees with the built store
+        let mut direct = DataDigest::default();
+        for (key, value) in &reversed {
+            direct.update(key, value);
+        }
+        assert_eq!(direct.finish(), hash_digest);
+
+        // Changing a single value changes the digest
+        let mut modified = entries.clone();
+        modified[7].1.push(0);
+        let modified_path = dir.path().join("index_modified.dat");
+        build::<HashDatStoreBuilder>(&modified_path, &modified);
+        let modified_digest = HashDatStore::open(&modified_path)
+            .unwrap()
+            .data_digest()
+            .unwrap();
+        assert_ne!(modified_digest, hash_digest);
+    }
+
+    #[test]
+    fn test_total_value_bytes_matches_fetched_values_across_backends() {
+        use crate::backends::{SqliteRowidStore, SqliteRowidStoreBuilder};
+
+        fn check<S: BlobStore>(path: &Path, expected: u64) {
+            let store = S::open(path).unwrap();
+            let fetched: u64 = store
+                .keys()
+                .unwrap()
+                .iter()
+                .map(|key| store.get(key).unwrap().unwrap().len() as u64)
+                .sum();
+            assert_eq!(fetched, expected, "{}", S::backend_name());
+            assert_eq!(
+                store.total_value_bytes().unwrap(),
+                expected,
+                "{}",
+                S::backend_name()
+            );
+
+            let streamed: Vec<Vec<u8>> = store.values().unwrap().map(|v| v.unwrap()).collect();
+            assert_eq!(streamed.len(), store.len());
+            assert_eq!(
+                streamed.iter().map(|v| v.len() as u64).sum::<u64>(),
+                expected
+            );
+        }
+
+        let dir = TempDir::new().unwrap();
+        // Includes an empty value, which must count as zero bytes
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = (0..30)
+            .map(|i| (format!("key_{:02}", i).into_bytes(), vec![i as u8; i * 7]))
+            .collect();
+        let expected: u64 = entries.iter().map(|(_, v)| v.len() as u64).sum();
+
+        let hash_path = dir.path().join("index_hash.dat");
+        let btree_path = dir.path().join("index_btree.dat");
+        let bplustree_path = dir.path().join("index_bplustree.dat");
+        let sqlite_path = dir.path().join("index.sqlite");
+        let sqlite_rowid_path = dir.path().join("index_rowid.sqlite");
+        let zip_path = dir.path().join("index.zip");
+        build::<HashDatStoreBuilder>(&hash_path, &entries);
+        build::<BTreeDatStoreBuilder>(&btree_path, &entries);
+        build::<BPlusTreeStoreBuilder>(&bplustree_path, &entries);
+        build::<SqliteStoreBuilder>(&sqlite_path, &entries);
+        build::<SqliteRowidStoreBuilder>(&sqlite_rowid_path, &entries);
+        build::<ZipStoreBuilder>(&zip_path, &entries);
+
+        check::<HashDatStore>(&hash_path, expected);
+        check::<HashDatMmapStore>(&hash_path, expected);
+        check::<BTreeDatStore>(&btree_path, expected);
+        check::<BPlusTreeStore>(&bplustree_path, expected);
+        check::<SqliteStore>(&sqlite_path, expected);
+        check::<SqliteRowidStore>(&sqlite_rowid_path, expected);
+        check::<ZipStore>(&zip_path, expected);
+    }
+
+    #[test]
+    fn test_get_into_matches_get_and_reuses_buffer() {
+        fn check<S: BlobStore>(path: &Path, entries: &[(Vec<u8>, Vec<u8>)]) {
+            let store = S::open(path).unwrap();
+            let name = S::backend_name();
+            let mut buf = Vec::with_capacity(4096);
+            let capacity = buf.capacity();
+
+            for (key, value) in entries {
+                assert!(store.get_into(key, &mut buf).unwrap(), "{}", name);
+                assert_eq!(&buf, value, "{}", name);
+            }
+            // Values fit the initial allocation, so it was never replaced
+            assert_eq!(buf.capacity(), capacity, "{}", name);
+
+            assert!(!store.get_into(b"missing", &mut buf).unwrap(), "{}", name);
+            assert!(buf.is_empty(), "{}", name);
+        }
+
+        let dir = TempDir::new().unwrap();
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = (0..40)
+            .map(|i| (format!("key_{:02}", i).into_bytes(), vec![i as u8; i * 50]))
+            .collect();
+
+        let hash_path = dir.path().join("index_hash.dat");
+        let btree_path = dir.path().join("index_btree.dat");
+        let bplustree_path = dir.path().join("index_bplustree.dat");
+        let sqlite_path = dir.path().join("index.sqlite");
+        let zip_path = dir.path().join("index.zip");
+        build::<HashDatStoreBuilder>(&hash_path, &entries);
+        build::<BTreeDatStoreBuilder>(&btree_path, &entries);
+        build::<BPlusTreeStoreBuilder>(&bplustree_path, &entries);
+        build::<SqliteStoreBuilder>(&sqlite_path, &entries);
+        build::<ZipStoreBuilder>(&zip_path, &entries);
+
+        check::<HashDatStore>(&hash_path, &entries);
+        check::<HashDatMmapStore>(&hash_path, &entries);
+        check::<BTreeDatStore>(&btree_path, &entries);
+        check::<BPlusTreeStore>(&bplustree_path, &entries);
+        check::<SqliteStore>(&sqlite_path, &entries);
+        check::<ZipStore>(&zip_path, &entries);
+    }
+
+    #[test]
+    fn test_range_covers_empty_and_whole_store() {
+        fn check<S: OrderedBlobStore>(path: &Path, entries: &[(Vec<u8>, Vec<u8>)]) {
+            let store = S::open(path).unwrap();
+            let name = S::backend_name();
+
+            // Empty: start == end, start > end, and a gap between keys
+            assert!(
+                store.range(b"key_05", b"key_05").unwrap().is_empty(),
+                "{}",
+                name
+            );
+            assert!(
+                store.range(b"key_09", b"key_02").unwrap().is_empty(),
+                "{}",
+                name
+            );
+            assert!(
+                store.range(b"key_05a", b"key_05b").unwrap().is_empty(),
+                "{}",
+                name
+            );
+            assert!(store.range(b"zzz", b"zzzz").unwrap().is_empty(), "{}", name);
+
+            // Everything, in key order
+            assert_eq!(store.range(b"", b"\xff").unwrap(), entries, "{}", name);
+
+            // Start is inclusive, end exclusive
+            assert_eq!(
+                store.range(b"key_03", b"key_06").unwrap(),
+                &entries[3..6],
+                "{}",
+                name
+            );
+
+            // Prefix scan via the incremented upper bound
+            let upper = prefix_upper_bound(b"key_1").unwrap();
+            assert_eq!(
+                store.range(b"key_1", &upper).unwrap(),
+                &entries[10..20],
+                "{}",
+                name
+            );
+        }
+
+        let dir = TempDir::new().unwrap();
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = (0..25)
+            .map(|i| (format!("key_{:02}", i).into_bytes(), vec![i as u8; i]))
+            .collect();
+        let mut reversed = entries.clone();
+        reversed.reverse();
+
+        let btree_path = dir.path().join("index_btree.dat");
+        let sqlite_path = dir.path().join("index.sqlite");
+        build::<BTreeDatStoreBuilder>(&btree_path, &reversed);
+        build::<SqliteStoreBuilder>(&sqlite_path, &reversed);
+
+        check::<BTreeDatStore>(&btree_path, &entries);
+        check::<SqliteStore>(&sqlite_path, &entries);
+    }
+
+    #[test]
+    fn test_prefix_upper_bound() {
+        assert_eq!(prefix_upper_bound(b"10KB_"), Some(b"10KB`".to_vec()));
+        assert_eq!(prefix_upper_bound(b"a\xff\xff"), Some(b"b".to_vec()));
+        assert_eq!(prefix_upper_bound(b"\xff"), None);
+        assert_eq!(prefix_upper_bound(b""), None);
+    }
+
+    #[test]
+    fn test_prefix_count_matches_manual_filter() {
+        let dir = TempDir::new().unwrap();
+        let config = DataGenConfig {
+            entries_override: [(BlobSize::Large, 3), (BlobSize::Huge, 2)]
+                .into_iter()
+                .collect(),
+            ..DataGenConfig::new(20, 7)
+        };
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = DataGenerator::new(config)
+            .generate_all()
+            .into_iter()
+            .map(|e| (e.key, e.value))
+            .collect();
+
+        let prefix = b"10KB_";
+        let expected = entries
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .count();
+        assert_eq!(expected, 20);
+
+        let hash_path = dir.path().join("index_hash.dat");
+        let btree_path = dir.path().join("index_btree.dat");
+        let sqlite_path = dir.path().join("index.sqlite");
+        build::<HashDatStoreBuilder>(&hash_path, &entries);
+        build::<BTreeDatStoreBuilder>(&btree_path, &entries);
+        build::<SqliteStoreBuilder>(&sqlite_path, &entries);
+
+        let hash = HashDatStore::open(&hash_path).unwrap();
+        let btree = BTreeDatStore::open(&btree_path).unwrap();
+        let sqlite = SqliteStore::open(&sqlite_path).unwrap();
+        assert_eq!(hash.prefix_count(prefix).unwrap(), expected);
+        assert_eq!(btree.prefix_count(prefix).unwrap(), expected);
+        assert_eq!(sqlite.prefix_count(prefix).unwrap(), expected);
+
+        assert_eq!(btree.prefix_count(b"").unwrap(), entries.len());
+        assert_eq!(sqlite.prefix_count(b"").unwrap(), entries.len());
+        assert_eq!(btree.prefix_count(b"nope").unwrap(), 0);
+        assert_eq!(sqlite.prefix_count(b"nope").unwrap(), 0);
+    }
+}
@@ -0,0 +1,436 @@
+use crate::benchmark::{
+    allocator_name, AccessPattern, BenchmarkConfig, BenchmarkResult, Provenance,
+};
+use crate::build_stats::BuildStats;
+use crate::data_gen::BlobSize;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Everything about a benchmark run in one serializable document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub provenance: Provenance,
+    pub allocator: String,
+    pub config: SummaryConfig,
+    /// True if the run was cancelled and some results are partial
+    pub interrupted: bool,
+    /// One row per (backend, size) measurement
+    pub results: Vec<SummaryRow>,
+    /// Index file size in bytes, by backend
+    pub file_sizes: BTreeMap<String, u64>,
+    /// Value compression ratio recorded at build time, by backend (empty without `build_stats.json`)
+    pub compression_ratios: BTreeMap<String, f64>,
+    /// Best backend for each metric
+    pub winners: Vec<Winner>,
+}
+
+/// Benchmark settings that shaped the results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SummaryConfig {
+    pub num_lookups: usize,
+    pub warmup_iterations: usize,
+    pub seed: u64,
+    pub access_order: String,
+    pub access_pattern: String,
+    /// Working-set size, only for the sliding-window pattern
+    pub window_size: Option<usize>,
+    pub shared_plan: bool,
+    pub phase_timing: bool,
+    /// Lookups timed together per latency sample (1 = unbatched)
+    pub batch_size: usize,
+    /// Distinct keys lookups were drawn from, if known
+    #[serde(default)]
+    pub key_count: Option<usize>,
+    /// `MADV_WILLNEED` was issued on mmap-backed stores before warmup
+    #[serde(default)]
+    pub willneed: bool,
+}
+
+/// Latency and throughput for one backend at one blob size
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SummaryRow {
+    pub backend: String,
+    pub size: String,
+    pub samples: usize,
+    /// Measured lookups performed; differs from `samples` when batching
+    #[serde(default)]
+    pub lookups: usize,
+    pub p50_ns: u64,
+    pub p95_ns: u64,
+    pub p99_ns: u64,
+    pub mean_ns: u64,
+    pub ops_per_sec: f64,
+    pub open_time_ns: u64,
+    pub memory_bytes: usize,
+    pub interrupted: bool,
+}
+
+/// Backend that came out on top for a metric, optionally within one blob size
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Winner {
+    pub metric: String,
+    pub size: Option<String>,
+    pub backend: String,
+    pub value: f64,
+}
+
+impl RunSummary {
+    pub fn new(
+        config: &BenchmarkConfig,
+        results: &[BenchmarkResult],
+        build_stats: Option<&BuildStats>,
+    ) -> Self {
+        let rows: Vec<SummaryRow> = results
+            .iter()
+            .map(|r| {
+                let p = r.percentiles(&[50.0, 95.0, 99.0]);
+                SummaryRow {
+                    backend: r.backend_name.clone(),
+                    size: r.blob_size.name().to_string(),
+                    samples: r.latencies_ns.len(),
+                    lookups: r.lookups,
+                    p50_ns: p[0].as_nanos() as u64,
+                    p95_ns: p[1].as_nanos() as u64,
+                    p99_ns: p[2].as_nanos() as u64,
+                    mean_ns: r.mean().as_nanos() as u64,
+                    ops_per_sec: r.ops_per_second(),
+                    open_time_ns: r.open_time.as_nanos() as u64,
+                    memory_bytes: r.memory_stats.physical_mem,
+                    interrupted: r.interrupted,
+                }
+            })
+            .collect();
+
+        let file_sizes: BTreeMap<String, u64> = results
+            .iter()
+            .map(|r| (r.backend_name.clone(), r.file_size))
+            .collect();
+
+        let compression_ratios = build_stats
+            .map(|stats| {
+                stats
+                    .backends
+                    .iter()
+                    .map(|(backend, s)| (backend.clone(), s.compression_ratio))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut winners = Vec::new();
+        for size in BlobSize::all() {
+            let size_rows: Vec<&SummaryRow> =
+                rows.iter().filter(|r| r.size == size.name()).collect();
+            let lowest = |metric: &str, value: fn(&SummaryRow) -> f64| {
+                size_rows
+                    .iter()
+                    .min_by(|a, b| value(a).total_cmp(&value(b)))
+                    .map(|r| Winner {
+                        metric: metric.to_string(),
+                        size: Some(size.name().to_string()),
+                        backend: r.backend.clone(),
+                        value: value(r),
+                    })
+            };
+            winners.extend(lowest("p50_ns", |r| r.p50_ns as f64));
+            winners.extend(lowest("p99_ns", |r| r.p99_ns as f64));
+            // Highest throughput, expressed as the lowest negated value
+            winners.extend(lowest("ops_per_sec", |r| -r.ops_per_sec).map(|mut w| {
+                w.value = -w.value;
+                w
+            }));
+        }
+        if let Some((backend, &bytes)) = file_sizes.iter().min_by_key(|(_, &bytes)| bytes) {
+            winners.push(Winner {
+                metric: "file_size".to_string(),
+                size: None,
+                backend: backend.clone(),
+                value: bytes as f64,
+            });
+        }
+
+        Self {
+            provenance: Provenance::current(),
+            allocator: allocator_name().to_string(),
+            config: SummaryConfig {
+                num_lookups: config.num_lookups,
+                warmup_iterations: config.warmup_iterations,
+                seed: config.seed,
+                access_order: config.access_order.name().to_string(),
+                access_pattern: config.access_pattern.name().to_string(),
+                window_size: (config.access_pattern == AccessPattern::SlidingWindow)
+                    .then_some(config.window_size),
+                shared_plan: config.shared_plan.is_some(),
+                phase_timing: config.phase_timing,
+                batch_size: config.batch_size,
+                key_count: None,
+                willneed: config.willneed,
+            },
+            interrupted: results.iter().any(|r| r.interrupted),
+            results: rows,
+            file_sizes,
+            compression_ratios,
+            winners,
+        }
+    }
+}
+
+impl RunSummary {
+    /// Record how many distinct keys the run looked up
+    pub fn with_key_count(mut self, key_count: usize) -> Self {
+        self.config.key_count = Some(key_count);
+        self
+    }
+}
+
+/// Write the run summary as pretty-printed JSON
+pub fn write_run_summary(path: &Path, summary: &RunSummary) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).context("Failed to create summary directory")?;
+    }
+    let json = serde_json::to_string_pretty(summary)?;
+    std::fs::write(path, json).context("Failed to write run summary")
+}
+
+/// Render the summary in the Prometheus text exposition format, one sample per
+/// (backend, size) row, ready to push to a Pushgateway
+pub fn prometheus_text(summary: &RunSummary) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    let labels = |row: &SummaryRow| {
+        format!(
+            "backend=\"{}\",size=\"{}\"",
+            escape_label(&row.backend),
+            escape_label(&row.size)
+        )
+    };
+    let family = |out: &mut String, name: &str, kind: &str, help: &str| {
+        writeln!(out, "# HELP {} {}", name, help).unwrap();
+        writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+    };
+    let micros = |ns: u64| ns as f64 / 1_000.0;
+
+    family(
+        &mut out,
+        "lookup_latency_microseconds",
+        "summary",
+        "Lookup latency per backend and blob size",
+    );
+    for row in &summary.results {
+        let labels = labels(row);
+        for (quantile, ns) in [
+            ("0.5", row.p50_ns),
+            ("0.95", row.p95_ns),
+            ("0.99", row.p99_ns),
+        ] {
+            writeln!(
+                out,
+                "lookup_latency_microseconds{{{},quantile=\"{}\"}} {}",
+                labels,
+                quantile,
+                micros(ns)
+            )
+            .unwrap();
+        }
+        writeln!(
+            out,
+            "lookup_latency_microseconds_sum{{{}}} {}",
+            labels,
+            micros(row.mean_ns) * row.samples as f64
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "lookup_latency_microseconds_count{{{}}} {}",
+            labels, row.samples
+        )
+        .unwrap();
+    }
+
+    let gauges: [(&str, &str, fn(&SummaryRow) -> f64); 3] = [
+        ("lookup_ops_per_second", "Lookup throughput", |r| {
+            r.ops_per_sec
+        }),
+        ("store_open_seconds", "Time to open the store", |r| {
+            r.open_time_ns as f64 / 1e9
+        }),
+        (
+            "store_memory_bytes",
+            "Physical memory after opening the store",
+            |r| r.memory_bytes as f64,
+        ),
+    ];
+    for (name, help, value) in gauges {
+        family(&mut out, name, "gauge", help);
+        for row in &summary.results {
+            writeln!(out, "{}{{{}}} {}", name, labels(row), value(row)).unwrap();
+        }
+    }
+
+    family(
+        &mut out,
+        "index_file_size_bytes",
+        "gauge",
+        "Index file size on disk",
+    );
+    for (backend, bytes) in &summary.file_sizes {
+        writeln!(
+            out,
+            "index_file_size_bytes{{backend=\"{}\"}} {}",
+            escape_label(backend),
+            bytes
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// Escape a label value per the exposition format (backslash, quote, newline)
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Write the summary's metrics in Prometheus text format
+pub fn write_prometheus(path: &Path, summary: &RunSummary) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).context("Failed to create metrics directory")?;
+    }
+    std::fs::write(path, prometheus_text(summary)).context("Failed to write Prometheus metrics")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn result(
+        backend: &str,
+        size: BlobSize,
+        latencies_ns: Vec<u64>,
+        file_size: u64,
+    ) -> BenchmarkResult {
+        let lookups = latencies_ns.len();
+        BenchmarkResult {
+            backend_name: backend.to_string(),
+            blob_size: size,
+            latencies_ns: latencies_ns.into(),
+            file_size,
+            open_time: Duration::from_micros(5),
+            lookups,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_run_summary_roundtrips_through_json() {
+        let dir = TempDir::new().unwrap();
+        let results = vec![
+            result("Fast", BlobSize::Tiny, vec![100, 200, 300], 4_000),
+            result("Slow", BlobSize::Tiny, vec![1_000, 2_000, 3_000], 2_000),
+        ];
+        let summary = RunSummary::new(&BenchmarkConfig::default(), &results, None);
+
+        let path = dir.path().join("summary.json");
+        write_run_summary(&path, &summary).unwrap();
+        let loaded: RunSummary =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+
+        assert_eq!(loaded.provenance.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(loaded.config.num_lookups, 10_000);
+        assert_eq!(loaded.results.len(), 2);
+        assert_eq!(loaded.results[0].p50_ns, 200);
+        assert_eq!(loaded.file_sizes["Slow"], 2_000);
+
+        let winner = |metric: &str| {
+            loaded
+                .winners
+                .iter()
+                .find(|w| w.metric == metric)
+                .unwrap()
+                .backend
+                .as_str()
+        };
+        assert_eq!(winner("p50_ns"), "Fast");
+        assert_eq!(winner("ops_per_sec"), "Fast");
+        assert_eq!(winner("file_size"), "Slow");
+    }
+
+    #[test]
+    fn test_prometheus_output_is_well_formed() {
+        let results = vec![
+            result("Hash DAT", BlobSize::Tiny, vec![1_000, 2_000, 3_000], 4_000),
+            result("Zip", BlobSize::Large, vec![5_000, 6_000], 2_000),
+        ];
+        let summary = RunSummary::new(&BenchmarkConfig::default(), &results, None);
+        let text = prometheus_text(&summary);
+
+        let mut samples = Vec::new();
+        let mut declared = Vec::new();
+        for line in text.lines() {
+            if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = rest.split_once(' ').unwrap();
+                assert!(["summary", "gauge"].contains(&kind), "{}", line);
+                declared.push(name.to_string());
+                continue;
+            }
+            if line.starts_with("# HELP ") {
+                continue;
+            }
+            // name{label="value",...} number
+            let (series, value) = line.rsplit_once(' ').unwrap();
+            value.parse::<f64>().unwrap();
+            let (name, labels) = series.split_once('{').unwrap();
+            let labels: BTreeMap<&str, &str> = labels
+                .strip_suffix('}')
+                .unwrap()
+                .split(',')
+                .map(|pair| {
+                    let (k, v) = pair.split_once('=').unwrap();
+                    (k, v.strip_prefix('"').unwrap().strip_suffix('"').unwrap())
+                })
+                .collect();
+            let family = name
+                .strip_suffix("_sum")
+                .or_else(|| name.strip_suffix("_count"))
+                .unwrap_or(name);
+            assert!(declared.iter().any(|d| d == family), "{}", line);
+            samples.push((name.to_string(), labels.into_keys().collect::<Vec<_>>()));
+        }
+
+        let label_sets = |metric: &str| {
+            samples
+                .iter()
+                .filter(|(name, _)| name == metric)
+                .map(|(_, labels)| labels.clone())
+                .collect::<Vec<_>>()
+        };
+        // Three quantiles for each of the two rows
+        let quantiles = label_sets("lookup_latency_microseconds");
+        assert_eq!(quantiles.len(), 6);
+        assert!(quantiles
+            .iter()
+            .all(|l| l == &["backend", "quantile", "size"]));
+        assert_eq!(label_sets("lookup_ops_per_second").len(), 2);
+        assert_eq!(
+            label_sets("index_file_size_bytes"),
+            vec![vec!["backend"]; 2]
+        );
+        assert!(text.contains(
+            "lookup_latency_microseconds{backend=\"Hash DAT\",size=\"100B\",quantile=\"0.5\"} 2"
+        ));
+        assert!(
+            text.contains("lookup_latency_microseconds_count{backend=\"Zip\",size=\"100KB\"} 2")
+        );
+    }
+
+    #[test]
+    fn test_prometheus_escapes_label_values() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,144 @@
+//! Standard base64 (RFC 4648 alphabet with `=` padding), used to store binary
+//! keys in keys.json.
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode `data` as padded base64
+pub fn encode(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let b0 = chunk[0];
+        let b1 = chunk.get(1).copied().unwrap_or(0);
+        let b2 = chunk.get(2).copied().unwrap_or(0);
+
+        result.push(ALPHABET[(b0 >> 2) as usize] as char);
+        result.push(ALPHABET[(((b0 & 0x03) << 4) | (b1 >> 4)) as usize] as char);
+        result.push(if chunk.len() > 1 {
+            ALPHABET[(((b1 & 0x0f) << 2) | (b2 >> 6)) as usize] as char
+        } else {
+            '='
+        });
+        result.push(if chunk.len() > 2 {
+            ALPHABET[(b2 & 0x3f) as usize] as char
+        } else {
+            '='
+        });
+    }
+
+    result
+}
+
+fn sextet(c: u8) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+/// Decode padded base64, or `None` unless `s` is exactly what `encode` gives
+/// for some input: its length is a multiple of 4, every character is in the
+/// alphabet, `=` only pads the final group (once or twice), and the bits that
+/// padding drops are zero.
+pub fn decode(s: &str) -> Option<Vec<u8>> {
+    let bytes = s.as_bytes();
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+
+    let groups = bytes.len() / 4;
+    let mut result = Vec::with_capacity(groups * 3);
+
+    for (i, group) in bytes.chunks(4).enumerate() {
+        let padding = group.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && i + 1 != groups) {
+            return None;
+        }
+
+        let mut value = 0u32;
+        for &c in &group[..4 - padding] {
+            value = (value << 6) | sextet(c)? as u32;
+        }
+        value <<= 6 * padding;
+
+        let [_, b0, b1, b2] = value.to_be_bytes();
+        let decoded = [b0, b1, b2];
+        let len = 3 - padding;
+        if decoded[len..].iter().any(|&b| b != 0) {
+            return None;
+        }
+        result.extend_from_slice(&decoded[..len]);
+    }
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec as prop_vec;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_rfc4648_vectors() {
+        let vectors: [(&[u8], &str); 7] = [
+            (b"", ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (b"fooba", "Zm9vYmE="),
+            (b"foobar", "Zm9vYmFy"),
+        ];
+        for (data, encoded) in vectors {
+            assert_eq!(encode(data), encoded);
+            assert_eq!(decode(encoded).as_deref(), Some(data));
+        }
+        assert_eq!(encode(&[0xfb, 0xff]), "+/8=");
+    }
+
+    #[test]
+    fn test_malformed_strings_are_rejected() {
+        for malformed in [
+            "Zg=",       // length not a multiple of 4
+            "Z===",      // three padding characters
+            "====",      // nothing but padding
+            "Zg==Zm9v",  // padding before the final group
+            "Z=g=",      // padding inside a group
+            "Zm9v=Zg=",  // stray padding mid-string
+            "Zm 9",      // whitespace
+            "Zm9-",      // URL-safe alphabet
+            "Zm9\u{e9}", // non-ASCII
+            "Zh==",      // nonzero bits dropped by the padding
+            "Zm9=",      // nonzero bits dropped by the padding
+        ] {
+            assert_eq!(decode(malformed), None, "{:?}", malformed);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_decode_inverts_encode(data in prop_vec(any::<u8>(), 0..200)) {
+            let encoded = encode(&data);
+            prop_assert_eq!(encoded.len() % 4, 0);
+            prop_assert_eq!(decode(&encoded), Some(data));
+        }
+
+        #[test]
+        fn prop_non_alphabet_char_is_rejected(
+            data in prop_vec(any::<u8>(), 1..100),
+            position in any::<prop::sample::Index>(),
+            bad in prop::sample::select(vec!['-', '_', ' ', '\n', '.', '*', '\0']),
+        ) {
+            let mut encoded: Vec<char> = encode(&data).chars().collect();
+            let position = position.index(encoded.len());
+            encoded[position] = bad;
+            let encoded: String = encoded.into_iter().collect();
+            prop_assert_eq!(decode(&encoded), None);
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Lowercase hex encoding, used for Zip entry names and for keys on the
+//! command line.
+
+const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
+
+/// Encode `data` as lowercase hex
+pub fn encode(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len() * 2);
+    for byte in data {
+        result.push(HEX_CHARS[(byte >> 4) as usize] as char);
+        result.push(HEX_CHARS[(byte & 0xf) as usize] as char);
+    }
+    result
+}
+
+/// Decode hex in either case, or `None` if `s` has an odd length or a
+/// non-hex character
+pub fn decode(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+
+    let mut result = Vec::with_capacity(s.len() / 2);
+    let bytes = s.as_bytes();
+
+    for chunk in bytes.chunks(2) {
+        let high = hex_char_to_nibble(chunk[0])?;
+        let low = hex_char_to_nibble(chunk[1])?;
+        result.push((high << 4) | low);
+    }
+
+    Some(result)
+}
+
+fn hex_char_to_nibble(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec as prop_vec;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_hex_known_values() {
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(&[0x00, 0x7f, 0xab, 0xff]), "007fabff");
+        assert_eq!(decode("007FabfF"), Some(vec![0x00, 0x7f, 0xab, 0xff]));
+        for malformed in ["0", "abc", "zz", "0x12", "12 3"] {
+            assert_eq!(decode(malformed), None, "{:?}", malformed);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_decode_inverts_encode(data in prop_vec(any::<u8>(), 0..200)) {
+            prop_assert_eq!(decode(&encode(&data)), Some(data));
+        }
+    }
+}
@@ -0,0 +1,4 @@
+//! Small helpers shared by the library and the binary.
+
+pub mod base64;
+pub mod hex;
@@ -0,0 +1,36 @@
+//! Runs the compiled binary, since spawning a `bench-child` needs the real
+//! executable rather than the unit test harness.
+
+use std::process::Command;
+use tempfile::TempDir;
+
+const BIN: &str = env!("CARGO_BIN_EXE_build-an-index");
+
+#[test]
+fn test_bench_child_reports_burst_timing() {
+    let dir = TempDir::new().unwrap();
+    let status = Command::new(BIN)
+        .args(["build", "--entries", "2", "--output"])
+        .arg(dir.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let output = Command::new(BIN)
+        .args(["bench-child", "--backend", "hash", "--lookups", "25"])
+        .args(["--seed", "1", "--input"])
+        .arg(dir.path())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let burst: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(burst["lookups"], 25);
+    assert!(burst["open_ns"].is_u64());
+    assert!(burst["lookup_ns"].as_u64().unwrap() > 0);
+}
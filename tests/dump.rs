@@ -0,0 +1,86 @@
+//! Runs `dump` through the compiled binary against a small Hash DAT index.
+
+use build_an_index::backends::HashDatStoreBuilder;
+use build_an_index::store::BlobStoreBuilder;
+use build_an_index::util::{base64, hex};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+const BIN: &str = env!("CARGO_BIN_EXE_build-an-index");
+
+fn dump(input: &Path, args: &[&str]) -> Output {
+    Command::new(BIN)
+        .arg("dump")
+        .arg("--input")
+        .arg(input)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn build_index(dir: &TempDir) -> (PathBuf, Vec<(Vec<u8>, Vec<u8>)>) {
+    let path = dir.path().join("index_hash.dat");
+    let entries: Vec<(Vec<u8>, Vec<u8>)> = (0..20usize)
+        .rev()
+        .map(|i| (format!("key{:02}", i).into_bytes(), vec![b'x'; i * 3]))
+        .chain([(vec![0, 0xff], b"binary key".to_vec())])
+        .collect();
+    let mut builder = HashDatStoreBuilder::create(&path).unwrap();
+    for (key, value) in &entries {
+        builder.insert(key, value).unwrap();
+    }
+    builder.finish().unwrap();
+    (path, entries)
+}
+
+#[test]
+fn test_dump_lists_sorted_keys_with_value_lengths() {
+    let dir = TempDir::new().unwrap();
+    let (path, mut entries) = build_index(&dir);
+    entries.sort();
+
+    let output = dump(&path, &[]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let expected: String = entries
+        .iter()
+        .map(|(key, value)| format!("{}\t{}\n", hex::encode(key), value.len()))
+        .collect();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+
+    let limited = dump(&path, &["--limit", "3"]);
+    let lines: Vec<String> = String::from_utf8(limited.stdout)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    assert_eq!(lines, expected.lines().take(3).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_dump_prints_one_value_by_hex_or_base64_key() {
+    let dir = TempDir::new().unwrap();
+    let (path, _) = build_index(&dir);
+
+    let by_hex = dump(&path, &["--key", &hex::encode(b"key07")]);
+    assert!(by_hex.status.success());
+    assert_eq!(by_hex.stdout, vec![b'x'; 21]);
+
+    let key = base64::encode(&[0, 0xff]);
+    let by_base64 = dump(&path, &["--key", &key, "--key-format", "base64"]);
+    assert!(by_base64.status.success());
+    assert_eq!(by_base64.stdout, b"binary key");
+
+    // Without --key-format the key is hex, never guessed as base64
+    let unmarked = dump(&path, &["--key", &key]);
+    assert!(!unmarked.status.success());
+    assert!(String::from_utf8_lossy(&unmarked.stderr).contains("not valid Hex"));
+
+    let missing = dump(&path, &["--key", &hex::encode(b"absent")]);
+    assert!(!missing.status.success());
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("not found"));
+}
@@ -0,0 +1,54 @@
+//! Runs `migrate` through the compiled binary and checks the converted index
+//! with the library's own readers.
+
+use build_an_index::backends::{HashDatStore, SqliteStore, SqliteStoreBuilder};
+use build_an_index::store::{BlobStore, BlobStoreBuilder};
+use std::process::Command;
+use tempfile::TempDir;
+
+const BIN: &str = env!("CARGO_BIN_EXE_build-an-index");
+
+#[test]
+fn test_migrate_sqlite_to_hash() {
+    let dir = TempDir::new().unwrap();
+    let from = dir.path().join("source.sqlite");
+    let to = dir.path().join("migrated.dat");
+
+    let entries: Vec<(Vec<u8>, Vec<u8>)> = (0..50usize)
+        .map(|i| {
+            let key = format!("key{:03}", i).into_bytes();
+            (key, vec![(i % 256) as u8; i * 13])
+        })
+        .collect();
+    let mut builder = SqliteStoreBuilder::create(&from).unwrap();
+    for (key, value) in &entries {
+        builder.insert(key, value).unwrap();
+    }
+    builder.finish().unwrap();
+
+    let output = Command::new(BIN)
+        .arg("migrate")
+        .arg("--from")
+        .arg(&from)
+        .arg("--to")
+        .arg(&to)
+        .args(["--to-format", "hash"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let source = SqliteStore::open(&from).unwrap();
+    let migrated = HashDatStore::open(&to).unwrap();
+    assert_eq!(migrated.len(), entries.len());
+    for (key, value) in &entries {
+        assert_eq!(migrated.get(key).unwrap().as_ref(), Some(value));
+    }
+    assert_eq!(
+        migrated.data_digest().unwrap(),
+        source.data_digest().unwrap()
+    );
+}